@@ -0,0 +1,21 @@
+ALTER TABLE tasks ADD COLUMN t_uuid TEXT;
+ALTER TABLE tasks ADD COLUMN t_created_at INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE tasks ADD COLUMN t_updated_at INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE tasks ADD COLUMN t_deleted INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE sessions ADD COLUMN s_uuid TEXT;
+ALTER TABLE sessions ADD COLUMN s_created_at INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE sessions ADD COLUMN s_updated_at INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE sessions ADD COLUMN s_deleted INTEGER NOT NULL DEFAULT 0;
+UPDATE tasks SET
+  t_uuid = lower(hex(randomblob(4))) || '-' || lower(hex(randomblob(2))) || '-4' ||
+    substr(lower(hex(randomblob(2))), 2) || '-' || substr('89ab', 1 + (abs(random()) % 4), 1) ||
+    substr(lower(hex(randomblob(2))), 2) || '-' || lower(hex(randomblob(6))),
+  t_created_at = CAST(strftime('%s', 'now') AS INTEGER) * 1000,
+  t_updated_at = CAST(strftime('%s', 'now') AS INTEGER) * 1000;
+UPDATE sessions SET
+  s_uuid = lower(hex(randomblob(4))) || '-' || lower(hex(randomblob(2))) || '-4' ||
+    substr(lower(hex(randomblob(2))), 2) || '-' || substr('89ab', 1 + (abs(random()) % 4), 1) ||
+    substr(lower(hex(randomblob(2))), 2) || '-' || lower(hex(randomblob(6))),
+  s_updated_at = CAST(strftime('%s', 'now') AS INTEGER) * 1000;
+CREATE UNIQUE INDEX IF NOT EXISTS tasks_uuid ON tasks (t_uuid);
+CREATE UNIQUE INDEX IF NOT EXISTS sessions_uuid ON sessions (s_uuid);
@@ -0,0 +1,10 @@
+ALTER TABLE tasks ADD COLUMN t_name_hlc TEXT NOT NULL DEFAULT '';
+ALTER TABLE tasks ADD COLUMN t_priority_hlc TEXT NOT NULL DEFAULT '';
+ALTER TABLE tasks ADD COLUMN t_deleted_hlc TEXT NOT NULL DEFAULT '';
+ALTER TABLE tasks ADD COLUMN t_name_base TEXT NOT NULL DEFAULT '';
+ALTER TABLE tasks ADD COLUMN t_priority_base TEXT NOT NULL DEFAULT '';
+ALTER TABLE tasks ADD COLUMN t_deleted_base TEXT NOT NULL DEFAULT '';
+UPDATE tasks SET
+  t_name_hlc = printf('%015d-00000-', t_updated_at),
+  t_priority_hlc = printf('%015d-00000-', t_updated_at),
+  t_deleted_hlc = printf('%015d-00000-', t_updated_at);
@@ -0,0 +1,2 @@
+ALTER TABLE tasks ADD COLUMN t_client INTEGER REFERENCES clients(cl_id) ON DELETE SET NULL;
+ALTER TABLE sessions ADD COLUMN s_billable INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,2 @@
+ALTER TABLE tasks ADD COLUMN t_estimate INTEGER;
+ALTER TABLE tasks ADD COLUMN t_budget INTEGER;
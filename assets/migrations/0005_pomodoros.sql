@@ -0,0 +1 @@
+ALTER TABLE sessions ADD COLUMN s_pomodoros INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE tasks ADD COLUMN t_daily_goal INTEGER;
+ALTER TABLE tasks ADD COLUMN t_weekly_goal INTEGER;
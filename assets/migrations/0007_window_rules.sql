@@ -0,0 +1 @@
+ALTER TABLE sessions ADD COLUMN s_rule INTEGER REFERENCES window_rules(wr_id) ON DELETE SET NULL;
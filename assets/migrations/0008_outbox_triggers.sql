@@ -0,0 +1,2 @@
+DROP TRIGGER IF EXISTS tasks_outbox_update;
+DROP TRIGGER IF EXISTS sessions_outbox_update;
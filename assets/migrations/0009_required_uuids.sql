@@ -0,0 +1,16 @@
+CREATE TRIGGER IF NOT EXISTS tasks_uuid_insert BEFORE INSERT ON tasks
+WHEN NEW.t_uuid IS NULL BEGIN
+  SELECT RAISE(ABORT, 'NOT NULL constraint failed: tasks.t_uuid');
+END;
+CREATE TRIGGER IF NOT EXISTS tasks_uuid_update BEFORE UPDATE OF t_uuid ON tasks
+WHEN NEW.t_uuid IS NULL BEGIN
+  SELECT RAISE(ABORT, 'NOT NULL constraint failed: tasks.t_uuid');
+END;
+CREATE TRIGGER IF NOT EXISTS sessions_uuid_insert BEFORE INSERT ON sessions
+WHEN NEW.s_uuid IS NULL BEGIN
+  SELECT RAISE(ABORT, 'NOT NULL constraint failed: sessions.s_uuid');
+END;
+CREATE TRIGGER IF NOT EXISTS sessions_uuid_update BEFORE UPDATE OF s_uuid ON sessions
+WHEN NEW.s_uuid IS NULL BEGIN
+  SELECT RAISE(ABORT, 'NOT NULL constraint failed: sessions.s_uuid');
+END;
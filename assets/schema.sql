@@ -1,8 +1,23 @@
 PRAGMA foreign_keys = ON;
 CREATE TABLE IF NOT EXISTS tasks (
   t_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  t_uuid TEXT NOT NULL UNIQUE,
   t_name TEXT NOT NULL,
-  t_priority INTEGER NOT NULL
+  t_priority INTEGER NOT NULL,
+  t_created_at INTEGER NOT NULL,
+  t_updated_at INTEGER NOT NULL,
+  t_deleted INTEGER NOT NULL DEFAULT 0,
+  t_name_hlc TEXT NOT NULL DEFAULT '',
+  t_priority_hlc TEXT NOT NULL DEFAULT '',
+  t_deleted_hlc TEXT NOT NULL DEFAULT '',
+  t_name_base TEXT NOT NULL DEFAULT '',
+  t_priority_base TEXT NOT NULL DEFAULT '',
+  t_deleted_base TEXT NOT NULL DEFAULT '',
+  t_client INTEGER REFERENCES clients(cl_id) ON DELETE SET NULL,
+  t_estimate INTEGER,
+  t_budget INTEGER,
+  t_daily_goal INTEGER,
+  t_weekly_goal INTEGER
 );
 CREATE TABLE IF NOT EXISTS users (
   u_id TEXT PRIMARY KEY,
@@ -10,10 +25,78 @@ CREATE TABLE IF NOT EXISTS users (
 );
 CREATE TABLE IF NOT EXISTS sessions (
   s_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  s_uuid TEXT NOT NULL UNIQUE,
   s_task INTEGER NOT NULL,
   s_user TEXT NOT NULL,
   s_duration INTEGER NOT NULL,
   s_comment TEXT NOT NULL,
+  s_created_at INTEGER NOT NULL,
+  s_updated_at INTEGER NOT NULL,
+  s_deleted INTEGER NOT NULL DEFAULT 0,
+  s_billable INTEGER NOT NULL DEFAULT 1,
+  s_pomodoros INTEGER NOT NULL DEFAULT 0,
+  s_rule INTEGER REFERENCES window_rules(wr_id) ON DELETE SET NULL,
   FOREIGN KEY (s_task) REFERENCES tasks(t_id) ON DELETE CASCADE,
   FOREIGN KEY (s_user) REFERENCES users(u_id) ON DELETE CASCADE
-);
\ No newline at end of file
+);
+CREATE TABLE IF NOT EXISTS clients (
+  cl_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  cl_name TEXT NOT NULL UNIQUE,
+  cl_currency TEXT NOT NULL,
+  cl_rounding TEXT NOT NULL DEFAULT 'up',
+  cl_rounding_minutes INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE IF NOT EXISTS rates (
+  r_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  r_client INTEGER REFERENCES clients(cl_id) ON DELETE CASCADE,
+  r_task INTEGER REFERENCES tasks(t_id) ON DELETE CASCADE,
+  r_hourly_cents INTEGER NOT NULL,
+  r_effective_from TEXT NOT NULL,
+  CHECK ((r_client IS NULL) <> (r_task IS NULL))
+);
+CREATE TABLE IF NOT EXISTS window_rules (
+  wr_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  wr_pattern TEXT NOT NULL,
+  wr_field TEXT NOT NULL DEFAULT 'title',
+  wr_task INTEGER NOT NULL REFERENCES tasks(t_id) ON DELETE CASCADE,
+  wr_action TEXT NOT NULL DEFAULT 'suggest'
+);
+CREATE TABLE IF NOT EXISTS hlc_clock (
+  h_id INTEGER PRIMARY KEY CHECK (h_id = 1),
+  h_node TEXT NOT NULL,
+  h_last TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS conflicts (
+  c_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  c_task TEXT NOT NULL,
+  c_field TEXT NOT NULL,
+  c_local_value TEXT NOT NULL,
+  c_remote_value TEXT NOT NULL,
+  c_kept TEXT NOT NULL,
+  c_detected_at INTEGER NOT NULL,
+  c_resolved INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE IF NOT EXISTS outbox (
+  o_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  o_kind TEXT NOT NULL,
+  o_record INTEGER NOT NULL,
+  o_attempts INTEGER NOT NULL DEFAULT 0,
+  o_next_attempt INTEGER NOT NULL DEFAULT 0
+);
+CREATE TRIGGER IF NOT EXISTS tasks_outbox_insert AFTER INSERT ON tasks BEGIN
+  INSERT INTO outbox (o_kind, o_record) VALUES ('task', NEW.t_id);
+END;
+CREATE TRIGGER IF NOT EXISTS tasks_outbox_update AFTER UPDATE OF
+  t_uuid, t_name, t_priority, t_created_at, t_updated_at, t_deleted,
+  t_name_hlc, t_priority_hlc, t_deleted_hlc
+ON tasks BEGIN
+  INSERT INTO outbox (o_kind, o_record) VALUES ('task', NEW.t_id);
+END;
+CREATE TRIGGER IF NOT EXISTS sessions_outbox_insert AFTER INSERT ON sessions BEGIN
+  INSERT INTO outbox (o_kind, o_record) VALUES ('session', NEW.s_id);
+END;
+CREATE TRIGGER IF NOT EXISTS sessions_outbox_update AFTER UPDATE OF
+  s_uuid, s_task, s_user, s_duration, s_comment, s_created_at, s_updated_at, s_deleted
+ON sessions BEGIN
+  INSERT INTO outbox (o_kind, o_record) VALUES ('session', NEW.s_id);
+END;
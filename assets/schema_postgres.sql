@@ -0,0 +1,69 @@
+CREATE TABLE IF NOT EXISTS tasks (
+  t_id BIGSERIAL PRIMARY KEY,
+  t_uuid TEXT NOT NULL UNIQUE,
+  t_name TEXT NOT NULL,
+  t_priority INTEGER NOT NULL,
+  t_created_at BIGINT NOT NULL,
+  t_updated_at BIGINT NOT NULL,
+  t_deleted BOOLEAN NOT NULL DEFAULT FALSE,
+  t_name_hlc TEXT NOT NULL DEFAULT '',
+  t_priority_hlc TEXT NOT NULL DEFAULT '',
+  t_deleted_hlc TEXT NOT NULL DEFAULT ''
+);
+CREATE TABLE IF NOT EXISTS users (
+  u_id TEXT PRIMARY KEY,
+  u_name TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS sessions (
+  s_id BIGSERIAL PRIMARY KEY,
+  s_uuid TEXT NOT NULL UNIQUE,
+  s_task BIGINT NOT NULL,
+  s_user TEXT NOT NULL,
+  s_duration BIGINT NOT NULL,
+  s_comment TEXT NOT NULL,
+  s_created_at BIGINT NOT NULL,
+  s_updated_at BIGINT NOT NULL,
+  s_deleted BOOLEAN NOT NULL DEFAULT FALSE,
+  FOREIGN KEY (s_task) REFERENCES tasks(t_id) ON DELETE CASCADE,
+  FOREIGN KEY (s_user) REFERENCES users(u_id) ON DELETE CASCADE
+);
+CREATE TABLE IF NOT EXISTS hlc_clock (
+  h_node TEXT PRIMARY KEY,
+  h_last TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS clients (
+  cl_id BIGSERIAL PRIMARY KEY,
+  cl_name TEXT NOT NULL UNIQUE,
+  cl_currency TEXT NOT NULL,
+  cl_rounding TEXT NOT NULL DEFAULT 'up',
+  cl_rounding_minutes INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE IF NOT EXISTS rates (
+  r_id BIGSERIAL PRIMARY KEY,
+  r_client BIGINT REFERENCES clients(cl_id) ON DELETE CASCADE,
+  r_task BIGINT REFERENCES tasks(t_id) ON DELETE CASCADE,
+  r_hourly_cents BIGINT NOT NULL,
+  r_effective_from TEXT NOT NULL,
+  CHECK ((r_client IS NULL) <> (r_task IS NULL))
+);
+CREATE TABLE IF NOT EXISTS window_rules (
+  wr_id BIGSERIAL PRIMARY KEY,
+  wr_pattern TEXT NOT NULL,
+  wr_field TEXT NOT NULL DEFAULT 'title',
+  wr_task BIGINT NOT NULL REFERENCES tasks(t_id) ON DELETE CASCADE,
+  wr_action TEXT NOT NULL DEFAULT 'suggest'
+);
+CREATE TABLE IF NOT EXISTS task_goals (
+  tg_task BIGINT NOT NULL REFERENCES tasks(t_id) ON DELETE CASCADE,
+  tg_user TEXT NOT NULL REFERENCES users(u_id) ON DELETE CASCADE,
+  tg_daily_goal BIGINT,
+  tg_weekly_goal BIGINT,
+  PRIMARY KEY (tg_task, tg_user)
+);
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS t_client BIGINT REFERENCES clients(cl_id) ON DELETE SET NULL;
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS t_estimate BIGINT;
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS t_budget BIGINT;
+ALTER TABLE window_rules ADD COLUMN IF NOT EXISTS wr_user TEXT REFERENCES users(u_id) ON DELETE CASCADE;
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS s_billable BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS s_pomodoros INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS s_rule BIGINT REFERENCES window_rules(wr_id) ON DELETE SET NULL;
@@ -0,0 +1,29 @@
+CREATE TABLE IF NOT EXISTS clients (
+  c_uid TEXT PRIMARY KEY,
+  c_last_push INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS tasks (
+  t_uuid TEXT PRIMARY KEY,
+  t_client TEXT NOT NULL,
+  t_name TEXT NOT NULL,
+  t_priority INTEGER NOT NULL,
+  t_created_at INTEGER NOT NULL,
+  t_updated_at INTEGER NOT NULL,
+  t_deleted INTEGER NOT NULL DEFAULT 0,
+  t_name_hlc TEXT NOT NULL DEFAULT '',
+  t_priority_hlc TEXT NOT NULL DEFAULT '',
+  t_deleted_hlc TEXT NOT NULL DEFAULT '',
+  FOREIGN KEY (t_client) REFERENCES clients(c_uid) ON DELETE CASCADE
+);
+CREATE TABLE IF NOT EXISTS sessions (
+  s_uuid TEXT PRIMARY KEY,
+  s_client TEXT NOT NULL,
+  s_task TEXT NOT NULL,
+  s_user TEXT NOT NULL,
+  s_duration INTEGER NOT NULL,
+  s_comment TEXT NOT NULL,
+  s_created_at INTEGER NOT NULL,
+  s_updated_at INTEGER NOT NULL,
+  s_deleted INTEGER NOT NULL DEFAULT 0,
+  FOREIGN KEY (s_client) REFERENCES clients(c_uid) ON DELETE CASCADE
+);
\ No newline at end of file
@@ -1,15 +1,33 @@
+pub mod active_window;
+pub mod breaks;
+pub mod clients;
 pub mod core;
+pub mod events;
 pub mod input;
+pub mod notifications;
+pub mod pomodoro;
+pub mod rates;
 pub mod sessions;
+mod shutdown;
 pub mod tasks;
 mod time;
+pub mod users;
+pub mod window_rules;
 
+pub use active_window::start_window_watcher;
 pub use core::AgentCommand;
 pub use core::start_agent;
 pub use input::start_input_listener;
+pub use notifications::DesktopNotifier;
+pub use notifications::Notifier;
+pub use notifications::RecordingNotifier;
 pub use sessions::Session;
 pub use sessions::save_session;
+pub use shutdown::start_shutdown_listener;
 pub use tasks::Task;
 pub use tasks::add_new_task;
 pub use tasks::get_all_tasks;
+pub use time::Clock;
+pub use time::ManualClock;
 pub use time::StopWatch;
+pub use time::SystemClock;
@@ -0,0 +1,171 @@
+use std::{
+    sync::{Arc, Mutex, mpsc::Sender},
+    thread,
+    time::Duration,
+};
+
+use crate::agent;
+
+/// The window with the keyboard focus.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ActiveWindow {
+    pub title: String,
+    /// The application's window class, e.g. `firefox`.
+    pub class: String,
+}
+
+/// Tells which window has the focus.
+pub trait WindowSource: Send {
+    /// `None` when no window has the focus, or it can't be told.
+    fn active_window(&mut self) -> Option<ActiveWindow>;
+}
+
+/// The window the X server's window manager reports as active in
+/// `_NET_ACTIVE_WINDOW`, named by `_NET_WM_NAME` or `WM_NAME` and `WM_CLASS`.
+#[cfg(target_os = "linux")]
+pub struct X11WindowSource {
+    conn: x11rb::rust_connection::RustConnection,
+    root: x11rb::protocol::xproto::Window,
+    net_active_window: x11rb::protocol::xproto::Atom,
+    net_wm_name: x11rb::protocol::xproto::Atom,
+    utf8_string: x11rb::protocol::xproto::Atom,
+}
+
+#[cfg(target_os = "linux")]
+impl X11WindowSource {
+    /// Connects to the display in `DISPLAY`.
+    pub fn connect() -> Result<Self, Box<dyn std::error::Error>> {
+        use x11rb::{connection::Connection, protocol::xproto::ConnectionExt};
+
+        let (conn, screen) = x11rb::connect(None)?;
+        let root = conn.setup().roots[screen].root;
+        let atom = |name: &[u8]| -> Result<_, Box<dyn std::error::Error>> {
+            Ok(conn.intern_atom(false, name)?.reply()?.atom)
+        };
+        let net_active_window = atom(b"_NET_ACTIVE_WINDOW")?;
+        let net_wm_name = atom(b"_NET_WM_NAME")?;
+        let utf8_string = atom(b"UTF8_STRING")?;
+        Ok(Self {
+            conn,
+            root,
+            net_active_window,
+            net_wm_name,
+            utf8_string,
+        })
+    }
+
+    fn text(
+        &self,
+        window: x11rb::protocol::xproto::Window,
+        property: impl Into<x11rb::protocol::xproto::Atom>,
+        kind: impl Into<x11rb::protocol::xproto::Atom>,
+    ) -> Result<String, x11rb::errors::ReplyError> {
+        use x11rb::protocol::xproto::ConnectionExt;
+
+        let reply = self
+            .conn
+            .get_property(false, window, property, kind, 0, 1024)?
+            .reply()?;
+        Ok(String::from_utf8_lossy(&reply.value).into_owned())
+    }
+
+    fn read(&self) -> Result<Option<ActiveWindow>, x11rb::errors::ReplyError> {
+        use x11rb::protocol::xproto::{AtomEnum, ConnectionExt};
+
+        let reply = self
+            .conn
+            .get_property(
+                false,
+                self.root,
+                self.net_active_window,
+                AtomEnum::WINDOW,
+                0,
+                1,
+            )?
+            .reply()?;
+        let Some(window) = reply.value32().and_then(|mut values| values.next()) else {
+            return Ok(None);
+        };
+        if window == x11rb::NONE {
+            return Ok(None);
+        }
+
+        let mut title = self.text(window, self.net_wm_name, self.utf8_string)?;
+        if title.is_empty() {
+            title = self.text(window, AtomEnum::WM_NAME, AtomEnum::ANY)?;
+        }
+        // The instance name and the class, each followed by a NUL.
+        let class = self.text(window, AtomEnum::WM_CLASS, AtomEnum::STRING)?;
+        let class = class.split('\0').nth(1).unwrap_or_default().to_string();
+        Ok(Some(ActiveWindow { title, class }))
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl WindowSource for X11WindowSource {
+    fn active_window(&mut self) -> Option<ActiveWindow> {
+        // The window may have closed between the reads.
+        self.read()
+            .inspect_err(|e| tracing::debug!("Couldn't read the active window: {}", e))
+            .ok()
+            .flatten()
+    }
+}
+
+/// The desktop's source, if it has one the app can read: only X11 so far.
+pub fn system_source() -> Option<Box<dyn WindowSource>> {
+    #[cfg(target_os = "linux")]
+    match X11WindowSource::connect() {
+        Ok(source) => return Some(Box::new(source)),
+        Err(e) => tracing::info!("Not watching the active window: {}", e),
+    }
+    None
+}
+
+/// Reports the window set by the test, which can keep a clone to change it.
+#[derive(Clone, Default)]
+pub struct FakeWindowSource {
+    window: Arc<Mutex<Option<ActiveWindow>>>,
+}
+
+impl FakeWindowSource {
+    pub fn set(&self, window: Option<ActiveWindow>) {
+        *self.window.lock().unwrap() = window;
+    }
+}
+
+impl WindowSource for FakeWindowSource {
+    fn active_window(&mut self) -> Option<ActiveWindow> {
+        self.window.lock().unwrap().clone()
+    }
+}
+
+/// Polls `source` every `interval` and tells the agent whenever another
+/// window gets the focus. Stops once the agent is gone.
+pub fn start_window_watcher(
+    command_tx: Sender<agent::AgentCommand>,
+    clock: Arc<dyn agent::Clock>,
+    mut source: Box<dyn WindowSource>,
+    interval: Duration,
+) {
+    thread::Builder::new()
+        .name("agent-window-watcher".to_string())
+        .spawn(move || {
+            let mut last = None;
+            loop {
+                let window = source.active_window();
+                if window != last {
+                    let command = agent::AgentCommand::ActiveWindow {
+                        window: window.clone(),
+                        time_stamp: clock.now(),
+                    };
+                    if command_tx.send(command).is_err() {
+                        return;
+                    }
+                    last = window;
+                }
+                thread::sleep(interval);
+            }
+        })
+        .expect("Failed to spawn agent-window-watcher thread");
+}
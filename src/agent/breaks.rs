@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::report::compliance::WorkLimits;
+
+/// Continuous activity, as seen by the input listener, so the user can be
+/// reminded to take a break whether or not a session is running.
+pub struct BreakTracker {
+    stretch_started: DateTime<Utc>,
+    last_activity: DateTime<Utc>,
+    /// Reminders given during the current stretch.
+    reminders: u32,
+}
+
+impl BreakTracker {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            stretch_started: now,
+            last_activity: now,
+            reminders: 0,
+        }
+    }
+
+    /// Activity after a pause of at least `limits.min_break` starts a new
+    /// stretch.
+    pub fn activity(&mut self, time: DateTime<Utc>, limits: &WorkLimits) {
+        if time < self.last_activity {
+            return;
+        }
+        if (time - self.last_activity).num_seconds() >= limits.min_break as i64 {
+            self.stretch_started = time;
+            self.reminders = 0;
+        }
+        self.last_activity = time;
+    }
+
+    /// How long the user has been at it without a break, each time that
+    /// passes another `limits.stretch`. Nothing while they're away.
+    pub fn reminder_due(&mut self, now: DateTime<Utc>, limits: &WorkLimits) -> Option<Duration> {
+        let remind_after = limits.stretch?;
+        if (now - self.last_activity).num_seconds() >= limits.min_break as i64 {
+            return None;
+        }
+        let stretch = (self.last_activity - self.stretch_started)
+            .to_std()
+            .unwrap_or_default();
+        if stretch.as_secs() < remind_after * u64::from(self.reminders + 1) {
+            return None;
+        }
+        self.reminders += 1;
+        Some(stretch)
+    }
+}
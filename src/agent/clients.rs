@@ -0,0 +1,73 @@
+use rusqlite::{Connection, Result, Row};
+
+use crate::billing::Rounding;
+
+const CLIENT_COLUMNS: &str = "cl_id, cl_name, cl_currency, cl_rounding, cl_rounding_minutes";
+
+/// Someone tasks are billed to. Clients aren't synced; with the PostgreSQL
+/// backend the whole team shares them, like their rates.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Client {
+    pub cl_id: i64,
+    pub cl_name: String,
+    /// ISO 4217 code, e.g. `EUR`; rates are in this currency.
+    pub cl_currency: String,
+    /// Applied to each billable session before it is invoiced.
+    pub cl_rounding: Rounding,
+}
+
+fn client_from_row(row: &Row) -> Result<Client> {
+    let mode: String = row.get(3)?;
+    Ok(Client {
+        cl_id: row.get(0)?,
+        cl_name: row.get(1)?,
+        cl_currency: row.get(2)?,
+        cl_rounding: Rounding {
+            mode: mode.parse().unwrap_or_default(),
+            minutes: row.get(4)?,
+        },
+    })
+}
+
+pub fn get_all_clients(conn: &Connection) -> Result<Vec<Client>> {
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM clients ORDER BY cl_name",
+        CLIENT_COLUMNS
+    ))?;
+    let client_iter = statement.query_map([], client_from_row)?;
+
+    let mut clients = Vec::new();
+    for client in client_iter {
+        clients.push(client?);
+    }
+    Ok(clients)
+}
+
+pub fn add_client(conn: &Connection, client: &Client) -> Result<usize> {
+    conn.execute(
+        "INSERT INTO clients (cl_name, cl_currency, cl_rounding, cl_rounding_minutes)
+            VALUES (?1, ?2, ?3, ?4)",
+        (
+            &client.cl_name,
+            &client.cl_currency,
+            client.cl_rounding.mode.to_string(),
+            client.cl_rounding.minutes,
+        ),
+    )
+}
+
+/// Renames the client with `client.cl_id` and changes its currency and
+/// rounding.
+pub fn update_client(conn: &Connection, client: &Client) -> Result<usize> {
+    conn.execute(
+        "UPDATE clients SET cl_name = ?1, cl_currency = ?2, cl_rounding = ?3,
+            cl_rounding_minutes = ?4 WHERE cl_id = ?5",
+        (
+            &client.cl_name,
+            &client.cl_currency,
+            client.cl_rounding.mode.to_string(),
+            client.cl_rounding.minutes,
+            client.cl_id,
+        ),
+    )
+}
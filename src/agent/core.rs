@@ -1,31 +1,143 @@
-use std::sync::{Arc, mpsc};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, mpsc},
+};
 
-use rusqlite::Connection;
+use crate::{
+    agent::{
+        self,
+        active_window::ActiveWindow,
+        breaks::BreakTracker,
+        events,
+        notifications::{Category, Notification},
+        pomodoro::{Phase, Pomodoro, PomodoroPlan, PomodoroStatus},
+        window_rules::{RuleAction, RuleSet, WindowRule},
+    },
+    billing, calendar, config, import,
+    report::{self, goals::GoalProgress},
+    storage, sync,
+};
+
+/// How often the agent checks whether today's backup has been taken.
+const BACKUP_CHECK_INTERVAL: chrono::Duration = chrono::Duration::hours(1);
+
+/// Shares of a task's budget, in percent, at which the user is warned.
+const BUDGET_WARNINGS: [u8; 2] = [80, 100];
+
+/// The running session's task's budget.
+struct Budget {
+    task: String,
+    seconds: u64,
+    /// Time tracked on the task before this session.
+    tracked_before: u64,
+    /// The highest warning given, 0 if none.
+    warned: u8,
+}
 
-use crate::{agent, config, storage, ui};
+impl Budget {
+    /// The highest warning the time tracked so far calls for, 0 if none.
+    fn reached(&self, elapsed: u64) -> u8 {
+        let tracked = u128::from(self.tracked_before + elapsed);
+        BUDGET_WARNINGS
+            .into_iter()
+            .filter(|percent| tracked * 100 >= u128::from(self.seconds) * u128::from(*percent))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Time worked before the running session, for the working-time limits.
+struct WorkTime {
+    /// The local day the session started on.
+    date: chrono::NaiveDate,
+    today_before: u64,
+    week_before: u64,
+}
+
+/// A window that got the focus, until its rule has been looked at.
+struct Focus {
+    window: ActiveWindow,
+    since: chrono::DateTime<chrono::Utc>,
+}
 
 struct AgentState {
-    db_connection: Connection,
+    repository: Box<dyn storage::Repository>,
+    clock: Arc<dyn agent::Clock>,
 
     session: agent::sessions::Session,
     stop_watch: agent::time::StopWatch,
     task_in_progress: bool,
+    budget: Option<Budget>,
+    session_started_at: chrono::DateTime<chrono::Utc>,
+    forgotten_timer_notified: bool,
+    /// When the running session is stopped for the end of the workday.
+    auto_stop_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Focus mode, on top of the running session.
+    pomodoro: Option<Pomodoro>,
+    /// The focus mode status last sent to the front ends.
+    pomodoro_reported: Option<PomodoroStatus>,
+    /// Set once stopping the running session by itself failed, so the error
+    /// is reported once and the user ends the session instead.
+    auto_stop_failed: bool,
+    work_time: Option<WorkTime>,
+    /// The last day the daily limit was warned about.
+    daily_limit_warned: Option<chrono::NaiveDate>,
+    /// The Monday of the last week the weekly limit was warned about.
+    weekly_limit_warned: Option<chrono::NaiveDate>,
+    breaks: BreakTracker,
+    /// Progress towards the goals before the running session.
+    goals: Vec<GoalProgress>,
+    /// The day `goals` are for; `None` has them loaded again.
+    goals_date: Option<chrono::NaiveDate>,
+    /// The progress last sent to the front ends.
+    goals_reported: Vec<GoalProgress>,
+    /// `None` has the window rules loaded again.
+    window_rules: Option<RuleSet>,
+    focus: Option<Focus>,
+    /// The rule whose suggestion the user hasn't taken up yet.
+    suggestion: Option<WindowRule>,
 
     last_user_activity_time_stamp: chrono::DateTime<chrono::Utc>,
-    user_state: ui::UserState,
+    user_state: events::UserState,
+
+    next_backup_check: chrono::DateTime<chrono::Utc>,
+    /// Set while the database is damaged, or couldn't be opened at all, so
+    /// that scheduled backups don't replace the good ones.
+    backups_paused: bool,
 }
 
 impl AgentState {
-    fn new(db_connection: Connection) -> Self {
+    fn new(repository: Box<dyn storage::Repository>, clock: Arc<dyn agent::Clock>) -> Self {
         AgentState {
-            db_connection,
+            repository,
+            clock: clock.clone(),
 
             session: agent::sessions::Session::default(),
-            stop_watch: agent::time::StopWatch::new(),
+            stop_watch: agent::time::StopWatch::new(clock.clone()),
             task_in_progress: false,
+            budget: None,
+            session_started_at: clock.now(),
+            forgotten_timer_notified: false,
+            auto_stop_at: None,
+            auto_stop_failed: false,
+            pomodoro: None,
+            pomodoro_reported: None,
+            work_time: None,
+            daily_limit_warned: None,
+            weekly_limit_warned: None,
+            breaks: BreakTracker::new(clock.now()),
+            goals: Vec::new(),
+            goals_date: None,
+            goals_reported: Vec::new(),
+            window_rules: None,
+            focus: None,
+            suggestion: None,
 
-            last_user_activity_time_stamp: chrono::Utc::now(),
-            user_state: ui::UserState::Active,
+            last_user_activity_time_stamp: clock.now(),
+            user_state: events::UserState::Active,
+
+            next_backup_check: clock.now(),
+            backups_paused: false,
         }
     }
 }
@@ -36,110 +148,1385 @@ pub enum AgentCommand {
     },
     EndSession {
         comment: String,
+        billable: bool,
     },
     AddTask {
         task: agent::tasks::Task,
     },
+    UpdateTask {
+        task: agent::tasks::Task,
+    },
+    DeleteTask {
+        id: i64,
+    },
+    RequestConflicts,
+    ResolveConflict {
+        id: i64,
+        keep_local: bool,
+    },
+    RequestBackups,
+    BackupNow,
+    RestoreBackup {
+        path: PathBuf,
+    },
+    /// Imports another tracker's export, or only reports what would be
+    /// imported when `dry_run` is set.
+    Import {
+        path: PathBuf,
+        format: import::Format,
+        dry_run: bool,
+    },
+    /// Writes the sessions of the local days `from` to `to` as an
+    /// iCalendar file.
+    ExportCalendar {
+        path: PathBuf,
+        from: chrono::NaiveDate,
+        to: chrono::NaiveDate,
+    },
+    /// Saves the timesheet of the week `date` falls in, for `user` or, by
+    /// default, this client's user.
+    ExportTimesheet {
+        path: PathBuf,
+        user: Option<String>,
+        date: chrono::NaiveDate,
+    },
+    RequestUsers,
+    /// Measures the last `weeks` weeks against the working-time limits.
+    RequestCompliance {
+        weeks: u32,
+    },
+    /// Each goal's last `periods` days or weeks.
+    RequestGoalHistory {
+        periods: u32,
+    },
+    /// Starts focus mode for the running session.
+    StartPomodoro,
+    StopPomodoro,
+    RequestBilling,
+    AddClient {
+        client: agent::clients::Client,
+    },
+    UpdateClient {
+        client: agent::clients::Client,
+    },
+    AddRate {
+        rate: agent::rates::Rate,
+    },
+    DeleteRate {
+        id: i64,
+    },
+    RequestWindowRules,
+    AddWindowRule {
+        rule: WindowRule,
+    },
+    DeleteWindowRule {
+        id: i64,
+    },
+    /// Another window got the focus; `None` when none has it.
+    ActiveWindow {
+        window: Option<ActiveWindow>,
+        time_stamp: chrono::DateTime<chrono::Utc>,
+    },
+    /// Switches to the task a window rule suggested last, saving the running
+    /// session with `comment`.
+    AcceptSuggestion {
+        comment: String,
+        billable: bool,
+    },
+    /// Totals a client's billable time for the local days `from` to `to`.
+    RequestInvoiceDraft {
+        client: i64,
+        from: chrono::NaiveDate,
+        to: chrono::NaiveDate,
+    },
+    /// Saves the invoice draft's line items as CSV.
+    ExportInvoiceDraft {
+        path: PathBuf,
+        client: i64,
+        from: chrono::NaiveDate,
+        to: chrono::NaiveDate,
+    },
+    /// Asks for the calendar feed's contents, sent back on `reply`.
+    CalendarFeed {
+        reply: mpsc::Sender<String>,
+    },
     UserActivity {
         time_stamp: chrono::DateTime<chrono::Utc>,
     },
     RequestTaskList,
+    SwitchProfile {
+        name: String,
+    },
+    AddProfile {
+        name: String,
+    },
+    /// Opens an encrypted database with the passphrase the user entered.
+    Unlock {
+        passphrase: String,
+        remember: bool,
+    },
     Quit,
+    /// The system is shutting down; a `Quit` follows.
+    Shutdown,
     RequestElapsedTime,
     ShowUI,
+    SyncNow,
+}
+
+/// The agent's outgoing channels.
+struct Channels {
+    window_tx: crossbeam_channel::Sender<events::UIEvent>,
+    tray_tx: mpsc::Sender<events::UIEvent>,
+    ui_control_tx: mpsc::Sender<events::UIControl>,
+    sync_tx: mpsc::Sender<sync::SyncCommand>,
+    notifier: Arc<dyn agent::Notifier>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn start_agent(
     command_rx: mpsc::Receiver<AgentCommand>,
-    window_tx: crossbeam_channel::Sender<ui::UIEvent>,
-    tray_tx: mpsc::Sender<ui::UIEvent>,
-    ui_control_tx: mpsc::Sender<ui::UIControl>,
+    window_tx: crossbeam_channel::Sender<events::UIEvent>,
+    tray_tx: mpsc::Sender<events::UIEvent>,
+    ui_control_tx: mpsc::Sender<events::UIControl>,
+    sync_tx: mpsc::Sender<sync::SyncCommand>,
     settings: Arc<config::settings::Settings>,
+    clock: Arc<dyn agent::Clock>,
+    notifier: Arc<dyn agent::Notifier>,
 ) {
-    let db_connection = storage::init_db(settings.clone()).unwrap();
-    let mut agent_state = AgentState::new(db_connection);
+    let channels = Channels {
+        window_tx,
+        tray_tx,
+        ui_control_tx,
+        sync_tx,
+        notifier,
+    };
+    let mut settings = settings;
+    let (repository, in_memory_fallback) = open_repository_or_memory(&settings, &clock, &channels);
+    let mut agent_state = AgentState::new(repository, clock);
+    check_integrity(&mut agent_state, &channels);
+    agent_state.backups_paused |= in_memory_fallback;
+    // The sync worker can't open an encrypted database before the agent has
+    // encrypted it or found the passphrase.
+    if settings.encrypt_database && !agent_state.repository.is_locked() {
+        let _ = channels.sync_tx.send(sync::SyncCommand::Reconfigure {
+            settings: settings.clone(),
+        });
+    }
     let mut running = true;
 
-    if settings.open_ui_at_start_up {
-        let _ = ui_control_tx.send(ui::UIControl::Show);
+    // The passphrase is asked for in the window.
+    if settings.open_ui_at_start_up || agent_state.repository.is_locked() {
+        let _ = channels.ui_control_tx.send(events::UIControl::Show);
     }
 
     while running {
         while let Ok(event) = command_rx.try_recv() {
-            match event {
-                AgentCommand::StartSession { id } => {
-                    agent_state.task_in_progress = true;
-                    agent_state.session = agent::sessions::Session::default();
-                    agent_state.stop_watch.start();
-                    agent_state.session.s_task = id;
-                }
-                AgentCommand::EndSession { comment } => {
-                    agent_state.task_in_progress = false;
-                    agent_state.stop_watch.stop();
-                    let elapsed_time = agent_state.stop_watch.elapsed();
-                    agent_state.stop_watch.reset();
-
-                    agent_state.session.s_user = settings.uid.clone();
-                    agent_state.session.s_comment = comment;
-                    agent_state.session.s_duration = elapsed_time.as_secs();
-                    agent::sessions::save_session(&agent_state.db_connection, &agent_state.session)
-                        .unwrap();
-                }
-                AgentCommand::AddTask { task } => {
-                    agent::tasks::add_new_task(&agent_state.db_connection, &task).unwrap();
-                }
-                AgentCommand::RequestTaskList => {
-                    let task_list =
-                        agent::tasks::get_all_tasks(&agent_state.db_connection).unwrap();
-                    window_tx.send(ui::UIEvent::TaskList { task_list }).unwrap();
-                }
-                AgentCommand::RequestElapsedTime => window_tx
-                    .send(ui::UIEvent::ElapsedTime {
-                        elapsed: agent_state.stop_watch.elapsed(),
-                    })
-                    .unwrap(),
-                AgentCommand::Quit => {
-                    let _ = window_tx.send(ui::UIEvent::Quit);
-                    let _ = tray_tx.send(ui::UIEvent::Quit);
-                    let _ = ui_control_tx.send(ui::UIControl::Quit);
-                    running = false;
-                }
-                AgentCommand::ShowUI => {
-                    let _ = ui_control_tx.send(ui::UIControl::Show);
+            if let AgentCommand::Quit = event {
+                tracing::info!("Quitting");
+                let _ = channels.window_tx.send(events::UIEvent::Quit);
+                let _ = channels.tray_tx.send(events::UIEvent::Quit);
+                let _ = channels.ui_control_tx.send(events::UIControl::Quit);
+                let _ = channels.sync_tx.send(sync::SyncCommand::Quit);
+                running = false;
+                break;
+            }
+
+            match handle_command(event, &mut agent_state, &mut settings, &channels) {
+                Ok(()) => (),
+                // Asked again rather than reported: nothing works until then.
+                Err(crate::Error::Storage(storage::StorageError::Locked)) => {
+                    send_locked(&settings, &channels, None);
                 }
-                AgentCommand::UserActivity { time_stamp } => {
-                    agent_state.user_state = ui::UserState::Active;
-                    agent_state.last_user_activity_time_stamp = time_stamp;
-                    let _ = window_tx.send(ui::UIEvent::UserState {
-                        state: agent_state.user_state,
+                Err(e) => {
+                    tracing::error!("Command failed: {}", e);
+                    let _ = channels.window_tx.send(events::UIEvent::Error {
+                        message: e.to_string(),
                     });
-                    let _ = tray_tx.send(ui::UIEvent::UserState {
-                        state: agent_state.user_state,
-                    });
-
-                    if agent_state.task_in_progress {
-                        agent_state.stop_watch.start();
-                    }
                 }
             }
         }
 
-        let idle_after = agent_state.last_user_activity_time_stamp
-            + chrono::Duration::seconds(settings.active_timeout_seconds.try_into().unwrap());
-        let now = chrono::Utc::now();
+        // A timeout too large to represent means the user never goes idle.
+        let idle_after = i64::try_from(settings.active_timeout_seconds)
+            .ok()
+            .and_then(chrono::Duration::try_seconds)
+            .and_then(|timeout| {
+                agent_state
+                    .last_user_activity_time_stamp
+                    .checked_add_signed(timeout)
+            });
+        let now = agent_state.clock.now();
 
-        if agent_state.user_state == ui::UserState::Active {
-            if now >= idle_after {
-                agent_state.user_state = ui::UserState::Idle;
-                let _ = window_tx.send(ui::UIEvent::UserState {
+        if now >= agent_state.next_backup_check {
+            agent_state.next_backup_check = now + BACKUP_CHECK_INTERVAL;
+            if !agent_state.backups_paused
+                && let Err(e) = storage::backup::back_up_if_due(
+                    agent_state.repository.as_ref(),
+                    &settings,
+                    now.with_timezone(&chrono::Local).date_naive(),
+                )
+            {
+                tracing::error!("Scheduled backup failed: {}", e);
+                let _ = channels.window_tx.send(events::UIEvent::Error {
+                    message: format!("Couldn't back up the database: {}", e),
+                });
+            }
+        }
+
+        if agent_state.user_state == events::UserState::Active {
+            if idle_after.is_some_and(|idle_after| now >= idle_after) {
+                agent_state.user_state = events::UserState::Idle;
+                // Idleness began with the last input, so the timeout waited
+                // out since isn't work.
+                let idle_for = now - agent_state.last_user_activity_time_stamp;
+                agent_state
+                    .stop_watch
+                    .stop_ago(idle_for.to_std().unwrap_or_default());
+                if let Some(pomodoro) = &mut agent_state.pomodoro {
+                    pomodoro.pause();
+                }
+                tracing::debug!("User went idle");
+                let _ = channels.window_tx.send(events::UIEvent::UserState {
                     state: agent_state.user_state,
                 });
-                let _ = tray_tx.send(ui::UIEvent::UserState {
+                let _ = channels.tray_tx.send(events::UIEvent::UserState {
                     state: agent_state.user_state,
                 });
-                agent_state.stop_watch.stop();
-                let _ = window_tx.send(ui::UIEvent::Repaint { time_out: 0 });
+                let _ = channels
+                    .window_tx
+                    .send(events::UIEvent::Repaint { time_out: 0 });
+                if agent_state.task_in_progress {
+                    notify(
+                        &settings,
+                        &channels,
+                        Category::Idle,
+                        "Time tracking paused".to_string(),
+                        format!(
+                            "{} is paused until you're back.",
+                            running_task_label(&agent_state)
+                        ),
+                    );
+                }
             };
         }
+
+        run_pomodoro(&mut agent_state, &settings, &channels);
+        check_breaks(&mut agent_state, &settings, &channels, now);
+        report_goals(&mut agent_state, &settings, &channels, now);
+
+        if agent_state.task_in_progress
+            && let Err(e) = apply_window_rules(&mut agent_state, &settings, &channels, now)
+        {
+            tracing::error!("Applying a window rule failed: {}", e);
+            let _ = channels.window_tx.send(events::UIEvent::Error {
+                message: format!("Couldn't switch tasks: {}", e),
+            });
+        }
+
+        if agent_state.task_in_progress {
+            check_budget(&mut agent_state, &settings, &channels);
+            check_work_limits(&mut agent_state, &settings, &channels);
+            check_forgotten_timer(&mut agent_state, &settings, &channels, now);
+            if !agent_state.auto_stop_failed
+                && let Err(e) = apply_auto_stop(&mut agent_state, &settings, &channels, now)
+            {
+                agent_state.auto_stop_failed = true;
+                tracing::error!("Stopping the session automatically failed: {}", e);
+                let _ = channels.window_tx.send(events::UIEvent::Error {
+                    message: format!("Couldn't stop the session automatically: {}", e),
+                });
+            }
+        }
     }
 }
+
+fn handle_command(
+    command: AgentCommand,
+    agent_state: &mut AgentState,
+    settings: &mut Arc<config::settings::Settings>,
+    channels: &Channels,
+) -> crate::Result<()> {
+    match command {
+        AgentCommand::StartSession { id } => start_session(agent_state, settings, id)?,
+        AgentCommand::EndSession { comment, billable } => {
+            agent_state.session.s_billable = billable;
+            end_session(agent_state, settings, comment)?;
+            send_tracked_time(agent_state, channels)?;
+        }
+        AgentCommand::AddTask { task } => {
+            storage::retry_busy(|| agent_state.repository.add_task(&task))?;
+            send_task_list(agent_state, channels)?;
+        }
+        AgentCommand::UpdateTask { task } => {
+            storage::retry_busy(|| agent_state.repository.update_task(&task))?;
+            if agent_state.task_in_progress && agent_state.session.s_task == task.t_id {
+                load_budget(agent_state)?;
+            }
+            send_task_list(agent_state, channels)?;
+        }
+        AgentCommand::DeleteTask { id } => {
+            storage::retry_busy(|| agent_state.repository.delete_task(id))?;
+            send_task_list(agent_state, channels)?;
+        }
+        AgentCommand::RequestTaskList => send_task_list(agent_state, channels)?,
+        AgentCommand::RequestConflicts => send_conflicts(agent_state, channels)?,
+        AgentCommand::ResolveConflict { id, keep_local } => {
+            storage::retry_busy(|| agent_state.repository.resolve_conflict(id, keep_local))?;
+            send_conflicts(agent_state, channels)?;
+            send_task_list(agent_state, channels)?;
+        }
+        AgentCommand::RequestBackups => send_backups(settings, channels)?,
+        AgentCommand::BackupNow => {
+            let dir = Path::new(&settings.backup_path);
+            let today = agent_state.clock.now().with_timezone(&chrono::Local);
+            storage::backup::back_up(agent_state.repository.as_ref(), dir, today.date_naive())?;
+            storage::backup::prune_backups(
+                dir,
+                settings.backup_keep_daily,
+                settings.backup_keep_weekly,
+            )?;
+            send_backups(settings, channels)?;
+        }
+        AgentCommand::RestoreBackup { path } => {
+            // Saved first, so the session ends up in the copy taken before
+            // the restore.
+            if agent_state.task_in_progress {
+                end_session(
+                    agent_state,
+                    settings,
+                    "Session closed by restoring a backup".to_string(),
+                )?;
+            }
+
+            storage::backup::restore(
+                agent_state.repository.as_mut(),
+                settings,
+                &path,
+                agent_state.clock.now(),
+            )?;
+            check_integrity(agent_state, channels);
+            send_backups(settings, channels)?;
+            send_conflicts(agent_state, channels)?;
+            send_task_list(agent_state, channels)?;
+        }
+        AgentCommand::Import {
+            path,
+            format,
+            dry_run,
+        } => {
+            let export = import::read_export(format, &path)?;
+            let report = import::import(
+                agent_state.repository.as_mut(),
+                &settings.uid,
+                export,
+                dry_run,
+            )?;
+            let _ = channels
+                .window_tx
+                .send(events::UIEvent::ImportReport { report });
+            if !dry_run {
+                send_task_list(agent_state, channels)?;
+            }
+        }
+        AgentCommand::ExportCalendar { path, from, to } => {
+            let calendar =
+                calendar::export(agent_state.repository.as_ref(), &settings.uid, from, to)?;
+            std::fs::write(&path, calendar)?;
+            tracing::info!(path = %path.display(), "Exported calendar");
+            let _ = channels.window_tx.send(events::UIEvent::Exported { path });
+        }
+        AgentCommand::ExportTimesheet { path, user, date } => {
+            let user = user.unwrap_or_else(|| settings.uid.clone());
+            report::save_timesheet(agent_state.repository.as_ref(), &user, date, &path)?;
+            let _ = channels.window_tx.send(events::UIEvent::Exported { path });
+        }
+        AgentCommand::RequestUsers => {
+            let users = storage::retry_busy(|| agent_state.repository.get_all_users())?;
+            let _ = channels.window_tx.send(events::UIEvent::Users { users });
+        }
+        AgentCommand::RequestCompliance { weeks } => {
+            let today = agent_state
+                .clock
+                .now()
+                .with_timezone(&chrono::Local)
+                .date_naive();
+            let compliance = storage::retry_busy(|| {
+                report::compliance::compliance(
+                    agent_state.repository.as_ref(),
+                    &settings.uid,
+                    report::compliance::WorkLimits::from_settings(settings),
+                    today,
+                    weeks,
+                )
+            })?;
+            let _ = channels
+                .window_tx
+                .send(events::UIEvent::Compliance { compliance });
+        }
+        AgentCommand::RequestGoalHistory { periods } => {
+            let today = agent_state
+                .clock
+                .now()
+                .with_timezone(&chrono::Local)
+                .date_naive();
+            let tasks = storage::retry_busy(|| agent_state.repository.get_all_tasks())?;
+            let goals = report::goals::goals(settings, &tasks);
+            let history = storage::retry_busy(|| {
+                report::goals::history(
+                    agent_state.repository.as_ref(),
+                    &settings.uid,
+                    &goals,
+                    today,
+                    periods,
+                )
+            })?;
+            let _ = channels
+                .window_tx
+                .send(events::UIEvent::GoalHistory { history });
+        }
+        AgentCommand::StartPomodoro => {
+            if !agent_state.task_in_progress {
+                tracing::warn!("Focus mode needs a running session");
+                return Ok(());
+            }
+            tracing::info!(task = agent_state.session.s_task, "Starting focus mode");
+            let mut pomodoro = Pomodoro::start(
+                PomodoroPlan::from_settings(settings),
+                agent_state.clock.clone(),
+            );
+            if agent_state.user_state == events::UserState::Idle {
+                pomodoro.pause();
+            }
+            agent_state.pomodoro = Some(pomodoro);
+        }
+        AgentCommand::StopPomodoro => {
+            tracing::info!("Stopping focus mode");
+            let was_on_break = on_break(agent_state);
+            agent_state.pomodoro = None;
+            if was_on_break && agent_state.user_state == events::UserState::Active {
+                agent_state.stop_watch.start();
+            }
+        }
+        AgentCommand::RequestBilling => send_billing(agent_state, channels)?,
+        AgentCommand::AddClient { client } => {
+            storage::retry_busy(|| agent_state.repository.add_client(&client))?;
+            send_billing(agent_state, channels)?;
+        }
+        AgentCommand::UpdateClient { client } => {
+            storage::retry_busy(|| agent_state.repository.update_client(&client))?;
+            send_billing(agent_state, channels)?;
+        }
+        AgentCommand::AddRate { rate } => {
+            storage::retry_busy(|| agent_state.repository.add_rate(&rate))?;
+            send_billing(agent_state, channels)?;
+        }
+        AgentCommand::DeleteRate { id } => {
+            storage::retry_busy(|| agent_state.repository.delete_rate(id))?;
+            send_billing(agent_state, channels)?;
+        }
+        AgentCommand::RequestWindowRules => send_window_rules(agent_state, channels)?,
+        AgentCommand::AddWindowRule { rule } => {
+            if let Err(message) = agent::window_rules::check_pattern(&rule.wr_pattern) {
+                let _ = channels.window_tx.send(events::UIEvent::Error { message });
+                return Ok(());
+            }
+            storage::retry_busy(|| agent_state.repository.add_window_rule(&rule))?;
+            agent_state.window_rules = None;
+            send_window_rules(agent_state, channels)?;
+        }
+        AgentCommand::DeleteWindowRule { id } => {
+            storage::retry_busy(|| agent_state.repository.delete_window_rule(id))?;
+            agent_state.window_rules = None;
+            send_window_rules(agent_state, channels)?;
+        }
+        AgentCommand::ActiveWindow { window, time_stamp } => {
+            agent_state.focus = window.map(|window| Focus {
+                window,
+                since: time_stamp,
+            });
+        }
+        AgentCommand::AcceptSuggestion { comment, billable } => {
+            let Some(rule) = agent_state.suggestion.take() else {
+                return Ok(());
+            };
+            agent_state.session.s_billable = billable;
+            switch_session(agent_state, settings, channels, &rule, comment)?;
+        }
+        AgentCommand::RequestInvoiceDraft { client, from, to } => {
+            let draft = billing::invoice_draft(agent_state.repository.as_ref(), client, from, to)?;
+            let _ = channels
+                .window_tx
+                .send(events::UIEvent::InvoiceDraft { draft });
+        }
+        AgentCommand::ExportInvoiceDraft {
+            path,
+            client,
+            from,
+            to,
+        } => {
+            billing::save_invoice_draft(agent_state.repository.as_ref(), client, from, to, &path)?;
+            let _ = channels.window_tx.send(events::UIEvent::Exported { path });
+        }
+        AgentCommand::CalendarFeed { reply } => {
+            // Not reported to the user: calendar apps poll the feed in the
+            // background and try again later.
+            let today = agent_state
+                .clock
+                .now()
+                .with_timezone(&chrono::Local)
+                .date_naive();
+            let from = today - chrono::Days::new(settings.calendar_feed_days.into());
+            match calendar::export(agent_state.repository.as_ref(), &settings.uid, from, today) {
+                Ok(calendar) => {
+                    let _ = reply.send(calendar);
+                }
+                Err(e) => tracing::warn!("Couldn't serve the calendar feed: {}", e),
+            }
+        }
+        AgentCommand::RequestElapsedTime => {
+            let _ = channels.window_tx.send(events::UIEvent::ElapsedTime {
+                elapsed: agent_state.stop_watch.elapsed(),
+            });
+        }
+        AgentCommand::SwitchProfile { name } => {
+            let Some(name) = config::Profiles::validate_name(&name) else {
+                return Ok(());
+            };
+            let mut profiles = config::Profiles::load();
+            if name == settings.profile || !profiles.set_active(&name) {
+                return Ok(());
+            }
+
+            tracing::info!(from = %settings.profile, to = %name, "Switching profile");
+            // Open the new profile first so a failure leaves the current one running.
+            let new_settings = Arc::new(config::Settings::load_profile(&name));
+            let repository =
+                open_repository_or_locked(&new_settings, &agent_state.clock, channels)?;
+            profiles.save()?;
+
+            if agent_state.task_in_progress {
+                end_session(
+                    agent_state,
+                    settings,
+                    format!("Session closed by switching to profile '{}'", name),
+                )?;
+            }
+
+            *settings = new_settings;
+            *agent_state = AgentState::new(repository, agent_state.clock.clone());
+            check_integrity(agent_state, channels);
+            let _ = channels.sync_tx.send(sync::SyncCommand::Reconfigure {
+                settings: settings.clone(),
+            });
+
+            send_profiles(channels, &profiles);
+            send_task_list(agent_state, channels)?;
+        }
+        AgentCommand::AddProfile { name } => {
+            let Some(name) = config::Profiles::validate_name(&name) else {
+                return Ok(());
+            };
+            let mut profiles = config::Profiles::load();
+            if profiles.add(&name) {
+                tracing::info!(profile = %name, "Adding profile");
+                profiles.save()?;
+                send_profiles(channels, &profiles);
+            }
+        }
+        AgentCommand::Unlock {
+            passphrase,
+            remember,
+        } => {
+            if !agent_state.repository.is_locked() {
+                return Ok(());
+            }
+
+            let unlocked_settings = Arc::new(config::Settings {
+                database_passphrase: Some(passphrase.clone()),
+                ..(**settings).clone()
+            });
+            let repository = match storage::retry_busy(|| {
+                storage::open_repository(unlocked_settings.clone(), agent_state.clock.clone())
+            }) {
+                Err(e @ storage::StorageError::WrongPassphrase) => {
+                    tracing::warn!("Unlocking the database failed: {}", e);
+                    send_locked(settings, channels, Some(e.to_string()));
+                    return Ok(());
+                }
+                result => result?,
+            };
+            tracing::info!("Unlocked the database");
+
+            *settings = unlocked_settings;
+            *agent_state = AgentState::new(repository, agent_state.clock.clone());
+            check_integrity(agent_state, channels);
+            let _ = channels.sync_tx.send(sync::SyncCommand::Reconfigure {
+                settings: settings.clone(),
+            });
+            let _ = channels.window_tx.send(events::UIEvent::Unlocked);
+            send_conflicts(agent_state, channels)?;
+            send_task_list(agent_state, channels)?;
+
+            if remember {
+                storage::encryption::remember_passphrase(settings, &passphrase)?;
+            }
+        }
+        AgentCommand::Quit => (),
+        AgentCommand::Shutdown => {
+            if settings.auto_stop_on_shutdown && agent_state.task_in_progress {
+                auto_stop(
+                    agent_state,
+                    settings,
+                    channels,
+                    "Stopped automatically because the system shut down".to_string(),
+                )?;
+            }
+        }
+        AgentCommand::ShowUI => {
+            let _ = channels.ui_control_tx.send(events::UIControl::Show);
+        }
+        AgentCommand::SyncNow => {
+            let _ = channels.sync_tx.send(sync::SyncCommand::SyncNow);
+        }
+        AgentCommand::UserActivity { time_stamp } => {
+            if agent_state.user_state == events::UserState::Idle {
+                tracing::debug!("User is back");
+            }
+            agent_state.user_state = events::UserState::Active;
+            agent_state.last_user_activity_time_stamp = time_stamp;
+            agent_state.breaks.activity(
+                time_stamp,
+                &report::compliance::WorkLimits::from_settings(settings),
+            );
+            let _ = channels.window_tx.send(events::UIEvent::UserState {
+                state: agent_state.user_state,
+            });
+            let _ = channels.tray_tx.send(events::UIEvent::UserState {
+                state: agent_state.user_state,
+            });
+
+            if let Some(pomodoro) = &mut agent_state.pomodoro {
+                pomodoro.resume();
+            }
+            if agent_state.task_in_progress && !on_break(agent_state) {
+                agent_state.stop_watch.start();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Opens the configured backend. If that fails the agent keeps running on an
+/// in-memory store, so the UI stays usable and the user is told what happened.
+/// The flag tells whether the fallback is in use.
+fn open_repository_or_memory(
+    settings: &Arc<config::settings::Settings>,
+    clock: &Arc<dyn agent::Clock>,
+    channels: &Channels,
+) -> (Box<dyn storage::Repository>, bool) {
+    match open_repository_or_locked(settings, clock, channels) {
+        Ok(repository) => (repository, false),
+        Err(e) => {
+            tracing::error!("Couldn't open the database, falling back to memory: {}", e);
+            let _ = channels.window_tx.send(events::UIEvent::Error {
+                message: format!(
+                    "Couldn't open the database: {}. Changes won't be saved until the app is restarted.",
+                    e
+                ),
+            });
+            (
+                Box::new(storage::MemoryRepository::new(&settings.uid, clock.clone())),
+                true,
+            )
+        }
+    }
+}
+
+/// Opens the configured backend. An encrypted database whose passphrase isn't
+/// known yet opens locked, and the user is asked for the passphrase.
+fn open_repository_or_locked(
+    settings: &Arc<config::settings::Settings>,
+    clock: &Arc<dyn agent::Clock>,
+    channels: &Channels,
+) -> storage::Result<Box<dyn storage::Repository>> {
+    match storage::retry_busy(|| storage::open_repository(settings.clone(), clock.clone())) {
+        Err(e @ (storage::StorageError::Locked | storage::StorageError::WrongPassphrase)) => {
+            tracing::info!("The database is locked: {}", e);
+            let error = match e {
+                storage::StorageError::WrongPassphrase => Some(e.to_string()),
+                _ => None,
+            };
+            send_locked(settings, channels, error);
+            Ok(Box::new(storage::LockedRepository))
+        }
+        result => result,
+    }
+}
+
+fn send_locked(settings: &config::Settings, channels: &Channels, error: Option<String>) {
+    let _ = channels.window_tx.send(events::UIEvent::Locked {
+        new_passphrase: storage::encryption::needs_new_passphrase(settings),
+        error,
+    });
+}
+
+/// Runs the backend's integrity check and warns the user about damage before
+/// it spreads into backups.
+fn check_integrity(agent_state: &mut AgentState, channels: &Channels) {
+    // Checked once the passphrase is entered; until then there is nothing to
+    // back up.
+    if agent_state.repository.is_locked() {
+        agent_state.backups_paused = true;
+        return;
+    }
+    let problems = match agent_state.repository.integrity_check() {
+        Ok(problems) => problems,
+        Err(e) => vec![e.to_string()],
+    };
+    agent_state.backups_paused = !problems.is_empty();
+    if agent_state.backups_paused {
+        tracing::error!(?problems, "Database integrity check failed");
+        let _ = channels.window_tx.send(events::UIEvent::Error {
+            message: format!(
+                "The database is damaged: {}. Backups are paused; restore one from File → Backups.",
+                problems.join("; ")
+            ),
+        });
+    }
+}
+
+/// Starts a session on task `id`. Window rules only apply to windows focused
+/// after this.
+fn start_session(
+    agent_state: &mut AgentState,
+    settings: &config::Settings,
+    id: i64,
+) -> crate::Result<()> {
+    tracing::info!(task = id, "Starting session");
+    agent_state.task_in_progress = true;
+    agent_state.session = agent::sessions::Session::default();
+    agent_state.stop_watch.start();
+    agent_state.session.s_task = id;
+    agent_state.session_started_at = agent_state.clock.now();
+    agent_state.forgotten_timer_notified = false;
+    agent_state.auto_stop_at = end_of_workday(settings, agent_state.session_started_at);
+    agent_state.auto_stop_failed = false;
+    agent_state.pomodoro = None;
+    agent_state.focus = None;
+    agent_state.suggestion = None;
+    load_budget(agent_state)?;
+    load_work_time(agent_state, settings)
+}
+
+/// Saves the running session. If saving fails the session stays in memory so
+/// the user can try to end it again.
+fn end_session(
+    agent_state: &mut AgentState,
+    settings: &config::Settings,
+    comment: String,
+) -> crate::Result<()> {
+    agent_state.stop_watch.stop();
+    agent_state.session.s_user = settings.uid.clone();
+    agent_state.session.s_comment = comment;
+    agent_state.session.s_duration = agent_state.stop_watch.elapsed().as_secs();
+    storage::retry_busy(|| agent_state.repository.save_session(&agent_state.session))?;
+    tracing::info!(
+        task = agent_state.session.s_task,
+        seconds = agent_state.session.s_duration,
+        "Saved session"
+    );
+
+    agent_state.task_in_progress = false;
+    agent_state.budget = None;
+    agent_state.auto_stop_at = None;
+    agent_state.pomodoro = None;
+    agent_state.work_time = None;
+    agent_state.goals_date = None;
+    agent_state.suggestion = None;
+    agent_state.stop_watch.reset();
+    Ok(())
+}
+
+/// Saves the running session with `comment` and starts one on `rule`'s task,
+/// recording the rule on it.
+fn switch_session(
+    agent_state: &mut AgentState,
+    settings: &config::Settings,
+    channels: &Channels,
+    rule: &WindowRule,
+    comment: String,
+) -> crate::Result<()> {
+    end_session(agent_state, settings, comment)?;
+    start_session(agent_state, settings, rule.wr_task)?;
+    agent_state.session.s_rule = Some(rule.wr_id);
+    let _ = channels
+        .window_tx
+        .send(events::UIEvent::SessionSwitched { task: rule.wr_task });
+    send_tracked_time(agent_state, channels)
+}
+
+/// Once a window has kept the focus for `window_rule_delay_seconds`, switches
+/// the running session to the task of the first rule matching it, or
+/// suggests doing so. Each focused window is looked at once.
+fn apply_window_rules(
+    agent_state: &mut AgentState,
+    settings: &config::Settings,
+    channels: &Channels,
+    now: chrono::DateTime<chrono::Utc>,
+) -> crate::Result<()> {
+    let delay = chrono::Duration::seconds(settings.window_rule_delay_seconds.into());
+    if agent_state.user_state == events::UserState::Idle
+        || agent_state
+            .focus
+            .as_ref()
+            .is_none_or(|focus| now - focus.since < delay)
+    {
+        return Ok(());
+    }
+    let Some(focus) = agent_state.focus.take() else {
+        return Ok(());
+    };
+
+    if agent_state.window_rules.is_none() {
+        let rules = storage::retry_busy(|| agent_state.repository.get_all_window_rules())?;
+        agent_state.window_rules = Some(RuleSet::new(rules));
+    }
+    let Some(rule) = agent_state
+        .window_rules
+        .as_ref()
+        .and_then(|rules| rules.matching(&focus.window))
+        .cloned()
+    else {
+        return Ok(());
+    };
+    if rule.wr_task == agent_state.session.s_task {
+        return Ok(());
+    }
+    let Some(task) = storage::retry_busy(|| agent_state.repository.get_task(rule.wr_task))?
+        .filter(|task| !task.t_deleted)
+    else {
+        return Ok(());
+    };
+
+    tracing::info!(
+        rule = rule.wr_id,
+        task = task.t_id,
+        action = %rule.wr_action,
+        "Window rule matched"
+    );
+    match rule.wr_action {
+        RuleAction::Switch => {
+            let reason = format!(
+                "Switched to '{}' by the window rule '{}'",
+                task.t_name, rule.wr_pattern
+            );
+            switch_session(agent_state, settings, channels, &rule, reason)?;
+            notify(
+                settings,
+                channels,
+                Category::TaskSwitch,
+                "Switched task".to_string(),
+                format!("Now tracking '{}'.", task.t_name),
+            );
+        }
+        RuleAction::Suggest => {
+            agent_state.suggestion = Some(rule.clone());
+            notify(
+                settings,
+                channels,
+                Category::TaskSwitch,
+                "Switch task?".to_string(),
+                format!(
+                    "The window '{}' looks like work on '{}'.",
+                    focus.window.title, task.t_name
+                ),
+            );
+            let _ = channels
+                .window_tx
+                .send(events::UIEvent::TaskSuggestion { task, rule });
+        }
+    }
+    Ok(())
+}
+
+/// Reads the running session's task's budget. Warnings the time tracked
+/// before the session already called for aren't repeated.
+fn load_budget(agent_state: &mut AgentState) -> crate::Result<()> {
+    let id = agent_state.session.s_task;
+    let task = storage::retry_busy(|| agent_state.repository.get_task(id))?;
+    agent_state.budget = match task {
+        Some(agent::Task {
+            t_name,
+            t_budget: Some(seconds),
+            ..
+        }) if seconds > 0 => {
+            let tracked = storage::retry_busy(|| agent_state.repository.get_tracked_seconds())?;
+            let mut budget = Budget {
+                task: t_name,
+                seconds,
+                tracked_before: tracked.get(&id).copied().unwrap_or(0),
+                warned: 0,
+            };
+            budget.warned = budget.reached(0);
+            Some(budget)
+        }
+        _ => None,
+    };
+    Ok(())
+}
+
+/// Warns once for each share of the budget the running session uses up.
+fn check_budget(agent_state: &mut AgentState, settings: &config::Settings, channels: &Channels) {
+    let elapsed = agent_state.stop_watch.elapsed().as_secs();
+    let Some(budget) = &mut agent_state.budget else {
+        return;
+    };
+    let reached = budget.reached(elapsed);
+    if reached > budget.warned {
+        budget.warned = reached;
+        tracing::info!(task = %budget.task, percent = reached, "Budget warning");
+        let _ = channels.window_tx.send(events::UIEvent::BudgetWarning {
+            task: budget.task.clone(),
+            percent: reached,
+            budget: budget.seconds,
+        });
+        notify(
+            settings,
+            channels,
+            Category::Budget,
+            format!("{}% of the budget used", reached),
+            format!("'{}' has used {}% of its budget.", budget.task, reached),
+        );
+    }
+}
+
+fn load_work_time(agent_state: &mut AgentState, settings: &config::Settings) -> crate::Result<()> {
+    let date = agent_state
+        .session_started_at
+        .with_timezone(&chrono::Local)
+        .date_naive();
+    let (today_before, week_before) = storage::retry_busy(|| {
+        report::compliance::worked(agent_state.repository.as_ref(), &settings.uid, date)
+    })?;
+    agent_state.work_time = Some(WorkTime {
+        date,
+        today_before,
+        week_before,
+    });
+    Ok(())
+}
+
+/// Warns once a day and once a week when the hours worked, counting the
+/// running session, pass their limits.
+fn check_work_limits(
+    agent_state: &mut AgentState,
+    settings: &config::Settings,
+    channels: &Channels,
+) {
+    let Some(work_time) = &agent_state.work_time else {
+        return;
+    };
+    let limits = report::compliance::WorkLimits::from_settings(settings);
+    let elapsed = agent_state.stop_watch.elapsed().as_secs();
+    let week = report::week_start(work_time.date);
+    let checks = [
+        (
+            false,
+            work_time.date,
+            work_time.today_before + elapsed,
+            limits.daily,
+        ),
+        (true, week, work_time.week_before + elapsed, limits.weekly),
+    ];
+    for (weekly, period, worked, limit) in checks {
+        let Some(limit) = limit else {
+            continue;
+        };
+        let warned = match weekly {
+            false => &mut agent_state.daily_limit_warned,
+            true => &mut agent_state.weekly_limit_warned,
+        };
+        if worked < limit || *warned == Some(period) {
+            continue;
+        }
+        *warned = Some(period);
+        tracing::info!(weekly, worked, limit, "Working-time limit passed");
+        let _ = channels.window_tx.send(events::UIEvent::WorkLimitWarning {
+            weekly,
+            worked,
+            limit,
+        });
+        let when = if weekly { "this week" } else { "today" };
+        notify(
+            settings,
+            channels,
+            Category::WorkLimit,
+            format!("Working-time limit reached {}", when),
+            format!(
+                "You've worked {} {}; the limit is {}.",
+                report::format_hours(worked),
+                when,
+                report::format_hours(limit)
+            ),
+        );
+    }
+}
+
+/// Sends the progress towards the goals, counting the running session, as
+/// it changes minute by minute. The goals are loaded again on a new day.
+fn report_goals(
+    agent_state: &mut AgentState,
+    settings: &config::Settings,
+    channels: &Channels,
+    now: chrono::DateTime<chrono::Utc>,
+) {
+    let today = now.with_timezone(&chrono::Local).date_naive();
+    if agent_state.goals_date != Some(today) {
+        if agent_state.repository.is_locked() {
+            return;
+        }
+        agent_state.goals_date = Some(today);
+        agent_state.goals = match load_goals(agent_state, settings, today) {
+            Ok(goals) => goals,
+            Err(e) => {
+                tracing::warn!("Couldn't load the goals: {}", e);
+                Vec::new()
+            }
+        };
+    }
+
+    let elapsed = if agent_state.task_in_progress {
+        agent_state.stop_watch.elapsed().as_secs()
+    } else {
+        0
+    };
+    let task = agent_state.session.s_task;
+    let worked = |before: &GoalProgress| {
+        let running = before.goal.task.is_none_or(|id| id == task);
+        (before.worked + if running { elapsed } else { 0 }) / 60 * 60
+    };
+    // Checked on every pass of the loop, so only allocates once it changed.
+    let changed = agent_state.goals.len() != agent_state.goals_reported.len()
+        || agent_state
+            .goals
+            .iter()
+            .zip(&agent_state.goals_reported)
+            .any(|(before, reported)| {
+                before.goal != reported.goal || worked(before) != reported.worked
+            });
+    if !changed {
+        return;
+    }
+
+    let progress: Vec<GoalProgress> = agent_state
+        .goals
+        .iter()
+        .map(|before| GoalProgress {
+            goal: before.goal.clone(),
+            worked: worked(before),
+        })
+        .collect();
+    agent_state.goals_reported = progress.clone();
+    let _ = channels.window_tx.send(events::UIEvent::Goals {
+        progress: progress.clone(),
+    });
+    let _ = channels.tray_tx.send(events::UIEvent::Goals { progress });
+}
+
+fn load_goals(
+    agent_state: &AgentState,
+    settings: &config::Settings,
+    today: chrono::NaiveDate,
+) -> crate::Result<Vec<GoalProgress>> {
+    let tasks = storage::retry_busy(|| agent_state.repository.get_all_tasks())?;
+    let goals = report::goals::goals(settings, &tasks);
+    Ok(storage::retry_busy(|| {
+        report::goals::progress(
+            agent_state.repository.as_ref(),
+            &settings.uid,
+            &goals,
+            today,
+        )
+    })?)
+}
+
+/// Reminds the user to step away after a long stretch of activity.
+fn check_breaks(
+    agent_state: &mut AgentState,
+    settings: &config::Settings,
+    channels: &Channels,
+    now: chrono::DateTime<chrono::Utc>,
+) {
+    let limits = report::compliance::WorkLimits::from_settings(settings);
+    if let Some(stretch) = agent_state.breaks.reminder_due(now, &limits) {
+        tracing::info!(minutes = stretch.as_secs() / 60, "Break reminder");
+        notify(
+            settings,
+            channels,
+            Category::Break,
+            "Time for a break".to_string(),
+            format!(
+                "You've worked for {} without a break.",
+                report::format_hours(stretch.as_secs())
+            ),
+        );
+    }
+}
+
+/// Reminds the user, once per session, of a session that has been running
+/// for longer than a working day.
+fn check_forgotten_timer(
+    agent_state: &mut AgentState,
+    settings: &config::Settings,
+    channels: &Channels,
+    now: chrono::DateTime<chrono::Utc>,
+) {
+    let after = chrono::Duration::hours(settings.forgotten_timer_hours.into());
+    if agent_state.forgotten_timer_notified || now - agent_state.session_started_at < after {
+        return;
+    }
+    agent_state.forgotten_timer_notified = true;
+    notify(
+        settings,
+        channels,
+        Category::ForgottenTimer,
+        "Still tracking?".to_string(),
+        format!(
+            "{} has been running for {} hours.",
+            running_task_label(agent_state),
+            settings.forgotten_timer_hours
+        ),
+    );
+}
+
+/// The first time `settings.auto_stop_at` comes around after `started`, or
+/// `None` if no end of the workday is set.
+fn end_of_workday(
+    settings: &config::Settings,
+    started: chrono::DateTime<chrono::Utc>,
+) -> Option<chrono::DateTime<chrono::Utc>> {
+    if settings.auto_stop_at.is_empty() {
+        return None;
+    }
+    let Ok(time) = chrono::NaiveTime::parse_from_str(&settings.auto_stop_at, "%H:%M") else {
+        tracing::warn!(value = %settings.auto_stop_at, "Ignoring invalid auto_stop_at");
+        return None;
+    };
+    let day = started.with_timezone(&chrono::Local).date_naive();
+    // A day without that time, skipped by a DST change, is passed over.
+    (0..3)
+        .filter_map(|days| {
+            (day + chrono::Days::new(days))
+                .and_time(time)
+                .and_local_timezone(chrono::Local)
+                .earliest()
+        })
+        .map(|at| at.with_timezone(&chrono::Utc))
+        .find(|at| *at > started)
+}
+
+/// Stops the running session once it has been idle for
+/// `auto_stop_idle_hours` or the workday is over.
+fn apply_auto_stop(
+    agent_state: &mut AgentState,
+    settings: &config::Settings,
+    channels: &Channels,
+    now: chrono::DateTime<chrono::Utc>,
+) -> crate::Result<()> {
+    let idle_for = now - agent_state.last_user_activity_time_stamp;
+    let reason = if settings.auto_stop_idle_hours > 0
+        && agent_state.user_state == events::UserState::Idle
+        && idle_for >= chrono::Duration::hours(settings.auto_stop_idle_hours.into())
+    {
+        // The session ended with the user's last input, not now.
+        agent_state.session.s_created_at =
+            agent_state.last_user_activity_time_stamp.timestamp_millis();
+        format!(
+            "Stopped automatically after {} hours without activity",
+            settings.auto_stop_idle_hours
+        )
+    } else if agent_state.auto_stop_at.is_some_and(|at| now >= at) {
+        format!(
+            "Stopped automatically at the end of the workday ({})",
+            settings.auto_stop_at
+        )
+    } else {
+        return Ok(());
+    };
+    let task = running_task_label(agent_state);
+    if let Err(e) = auto_stop(agent_state, settings, channels, reason.clone()) {
+        // The session keeps running, so it's stamped when it does end.
+        agent_state.session.s_created_at = 0;
+        return Err(e);
+    }
+    notify(
+        settings,
+        channels,
+        Category::ForgottenTimer,
+        "Session stopped".to_string(),
+        format!("{}: {}.", task, reason),
+    );
+    Ok(())
+}
+
+/// Saves the running session with `reason` as its comment and tells the
+/// front ends it's over.
+fn auto_stop(
+    agent_state: &mut AgentState,
+    settings: &config::Settings,
+    channels: &Channels,
+    reason: String,
+) -> crate::Result<()> {
+    tracing::info!(task = agent_state.session.s_task, %reason, "Stopping session");
+    end_session(agent_state, settings, reason.clone())?;
+    let _ = channels.window_tx.send(events::UIEvent::SessionStopped {
+        reason: reason.clone(),
+    });
+    let _ = channels
+        .tray_tx
+        .send(events::UIEvent::SessionStopped { reason });
+    send_tracked_time(agent_state, channels)
+}
+
+/// Whether focus mode is on a break, during which the session is paused.
+fn on_break(agent_state: &AgentState) -> bool {
+    agent_state
+        .pomodoro
+        .as_ref()
+        .is_some_and(|pomodoro| pomodoro.phase() != Phase::Focus)
+}
+
+/// Moves focus mode on to its next phase when one ends, counting completed
+/// focus periods on the session and pausing it during breaks, and tells the
+/// front ends when the phase or the minutes left change.
+fn run_pomodoro(agent_state: &mut AgentState, settings: &config::Settings, channels: &Channels) {
+    if let Some(pomodoro) = &mut agent_state.pomodoro
+        && let Some(phase) = pomodoro.advance()
+    {
+        let plan = PomodoroPlan::from_settings(settings);
+        let body = if phase == Phase::Focus {
+            if agent_state.user_state == events::UserState::Active {
+                agent_state.stop_watch.start();
+            } else {
+                pomodoro.pause();
+            }
+            format!("Back to work for {} minutes.", plan.focus.as_secs() / 60)
+        } else {
+            agent_state.session.s_pomodoros += 1;
+            agent_state.stop_watch.stop();
+            format!(
+                "Pomodoro done. Take {} minutes off.",
+                plan.length(phase).as_secs() / 60
+            )
+        };
+        tracing::info!(?phase, "Focus mode moved on");
+        notify(
+            settings,
+            channels,
+            Category::Pomodoro,
+            phase.label().to_string(),
+            body,
+        );
+    }
+
+    let status = agent_state
+        .pomodoro
+        .as_mut()
+        .map(|pomodoro| pomodoro.status());
+    if status != agent_state.pomodoro_reported {
+        agent_state.pomodoro_reported = status;
+        let _ = channels
+            .window_tx
+            .send(events::UIEvent::Pomodoro { status });
+        let _ = channels.tray_tx.send(events::UIEvent::Pomodoro { status });
+    }
+}
+
+/// The running task's name, quoted, for notifications.
+fn running_task_label(agent_state: &AgentState) -> String {
+    match agent_state.repository.get_task(agent_state.session.s_task) {
+        Ok(Some(task)) => format!("'{}'", task.t_name),
+        _ => "The running task".to_string(),
+    }
+}
+
+/// Shows a desktop notification unless its category is turned off.
+fn notify(
+    settings: &config::Settings,
+    channels: &Channels,
+    category: Category,
+    summary: String,
+    body: String,
+) {
+    if !category.enabled(settings) {
+        return;
+    }
+    tracing::debug!(?category, "Notifying");
+    channels.notifier.notify(&Notification {
+        category,
+        summary,
+        body,
+    });
+}
+
+/// Also has the goals loaded again, as they come with the tasks.
+fn send_task_list(agent_state: &mut AgentState, channels: &Channels) -> crate::Result<()> {
+    agent_state.goals_date = None;
+    let task_list = storage::retry_busy(|| agent_state.repository.get_all_tasks())?;
+    let _ = channels
+        .window_tx
+        .send(events::UIEvent::TaskList { task_list });
+    send_tracked_time(agent_state, channels)
+}
+
+fn send_tracked_time(agent_state: &AgentState, channels: &Channels) -> crate::Result<()> {
+    let tracked = storage::retry_busy(|| agent_state.repository.get_tracked_seconds())?;
+    let _ = channels
+        .window_tx
+        .send(events::UIEvent::TrackedTime { tracked });
+    Ok(())
+}
+
+fn send_billing(agent_state: &AgentState, channels: &Channels) -> crate::Result<()> {
+    let clients = storage::retry_busy(|| agent_state.repository.get_all_clients())?;
+    let rates = storage::retry_busy(|| agent_state.repository.get_all_rates())?;
+    let _ = channels
+        .window_tx
+        .send(events::UIEvent::Billing { clients, rates });
+    Ok(())
+}
+
+fn send_window_rules(agent_state: &AgentState, channels: &Channels) -> crate::Result<()> {
+    let rules = storage::retry_busy(|| agent_state.repository.get_all_window_rules())?;
+    let _ = channels
+        .window_tx
+        .send(events::UIEvent::WindowRules { rules });
+    Ok(())
+}
+
+fn send_conflicts(agent_state: &AgentState, channels: &Channels) -> crate::Result<()> {
+    let conflicts = storage::retry_busy(|| agent_state.repository.get_unresolved_conflicts())?;
+    let _ = channels
+        .window_tx
+        .send(events::UIEvent::Conflicts { conflicts });
+    Ok(())
+}
+
+fn send_backups(settings: &config::Settings, channels: &Channels) -> crate::Result<()> {
+    let backups = storage::backup::list_backups(Path::new(&settings.backup_path))?;
+    let _ = channels
+        .window_tx
+        .send(events::UIEvent::Backups { backups });
+    Ok(())
+}
+
+fn send_profiles(channels: &Channels, profiles: &config::Profiles) {
+    let _ = channels.window_tx.send(events::UIEvent::Profiles {
+        active: profiles.active.clone(),
+        names: profiles.names.clone(),
+    });
+    let _ = channels.tray_tx.send(events::UIEvent::Profiles {
+        active: profiles.active.clone(),
+        names: profiles.names.clone(),
+    });
+}
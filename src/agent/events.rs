@@ -0,0 +1,123 @@
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+use crate::{agent, billing, import, report, storage, sync};
+
+/// Updates sent by the agent to the front ends (window and tray).
+pub enum UIEvent {
+    TaskList {
+        task_list: Vec<agent::tasks::Task>,
+    },
+    /// Total seconds tracked per task, not counting the running session.
+    TrackedTime {
+        tracked: HashMap<i64, u64>,
+    },
+    ElapsedTime {
+        elapsed: Duration,
+    },
+    /// The agent ended the running session by itself; `reason` was saved as
+    /// its comment.
+    SessionStopped {
+        reason: String,
+    },
+    /// The running session was saved and a new one started on `task`, by a
+    /// window rule or because the user accepted its suggestion.
+    SessionSwitched {
+        task: i64,
+    },
+    /// A window rule suggests switching the running session to `task`.
+    TaskSuggestion {
+        task: agent::tasks::Task,
+        rule: agent::window_rules::WindowRule,
+    },
+    /// Focus mode's countdown, or `None` once it's off.
+    Pomodoro {
+        status: Option<agent::pomodoro::PomodoroStatus>,
+    },
+    /// The running session took its task past `percent` of its budget.
+    BudgetWarning {
+        task: String,
+        percent: u8,
+        budget: u64,
+    },
+    /// The hours worked today, or this week if `weekly`, passed their
+    /// limit.
+    WorkLimitWarning {
+        weekly: bool,
+        worked: u64,
+        limit: u64,
+    },
+    UserState {
+        state: UserState,
+    },
+    Repaint {
+        time_out: u64,
+    },
+    Profiles {
+        active: String,
+        names: Vec<String>,
+    },
+    SyncStatus {
+        status: sync::SyncStatus,
+    },
+    Conflicts {
+        conflicts: Vec<storage::Conflict>,
+    },
+    Backups {
+        backups: Vec<storage::backup::Backup>,
+    },
+    Users {
+        users: Vec<agent::users::User>,
+    },
+    Billing {
+        clients: Vec<agent::clients::Client>,
+        rates: Vec<agent::rates::Rate>,
+    },
+    InvoiceDraft {
+        draft: billing::InvoiceDraft,
+    },
+    WindowRules {
+        rules: Vec<agent::window_rules::WindowRule>,
+    },
+    Compliance {
+        compliance: report::compliance::Compliance,
+    },
+    /// Progress towards the goals in their current periods, counting the
+    /// running session. Sent as it changes, at most once a minute.
+    Goals {
+        progress: Vec<report::goals::GoalProgress>,
+    },
+    GoalHistory {
+        history: Vec<report::goals::GoalHistory>,
+    },
+    /// A file was written on request.
+    Exported {
+        path: PathBuf,
+    },
+    ImportReport {
+        report: import::ImportReport,
+    },
+    /// The database is encrypted and waits for its passphrase.
+    Locked {
+        /// No passphrase has been chosen yet.
+        new_passphrase: bool,
+        error: Option<String>,
+    },
+    Unlocked,
+    /// A command failed; the agent keeps running.
+    Error {
+        message: String,
+    },
+    Quit,
+}
+
+/// Requests from the agent to open or close the window.
+pub enum UIControl {
+    Show,
+    Quit,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum UserState {
+    Idle,
+    Active,
+}
@@ -1,16 +1,19 @@
-use std::{sync::mpsc::Sender, thread};
+use std::{
+    sync::{Arc, mpsc::Sender},
+    thread,
+};
 
 use rdev::{Event, listen};
 
 use crate::agent;
 
-pub fn start_input_listener(command_tx: Sender<agent::AgentCommand>) {
+pub fn start_input_listener(command_tx: Sender<agent::AgentCommand>, clock: Arc<dyn agent::Clock>) {
     thread::Builder::new()
         .name("agent-listener".to_string())
         .spawn(move || {
             let _ = listen(move |_event: Event| {
                 let _ = command_tx.send(agent::AgentCommand::UserActivity {
-                    time_stamp: chrono::Utc::now(),
+                    time_stamp: clock.now(),
                 });
             });
         })
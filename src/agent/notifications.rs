@@ -0,0 +1,123 @@
+use std::{
+    sync::{Mutex, mpsc},
+    thread,
+};
+
+use crate::config::Settings;
+
+/// What a notification is about, so each kind can be turned off in the
+/// settings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Category {
+    /// The user went idle while a session was running.
+    Idle,
+    /// A running session took its task past a share of its budget.
+    Budget,
+    /// A session has been running for longer than anyone works in one go.
+    ForgottenTimer,
+    /// A focus period or break ended.
+    Pomodoro,
+    /// The user has been active for a long stretch without a break.
+    Break,
+    /// The hours worked today or this week passed their limit.
+    WorkLimit,
+    /// A window rule switched the running session to another task, or
+    /// suggests doing so.
+    TaskSwitch,
+}
+
+impl Category {
+    pub fn enabled(self, settings: &Settings) -> bool {
+        match self {
+            Category::Idle => settings.notify_idle,
+            Category::Budget => settings.notify_budget,
+            Category::ForgottenTimer => settings.notify_forgotten_timer,
+            Category::Pomodoro => settings.notify_pomodoro,
+            Category::Break => settings.notify_break,
+            Category::WorkLimit => settings.notify_work_limit,
+            Category::TaskSwitch => settings.notify_task_switch,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notification {
+    pub category: Category,
+    pub summary: String,
+    pub body: String,
+}
+
+/// Where the agent's notifications end up, so tests can look at them
+/// instead of the desktop.
+pub trait Notifier: Send + Sync {
+    /// Shows the notification. Failures are logged, not returned: a missed
+    /// notification shouldn't stop the agent.
+    fn notify(&self, notification: &Notification);
+}
+
+/// The desktop's notifications: freedesktop notifications over D-Bus on
+/// Linux, and the system's own on macOS and Windows.
+///
+/// Showing one can block, e.g. while D-Bus waits for a notification daemon,
+/// so they are shown by a thread of their own rather than the agent's.
+pub struct DesktopNotifier {
+    notification_tx: mpsc::Sender<Notification>,
+}
+
+impl DesktopNotifier {
+    /// Starts the thread showing the notifications. It ends once the
+    /// notifier is dropped.
+    pub fn start() -> Self {
+        let (notification_tx, notification_rx) = mpsc::channel::<Notification>();
+        thread::Builder::new()
+            .name("notifier".into())
+            .spawn(move || {
+                for notification in notification_rx {
+                    show(&notification);
+                }
+            })
+            .expect("Failed to spawn notifier thread");
+        Self { notification_tx }
+    }
+}
+
+impl Notifier for DesktopNotifier {
+    fn notify(&self, notification: &Notification) {
+        if self.notification_tx.send(notification.clone()).is_err() {
+            tracing::warn!(category = ?notification.category, "Notifier thread has stopped");
+        }
+    }
+}
+
+fn show(notification: &Notification) {
+    let result = notify_rust::Notification::new()
+        .appname("Time Tracker")
+        .summary(&notification.summary)
+        .body(&notification.body)
+        .show();
+    if let Err(e) = result {
+        tracing::warn!(category = ?notification.category, "Couldn't show notification: {}", e);
+    }
+}
+
+/// Keeps notifications instead of showing them.
+#[derive(Default)]
+pub struct RecordingNotifier {
+    notifications: Mutex<Vec<Notification>>,
+}
+
+impl RecordingNotifier {
+    /// The notifications received so far, oldest first.
+    pub fn notifications(&self) -> Vec<Notification> {
+        self.notifications.lock().unwrap().clone()
+    }
+}
+
+impl Notifier for RecordingNotifier {
+    fn notify(&self, notification: &Notification) {
+        self.notifications
+            .lock()
+            .unwrap()
+            .push(notification.clone());
+    }
+}
@@ -0,0 +1,137 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    agent::{Clock, StopWatch},
+    config::Settings,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Phase {
+    Focus,
+    ShortBreak,
+    LongBreak,
+}
+
+impl Phase {
+    pub fn label(self) -> &'static str {
+        match self {
+            Phase::Focus => "Focus",
+            Phase::ShortBreak => "Short break",
+            Phase::LongBreak => "Long break",
+        }
+    }
+}
+
+/// How long focus periods and breaks last.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PomodoroPlan {
+    pub focus: Duration,
+    pub short_break: Duration,
+    pub long_break: Duration,
+    /// Every this many focus periods are followed by a long break.
+    pub long_break_every: u32,
+}
+
+impl PomodoroPlan {
+    /// The plan in the settings. Phases last at least a minute.
+    pub fn from_settings(settings: &Settings) -> Self {
+        let minutes = |minutes: u32| Duration::from_secs(u64::from(minutes.max(1)) * 60);
+        Self {
+            focus: minutes(settings.pomodoro_focus_minutes),
+            short_break: minutes(settings.pomodoro_break_minutes),
+            long_break: minutes(settings.pomodoro_long_break_minutes),
+            long_break_every: settings.pomodoro_long_break_every.max(1),
+        }
+    }
+
+    pub fn length(&self, phase: Phase) -> Duration {
+        match phase {
+            Phase::Focus => self.focus,
+            Phase::ShortBreak => self.short_break,
+            Phase::LongBreak => self.long_break,
+        }
+    }
+}
+
+/// Where a pomodoro run stands, as shown in the tray and window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PomodoroStatus {
+    pub phase: Phase,
+    /// Rounded up to whole minutes, so the status only changes, and is only
+    /// sent to the front ends, once a minute.
+    pub remaining: Duration,
+    /// Focus periods completed since the run started.
+    pub completed: u32,
+}
+
+/// Alternating focus periods and breaks, timed by a `StopWatch` so focus
+/// time can pause while the user is idle.
+pub struct Pomodoro {
+    plan: PomodoroPlan,
+    phase: Phase,
+    stop_watch: StopWatch,
+    completed: u32,
+}
+
+impl Pomodoro {
+    /// Starts with a focus period.
+    pub fn start(plan: PomodoroPlan, clock: Arc<dyn Clock>) -> Self {
+        let mut stop_watch = StopWatch::new(clock);
+        stop_watch.start();
+        Self {
+            plan,
+            phase: Phase::Focus,
+            stop_watch,
+            completed: 0,
+        }
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    pub fn status(&mut self) -> PomodoroStatus {
+        let remaining = self
+            .plan
+            .length(self.phase)
+            .saturating_sub(self.stop_watch.elapsed());
+        PomodoroStatus {
+            phase: self.phase,
+            remaining: Duration::from_secs((remaining.as_secs_f64() / 60.0).ceil() as u64 * 60),
+            completed: self.completed,
+        }
+    }
+
+    /// Moves on to the next phase once the current one is over, and returns
+    /// the phase that began.
+    pub fn advance(&mut self) -> Option<Phase> {
+        if self.stop_watch.elapsed() < self.plan.length(self.phase) {
+            return None;
+        }
+        self.phase = match self.phase {
+            Phase::Focus => {
+                self.completed += 1;
+                if self.completed.is_multiple_of(self.plan.long_break_every) {
+                    Phase::LongBreak
+                } else {
+                    Phase::ShortBreak
+                }
+            }
+            Phase::ShortBreak | Phase::LongBreak => Phase::Focus,
+        };
+        self.stop_watch.reset();
+        self.stop_watch.start();
+        Some(self.phase)
+    }
+
+    /// Stops the clock of a focus period; breaks go on regardless.
+    pub fn pause(&mut self) {
+        if self.phase == Phase::Focus {
+            self.stop_watch.stop();
+        }
+    }
+
+    pub fn resume(&mut self) {
+        self.stop_watch.start();
+    }
+}
@@ -0,0 +1,64 @@
+use chrono::NaiveDate;
+use rusqlite::{Connection, Result, Row, types::Type};
+
+const RATE_COLUMNS: &str = "r_id, r_client, r_task, r_hourly_cents, r_effective_from";
+
+/// Dates are stored as `YYYY-MM-DD` text.
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// An hourly rate that applies from a day on, until a later rate for the
+/// same client or task takes over. Exactly one of `r_client` and `r_task`
+/// is set; a task's rate wins over its client's.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Rate {
+    pub r_id: i64,
+    pub r_client: Option<i64>,
+    pub r_task: Option<i64>,
+    /// In cents, or the currency's other minor unit, per hour.
+    pub r_hourly_cents: i64,
+    pub r_effective_from: NaiveDate,
+}
+
+fn rate_from_row(row: &Row) -> Result<Rate> {
+    let effective_from: String = row.get(4)?;
+    Ok(Rate {
+        r_id: row.get(0)?,
+        r_client: row.get(1)?,
+        r_task: row.get(2)?,
+        r_hourly_cents: row.get(3)?,
+        r_effective_from: NaiveDate::parse_from_str(&effective_from, DATE_FORMAT)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, Type::Text, e.into()))?,
+    })
+}
+
+/// All rates, oldest first.
+pub fn get_all_rates(conn: &Connection) -> Result<Vec<Rate>> {
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM rates ORDER BY r_effective_from, r_id",
+        RATE_COLUMNS
+    ))?;
+    let rate_iter = statement.query_map([], rate_from_row)?;
+
+    let mut rates = Vec::new();
+    for rate in rate_iter {
+        rates.push(rate?);
+    }
+    Ok(rates)
+}
+
+pub fn add_rate(conn: &Connection, rate: &Rate) -> Result<usize> {
+    conn.execute(
+        "INSERT INTO rates (r_client, r_task, r_hourly_cents, r_effective_from)
+            VALUES (?1, ?2, ?3, ?4)",
+        (
+            rate.r_client,
+            rate.r_task,
+            rate.r_hourly_cents,
+            rate.r_effective_from.format(DATE_FORMAT).to_string(),
+        ),
+    )
+}
+
+pub fn delete_rate(conn: &Connection, id: i64) -> Result<usize> {
+    conn.execute("DELETE FROM rates WHERE r_id = ?1", [id])
+}
@@ -1,37 +1,137 @@
-use rusqlite::{Connection, Result};
+use rusqlite::{Connection, OptionalExtension, Result, Row};
+use uuid::Uuid;
 
-#[derive(Debug)]
+const SESSION_COLUMNS: &str = "s_id, s_uuid, s_task, s_user, s_duration, s_comment, \
+    s_created_at, s_updated_at, s_deleted, s_billable, s_pomodoros, s_rule";
+
+#[derive(Clone, Debug)]
 pub struct Session {
-    pub _s_id: i64,
+    pub s_id: i64,
+    pub s_uuid: String,
     pub s_task: i64,
     pub s_user: String,
     pub s_duration: u64,
     pub s_comment: String,
+    pub s_created_at: i64,
+    pub s_updated_at: i64,
+    pub s_deleted: bool,
+    /// Whether the session counts towards invoices.
+    pub s_billable: bool,
+    /// Focus periods completed during the session. Like the billable flag,
+    /// kept on this device only.
+    pub s_pomodoros: u32,
+    /// The window rule that switched to the task, if one did. Also local.
+    pub s_rule: Option<i64>,
 }
 
 impl Default for Session {
     fn default() -> Self {
         Session {
-            _s_id: 1,
+            s_id: 1,
+            s_uuid: "".into(),
             s_task: 1,
             s_user: "".into(),
             s_duration: 0,
             s_comment: "".to_string(),
+            s_created_at: 0,
+            s_updated_at: 0,
+            s_deleted: false,
+            s_billable: true,
+            s_pomodoros: 0,
+            s_rule: None,
         }
     }
 }
 
-pub fn save_session(conn: &Connection, session: &Session) -> Result<usize> {
+impl Session {
+    /// Sessions are saved, and so stamped as created, when they end.
+    pub fn ended_at(&self) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp_millis(self.s_created_at).unwrap_or_default()
+    }
+
+    pub fn started_at(&self) -> chrono::DateTime<chrono::Utc> {
+        self.ended_at() - chrono::Duration::seconds(self.s_duration as i64)
+    }
+
+    /// Sessions recorded before they were timestamped keep an `s_created_at`
+    /// of zero: when they were worked is unknown.
+    pub fn time_known(&self) -> bool {
+        self.s_created_at > 0
+    }
+}
+
+/// Inserts a session recorded on this device at `now`, in Unix milliseconds,
+/// assigning it a fresh global id.
+/// A preset `s_created_at`, the time an imported session ended, is kept.
+pub fn save_session(conn: &Connection, session: &Session, now: i64) -> Result<usize> {
+    let created_at = created_at(session, now);
     conn.execute(
         "INSERT INTO sessions
-            (s_task, s_user, s_duration, s_comment)
+            (s_uuid, s_task, s_user, s_duration, s_comment, s_created_at, s_updated_at,
+                s_billable, s_pomodoros, s_rule)
             VALUES
-            (?1, ?2, ?3, ?4)",
+            (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
         (
+            Uuid::new_v4().to_string(),
             session.s_task,
             session.s_user.clone(),
             session.s_duration,
             &session.s_comment,
+            created_at,
+            now,
+            session.s_billable,
+            session.s_pomodoros,
+            session.s_rule,
         ),
     )
 }
+
+/// When a session being saved was created: normally now, when it ends.
+pub fn created_at(session: &Session, now: i64) -> i64 {
+    if session.s_created_at > 0 {
+        session.s_created_at
+    } else {
+        now
+    }
+}
+
+fn session_from_row(row: &Row) -> Result<Session> {
+    Ok(Session {
+        s_id: row.get(0)?,
+        s_uuid: row.get(1)?,
+        s_task: row.get(2)?,
+        s_user: row.get(3)?,
+        s_duration: row.get(4)?,
+        s_comment: row.get(5)?,
+        s_created_at: row.get(6)?,
+        s_updated_at: row.get(7)?,
+        s_deleted: row.get(8)?,
+        s_billable: row.get(9)?,
+        s_pomodoros: row.get(10)?,
+        s_rule: row.get(11)?,
+    })
+}
+
+pub fn get_all_sessions(conn: &Connection) -> Result<Vec<Session>> {
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM sessions WHERE s_deleted = 0 ORDER BY s_id",
+        SESSION_COLUMNS
+    ))?;
+    let session_iter = statement.query_map([], session_from_row)?;
+
+    let mut sessions = Vec::new();
+    for session in session_iter {
+        sessions.push(session?);
+    }
+    Ok(sessions)
+}
+
+/// Looks up a session by its local id, including deleted sessions.
+pub fn get_session(conn: &Connection, id: i64) -> Result<Option<Session>> {
+    conn.query_row(
+        &format!("SELECT {} FROM sessions WHERE s_id = ?1", SESSION_COLUMNS),
+        [id],
+        session_from_row,
+    )
+    .optional()
+}
@@ -0,0 +1,158 @@
+use std::sync::mpsc::Sender;
+
+use crate::agent;
+
+/// Has the agent save the running session and quit when the system shuts
+/// down, or the app is otherwise told to terminate (SIGTERM, SIGHUP or
+/// Ctrl+C; closing the console, signing out or shutting down on Windows).
+pub fn start_shutdown_listener(command_tx: Sender<agent::AgentCommand>) {
+    #[cfg(target_os = "windows")]
+    session_end::listen(command_tx.clone());
+
+    let result = ctrlc::set_handler(move || shut_down(&command_tx));
+    if let Err(e) = result {
+        tracing::warn!("Couldn't listen for the system shutting down: {}", e);
+    }
+}
+
+fn shut_down(command_tx: &Sender<agent::AgentCommand>) {
+    tracing::info!("Shutting down");
+    let _ = command_tx.send(agent::AgentCommand::Shutdown);
+    let _ = command_tx.send(agent::AgentCommand::Quit);
+}
+
+/// A GUI app on Windows gets no console event when the user signs out or
+/// shuts down, only `WM_QUERYENDSESSION` and `WM_ENDSESSION` sent to its
+/// top-level windows. A hidden window of our own receives them, so it
+/// doesn't depend on the egui window being open.
+#[cfg(target_os = "windows")]
+mod session_end {
+    use std::{
+        mem, ptr,
+        sync::{OnceLock, mpsc::Sender},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use winapi::{
+        shared::{
+            minwindef::{LPARAM, LRESULT, TRUE, UINT, WPARAM},
+            windef::HWND,
+        },
+        um::{
+            libloaderapi::GetModuleHandleW,
+            winuser::{
+                CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, MSG,
+                RegisterClassW, TranslateMessage, WM_ENDSESSION, WM_QUERYENDSESSION, WNDCLASSW,
+            },
+        },
+    };
+
+    use crate::agent;
+
+    const WINDOW_CLASS: &str = "TimeTrackerSessionEnd";
+    /// How long the session may take to be saved. Windows ends the process
+    /// once `WM_ENDSESSION` is handled, and shows the app as blocking the
+    /// shutdown after about five seconds.
+    const SAVE_TIMEOUT: Duration = Duration::from_secs(4);
+    const SAVE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+    /// The window procedure can't capture anything, so it finds the agent
+    /// here.
+    static COMMAND_TX: OnceLock<Sender<agent::AgentCommand>> = OnceLock::new();
+
+    pub fn listen(command_tx: Sender<agent::AgentCommand>) {
+        if COMMAND_TX.set(command_tx).is_err() {
+            return;
+        }
+        let result = thread::Builder::new()
+            .name("session-end-listener".into())
+            .spawn(run_window);
+        if let Err(e) = result {
+            tracing::warn!("Couldn't listen for the session ending: {}", e);
+        }
+    }
+
+    fn run_window() {
+        let class_name: Vec<u16> = WINDOW_CLASS.encode_utf16().chain([0]).collect();
+        // SAFETY: the class name outlives the window, which lives as long as
+        // this thread, and the structs are plain data that Windows accepts
+        // zeroed.
+        unsafe {
+            let instance = GetModuleHandleW(ptr::null());
+            let class = WNDCLASSW {
+                lpfnWndProc: Some(window_proc),
+                hInstance: instance,
+                lpszClassName: class_name.as_ptr(),
+                ..mem::zeroed()
+            };
+            if RegisterClassW(&class) == 0 {
+                tracing::warn!("Couldn't register the session end window class");
+                return;
+            }
+            // Never shown. Message-only windows would miss the broadcast.
+            let window = CreateWindowExW(
+                0,
+                class_name.as_ptr(),
+                class_name.as_ptr(),
+                0,
+                0,
+                0,
+                0,
+                0,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                instance,
+                ptr::null_mut(),
+            );
+            if window.is_null() {
+                tracing::warn!("Couldn't create the session end window");
+                return;
+            }
+
+            let mut message: MSG = mem::zeroed();
+            while GetMessageW(&mut message, ptr::null_mut(), 0, 0) > 0 {
+                TranslateMessage(&message);
+                DispatchMessageW(&message);
+            }
+        }
+    }
+
+    unsafe extern "system" fn window_proc(
+        window: HWND,
+        message: UINT,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        match message {
+            // Nothing to object to: the session is saved when it does end.
+            WM_QUERYENDSESSION => TRUE as LRESULT,
+            WM_ENDSESSION => {
+                // `wparam` is zero when another app cancelled the shutdown.
+                if wparam != 0
+                    && let Some(command_tx) = COMMAND_TX.get()
+                {
+                    super::shut_down(command_tx);
+                    wait_for_agent(command_tx);
+                }
+                0
+            }
+            // SAFETY: passes the arguments Windows called us with on unchanged.
+            _ => unsafe { DefWindowProcW(window, message, wparam, lparam) },
+        }
+    }
+
+    /// Returns once the agent has quit, so the process isn't ended before
+    /// the session is saved, or when it takes too long.
+    fn wait_for_agent(command_tx: &Sender<agent::AgentCommand>) {
+        let started = Instant::now();
+        // Sending only fails once the agent has dropped its end.
+        while command_tx
+            .send(agent::AgentCommand::RequestElapsedTime)
+            .is_ok()
+            && started.elapsed() < SAVE_TIMEOUT
+        {
+            thread::sleep(SAVE_POLL_INTERVAL);
+        }
+    }
+}
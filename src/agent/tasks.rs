@@ -1,33 +1,89 @@
-use rusqlite::{Connection, Result};
+use rusqlite::{Connection, OptionalExtension, Result, Row};
+use uuid::Uuid;
+
+use crate::storage;
 
 pub const PRIORITY_LEVELS: &[&str] = &["Low", "Medium", "High"];
 
+const TASK_COLUMNS: &str = "t_id, t_uuid, t_name, t_priority, t_created_at, t_updated_at, \
+    t_deleted, t_name_hlc, t_priority_hlc, t_deleted_hlc, t_client, t_estimate, t_budget, \
+    t_daily_goal, t_weekly_goal";
+
 #[derive(Clone)]
 pub struct Task {
     pub t_id: i64,
+    pub t_uuid: String,
     pub t_name: String,
     pub t_priority: usize,
+    pub t_created_at: i64,
+    pub t_updated_at: i64,
+    pub t_deleted: bool,
+    pub t_name_hlc: String,
+    pub t_priority_hlc: String,
+    pub t_deleted_hlc: String,
+    /// Who the task is billed to. Not synced, but shared by the team on
+    /// PostgreSQL like the clients themselves.
+    pub t_client: Option<i64>,
+    /// Expected effort in seconds. Like the budget, not synced; on PostgreSQL
+    /// both are the team's, as is the time tracked against them.
+    pub t_estimate: Option<u64>,
+    /// Tracked time in seconds the task must not go over; the agent warns as
+    /// running sessions approach it.
+    pub t_budget: Option<u64>,
+    /// Seconds to work on the task each day and each week. Not synced, and
+    /// kept per user on PostgreSQL, since goals count only the user's work.
+    pub t_daily_goal: Option<u64>,
+    pub t_weekly_goal: Option<u64>,
 }
 
 impl Default for Task {
     fn default() -> Self {
         Task {
             t_id: 1,
+            t_uuid: "".to_string(),
             t_name: "".to_string(),
             t_priority: 0,
+            t_created_at: 0,
+            t_updated_at: 0,
+            t_deleted: false,
+            t_name_hlc: "".to_string(),
+            t_priority_hlc: "".to_string(),
+            t_deleted_hlc: "".to_string(),
+            t_client: None,
+            t_estimate: None,
+            t_budget: None,
+            t_daily_goal: None,
+            t_weekly_goal: None,
         }
     }
 }
 
+fn task_from_row(row: &Row) -> Result<Task> {
+    Ok(Task {
+        t_id: row.get(0)?,
+        t_uuid: row.get(1)?,
+        t_name: row.get(2)?,
+        t_priority: row.get(3)?,
+        t_created_at: row.get(4)?,
+        t_updated_at: row.get(5)?,
+        t_deleted: row.get(6)?,
+        t_name_hlc: row.get(7)?,
+        t_priority_hlc: row.get(8)?,
+        t_deleted_hlc: row.get(9)?,
+        t_client: row.get(10)?,
+        t_estimate: row.get(11)?,
+        t_budget: row.get(12)?,
+        t_daily_goal: row.get(13)?,
+        t_weekly_goal: row.get(14)?,
+    })
+}
+
 pub fn get_all_tasks(conn: &Connection) -> Result<Vec<Task>> {
-    let mut statement = conn.prepare("SELECT * FROM tasks")?;
-    let task_iter = statement.query_map([], |row| {
-        Ok(Task {
-            t_id: row.get(0)?,
-            t_name: row.get(1)?,
-            t_priority: row.get(2)?,
-        })
-    })?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM tasks WHERE t_deleted = 0",
+        TASK_COLUMNS
+    ))?;
+    let task_iter = statement.query_map([], task_from_row)?;
 
     let mut tasks = Vec::new();
     for task in task_iter {
@@ -36,12 +92,105 @@ pub fn get_all_tasks(conn: &Connection) -> Result<Vec<Task>> {
     Ok(tasks)
 }
 
-pub fn add_new_task(conn: &Connection, task: &Task) -> Result<usize> {
+/// Looks up a task by its local id, including deleted tasks.
+pub fn get_task(conn: &Connection, id: i64) -> Result<Option<Task>> {
+    conn.query_row(
+        &format!("SELECT {} FROM tasks WHERE t_id = ?1", TASK_COLUMNS),
+        [id],
+        task_from_row,
+    )
+    .optional()
+}
+
+/// Looks up a task by its global id, including deleted tasks.
+pub fn get_task_by_uuid(conn: &Connection, uuid: &str) -> Result<Option<Task>> {
+    conn.query_row(
+        &format!("SELECT {} FROM tasks WHERE t_uuid = ?1", TASK_COLUMNS),
+        [uuid],
+        task_from_row,
+    )
+    .optional()
+}
+
+/// Inserts a task created on this device at `now`, in Unix milliseconds,
+/// assigning it a fresh global id.
+pub fn add_new_task(conn: &Connection, task: &Task, now: i64) -> Result<usize> {
+    let hlc = storage::hlc::next(conn, now)?.to_string();
     conn.execute(
         "INSERT INTO tasks
-            (t_name, t_priority)
+            (t_uuid, t_name, t_priority, t_created_at, t_updated_at,
+                t_name_hlc, t_priority_hlc, t_deleted_hlc, t_client, t_estimate, t_budget,
+                t_daily_goal, t_weekly_goal)
             VALUES
-            (?1, ?2)",
-        (&task.t_name, &task.t_priority),
+            (?1, ?2, ?3, ?4, ?5, ?6, ?6, ?6, ?7, ?8, ?9, ?10, ?11)",
+        (
+            Uuid::new_v4().to_string(),
+            &task.t_name,
+            &task.t_priority,
+            now,
+            now,
+            hlc,
+            task.t_client,
+            task.t_estimate,
+            task.t_budget,
+            task.t_daily_goal,
+            task.t_weekly_goal,
+        ),
+    )
+}
+
+/// Writes a task's changed fields, giving each changed synced field a new clock.
+pub fn update_task(conn: &Connection, task: &Task, now: i64) -> Result<usize> {
+    // All or nothing, so a half-applied edit is never queued for sync.
+    let tx = conn.unchecked_transaction()?;
+    let Some(current) = get_task(&tx, task.t_id)? else {
+        return Ok(0);
+    };
+
+    let mut changed = 0;
+    if current.t_name != task.t_name {
+        let hlc = storage::hlc::next(&tx, now)?.to_string();
+        changed += tx.execute(
+            "UPDATE tasks SET t_name = ?1, t_name_hlc = ?2, t_updated_at = ?3 WHERE t_id = ?4",
+            (&task.t_name, hlc, now, task.t_id),
+        )?;
+    }
+    if current.t_priority != task.t_priority {
+        let hlc = storage::hlc::next(&tx, now)?.to_string();
+        changed += tx.execute(
+            "UPDATE tasks SET t_priority = ?1, t_priority_hlc = ?2, t_updated_at = ?3
+                WHERE t_id = ?4",
+            (&task.t_priority, hlc, now, task.t_id),
+        )?;
+    }
+    if current.t_client != task.t_client {
+        changed += tx.execute(
+            "UPDATE tasks SET t_client = ?1 WHERE t_id = ?2",
+            (task.t_client, task.t_id),
+        )?;
+    }
+    if current.t_estimate != task.t_estimate || current.t_budget != task.t_budget {
+        changed += tx.execute(
+            "UPDATE tasks SET t_estimate = ?1, t_budget = ?2 WHERE t_id = ?3",
+            (task.t_estimate, task.t_budget, task.t_id),
+        )?;
+    }
+    if current.t_daily_goal != task.t_daily_goal || current.t_weekly_goal != task.t_weekly_goal {
+        changed += tx.execute(
+            "UPDATE tasks SET t_daily_goal = ?1, t_weekly_goal = ?2 WHERE t_id = ?3",
+            (task.t_daily_goal, task.t_weekly_goal, task.t_id),
+        )?;
+    }
+    tx.commit()?;
+    Ok(changed)
+}
+
+/// Marks a task as deleted. The row is kept as a tombstone so the deletion
+/// can be merged into other devices.
+pub fn delete_task(conn: &Connection, id: i64, now: i64) -> Result<usize> {
+    let hlc = storage::hlc::next(conn, now)?.to_string();
+    conn.execute(
+        "UPDATE tasks SET t_deleted = 1, t_deleted_hlc = ?1, t_updated_at = ?2 WHERE t_id = ?3",
+        (hlc, now, id),
     )
 }
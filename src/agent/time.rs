@@ -1,13 +1,82 @@
-use std::time::{Duration, Instant};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
+use chrono::{DateTime, Utc};
+
+/// Source of the current time for the agent, `StopWatch`, the storage
+/// backends and the input listener, so idle transitions, durations and
+/// timestamps can be driven by tests.
+pub trait Clock: Send + Sync {
+    /// The wall clock, for timestamps.
+    fn now(&self) -> DateTime<Utc>;
+    /// A monotonic reading, for measuring durations. Unlike `now` it doesn't
+    /// jump when the system clock is changed or corrected, and it doesn't
+    /// count time the computer spent suspended.
+    fn monotonic(&self) -> Instant;
+}
+
+/// The wall clock.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn monotonic(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when told to, for simulating hours of activity
+/// and idleness instantly.
+pub struct ManualClock {
+    now: Mutex<(DateTime<Utc>, Instant)>,
+}
+
+impl ManualClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new((start, Instant::now())),
+        }
+    }
+
+    /// Moves both clocks; the monotonic one only ever moves forward.
+    pub fn advance(&self, by: chrono::Duration) {
+        let mut now = self.now.lock().unwrap();
+        now.0 += by;
+        now.1 += by.to_std().unwrap_or_default();
+    }
+
+    /// Changes the wall clock only, like setting the system clock does.
+    pub fn set(&self, now: DateTime<Utc>) {
+        self.now.lock().unwrap().0 = now;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.now.lock().unwrap().0
+    }
+
+    fn monotonic(&self) -> Instant {
+        self.now.lock().unwrap().1
+    }
+}
+
+/// Measures time on the clock's monotonic reading.
 pub struct StopWatch {
+    clock: Arc<dyn Clock>,
     start: Option<Instant>,
     elapsed: Duration,
 }
 
 impl StopWatch {
-    pub fn new() -> Self {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
         Self {
+            clock,
             start: None,
             elapsed: Duration::ZERO,
         }
@@ -15,13 +84,18 @@ impl StopWatch {
 
     pub fn start(&mut self) {
         if self.start.is_none() {
-            self.start = Some(Instant::now());
+            self.start = Some(self.clock.monotonic());
         }
     }
 
     pub fn stop(&mut self) {
+        self.stop_ago(Duration::ZERO);
+    }
+
+    /// Stops as if it had been stopped `ago`, but not before it was started.
+    pub fn stop_ago(&mut self, ago: Duration) {
         if let Some(s) = self.start.take() {
-            self.elapsed += s.elapsed();
+            self.elapsed += self.since(s).saturating_sub(ago);
         }
     }
 
@@ -32,9 +106,12 @@ impl StopWatch {
 
     pub fn elapsed(&mut self) -> Duration {
         match self.start {
-            Some(s) => self.elapsed + s.elapsed(),
+            Some(s) => self.elapsed + self.since(s),
             None => self.elapsed,
         }
     }
-}
 
+    fn since(&self, start: Instant) -> Duration {
+        self.clock.monotonic().saturating_duration_since(start)
+    }
+}
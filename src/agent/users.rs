@@ -0,0 +1,31 @@
+use rusqlite::{Connection, Result};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct User {
+    pub u_id: String,
+    pub u_name: String,
+}
+
+/// Adds the user if it doesn't exist yet, so sessions can reference it.
+pub fn ensure_user(conn: &Connection, user: &User) -> Result<usize> {
+    conn.execute(
+        "INSERT OR IGNORE INTO users (u_id, u_name) VALUES (?1, ?2)",
+        (&user.u_id, &user.u_name),
+    )
+}
+
+pub fn get_all_users(conn: &Connection) -> Result<Vec<User>> {
+    let mut statement = conn.prepare("SELECT u_id, u_name FROM users ORDER BY u_id")?;
+    let user_iter = statement.query_map([], |row| {
+        Ok(User {
+            u_id: row.get(0)?,
+            u_name: row.get(1)?,
+        })
+    })?;
+
+    let mut users = Vec::new();
+    for user in user_iter {
+        users.push(user?);
+    }
+    Ok(users)
+}
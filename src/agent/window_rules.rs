@@ -0,0 +1,178 @@
+use std::{fmt, str::FromStr};
+
+use regex::Regex;
+use rusqlite::{Connection, Result, Row};
+
+use crate::agent::active_window::ActiveWindow;
+
+const WINDOW_RULE_COLUMNS: &str = "wr_id, wr_pattern, wr_field, wr_task, wr_action";
+
+/// The part of the focused window a rule's pattern is matched against.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RuleField {
+    #[default]
+    Title,
+    /// The application, e.g. `firefox` or `code`.
+    Class,
+}
+
+impl RuleField {
+    pub const ALL: [RuleField; 2] = [RuleField::Title, RuleField::Class];
+}
+
+impl FromStr for RuleField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "title" => Ok(RuleField::Title),
+            "class" => Ok(RuleField::Class),
+            _ => Err(format!("Unknown window field '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for RuleField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RuleField::Title => "title",
+            RuleField::Class => "class",
+        })
+    }
+}
+
+/// What happens when a rule matches while another task is running.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RuleAction {
+    /// The user is asked whether to switch.
+    #[default]
+    Suggest,
+    Switch,
+}
+
+impl RuleAction {
+    pub const ALL: [RuleAction; 2] = [RuleAction::Suggest, RuleAction::Switch];
+}
+
+impl FromStr for RuleAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "suggest" => Ok(RuleAction::Suggest),
+            "switch" => Ok(RuleAction::Switch),
+            _ => Err(format!("Unknown rule action '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for RuleAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RuleAction::Suggest => "suggest",
+            RuleAction::Switch => "switch",
+        })
+    }
+}
+
+/// Ties windows whose title or class matches `wr_pattern`, a regular
+/// expression, to a task. Rules aren't synced, and on PostgreSQL each user
+/// only sees their own.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WindowRule {
+    pub wr_id: i64,
+    pub wr_pattern: String,
+    pub wr_field: RuleField,
+    pub wr_task: i64,
+    pub wr_action: RuleAction,
+}
+
+/// Why `pattern` can't be used in a rule, if it can't.
+pub fn check_pattern(pattern: &str) -> Result<(), String> {
+    if pattern.is_empty() {
+        return Err("The pattern is empty".to_string());
+    }
+    Regex::new(pattern)
+        .map(|_| ())
+        .map_err(|e| format!("Invalid pattern: {}", e))
+}
+
+/// The rules with their patterns compiled, ready to match windows.
+pub struct RuleSet {
+    rules: Vec<(WindowRule, Regex)>,
+}
+
+impl RuleSet {
+    /// Rules whose pattern doesn't compile, e.g. ones added by an older
+    /// version, are left out.
+    pub fn new(rules: Vec<WindowRule>) -> Self {
+        let rules = rules
+            .into_iter()
+            .filter_map(|rule| match Regex::new(&rule.wr_pattern) {
+                Ok(regex) => Some((rule, regex)),
+                Err(e) => {
+                    tracing::warn!(rule = rule.wr_id, "Ignoring window rule: {}", e);
+                    None
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// The first rule, in the order they were added, that matches `window`.
+    pub fn matching(&self, window: &ActiveWindow) -> Option<&WindowRule> {
+        self.rules
+            .iter()
+            .find(|(rule, regex)| {
+                regex.is_match(match rule.wr_field {
+                    RuleField::Title => &window.title,
+                    RuleField::Class => &window.class,
+                })
+            })
+            .map(|(rule, _)| rule)
+    }
+}
+
+fn window_rule_from_row(row: &Row) -> Result<WindowRule> {
+    let field: String = row.get(2)?;
+    let action: String = row.get(4)?;
+    Ok(WindowRule {
+        wr_id: row.get(0)?,
+        wr_pattern: row.get(1)?,
+        wr_field: field.parse().unwrap_or_default(),
+        wr_task: row.get(3)?,
+        wr_action: action.parse().unwrap_or_default(),
+    })
+}
+
+/// All rules, in the order they were added.
+pub fn get_all_window_rules(conn: &Connection) -> Result<Vec<WindowRule>> {
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM window_rules ORDER BY wr_id",
+        WINDOW_RULE_COLUMNS
+    ))?;
+    let rule_iter = statement.query_map([], window_rule_from_row)?;
+
+    let mut rules = Vec::new();
+    for rule in rule_iter {
+        rules.push(rule?);
+    }
+    Ok(rules)
+}
+
+pub fn add_window_rule(conn: &Connection, rule: &WindowRule) -> Result<usize> {
+    conn.execute(
+        "INSERT INTO window_rules (wr_pattern, wr_field, wr_task, wr_action)
+            VALUES (?1, ?2, ?3, ?4)",
+        (
+            &rule.wr_pattern,
+            rule.wr_field.to_string(),
+            rule.wr_task,
+            rule.wr_action.to_string(),
+        ),
+    )
+}
+
+pub fn delete_window_rule(conn: &Connection, id: i64) -> Result<usize> {
+    conn.execute("DELETE FROM window_rules WHERE wr_id = ?1", [id])
+}
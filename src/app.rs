@@ -1,3 +1,4 @@
+pub mod report;
 pub mod startup;
 
 pub use startup::start;
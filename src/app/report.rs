@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use crate::{agent, config, report, storage};
+
+/// Runs `time_tracker report ...` and returns the exit code.
+pub fn run(args: &[String]) -> i32 {
+    if args.iter().any(|arg| arg == "--help") {
+        println!("{}", report::USAGE);
+        return 0;
+    }
+    let request = match report::ReportRequest::from_args(args) {
+        Ok(request) => request,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, report::USAGE);
+            return 2;
+        }
+    };
+
+    let settings = config::Settings::load();
+    let user = request.user.unwrap_or_else(|| settings.uid.clone());
+    let date = request
+        .week
+        .unwrap_or_else(|| chrono::Local::now().date_naive());
+    let path = request
+        .output
+        .unwrap_or_else(|| report::default_file_name(date).into());
+
+    let repository = match open_repository(settings) {
+        Ok(repository) => repository,
+        Err(e) => {
+            eprintln!("Couldn't open the database: {}", e);
+            return 1;
+        }
+    };
+    match report::save_timesheet(repository.as_ref(), &user, date, &path) {
+        Ok(()) => {
+            println!("Saved {}", path.display());
+            0
+        }
+        Err(e) => {
+            eprintln!("Couldn't save the timesheet: {}", e);
+            1
+        }
+    }
+}
+
+/// Opens the database, asking for its passphrase if it is encrypted and the
+/// passphrase isn't remembered.
+fn open_repository(
+    settings: config::Settings,
+) -> Result<Box<dyn storage::Repository>, Box<dyn std::error::Error>> {
+    let clock: Arc<dyn agent::Clock> = Arc::new(agent::SystemClock);
+    match storage::open_repository(Arc::new(settings.clone()), clock.clone()) {
+        Err(storage::StorageError::Locked) => {
+            let passphrase = rpassword::prompt_password("Database passphrase: ")?;
+            Ok(storage::open_repository(
+                Arc::new(config::Settings {
+                    database_passphrase: Some(passphrase),
+                    ..settings
+                }),
+                clock,
+            )?)
+        }
+        result => Ok(result?),
+    }
+}
@@ -1,33 +1,150 @@
 use std::{
     sync::{Arc, mpsc},
     thread,
+    time::Duration,
 };
 
-use crate::{agent, config, ui};
+use crate::{agent, app::report, calendar, config, instance, sync, ui};
+
+/// How often the focused window is checked for window rules.
+const WINDOW_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 pub fn start() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // Reports are written by this process, whether or not another one runs.
+    if let Some(("report", report_args)) = args
+        .split_first()
+        .map(|(command, rest)| (command.as_str(), rest))
+    {
+        std::process::exit(report::run(report_args));
+    }
+    let mut request = match instance::Request::from_args(&args) {
+        Ok(request) => request,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, instance::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Some(instance::Request::Unlock { passphrase }) = &mut request {
+        match rpassword::prompt_password("Database passphrase: ") {
+            Ok(entered) => *passphrase = entered,
+            Err(e) => {
+                eprintln!("Couldn't read the passphrase: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Checked before anything else so a second launch never touches the
+    // database or the log file.
+    let instance_dir = config::settings::config_dir();
+    let instance = match instance::Instance::acquire(&instance_dir) {
+        Ok(Some(instance)) => Some(instance),
+        Ok(None) => {
+            let request = request.unwrap_or(instance::Request::Show);
+            if let Err(e) = instance::forward(&instance_dir, request) {
+                eprintln!("Couldn't reach the running time tracker: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Err(e) => {
+            eprintln!("Couldn't check for a running time tracker: {}", e);
+            None
+        }
+    };
+    if request == Some(instance::Request::Quit) {
+        eprintln!("Time tracker isn't running");
+        return;
+    }
+
     let (command_tx, command_rx) = mpsc::channel();
     let (window_tx, window_rx) = crossbeam_channel::unbounded();
     let (tray_tx, tray_rx) = mpsc::channel();
     let (ui_control_tx, ui_control_rx) = mpsc::channel();
+    let (sync_tx, sync_rx) = mpsc::channel();
 
     let settings = Arc::new(config::Settings::load());
+    // Without a log file the app still runs, it just can't be diagnosed.
+    let _log_guard = config::logging::init_logging(&config::logging::log_dir(), settings.log_level)
+        .inspect_err(|e| eprintln!("Couldn't start logging: {}", e))
+        .ok();
+    log_panics();
+    tracing::info!(
+        version = env!("CARGO_PKG_VERSION"),
+        profile = %settings.profile,
+        backend = ?settings.storage_backend,
+        "Starting time tracker"
+    );
+
+    let sync_thread = sync::start_sync_worker(settings.clone(), sync_rx, window_tx.clone());
 
     let agent_settings = settings.clone();
     let agent_command_tx = command_tx.clone();
+    let clock: Arc<dyn agent::Clock> = Arc::new(agent::SystemClock);
+    let watcher_clock = clock.clone();
     let agent_thread = thread::Builder::new()
         .name("agent-worker".into())
         .spawn(move || {
-            agent::start_input_listener(agent_command_tx);
+            agent::start_input_listener(agent_command_tx, clock.clone());
             agent::start_agent(
                 command_rx,
                 window_tx,
                 tray_tx,
                 ui_control_tx,
+                sync_tx,
                 agent_settings,
+                clock,
+                Arc::new(agent::DesktopNotifier::start()),
             );
         })
         .expect("Failed to spawn agent-worker thread");
+    agent::start_shutdown_listener(command_tx.clone());
+    if settings.watch_active_window
+        && let Some(source) = agent::active_window::system_source()
+    {
+        agent::start_window_watcher(
+            command_tx.clone(),
+            watcher_clock,
+            source,
+            WINDOW_POLL_INTERVAL,
+        );
+    }
+
+    if let Some(instance) = instance {
+        instance.listen(command_tx.clone());
+    }
+    let mut calendar_feed_url = None;
+    if settings.calendar_feed_port != 0 {
+        let feed = calendar::feed::load_token(&instance_dir)
+            .map_err(Into::into)
+            .and_then(|token| {
+                calendar::feed::CalendarFeed::bind(settings.calendar_feed_port, token)
+            });
+        match feed {
+            Ok(feed) => {
+                calendar_feed_url = feed.url();
+                tracing::info!(
+                    url = calendar_feed_url.as_deref().unwrap_or_default(),
+                    "Serving the calendar feed"
+                );
+                feed.serve(command_tx.clone());
+            }
+            Err(e) => tracing::error!("Couldn't start the calendar feed: {}", e),
+        }
+    }
+    match request {
+        Some(instance::Request::Show) if !settings.open_ui_at_start_up => {
+            let _ = command_tx.send(agent::AgentCommand::ShowUI);
+        }
+        Some(instance::Request::SyncNow) => {
+            let _ = command_tx.send(agent::AgentCommand::SyncNow);
+        }
+        Some(request @ instance::Request::Unlock { .. }) => {
+            let _ = command_tx.send(request.command());
+        }
+        _ => (),
+    }
 
     let tray_command_tx = command_tx.clone();
     let tray_thread = thread::Builder::new()
@@ -42,7 +159,13 @@ pub fn start() {
         if let Ok(event) = ui_control_rx.try_recv() {
             match event {
                 ui::UIControl::Show => {
-                    ui::run_ui(command_tx.clone(), window_rx.clone(), settings.clone())
+                    tracing::debug!("Opening window");
+                    ui::run_ui(
+                        command_tx.clone(),
+                        window_rx.clone(),
+                        settings.clone(),
+                        calendar_feed_url.clone(),
+                    )
                 }
                 ui::UIControl::Quit => {
                     break;
@@ -53,4 +176,16 @@ pub fn start() {
 
     let _ = tray_thread.join();
     let _ = agent_thread.join();
+    let _ = sync_thread.join();
+    tracing::info!("Stopped time tracker");
+}
+
+/// Writes panics to the log as well, since release builds have no console.
+fn log_panics() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let thread = thread::current();
+        tracing::error!(thread = thread.name().unwrap_or("unnamed"), "{}", info);
+        default_hook(info);
+    }));
 }
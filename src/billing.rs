@@ -0,0 +1,303 @@
+//! Money for billable time: hourly rates per client or task that change
+//! over time, rounding of each session, and invoice drafts totalling a
+//! client's billable sessions for a period.
+
+use std::{
+    collections::{BTreeMap, HashMap, hash_map::Entry},
+    fmt, fs, io,
+    path::Path,
+    str::FromStr,
+};
+
+use chrono::NaiveDate;
+
+use crate::{
+    agent::{clients::Client, rates::Rate, tasks::Task},
+    calendar,
+    storage::{self, StorageError},
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RoundingMode {
+    #[default]
+    Up,
+    Nearest,
+    Down,
+}
+
+impl RoundingMode {
+    pub const ALL: [RoundingMode; 3] =
+        [RoundingMode::Up, RoundingMode::Nearest, RoundingMode::Down];
+}
+
+impl FromStr for RoundingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "up" => Ok(RoundingMode::Up),
+            "nearest" => Ok(RoundingMode::Nearest),
+            "down" => Ok(RoundingMode::Down),
+            _ => Err(format!("Unknown rounding '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for RoundingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RoundingMode::Up => "up",
+            RoundingMode::Nearest => "nearest",
+            RoundingMode::Down => "down",
+        })
+    }
+}
+
+/// Rounds a session to a multiple of `minutes`, e.g. up to the next quarter
+/// of an hour. Zero minutes leaves sessions as they are.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rounding {
+    pub mode: RoundingMode,
+    pub minutes: u32,
+}
+
+impl Rounding {
+    pub fn apply(&self, seconds: u64) -> u64 {
+        let step = u64::from(self.minutes) * 60;
+        if step == 0 {
+            return seconds;
+        }
+        let steps = match self.mode {
+            RoundingMode::Up => seconds.div_ceil(step),
+            RoundingMode::Nearest => (seconds + step / 2) / step,
+            RoundingMode::Down => seconds / step,
+        };
+        steps * step
+    }
+}
+
+/// The rate for work on `task` on `date`: the task's own latest rate in
+/// effect, else its client's.
+pub fn rate_on<'a>(rates: &'a [Rate], task: &Task, date: NaiveDate) -> Option<&'a Rate> {
+    latest_rate(rates, date, |rate| rate.r_task == Some(task.t_id)).or_else(|| {
+        let client = task.t_client?;
+        latest_rate(rates, date, |rate| rate.r_client == Some(client))
+    })
+}
+
+fn latest_rate(rates: &[Rate], date: NaiveDate, applies: impl Fn(&Rate) -> bool) -> Option<&Rate> {
+    rates
+        .iter()
+        .filter(|rate| applies(rate) && rate.r_effective_from <= date)
+        .max_by_key(|rate| (rate.r_effective_from, rate.r_id))
+}
+
+/// A task's billable time at one rate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvoiceLine {
+    pub task: String,
+    /// `None` if no rate was in effect; the time is then listed but not
+    /// charged.
+    pub hourly_cents: Option<i64>,
+    pub sessions: usize,
+    /// The sum of the rounded sessions.
+    pub seconds: u64,
+    pub amount_cents: i64,
+}
+
+/// What a client would be invoiced for the days `from` to `to`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvoiceDraft {
+    pub client: Client,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Sorted by task, then by when the rate took effect.
+    pub lines: Vec<InvoiceLine>,
+}
+
+impl InvoiceDraft {
+    pub fn total_seconds(&self) -> u64 {
+        self.lines.iter().map(|line| line.seconds).sum()
+    }
+
+    pub fn total_cents(&self) -> i64 {
+        self.lines.iter().map(|line| line.amount_cents).sum()
+    }
+
+    /// Tasks with billable time that no rate applies to.
+    pub fn unpriced_tasks(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter(|line| line.hourly_cents.is_none())
+            .map(|line| line.task.as_str())
+            .collect()
+    }
+}
+
+/// Totals the billable sessions of the client's tasks that started on the
+/// local days `from` to `to`. Each session is rounded by the client's
+/// rounding and priced at the rate in effect on the day it started.
+pub fn invoice_draft(
+    repository: &dyn storage::Repository,
+    client_id: i64,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> storage::Result<InvoiceDraft> {
+    let client = repository
+        .get_all_clients()?
+        .into_iter()
+        .find(|client| client.cl_id == client_id)
+        .ok_or_else(|| StorageError::Constraint(format!("unknown client {}", client_id)))?;
+    let rates = repository.get_all_rates()?;
+    let (start, end) = calendar::day_range(from, to);
+
+    // Deleted tasks are looked up too, their time was still worked.
+    let mut tasks: HashMap<i64, Option<Task>> = HashMap::new();
+    let mut lines: BTreeMap<(String, Option<(NaiveDate, i64)>), InvoiceLine> = BTreeMap::new();
+    for session in repository.get_sessions_between(start, end)? {
+        let started_on = session
+            .started_at()
+            .with_timezone(&chrono::Local)
+            .date_naive();
+        if !session.s_billable || started_on < from || started_on > to {
+            continue;
+        }
+        let task = match tasks.entry(session.s_task) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(repository.get_task(session.s_task)?),
+        };
+        let Some(task) = task
+            .as_ref()
+            .filter(|task| task.t_client == Some(client_id))
+        else {
+            continue;
+        };
+
+        let rate = rate_on(&rates, task, started_on);
+        let line = lines
+            .entry((
+                task.t_name.clone(),
+                rate.map(|rate| (rate.r_effective_from, rate.r_id)),
+            ))
+            .or_insert_with(|| InvoiceLine {
+                task: task.t_name.clone(),
+                hourly_cents: rate.map(|rate| rate.r_hourly_cents),
+                sessions: 0,
+                seconds: 0,
+                amount_cents: 0,
+            });
+        line.sessions += 1;
+        line.seconds += client.cl_rounding.apply(session.s_duration);
+    }
+
+    let lines = lines
+        .into_values()
+        .map(|line| InvoiceLine {
+            amount_cents: line
+                .hourly_cents
+                .map_or(0, |cents| amount_cents(line.seconds, cents)),
+            ..line
+        })
+        .collect();
+    Ok(InvoiceDraft {
+        client,
+        from,
+        to,
+        lines,
+    })
+}
+
+/// The price of `seconds` at an hourly rate, to the nearest cent.
+pub fn amount_cents(seconds: u64, hourly_cents: i64) -> i64 {
+    (seconds as i64 * hourly_cents + 1800) / 3600
+}
+
+/// An amount in cents as a decimal, e.g. `1234.50`.
+pub fn format_amount(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    format!("{}{}.{:02}", sign, cents.abs() / 100, cents.abs() % 100)
+}
+
+pub fn format_money(cents: i64, currency: &str) -> String {
+    format!("{} {}", format_amount(cents), currency)
+}
+
+/// Hours as a decimal to two places, as invoices show them, e.g. `1.25`.
+pub fn format_decimal_hours(seconds: u64) -> String {
+    let hundredths = (seconds * 100 + 1800) / 3600;
+    format!("{}.{:02}", hundredths / 100, hundredths % 100)
+}
+
+/// Reads an amount like `85`, `85.5` or `85,50` as cents.
+pub fn parse_amount(text: &str) -> Option<i64> {
+    let (whole, fraction) = match text.trim().split_once(['.', ',']) {
+        Some((whole, fraction)) => (whole, fraction),
+        None => (text.trim(), ""),
+    };
+    if whole.is_empty()
+        || fraction.len() > 2
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let cents: i64 = format!("{:0<2}", fraction).parse().ok()?;
+    Some(whole.parse::<i64>().ok()? * 100 + cents)
+}
+
+/// The draft's line items as CSV, closed by a total row.
+pub fn to_csv(draft: &InvoiceDraft) -> io::Result<String> {
+    let currency = draft.client.cl_currency.as_str();
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(["Task", "Hours", "Hourly rate", "Amount", "Currency"])?;
+    for line in &draft.lines {
+        writer.write_record([
+            line.task.as_str(),
+            &format_decimal_hours(line.seconds),
+            &line.hourly_cents.map(format_amount).unwrap_or_default(),
+            &format_amount(line.amount_cents),
+            currency,
+        ])?;
+    }
+    writer.write_record([
+        "Total",
+        &format_decimal_hours(draft.total_seconds()),
+        "",
+        &format_amount(draft.total_cents()),
+        currency,
+    ])?;
+    let bytes = writer.into_inner().map_err(|e| e.into_error())?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Builds the client's invoice draft and saves its line items to `path`.
+pub fn save_invoice_draft(
+    repository: &dyn storage::Repository,
+    client_id: i64,
+    from: NaiveDate,
+    to: NaiveDate,
+    path: &Path,
+) -> crate::Result<()> {
+    let draft = invoice_draft(repository, client_id, from, to)?;
+    fs::write(path, to_csv(&draft)?)?;
+    tracing::info!(path = %path.display(), "Saved invoice draft");
+    Ok(())
+}
+
+/// E.g. `invoice-acme-2024-01-01-2024-01-31.csv`.
+pub fn default_file_name(client: &Client, from: NaiveDate, to: NaiveDate) -> String {
+    let name: String = client
+        .cl_name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    format!(
+        "invoice-{}-{}-{}.csv",
+        name,
+        from.format("%Y-%m-%d"),
+        to.format("%Y-%m-%d")
+    )
+}
@@ -0,0 +1,41 @@
+//! Team sync server: accepts pushes from `time_tracker` clients, serves their
+//! shared tasks back and aggregates team reports.
+//!
+//! Usage: `time_tracker_server [--addr 127.0.0.1:8080] [--db time_tracker_server.db]`
+//!
+//! Clients authenticate with the token in `TIME_TRACKER_SYNC_TOKEN`, which
+//! must be set to the `sync_token` in their settings.
+
+use time_tracker::server::SyncServer;
+
+const DEFAULT_ADDR: &str = "127.0.0.1:8080";
+const DEFAULT_DB_PATH: &str = "time_tracker_server.db";
+const TOKEN_VARIABLE: &str = "TIME_TRACKER_SYNC_TOKEN";
+
+fn main() {
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut db_path = DEFAULT_DB_PATH.to_string();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--addr", Some(value)) => addr = value,
+            ("--db", Some(value)) => db_path = value,
+            _ => {
+                eprintln!("Usage: time_tracker_server [--addr <host:port>] [--db <path>]");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let Ok(token) = std::env::var(TOKEN_VARIABLE) else {
+        eprintln!("Set {} to the token clients sync with", TOKEN_VARIABLE);
+        std::process::exit(2);
+    };
+
+    let server = SyncServer::bind(&addr, &db_path, &token).expect("Failed to start sync server");
+    if let Some(local_addr) = server.local_addr() {
+        println!("Listening on http://{}", local_addr);
+    }
+    server.run();
+}
@@ -0,0 +1,138 @@
+//! Sessions as iCalendar (RFC 5545) events, so worked time can be shown in
+//! a calendar app.
+
+pub mod feed;
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::{agent, storage};
+
+const PRODUCT_ID: &str = "-//Time Tracker//Sessions//EN";
+const TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+/// Lines longer than this many bytes are folded.
+const MAX_LINE_LENGTH: usize = 75;
+
+/// The time from the start of `from` to the end of `to`, in local time.
+pub fn day_range(from: NaiveDate, to: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+    (
+        local_midnight(from),
+        local_midnight(to + chrono::Days::new(1)),
+    )
+}
+
+fn local_midnight(date: NaiveDate) -> DateTime<Utc> {
+    date.and_time(chrono::NaiveTime::MIN)
+        .and_local_timezone(chrono::Local)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+        .unwrap_or_else(|| date.and_time(chrono::NaiveTime::MIN).and_utc())
+}
+
+/// Names of all tasks by local id, including deleted ones that still have
+/// sessions among `sessions`.
+pub fn task_names(
+    repository: &dyn storage::Repository,
+    sessions: &[agent::Session],
+) -> storage::Result<HashMap<i64, String>> {
+    let mut names: HashMap<i64, String> = repository
+        .get_all_tasks()?
+        .into_iter()
+        .map(|task| (task.t_id, task.t_name))
+        .collect();
+    for session in sessions {
+        if !names.contains_key(&session.s_task)
+            && let Some(task) = repository.get_task(session.s_task)?
+        {
+            names.insert(task.t_id, task.t_name);
+        }
+    }
+    Ok(names)
+}
+
+/// An iCalendar file with the sessions `user` worked between the local days
+/// `from` and `to`, both included.
+pub fn export(
+    repository: &dyn storage::Repository,
+    user: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> storage::Result<String> {
+    let (start, end) = day_range(from, to);
+    let sessions: Vec<agent::Session> = repository
+        .get_sessions_between(start, end)?
+        .into_iter()
+        .filter(|session| session.s_user == user)
+        .collect();
+    let names = task_names(repository, &sessions)?;
+    Ok(to_ics(&sessions, &names))
+}
+
+/// One event per session: the task's name is the summary and the session's
+/// comment the description.
+pub fn to_ics(sessions: &[agent::Session], task_names: &HashMap<i64, String>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODUCT_ID),
+        "CALSCALE:GREGORIAN".to_string(),
+        "X-WR-CALNAME:Time Tracker".to_string(),
+    ];
+    for session in sessions {
+        let name = task_names
+            .get(&session.s_task)
+            .map(String::as_str)
+            .unwrap_or("Unknown task");
+        let updated_at = DateTime::from_timestamp_millis(session.s_updated_at).unwrap_or_default();
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}@time-tracker", session.s_uuid));
+        lines.push(format!("DTSTAMP:{}", updated_at.format(TIME_FORMAT)));
+        lines.push(format!(
+            "DTSTART:{}",
+            session.started_at().format(TIME_FORMAT)
+        ));
+        lines.push(format!("DTEND:{}", session.ended_at().format(TIME_FORMAT)));
+        lines.push(format!("SUMMARY:{}", escape(name)));
+        if !session.s_comment.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape(&session.s_comment)));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+/// Escapes a text value.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => (),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Splits a long line into continuation lines that start with a space,
+/// never inside a UTF-8 character.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
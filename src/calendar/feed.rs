@@ -0,0 +1,139 @@
+//! A calendar subscription served on localhost, so calendar apps show the
+//! sessions as they are recorded.
+//!
+//! The feed's URL carries a random token, kept in a file only the user can
+//! read, so other local users and web pages can't read the sessions.
+//! Requests naming another host are refused as well, which stops web pages
+//! from reaching the feed through a domain that resolves to localhost.
+
+use std::{
+    error::Error,
+    fs, io,
+    net::{Ipv4Addr, SocketAddr},
+    path::Path,
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use tiny_http::{Header, Method, Request, Response};
+use uuid::Uuid;
+
+use crate::{agent, instance, server::routes};
+
+pub const FEED_PATH: &str = "/calendar.ics";
+const TOKEN_FILE: &str = "calendar_feed.token";
+/// Host names the feed answers to, with or without a port.
+const LOCAL_HOSTS: &[&str] = &["localhost", "127.0.0.1"];
+/// How long a request waits for the agent to read the sessions.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The feed's token from `dir`, created on first use so subscriptions keep
+/// working across restarts.
+pub fn load_token(dir: &Path) -> io::Result<String> {
+    let path = dir.join(TOKEN_FILE);
+    match fs::read_to_string(&path) {
+        Ok(token) if !token.trim().is_empty() => Ok(token.trim().to_string()),
+        Ok(_) => create_token(&path),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => create_token(&path),
+        Err(e) => Err(e),
+    }
+}
+
+fn create_token(path: &Path) -> io::Result<String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let token = Uuid::new_v4().simple().to_string();
+    instance::write_private(path, &token)?;
+    Ok(token)
+}
+
+pub struct CalendarFeed {
+    http: tiny_http::Server,
+    token: String,
+}
+
+impl CalendarFeed {
+    /// Listens on `port` of localhost only and answers requests that carry
+    /// `token`. Use port `0` to let the OS pick a free port, see
+    /// [`CalendarFeed::local_addr`].
+    pub fn bind(port: u16, token: String) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        if token.is_empty() {
+            return Err("The calendar feed needs a token".into());
+        }
+        let http = tiny_http::Server::http((Ipv4Addr::LOCALHOST, port))?;
+        Ok(Self { http, token })
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.http.server_addr().to_ip()
+    }
+
+    /// The address to subscribe to, token included.
+    pub fn url(&self) -> Option<String> {
+        let port = self.local_addr()?.port();
+        Some(format!(
+            "http://localhost:{}{}?token={}",
+            port, FEED_PATH, self.token
+        ))
+    }
+
+    /// Answers requests with the calendar the agent builds.
+    pub fn serve(self, command_tx: mpsc::Sender<agent::AgentCommand>) -> thread::JoinHandle<()> {
+        thread::Builder::new()
+            .name("calendar-feed".into())
+            .spawn(move || {
+                for request in self.http.incoming_requests() {
+                    respond(request, &self.token, &command_tx);
+                }
+            })
+            .expect("Failed to spawn calendar-feed thread")
+    }
+}
+
+fn respond(request: Request, token: &str, command_tx: &mpsc::Sender<agent::AgentCommand>) {
+    let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+    let response = match (request.method(), path) {
+        _ if !is_local_host(&request) => {
+            Response::from_string("Unknown host").with_status_code(403)
+        }
+        (Method::Get, FEED_PATH) if !has_token(query, token) => {
+            Response::from_string("Wrong or missing token").with_status_code(403)
+        }
+        (Method::Get, FEED_PATH) => {
+            let (reply_tx, reply_rx) = mpsc::channel();
+            let _ = command_tx.send(agent::AgentCommand::CalendarFeed { reply: reply_tx });
+            match reply_rx.recv_timeout(REPLY_TIMEOUT) {
+                Ok(calendar) => Response::from_string(calendar).with_header(
+                    Header::from_bytes(&b"Content-Type"[..], &b"text/calendar; charset=utf-8"[..])
+                        .unwrap(),
+                ),
+                // The database may be locked or busy; calendar apps retry.
+                Err(_) => Response::from_string("Sessions unavailable").with_status_code(503),
+            }
+        }
+        _ => Response::from_string("Not found").with_status_code(404),
+    };
+    let _ = request.respond(response);
+}
+
+/// Whether the `Host` header names this computer.
+fn is_local_host(request: &Request) -> bool {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Host"))
+        .is_some_and(|header| {
+            let host = header.value.as_str();
+            let name = host.rsplit_once(':').map_or(host, |(name, _)| name);
+            LOCAL_HOSTS.contains(&name)
+        })
+}
+
+fn has_token(query: &str, token: &str) -> bool {
+    query
+        .split('&')
+        .filter_map(|pair| pair.strip_prefix("token="))
+        .any(|sent| routes::constant_time_eq(sent.as_bytes(), token.as_bytes()))
+}
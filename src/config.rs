@@ -1,3 +1,9 @@
+pub mod logging;
+pub mod profiles;
 pub mod settings;
 
-pub use settings::Settings;
\ No newline at end of file
+pub use profiles::DEFAULT_PROFILE;
+pub use profiles::Profiles;
+pub use settings::LogLevel;
+pub use settings::Settings;
+pub use settings::StorageBackend;
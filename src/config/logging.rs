@@ -0,0 +1,70 @@
+use std::{io, path::Path, path::PathBuf, process::Command};
+
+use tracing::level_filters::LevelFilter;
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+
+use crate::config;
+
+/// Daily log files older than this many days are deleted.
+const MAX_LOG_FILES: usize = 7;
+
+impl From<config::LogLevel> for LevelFilter {
+    fn from(level: config::LogLevel) -> Self {
+        match level {
+            config::LogLevel::Error => LevelFilter::ERROR,
+            config::LogLevel::Warn => LevelFilter::WARN,
+            config::LogLevel::Info => LevelFilter::INFO,
+            config::LogLevel::Debug => LevelFilter::DEBUG,
+            config::LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// The `logs` folder next to the default profile's `settings.json`.
+pub fn log_dir() -> PathBuf {
+    config::settings::config_dir().join("logs")
+}
+
+/// Sends `tracing` events at `level` and above to a daily log file in `dir`.
+///
+/// Lines are written on a background thread; keep the returned guard alive
+/// until exit so the last ones are flushed.
+pub fn init_logging(
+    dir: &Path,
+    level: config::LogLevel,
+) -> Result<WorkerGuard, Box<dyn std::error::Error + Send + Sync>> {
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("time-tracker")
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(dir)?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    tracing_subscriber::fmt()
+        .with_writer(writer)
+        .with_ansi(false)
+        .with_thread_names(true)
+        .with_max_level(LevelFilter::from(level))
+        .try_init()?;
+    Ok(guard)
+}
+
+/// Shows the log folder in the platform's file manager.
+pub fn open_log_folder() -> io::Result<()> {
+    let dir = log_dir();
+    std::fs::create_dir_all(&dir)?;
+
+    #[cfg(target_os = "windows")]
+    let program = "explorer";
+    #[cfg(target_os = "macos")]
+    let program = "open";
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let program = "xdg-open";
+
+    Command::new(program).arg(dir).spawn()?;
+    Ok(())
+}
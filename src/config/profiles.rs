@@ -0,0 +1,102 @@
+use std::{fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Profiles {
+    pub active: String,
+    pub names: Vec<String>,
+}
+
+impl Profiles {
+    pub fn load() -> Self {
+        let mut profiles = if let Ok(data) = fs::read_to_string(profiles_path()) {
+            serde_json::from_str::<Self>(&data).unwrap_or_default()
+        } else {
+            Self::default()
+        };
+
+        if !profiles.names.contains(&DEFAULT_PROFILE.to_string()) {
+            profiles.names.insert(0, DEFAULT_PROFILE.to_string());
+        }
+        if !profiles.names.contains(&profiles.active) {
+            profiles.active = DEFAULT_PROFILE.to_string();
+        }
+
+        let _ = profiles.save();
+        profiles
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self).unwrap();
+        fs::write(profiles_path(), json)
+    }
+
+    /// The name as profiles are stored under, or `None` if it cannot be used
+    /// as a directory name.
+    pub fn validate_name(name: &str) -> Option<String> {
+        let name = name.trim();
+        is_valid_name(name).then(|| name.to_string())
+    }
+
+    /// Registers a new profile. Returns `false` if the name is taken, in any
+    /// case, or cannot be used as a directory name.
+    pub fn add(&mut self, name: &str) -> bool {
+        let Some(name) = Self::validate_name(name) else {
+            return false;
+        };
+        if self.find(&name).is_some() {
+            return false;
+        }
+
+        self.names.push(name);
+        true
+    }
+
+    pub fn set_active(&mut self, name: &str) -> bool {
+        let Some(name) = Self::validate_name(name) else {
+            return false;
+        };
+        let Some(name) = self.find(&name) else {
+            return false;
+        };
+
+        self.active = name.clone();
+        true
+    }
+
+    /// The profile stored under `name`. Names are compared ignoring case, as
+    /// the directories named after them are on Windows and macOS.
+    fn find(&self, name: &str) -> Option<&String> {
+        let name = name.to_lowercase();
+        self.names
+            .iter()
+            .find(|profile| profile.to_lowercase() == name)
+    }
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Self {
+            active: DEFAULT_PROFILE.to_string(),
+            names: vec![DEFAULT_PROFILE.to_string()],
+        }
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == ' ')
+}
+
+fn profiles_path() -> PathBuf {
+    config::settings::config_dir().join("profiles.json")
+}
@@ -3,34 +3,155 @@ use std::{fs, path::PathBuf};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::config;
+
+/// Where the agent keeps tasks and sessions.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// The SQLite database at `local_database_path`.
+    #[default]
+    Sqlite,
+    /// Nothing is written to disk; everything is lost on exit.
+    Memory,
+    /// A database shared by the team at `postgres_url`. Needs a build with
+    /// the `postgres` feature.
+    Postgres,
+}
+
+/// The most detailed messages written to the log file.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct Settings {
     pub auto_sync_interval_seconds: u64,
+    pub sync_server_url: String,
+    /// Sent with every request to the sync server, which must be started
+    /// with the same token.
+    pub sync_token: String,
     pub active_timeout_seconds: u64,
     pub local_database_path: String,
+    pub storage_backend: StorageBackend,
+    pub postgres_url: String,
+    pub backup_path: String,
+    pub backup_keep_daily: usize,
+    pub backup_keep_weekly: usize,
+    /// Keep `local_database_path` encrypted with SQLCipher. Needs a build with
+    /// the `encryption` feature.
+    pub encrypt_database: bool,
+    /// Serves a calendar of the sessions at
+    /// `http://localhost:<port>/calendar.ics?token=<token>`, with the token
+    /// kept in `calendar_feed.token` in the config folder. The Export Calendar
+    /// dialog shows the address to subscribe to. `0` turns the feed off.
+    pub calendar_feed_port: u16,
+    /// How many days back the calendar feed reaches.
+    pub calendar_feed_days: u32,
+    /// Desktop notifications when you go idle while a session runs.
+    pub notify_idle: bool,
+    /// Desktop notifications when a session uses up most of a task's budget.
+    pub notify_budget: bool,
+    /// Desktop notifications when a session has run for
+    /// `forgotten_timer_hours`.
+    pub notify_forgotten_timer: bool,
+    pub forgotten_timer_hours: u32,
+    pub pomodoro_focus_minutes: u32,
+    pub pomodoro_break_minutes: u32,
+    pub pomodoro_long_break_minutes: u32,
+    /// Every this many focus periods are followed by a long break.
+    pub pomodoro_long_break_every: u32,
+    /// Desktop notifications when a focus period or break ends.
+    pub notify_pomodoro: bool,
+    /// Reminds you to take a break after this many minutes of activity
+    /// without one; `0` never does.
+    pub break_reminder_minutes: u32,
+    /// A pause of at least this many minutes counts as a break.
+    pub min_break_minutes: u32,
+    /// Warns when the hours worked in a day pass this; `0` never does.
+    pub daily_hours_limit: u32,
+    /// Warns when the hours worked in a week pass this; `0` never does.
+    pub weekly_hours_limit: u32,
+    pub notify_break: bool,
+    pub notify_work_limit: bool,
+    /// Hours to work each day and each week, over all tasks; `0` sets no
+    /// goal.
+    pub daily_goal_hours: f64,
+    pub weekly_goal_hours: f64,
+    /// Watches which window has the focus, so window rules can switch
+    /// tasks. Only on X11 so far.
+    pub watch_active_window: bool,
+    /// How long a window has to keep the focus before its rule applies.
+    pub window_rule_delay_seconds: u32,
+    /// Desktop notifications when a window rule switches or suggests a task.
+    pub notify_task_switch: bool,
+    /// Stops a session after this many hours without activity; `0` never
+    /// does.
+    pub auto_stop_idle_hours: u32,
+    /// Stops sessions at this local time, e.g. `18:00`; empty never does.
+    pub auto_stop_at: String,
+    /// Saves the running session when the system shuts down.
+    pub auto_stop_on_shutdown: bool,
     pub uid: String,
     pub open_ui_at_start_up: bool,
+    pub log_level: LogLevel,
+
+    #[serde(skip)]
+    pub profile: String,
+    /// The passphrase entered for the encrypted database in this session.
+    #[serde(skip)]
+    pub database_passphrase: Option<String>,
 }
 
 impl Settings {
     pub fn load() -> Self {
-        let settings = if let Ok(data) = fs::read_to_string(settings_path()) {
-            if let Ok(settings) = serde_json::from_str::<Self>(&data) {
-                settings
-            } else {
-                Self::default()
-            }
-        } else {
-            Self::default()
-        };
+        let profiles = config::Profiles::load();
+        Self::load_profile(&profiles.active)
+    }
+
+    pub fn load_profile(profile: &str) -> Self {
+        let mut settings = fs::read_to_string(settings_path(profile))
+            .ok()
+            .and_then(|data| Self::from_json(profile, &data))
+            .unwrap_or_else(|| Self::for_profile(profile));
+        settings.profile = profile.to_string();
 
         let _ = Self::save(&settings);
         settings
     }
 
+    /// Settings missing from `data` take the profile's defaults rather than
+    /// `Settings::default()`, so a profile never falls back to the default
+    /// profile's database.
+    fn from_json(profile: &str, data: &str) -> Option<Self> {
+        let serde_json::Value::Object(fields) = serde_json::from_str(data).ok()? else {
+            return None;
+        };
+        let mut settings = serde_json::to_value(Self::for_profile(profile)).ok()?;
+        settings.as_object_mut()?.extend(fields);
+        serde_json::from_value(settings).ok()
+    }
+
+    fn for_profile(profile: &str) -> Self {
+        Self {
+            local_database_path: local_database_path(profile).to_string_lossy().to_string(),
+            backup_path: backup_path(profile).to_string_lossy().to_string(),
+            profile: profile.to_string(),
+            ..Self::default()
+        }
+    }
+
     fn save(&self) -> std::io::Result<()> {
         let json = serde_json::to_string_pretty(self).unwrap();
-        std::fs::write(settings_path(), json)
+        std::fs::write(settings_path(&self.profile), json)
     }
 }
 
@@ -38,26 +159,82 @@ impl Default for Settings {
     fn default() -> Self {
         Self {
             auto_sync_interval_seconds: 30,
+            sync_server_url: "".to_string(),
+            sync_token: "".to_string(),
             active_timeout_seconds: 15,
-            local_database_path: local_database_path().to_string_lossy().to_string(),
+            local_database_path: local_database_path(config::DEFAULT_PROFILE)
+                .to_string_lossy()
+                .to_string(),
+            storage_backend: StorageBackend::default(),
+            postgres_url: "".to_string(),
+            backup_path: backup_path(config::DEFAULT_PROFILE)
+                .to_string_lossy()
+                .to_string(),
+            backup_keep_daily: 7,
+            backup_keep_weekly: 4,
+            encrypt_database: false,
+            calendar_feed_port: 0,
+            calendar_feed_days: 90,
+            notify_idle: true,
+            notify_budget: true,
+            notify_forgotten_timer: true,
+            forgotten_timer_hours: 10,
+            pomodoro_focus_minutes: 25,
+            pomodoro_break_minutes: 5,
+            pomodoro_long_break_minutes: 15,
+            pomodoro_long_break_every: 4,
+            notify_pomodoro: true,
+            break_reminder_minutes: 60,
+            min_break_minutes: 5,
+            daily_hours_limit: 10,
+            weekly_hours_limit: 48,
+            notify_break: true,
+            notify_work_limit: true,
+            daily_goal_hours: 0.0,
+            weekly_goal_hours: 0.0,
+            watch_active_window: true,
+            window_rule_delay_seconds: 10,
+            notify_task_switch: true,
+            auto_stop_idle_hours: 0,
+            auto_stop_at: "".to_string(),
+            auto_stop_on_shutdown: true,
             uid: Uuid::new_v4().to_string(),
             open_ui_at_start_up: true,
+            log_level: LogLevel::default(),
+            profile: config::DEFAULT_PROFILE.to_string(),
+            database_passphrase: None,
         }
     }
 }
 
-fn settings_path() -> PathBuf {
+pub fn config_dir() -> PathBuf {
     let dir = dirs::config_dir()
         .unwrap_or(std::env::current_dir().unwrap())
         .join("time-tracker");
     std::fs::create_dir_all(&dir).ok();
-    dir.join("settings.json")
+    dir
 }
 
-fn local_database_path() -> PathBuf {
-    let dir = dirs::config_dir()
-        .unwrap_or(std::env::current_dir().unwrap())
-        .join("time-tracker");
+/// The default profile lives directly in the config directory so that
+/// installs predating profiles keep their settings and database.
+fn profile_dir(profile: &str) -> PathBuf {
+    let dir = if profile == config::DEFAULT_PROFILE {
+        config_dir()
+    } else {
+        config_dir().join("profiles").join(profile)
+    };
     std::fs::create_dir_all(&dir).ok();
-    dir.join("sessions.db")
+    dir
+}
+
+fn settings_path(profile: &str) -> PathBuf {
+    profile_dir(profile).join("settings.json")
+}
+
+fn local_database_path(profile: &str) -> PathBuf {
+    profile_dir(profile).join("sessions.db")
+}
+
+fn backup_path(profile: &str) -> PathBuf {
+    profile_dir(profile).join("backups")
 }
@@ -0,0 +1,51 @@
+use std::{error, fmt, io};
+
+use crate::{import::ImportError, storage::StorageError};
+
+/// Errors the agent reports to the UI instead of panicking.
+#[derive(Debug)]
+pub enum Error {
+    Storage(StorageError),
+    Io(io::Error),
+    Import(ImportError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Storage(e) => write!(f, "{}", e),
+            Self::Io(e) => write!(f, "I/O error: {}", e),
+            Self::Import(e) => write!(f, "Couldn't import the file: {}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Storage(e) => Some(e),
+            Self::Io(e) => Some(e),
+            Self::Import(e) => Some(e),
+        }
+    }
+}
+
+impl From<StorageError> for Error {
+    fn from(e: StorageError) -> Self {
+        Self::Storage(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<ImportError> for Error {
+    fn from(e: ImportError) -> Self {
+        Self::Import(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
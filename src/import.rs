@@ -0,0 +1,233 @@
+//! Brings in history from other time trackers. Projects, with their task if
+//! the export has one, become tasks and time entries become sessions.
+
+pub mod csv_report;
+pub mod timewarrior;
+
+use std::{
+    collections::{HashMap, HashSet},
+    error, fmt, fs,
+    path::Path,
+    str::FromStr,
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::{agent, storage};
+
+/// The tracker an export was made with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// A detailed report exported as CSV.
+    Toggl,
+    /// A detailed report exported as CSV.
+    Clockify,
+    /// The output of `timew export`.
+    Timewarrior,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Toggl, Format::Clockify, Format::Timewarrior];
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "toggl" => Ok(Format::Toggl),
+            "clockify" => Ok(Format::Clockify),
+            "timewarrior" => Ok(Format::Timewarrior),
+            _ => Err(format!("Unknown import format '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Toggl => "toggl",
+            Format::Clockify => "clockify",
+            Format::Timewarrior => "timewarrior",
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Csv(csv::Error),
+    Json(serde_json::Error),
+    /// The CSV file lacks a column every entry needs.
+    MissingColumn(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Csv(e) => write!(f, "Invalid CSV: {}", e),
+            Self::Json(e) => write!(f, "Invalid JSON: {}", e),
+            Self::MissingColumn(name) => write!(f, "The export has no '{}' column", name),
+        }
+    }
+}
+
+impl error::Error for ImportError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Csv(e) => Some(e),
+            Self::Json(e) => Some(e),
+            Self::MissingColumn(_) => None,
+        }
+    }
+}
+
+impl From<csv::Error> for ImportError {
+    fn from(e: csv::Error) -> Self {
+        Self::Csv(e)
+    }
+}
+
+impl From<serde_json::Error> for ImportError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+/// A time entry read from an export.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    /// The task the entry is booked on, e.g. `Website / Design`.
+    pub task: String,
+    pub description: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Exports without billing information count everything as billable,
+    /// like sessions tracked here.
+    pub billable: bool,
+}
+
+/// A row of the export that can't become a session.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Unmapped {
+    /// Counted from 1, including a CSV header.
+    pub row: usize,
+    pub reason: String,
+}
+
+/// The entries read from an export, and the rows that were left out.
+#[derive(Debug, Default, PartialEq)]
+pub struct Export {
+    pub entries: Vec<Entry>,
+    pub unmapped: Vec<Unmapped>,
+}
+
+impl Export {
+    fn push(&mut self, row: usize, entry: Entry) {
+        if entry.end < entry.start {
+            self.skip(row, "ends before it starts");
+        } else {
+            self.entries.push(entry);
+        }
+    }
+
+    fn skip(&mut self, row: usize, reason: &str) {
+        self.unmapped.push(Unmapped {
+            row,
+            reason: reason.to_string(),
+        });
+    }
+}
+
+/// What an import did, or would do in a dry run.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    /// Tasks that didn't exist yet, in the order they were first used.
+    pub new_tasks: Vec<String>,
+    pub imported: usize,
+    /// Entries already in the database, or repeated in the export.
+    pub duplicates: usize,
+    pub unmapped: Vec<Unmapped>,
+}
+
+pub fn parse(format: Format, reader: impl std::io::Read) -> Result<Export, ImportError> {
+    match format {
+        Format::Toggl | Format::Clockify => csv_report::parse(reader),
+        Format::Timewarrior => timewarrior::parse(reader),
+    }
+}
+
+pub fn read_export(format: Format, path: &Path) -> crate::Result<Export> {
+    Ok(parse(format, fs::File::open(path)?)?)
+}
+
+/// Saves the export's entries as sessions of `user`, creating missing tasks
+/// by name. Nothing is saved if one of them fails. An entry is a duplicate
+/// when its task already has a session ending at the same time with the same
+/// duration, so importing the same export again adds nothing twice.
+pub fn import(
+    repository: &mut dyn storage::Repository,
+    user: &str,
+    export: Export,
+    dry_run: bool,
+) -> storage::Result<ImportReport> {
+    let task_ids: HashMap<String, i64> = repository
+        .get_all_tasks()?
+        .into_iter()
+        .map(|task| (task.t_name, task.t_id))
+        .collect();
+    let task_names: HashMap<i64, String> = task_ids
+        .iter()
+        .map(|(name, id)| (*id, name.clone()))
+        .collect();
+    let mut seen: HashSet<(String, i64, u64)> = repository
+        .get_all_sessions()?
+        .into_iter()
+        .filter_map(|session| {
+            let name = task_names.get(&session.s_task)?;
+            Some((name.clone(), session.s_created_at, session.s_duration))
+        })
+        .collect();
+
+    let mut sessions = Vec::new();
+    let mut report = ImportReport {
+        dry_run,
+        unmapped: export.unmapped,
+        ..ImportReport::default()
+    };
+    for entry in export.entries {
+        let duration = (entry.end - entry.start).num_seconds() as u64;
+        let ended_at = entry.end.timestamp_millis();
+        if !seen.insert((entry.task.clone(), ended_at, duration)) {
+            report.duplicates += 1;
+            continue;
+        }
+
+        if !task_ids.contains_key(&entry.task) && !report.new_tasks.contains(&entry.task) {
+            report.new_tasks.push(entry.task.clone());
+        }
+        report.imported += 1;
+        sessions.push((
+            entry.task,
+            agent::Session {
+                s_user: user.to_string(),
+                s_duration: duration,
+                s_comment: entry.description,
+                s_created_at: ended_at,
+                s_billable: entry.billable,
+                ..Default::default()
+            },
+        ));
+    }
+
+    if !dry_run {
+        repository.import_sessions(&sessions)?;
+        tracing::info!(
+            imported = report.imported,
+            duplicates = report.duplicates,
+            unmapped = report.unmapped.len(),
+            "Imported time entries"
+        );
+    }
+    Ok(report)
+}
@@ -0,0 +1,92 @@
+//! Detailed reports exported as CSV by Toggl Track and Clockify. Both name
+//! their columns alike; times are in the exporting user's time zone, which is
+//! assumed to be this computer's. A `Billable` column of `Yes` or `No` marks
+//! whether the session is billable.
+
+use std::io;
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+
+use crate::import::{Entry, Export, ImportError};
+
+const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%m/%d/%Y", "%d.%m.%Y"];
+const TIME_FORMATS: &[&str] = &["%H:%M:%S", "%H:%M", "%I:%M:%S %p", "%I:%M %p"];
+
+pub fn parse(reader: impl io::Read) -> Result<Export, ImportError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| {
+        headers.iter().position(|header| {
+            header
+                .trim_start_matches('\u{feff}')
+                .eq_ignore_ascii_case(name)
+        })
+    };
+    let required =
+        |name: &str| column(name).ok_or_else(|| ImportError::MissingColumn(name.to_string()));
+
+    let project = required("Project")?;
+    let task = column("Task");
+    let description = column("Description");
+    let billable = column("Billable");
+    let start_date = required("Start date")?;
+    let start_time = required("Start time")?;
+    let end_date = required("End date")?;
+    let end_time = required("End time")?;
+
+    let mut export = Export::default();
+    for (index, record) in reader.records().enumerate() {
+        // The header is row 1.
+        let row = index + 2;
+        let record = record?;
+        let field = |column: Option<usize>| column.and_then(|i| record.get(i)).unwrap_or("");
+
+        let project = field(Some(project));
+        if project.is_empty() {
+            export.skip(row, "no project");
+            continue;
+        }
+        let (Some(start), Some(end)) = (
+            local_time(field(Some(start_date)), field(Some(start_time))),
+            local_time(field(Some(end_date)), field(Some(end_time))),
+        ) else {
+            export.skip(row, "unreadable start or end time");
+            continue;
+        };
+
+        let task = match field(task) {
+            "" => project.to_string(),
+            task => format!("{} / {}", project, task),
+        };
+        export.push(
+            row,
+            Entry {
+                task,
+                description: field(description).to_string(),
+                start,
+                end,
+                billable: !matches!(
+                    field(billable).to_ascii_lowercase().as_str(),
+                    "no" | "false"
+                ),
+            },
+        );
+    }
+    Ok(export)
+}
+
+fn local_time(date: &str, time: &str) -> Option<DateTime<Utc>> {
+    let date = DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(date, format).ok())?;
+    let time = TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(time, format).ok())?;
+    date.and_time(time)
+        .and_local_timezone(chrono::Local)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+}
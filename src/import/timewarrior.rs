@@ -0,0 +1,63 @@
+//! The JSON written by `timew export`. Timewarrior has no projects, so the
+//! first tag of an interval is taken as its task.
+
+use std::io;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Deserialize;
+
+use crate::import::{Entry, Export, ImportError};
+
+const TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+#[derive(Deserialize)]
+struct Interval {
+    start: String,
+    end: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    annotation: Option<String>,
+}
+
+pub fn parse(reader: impl io::Read) -> Result<Export, ImportError> {
+    let intervals: Vec<Interval> = serde_json::from_reader(reader)?;
+
+    let mut export = Export::default();
+    for (index, interval) in intervals.into_iter().enumerate() {
+        let row = index + 1;
+        let Some(end) = &interval.end else {
+            export.skip(row, "still running");
+            continue;
+        };
+        let Some((task, other_tags)) = interval.tags.split_first() else {
+            export.skip(row, "no tags");
+            continue;
+        };
+        let (Some(start), Some(end)) = (utc_time(&interval.start), utc_time(end)) else {
+            export.skip(row, "unreadable start or end time");
+            continue;
+        };
+
+        let description = interval
+            .annotation
+            .clone()
+            .unwrap_or_else(|| other_tags.join(", "));
+        export.push(
+            row,
+            Entry {
+                task: task.clone(),
+                description,
+                start,
+                end,
+                billable: true,
+            },
+        );
+    }
+    Ok(export)
+}
+
+fn utc_time(time: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(time, TIME_FORMAT)
+        .ok()
+        .map(|time| time.and_utc())
+}
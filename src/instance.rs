@@ -0,0 +1,260 @@
+//! Keeps a single time tracker running per user. The first launch holds a
+//! lock file and listens on a localhost port; later launches forward their
+//! request to it and exit.
+//!
+//! Any local process can connect to the port, so the first launch also
+//! writes a random token next to it, readable by the user only. Requests
+//! without that token are dropped.
+
+use std::{
+    fmt, fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use uuid::Uuid;
+
+use crate::{agent, server::routes};
+
+const LOCK_FILE: &str = "instance.lock";
+const PORT_FILE: &str = "instance.port";
+/// Longest request line accepted, so a stray client can't make the listener
+/// buffer without end.
+const MAX_LINE_BYTES: u64 = 4096;
+const IO_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long a second launch waits for a starting instance to publish its port.
+const CONNECT_ATTEMPTS: u32 = 20;
+const CONNECT_DELAY: Duration = Duration::from_millis(100);
+
+pub const USAGE: &str = "Usage: time_tracker [show | sync | unlock | quit | report ...]
+
+  show    Open the window
+  sync    Sync with the team server now
+  unlock  Ask for the passphrase of the encrypted database
+  quit    Quit the running time tracker
+  report  Save a weekly timesheet, see `time_tracker report --help`";
+
+/// What a launch asks the running instance to do.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Request {
+    Show,
+    SyncNow,
+    /// Parsed from the command line with an empty passphrase, which is then
+    /// prompted for.
+    Unlock {
+        passphrase: String,
+    },
+    Quit,
+}
+
+impl Request {
+    /// Parses the command line; no arguments means a plain launch.
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, String> {
+        match args {
+            [] => Ok(None),
+            [arg] => arg.parse().map(Some),
+            _ => Err(format!("Unexpected argument '{}'", args[1])),
+        }
+    }
+
+    pub fn command(self) -> agent::AgentCommand {
+        match self {
+            Request::Show => agent::AgentCommand::ShowUI,
+            Request::SyncNow => agent::AgentCommand::SyncNow,
+            Request::Unlock { passphrase } => agent::AgentCommand::Unlock {
+                passphrase,
+                remember: false,
+            },
+            Request::Quit => agent::AgentCommand::Quit,
+        }
+    }
+
+    /// The line sent to the running instance. Unlike `Display` it includes
+    /// the passphrase, so it must never be logged.
+    fn to_line(&self) -> String {
+        match self {
+            Request::Unlock { passphrase } => format!("{} {}", self, passphrase),
+            _ => self.to_string(),
+        }
+    }
+
+    fn from_line(line: &str) -> Result<Self, String> {
+        let (name, passphrase) = line.split_once(' ').unwrap_or((line, ""));
+        match name.parse()? {
+            Request::Unlock { .. } => Ok(Request::Unlock {
+                passphrase: passphrase.to_string(),
+            }),
+            request => Ok(request),
+        }
+    }
+}
+
+impl FromStr for Request {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "show" => Ok(Request::Show),
+            "sync" => Ok(Request::SyncNow),
+            "unlock" => Ok(Request::Unlock {
+                passphrase: String::new(),
+            }),
+            "quit" => Ok(Request::Quit),
+            _ => Err(format!("Unknown command '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Request::Show => "show",
+            Request::SyncNow => "sync",
+            Request::Unlock { .. } => "unlock",
+            Request::Quit => "quit",
+        })
+    }
+}
+
+/// The running instance. Dropping it releases the lock.
+pub struct Instance {
+    _lock: fs::File,
+    listener: TcpListener,
+    token: String,
+}
+
+impl Instance {
+    /// Takes the lock in `dir`, or returns `None` if another instance holds it.
+    pub fn acquire(dir: &Path) -> io::Result<Option<Self>> {
+        fs::create_dir_all(dir)?;
+        let lock = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(dir.join(LOCK_FILE))?;
+        match lock.try_lock() {
+            Ok(()) => (),
+            Err(fs::TryLockError::WouldBlock) => return Ok(None),
+            Err(fs::TryLockError::Error(e)) => return Err(e),
+        }
+
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let token = Uuid::new_v4().simple().to_string();
+        write_private(
+            &port_path(dir),
+            &format!("{} {}", listener.local_addr()?.port(), token),
+        )?;
+        Ok(Some(Self {
+            _lock: lock,
+            listener,
+            token,
+        }))
+    }
+
+    /// Passes requests from later launches on to the agent.
+    pub fn listen(self, command_tx: mpsc::Sender<agent::AgentCommand>) -> thread::JoinHandle<()> {
+        thread::Builder::new()
+            .name("instance-listener".into())
+            .spawn(move || {
+                for stream in self.listener.incoming().flatten() {
+                    match receive(stream, &self.token) {
+                        Ok(request) => {
+                            tracing::info!(%request, "Request from another launch");
+                            if command_tx.send(request.command()).is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) => tracing::warn!("Ignoring bad instance request: {}", e),
+                    }
+                }
+            })
+            .expect("Failed to spawn instance-listener thread")
+    }
+}
+
+fn port_path(dir: &Path) -> PathBuf {
+    dir.join(PORT_FILE)
+}
+
+/// Writes `contents` to a file only the current user can read. On Windows
+/// the per-user config directory already keeps other users out.
+pub(crate) fn write_private(path: &Path, contents: &str) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.create(true).truncate(true).write(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let file = options.open(path)?;
+        // The mode only applies to new files; tighten one left by an older
+        // version too.
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        (&file).write_all(contents.as_bytes())
+    }
+    #[cfg(not(unix))]
+    {
+        options.open(path)?.write_all(contents.as_bytes())
+    }
+}
+
+fn receive(stream: TcpStream, token: &str) -> io::Result<Request> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new((&stream).take(MAX_LINE_BYTES)).read_line(&mut line)?;
+    // Only the line break is stripped: spaces may be part of a passphrase.
+    let line = line.trim_end_matches(['\r', '\n']);
+    let (sent_token, line) = line.split_once(' ').unwrap_or(("", line));
+    if !routes::constant_time_eq(sent_token.as_bytes(), token.as_bytes()) {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "wrong or missing token",
+        ));
+    }
+    let request =
+        Request::from_line(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    (&stream).write_all(b"ok\n")?;
+    Ok(request)
+}
+
+/// Sends `request` to the instance holding the lock in `dir` and waits for it
+/// to be accepted.
+pub fn forward(dir: &Path, request: Request) -> io::Result<()> {
+    let mut attempt = 0;
+    let (stream, token) = loop {
+        match connect(dir) {
+            Ok(connection) => break connection,
+            // The other instance may still be starting up.
+            Err(_) if attempt < CONNECT_ATTEMPTS => {
+                thread::sleep(CONNECT_DELAY);
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    };
+
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    (&stream).write_all(format!("{} {}\n", token, request.to_line()).as_bytes())?;
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply)?;
+    if reply.trim() != "ok" {
+        return Err(io::Error::other(
+            "the running instance didn't accept the request",
+        ));
+    }
+    Ok(())
+}
+
+/// Connects to the port in the port file and returns the token to send.
+fn connect(dir: &Path) -> io::Result<(TcpStream, String)> {
+    let bad_file = || io::Error::new(io::ErrorKind::InvalidData, "bad port file");
+    let contents = fs::read_to_string(port_path(dir))?;
+    let (port, token) = contents.trim().split_once(' ').ok_or_else(bad_file)?;
+    let port: u16 = port.parse().map_err(|_| bad_file())?;
+    let stream = TcpStream::connect_timeout(&(Ipv4Addr::LOCALHOST, port).into(), IO_TIMEOUT)?;
+    Ok((stream, token.to_string()))
+}
@@ -0,0 +1,33 @@
+//! Core of the time tracker: the agent state machine, storage, settings and
+//! sync, independent of the egui window and the tray icon.
+
+pub static DB_SCHEMA: &str = include_str!("../assets/schema.sql");
+pub static DB_MIGRATIONS: &[&str] = &[
+    include_str!("../assets/migrations/0001_global_ids.sql"),
+    include_str!("../assets/migrations/0002_field_clocks.sql"),
+    include_str!("../assets/migrations/0003_billing.sql"),
+    include_str!("../assets/migrations/0004_estimates.sql"),
+    include_str!("../assets/migrations/0005_pomodoros.sql"),
+    include_str!("../assets/migrations/0006_goals.sql"),
+    include_str!("../assets/migrations/0007_window_rules.sql"),
+    include_str!("../assets/migrations/0008_outbox_triggers.sql"),
+    include_str!("../assets/migrations/0009_required_uuids.sql"),
+];
+#[cfg(feature = "postgres")]
+pub static POSTGRES_DB_SCHEMA: &str = include_str!("../assets/schema_postgres.sql");
+pub static SERVER_DB_SCHEMA: &str = include_str!("../assets/server_schema.sql");
+
+pub mod agent;
+pub mod billing;
+pub mod calendar;
+pub mod config;
+pub mod error;
+pub mod import;
+pub mod instance;
+pub mod report;
+pub mod server;
+pub mod storage;
+pub mod sync;
+
+pub use error::Error;
+pub use error::Result;
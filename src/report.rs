@@ -0,0 +1,311 @@
+//! Weekly timesheets per person, rendered to standalone HTML that prints
+//! well to PDF. The layout comes from a template the user can edit.
+
+pub mod compliance;
+pub mod goals;
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::{calendar, config, storage};
+
+/// The template used until the user edits the copy in [`template_path`].
+pub const DEFAULT_TEMPLATE: &str = include_str!("../assets/timesheet.html");
+const DAY_FORMAT: &str = "%a %-d %b";
+const DATE_FORMAT: &str = "%-d %B %Y";
+
+pub const USAGE: &str = "Usage: time_tracker report [--week YYYY-MM-DD] [--user ID] [--output FILE]
+
+  --week    Any day of the week to report, by default this week
+  --user    The person whose sessions are reported, by default you
+  --output  Where to save the timesheet, by default timesheet-<week>.html";
+
+/// The time one person spent on each task on each day of a week.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timesheet {
+    pub person: String,
+    /// Monday to Sunday.
+    pub days: Vec<NaiveDate>,
+    /// Sorted by task name.
+    pub rows: Vec<TimesheetRow>,
+    pub comments: Vec<TimesheetComment>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimesheetRow {
+    pub task: String,
+    /// One entry per day of the timesheet.
+    pub seconds: Vec<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimesheetComment {
+    pub date: NaiveDate,
+    pub task: String,
+    pub comment: String,
+}
+
+impl TimesheetRow {
+    pub fn total(&self) -> u64 {
+        self.seconds.iter().sum()
+    }
+}
+
+impl Timesheet {
+    pub fn day_totals(&self) -> Vec<u64> {
+        (0..self.days.len())
+            .map(|day| self.rows.iter().map(|row| row.seconds[day]).sum())
+            .collect()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.rows.iter().map(TimesheetRow::total).sum()
+    }
+}
+
+/// The Monday of the week `date` falls in.
+pub fn week_start(date: NaiveDate) -> NaiveDate {
+    date - chrono::Days::new(date.weekday().num_days_from_monday().into())
+}
+
+/// Builds the timesheet of `user` for the week `date` falls in. Sessions
+/// count on the local day they started.
+pub fn timesheet(
+    repository: &dyn storage::Repository,
+    user: &str,
+    date: NaiveDate,
+) -> storage::Result<Timesheet> {
+    let first = week_start(date);
+    let days: Vec<NaiveDate> = first.iter_days().take(7).collect();
+    let (from, to) = calendar::day_range(days[0], days[6]);
+    let sessions: Vec<_> = repository
+        .get_sessions_between(from, to)?
+        .into_iter()
+        .filter(|session| session.s_user == user)
+        .collect();
+    let names = calendar::task_names(repository, &sessions)?;
+
+    let mut rows: BTreeMap<String, Vec<u64>> = BTreeMap::new();
+    let mut comments = Vec::new();
+    for session in &sessions {
+        let task = names
+            .get(&session.s_task)
+            .cloned()
+            .unwrap_or_else(|| "Unknown task".to_string());
+        let started_on = session
+            .started_at()
+            .with_timezone(&chrono::Local)
+            .date_naive();
+        // A session running into the week from the Sunday before counts on Monday.
+        let day = (started_on - first).num_days().clamp(0, 6) as usize;
+        rows.entry(task.clone()).or_insert_with(|| vec![0; 7])[day] += session.s_duration;
+        if !session.s_comment.is_empty() {
+            comments.push(TimesheetComment {
+                date: days[day],
+                task,
+                comment: session.s_comment.clone(),
+            });
+        }
+    }
+    comments.sort_by_key(|comment| comment.date);
+
+    let person = repository
+        .get_all_users()?
+        .into_iter()
+        .find(|candidate| candidate.u_id == user)
+        .map(|found| found.u_name)
+        .unwrap_or_else(|| user.to_string());
+    Ok(Timesheet {
+        person,
+        days,
+        rows: rows
+            .into_iter()
+            .map(|(task, seconds)| TimesheetRow { task, seconds })
+            .collect(),
+        comments,
+    })
+}
+
+/// Where the user's copy of the template is kept.
+pub fn template_path() -> PathBuf {
+    config::settings::config_dir()
+        .join("templates")
+        .join("timesheet.html")
+}
+
+/// Reads the template at `path`, first saving the default there so there
+/// is a copy to customise.
+pub fn load_template(path: &Path) -> io::Result<String> {
+    match fs::read_to_string(path) {
+        Ok(template) => Ok(template),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, DEFAULT_TEMPLATE)?;
+            Ok(DEFAULT_TEMPLATE.to_string())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Fills the template's placeholders: `{{person}}`, `{{period}}`,
+/// `{{total}}`, `{{table}}`, `{{comments}}` and `{{generated_at}}`.
+pub fn render_html(
+    timesheet: &Timesheet,
+    template: &str,
+    generated_at: chrono::DateTime<chrono::Local>,
+) -> String {
+    let period = match (timesheet.days.first(), timesheet.days.last()) {
+        (Some(first), Some(last)) => format!(
+            "{} – {}",
+            first.format(DATE_FORMAT),
+            last.format(DATE_FORMAT)
+        ),
+        _ => String::new(),
+    };
+    template
+        .replace("{{person}}", &escape(&timesheet.person))
+        .replace("{{period}}", &period)
+        .replace("{{total}}", &format_hours(timesheet.total()))
+        .replace("{{table}}", &table_html(timesheet))
+        .replace("{{comments}}", &comments_html(timesheet))
+        .replace(
+            "{{generated_at}}",
+            &generated_at.format("%Y-%m-%d %H:%M").to_string(),
+        )
+}
+
+fn table_html(timesheet: &Timesheet) -> String {
+    let mut html = String::from("<table class=\"timesheet\">\n<thead><tr><th>Task</th>");
+    for day in &timesheet.days {
+        html.push_str(&format!(
+            "<th class=\"hours\">{}</th>",
+            day.format(DAY_FORMAT)
+        ));
+    }
+    html.push_str("<th class=\"hours\">Total</th></tr></thead>\n<tbody>\n");
+
+    for row in &timesheet.rows {
+        html.push_str(&format!(
+            "<tr><th class=\"task\">{}</th>",
+            escape(&row.task)
+        ));
+        for seconds in &row.seconds {
+            html.push_str(&hours_cell("td", *seconds));
+        }
+        html.push_str(&hours_cell("td", row.total()));
+        html.push_str("</tr>\n");
+    }
+
+    html.push_str("</tbody>\n<tfoot><tr><th>Total</th>");
+    for seconds in timesheet.day_totals() {
+        html.push_str(&hours_cell("th", seconds));
+    }
+    html.push_str(&hours_cell("th", timesheet.total()));
+    html.push_str("</tr></tfoot>\n</table>");
+    html
+}
+
+/// Empty for days without work, so the worked days stand out.
+fn hours_cell(tag: &str, seconds: u64) -> String {
+    let hours = if seconds == 0 {
+        String::new()
+    } else {
+        format_hours(seconds)
+    };
+    format!("<{tag} class=\"hours\">{hours}</{tag}>")
+}
+
+fn comments_html(timesheet: &Timesheet) -> String {
+    if timesheet.comments.is_empty() {
+        return "<p class=\"comments\">No comments.</p>".to_string();
+    }
+    let mut html = String::from("<ul class=\"comments\">\n");
+    for comment in &timesheet.comments {
+        html.push_str(&format!(
+            "<li><span class=\"date\">{}</span> <span class=\"task\">{}</span>: {}</li>\n",
+            comment.date.format(DAY_FORMAT),
+            escape(&comment.task),
+            escape(&comment.comment)
+        ));
+    }
+    html.push_str("</ul>");
+    html
+}
+
+/// Hours and minutes, e.g. `7:05`.
+pub fn format_hours(seconds: u64) -> String {
+    let minutes = seconds / 60;
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// What `time_tracker report` was asked for.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReportRequest {
+    pub week: Option<NaiveDate>,
+    pub user: Option<String>,
+    pub output: Option<PathBuf>,
+}
+
+impl ReportRequest {
+    /// Parses the arguments after `report`.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut request = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("'{}' needs a value", arg))
+            };
+            match arg.as_str() {
+                "--week" => {
+                    let week = value()?;
+                    request.week = Some(
+                        NaiveDate::parse_from_str(week, "%Y-%m-%d")
+                            .map_err(|_| format!("'{}' is not a YYYY-MM-DD date", week))?,
+                    );
+                }
+                "--user" => request.user = Some(value()?.clone()),
+                "--output" => request.output = Some(value()?.into()),
+                _ => return Err(format!("Unexpected argument '{}'", arg)),
+            }
+        }
+        Ok(request)
+    }
+}
+
+/// Builds a timesheet and saves it to `path`; used by both the window and
+/// the command line.
+pub fn save_timesheet(
+    repository: &dyn storage::Repository,
+    user: &str,
+    date: NaiveDate,
+    path: &Path,
+) -> crate::Result<()> {
+    let timesheet = timesheet(repository, user, date)?;
+    let template = load_template(&template_path())?;
+    fs::write(
+        path,
+        render_html(&timesheet, &template, chrono::Local::now()),
+    )?;
+    tracing::info!(path = %path.display(), "Saved timesheet");
+    Ok(())
+}
+
+/// The default file name for the timesheet of the week `date` falls in.
+pub fn default_file_name(date: NaiveDate) -> String {
+    format!("timesheet-{}.html", week_start(date).format("%Y-%m-%d"))
+}
@@ -0,0 +1,181 @@
+//! How the hours worked measure up to working-time limits: hours per day and
+//! per week, and the longest stretch worked without a break.
+
+use chrono::NaiveDate;
+
+use crate::{agent, calendar, config, report, storage};
+
+/// The limits in the settings, in seconds; `None` where there is none.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorkLimits {
+    pub daily: Option<u64>,
+    pub weekly: Option<u64>,
+    /// The longest anyone should work without a break.
+    pub stretch: Option<u64>,
+    /// The shortest pause that counts as a break.
+    pub min_break: u64,
+}
+
+impl WorkLimits {
+    pub fn from_settings(settings: &config::Settings) -> Self {
+        let limit = |seconds: u64| (seconds > 0).then_some(seconds);
+        Self {
+            daily: limit(u64::from(settings.daily_hours_limit) * 3600),
+            weekly: limit(u64::from(settings.weekly_hours_limit) * 3600),
+            stretch: limit(u64::from(settings.break_reminder_minutes) * 60),
+            min_break: u64::from(settings.min_break_minutes.max(1)) * 60,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DayCompliance {
+    pub date: NaiveDate,
+    pub worked: u64,
+    /// From the start of the first session to the end of the last one with
+    /// no break in between.
+    pub longest_stretch: u64,
+}
+
+impl DayCompliance {
+    pub fn over_limit(&self, limits: &WorkLimits) -> bool {
+        limits.daily.is_some_and(|limit| self.worked > limit)
+    }
+
+    pub fn missed_break(&self, limits: &WorkLimits) -> bool {
+        limits
+            .stretch
+            .is_some_and(|limit| self.longest_stretch > limit)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeekCompliance {
+    /// The Monday.
+    pub start: NaiveDate,
+    /// Monday to Sunday.
+    pub days: Vec<DayCompliance>,
+}
+
+impl WeekCompliance {
+    pub fn worked(&self) -> u64 {
+        self.days.iter().map(|day| day.worked).sum()
+    }
+
+    pub fn over_limit(&self, limits: &WorkLimits) -> bool {
+        limits.weekly.is_some_and(|limit| self.worked() > limit)
+    }
+
+    pub fn days_over_limit(&self, limits: &WorkLimits) -> usize {
+        self.days
+            .iter()
+            .filter(|day| day.over_limit(limits))
+            .count()
+    }
+
+    pub fn missed_breaks(&self, limits: &WorkLimits) -> usize {
+        self.days
+            .iter()
+            .filter(|day| day.missed_break(limits))
+            .count()
+    }
+
+    pub fn compliant(&self, limits: &WorkLimits) -> bool {
+        !self.over_limit(limits)
+            && self.days_over_limit(limits) == 0
+            && self.missed_breaks(limits) == 0
+    }
+}
+
+/// A few weeks measured against the limits they were checked with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Compliance {
+    pub limits: WorkLimits,
+    /// Oldest first.
+    pub weeks: Vec<WeekCompliance>,
+}
+
+/// Measures the `weeks` weeks up to the one `date` falls in against `limits`.
+/// Only `user`'s sessions count, each on the local day it started.
+pub fn compliance(
+    repository: &dyn storage::Repository,
+    user: &str,
+    limits: WorkLimits,
+    date: NaiveDate,
+    weeks: u32,
+) -> storage::Result<Compliance> {
+    let last = report::week_start(date);
+    let first = last - chrono::Days::new(7 * u64::from(weeks.max(1) - 1));
+    let days: Vec<NaiveDate> = first
+        .iter_days()
+        .take_while(|day| *day <= last + chrono::Days::new(6))
+        .collect();
+    let (from, to) = calendar::day_range(first, last + chrono::Days::new(6));
+    let mut sessions: Vec<agent::Session> = repository
+        .get_sessions_between(from, to)?
+        .into_iter()
+        .filter(|session| session.s_user == user)
+        .collect();
+    sessions.sort_by_key(|session| session.started_at());
+
+    let days: Vec<DayCompliance> = days
+        .into_iter()
+        .map(|date| {
+            let sessions: Vec<&agent::Session> = sessions
+                .iter()
+                .filter(|session| {
+                    session
+                        .started_at()
+                        .with_timezone(&chrono::Local)
+                        .date_naive()
+                        == date
+                })
+                .collect();
+            DayCompliance {
+                date,
+                worked: sessions.iter().map(|session| session.s_duration).sum(),
+                longest_stretch: longest_stretch(&sessions, limits.min_break),
+            }
+        })
+        .collect();
+    Ok(Compliance {
+        limits,
+        weeks: days
+            .chunks(7)
+            .map(|days| WeekCompliance {
+                start: days[0].date,
+                days: days.to_vec(),
+            })
+            .collect(),
+    })
+}
+
+/// The longest span of `sessions`, sorted by start, with pauses shorter
+/// than `min_break` between them.
+fn longest_stretch(sessions: &[&agent::Session], min_break: u64) -> u64 {
+    let min_break = chrono::Duration::seconds(min_break as i64);
+    let mut longest = chrono::Duration::zero();
+    let mut stretch: Option<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)> = None;
+    for session in sessions {
+        let (start, end) = match stretch {
+            Some((start, end)) if session.started_at() - end < min_break => {
+                (start, end.max(session.ended_at()))
+            }
+            _ => (session.started_at(), session.ended_at()),
+        };
+        longest = longest.max(end - start);
+        stretch = Some((start, end));
+    }
+    longest.num_seconds().max(0) as u64
+}
+
+/// What `user` has worked on the day `date` and in its week.
+pub fn worked(
+    repository: &dyn storage::Repository,
+    user: &str,
+    date: NaiveDate,
+) -> storage::Result<(u64, u64)> {
+    let timesheet = report::timesheet(repository, user, date)?;
+    let day = (date - report::week_start(date)).num_days() as usize;
+    Ok((timesheet.day_totals()[day], timesheet.total()))
+}
@@ -0,0 +1,237 @@
+//! Targets for the hours worked each day or week, overall or on one task,
+//! and how often they were met.
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::{agent, calendar, config, report, storage};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Period {
+    Day,
+    Week,
+}
+
+impl Period {
+    /// The first day of the period `date` falls in.
+    pub fn start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => date,
+            Period::Week => report::week_start(date),
+        }
+    }
+
+    pub fn days(self) -> u64 {
+        match self {
+            Period::Day => 1,
+            Period::Week => 7,
+        }
+    }
+
+    /// How the current period is called, e.g. in the status bar.
+    pub fn label(self) -> &'static str {
+        match self {
+            Period::Day => "Today",
+            Period::Week => "This week",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Goal {
+    /// `None` for all work.
+    pub task: Option<i64>,
+    /// The task's name, or "All tasks".
+    pub name: String,
+    pub period: Period,
+    /// Seconds.
+    pub target: u64,
+}
+
+/// The goals for all work in the settings, then each task's own.
+pub fn goals(settings: &config::Settings, tasks: &[agent::Task]) -> Vec<Goal> {
+    let all = [
+        (Period::Day, settings.daily_goal_hours),
+        (Period::Week, settings.weekly_goal_hours),
+    ]
+    .into_iter()
+    .filter(|(_, hours)| *hours > 0.0)
+    .map(|(period, hours)| Goal {
+        task: None,
+        name: "All tasks".to_string(),
+        period,
+        target: (hours * 3600.0).round() as u64,
+    });
+    let tasks = tasks.iter().flat_map(|task| {
+        [
+            (Period::Day, task.t_daily_goal),
+            (Period::Week, task.t_weekly_goal),
+        ]
+        .into_iter()
+        .filter_map(|(period, target)| {
+            target.filter(|target| *target > 0).map(|target| Goal {
+                task: Some(task.t_id),
+                name: task.t_name.clone(),
+                period,
+                target,
+            })
+        })
+    });
+    all.chain(tasks).collect()
+}
+
+/// How far one goal got in one period.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GoalProgress {
+    pub goal: Goal,
+    /// Seconds.
+    pub worked: u64,
+}
+
+impl GoalProgress {
+    pub fn met(&self) -> bool {
+        self.worked >= self.goal.target
+    }
+
+    /// Between 0 and 1.
+    pub fn fraction(&self) -> f32 {
+        (self.worked as f32 / self.goal.target.max(1) as f32).min(1.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeriodResult {
+    pub start: NaiveDate,
+    /// Seconds.
+    pub worked: u64,
+}
+
+/// A goal's results over its recent periods, the current one last.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GoalHistory {
+    pub goal: Goal,
+    pub periods: Vec<PeriodResult>,
+}
+
+impl GoalHistory {
+    pub fn current(&self) -> GoalProgress {
+        GoalProgress {
+            goal: self.goal.clone(),
+            worked: self.periods.last().map_or(0, |period| period.worked),
+        }
+    }
+
+    pub fn met(&self, period: &PeriodResult) -> bool {
+        period.worked >= self.goal.target
+    }
+
+    /// The periods that count towards attainment and streaks: weekends only
+    /// count for daily goals when they were met, and the current period only
+    /// once it is met.
+    pub fn counted(&self) -> Vec<&PeriodResult> {
+        let current = self.periods.len().saturating_sub(1);
+        self.periods
+            .iter()
+            .enumerate()
+            .filter(|(index, period)| {
+                let weekend = self.goal.period == Period::Day
+                    && period.start.weekday().number_from_monday() > 5;
+                self.met(period) || (*index != current && !weekend)
+            })
+            .map(|(_, period)| period)
+            .collect()
+    }
+
+    /// How many of the counted periods met the goal.
+    pub fn attained(&self) -> usize {
+        self.counted()
+            .into_iter()
+            .filter(|period| self.met(period))
+            .count()
+    }
+
+    /// The met periods in a row up to now.
+    pub fn streak(&self) -> usize {
+        self.counted()
+            .into_iter()
+            .rev()
+            .take_while(|period| self.met(period))
+            .count()
+    }
+
+    pub fn best_streak(&self) -> usize {
+        let mut best = 0;
+        let mut streak = 0;
+        for period in self.counted() {
+            streak = if self.met(period) { streak + 1 } else { 0 };
+            best = best.max(streak);
+        }
+        best
+    }
+}
+
+/// The last `periods` periods of each goal up to the one `date` falls in.
+/// Only `user`'s sessions count, each on the local day it started.
+pub fn history(
+    repository: &dyn storage::Repository,
+    user: &str,
+    goals: &[Goal],
+    date: NaiveDate,
+    periods: u32,
+) -> storage::Result<Vec<GoalHistory>> {
+    let periods = u64::from(periods.max(1));
+    let first_start = |goal: &Goal| {
+        goal.period.start(date) - chrono::Days::new((periods - 1) * goal.period.days())
+    };
+    let first = goals.iter().map(first_start).min().unwrap_or(date);
+    let last = report::week_start(date) + chrono::Days::new(6);
+    let (from, to) = calendar::day_range(first, last);
+    let worked: Vec<(NaiveDate, i64, u64)> = repository
+        .get_sessions_between(from, to)?
+        .into_iter()
+        .filter(|session| session.s_user == user)
+        .map(|session| {
+            let day = session
+                .started_at()
+                .with_timezone(&chrono::Local)
+                .date_naive();
+            (day, session.s_task, session.s_duration)
+        })
+        .collect();
+
+    Ok(goals
+        .iter()
+        .map(|goal| {
+            let periods = (0..periods)
+                .map(|index| {
+                    let start = first_start(goal) + chrono::Days::new(index * goal.period.days());
+                    let end = start + chrono::Days::new(goal.period.days());
+                    let worked = worked
+                        .iter()
+                        .filter(|(day, task, _)| {
+                            *day >= start && *day < end && goal.task.is_none_or(|id| id == *task)
+                        })
+                        .map(|(_, _, seconds)| seconds)
+                        .sum();
+                    PeriodResult { start, worked }
+                })
+                .collect();
+            GoalHistory {
+                goal: goal.clone(),
+                periods,
+            }
+        })
+        .collect())
+}
+
+/// Each goal's progress in the period `date` falls in.
+pub fn progress(
+    repository: &dyn storage::Repository,
+    user: &str,
+    goals: &[Goal],
+    date: NaiveDate,
+) -> storage::Result<Vec<GoalProgress>> {
+    Ok(history(repository, user, goals, date, 1)?
+        .iter()
+        .map(GoalHistory::current)
+        .collect())
+}
@@ -0,0 +1,49 @@
+pub mod routes;
+pub mod store;
+
+use std::net::SocketAddr;
+
+use rusqlite::Connection;
+
+/// Team sync server: accepts pushes from clients, serves their shared tasks
+/// back and aggregates team reports.
+pub struct SyncServer {
+    conn: Connection,
+    http: tiny_http::Server,
+    token: String,
+}
+
+impl SyncServer {
+    /// Opens the server database and binds the HTTP listener. Use port `0`
+    /// to let the OS pick a free port, see [`SyncServer::local_addr`].
+    ///
+    /// Clients must send `token`, the `sync_token` in their settings, with
+    /// every request.
+    pub fn bind(
+        addr: &str,
+        db_path: &str,
+        token: &str,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        if token.is_empty() {
+            return Err("A sync token is required".into());
+        }
+        let conn = store::open(db_path)?;
+        let http = tiny_http::Server::http(addr)?;
+        Ok(Self {
+            conn,
+            http,
+            token: token.to_string(),
+        })
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.http.server_addr().to_ip()
+    }
+
+    /// Serves requests until the process exits.
+    pub fn run(mut self) {
+        for request in self.http.incoming_requests() {
+            routes::handle_request(&mut self.conn, &self.token, request);
+        }
+    }
+}
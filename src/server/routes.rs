@@ -0,0 +1,81 @@
+use std::io::Read;
+
+use rusqlite::Connection;
+use tiny_http::{Header, Method, Request, Response};
+
+use crate::{server::store, sync::protocol};
+
+/// Larger bodies are refused rather than read into memory. A client pushes
+/// at most a batch of 100 records, which stays far below this.
+pub const MAX_BODY_BYTES: u64 = 4 * 1024 * 1024;
+
+pub fn handle_request(conn: &mut Connection, token: &str, mut request: Request) {
+    let path = request.url().split('?').next().unwrap_or("").to_string();
+    let mut body = String::new();
+    let (status, json) = if path != "/health" && !is_authorized(&request, token) {
+        (401, error_json("Missing or wrong sync token"))
+    } else if request
+        .as_reader()
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_string(&mut body)
+        .is_err()
+    {
+        (400, error_json("Request body is not valid UTF-8"))
+    } else if body.len() as u64 > MAX_BODY_BYTES {
+        (413, error_json("Request body is too large"))
+    } else {
+        route(conn, request.method(), &path, &body)
+    };
+
+    let response = Response::from_string(json)
+        .with_status_code(status)
+        .with_header(Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap());
+    let _ = request.respond(response);
+}
+
+pub fn route(conn: &mut Connection, method: &Method, path: &str, body: &str) -> (u16, String) {
+    match (method, path) {
+        (Method::Post, "/push") => match serde_json::from_str::<protocol::PushRequest>(body) {
+            Ok(push) if push.uid.is_empty() => (400, error_json("Missing client uid")),
+            Ok(push) => match store::apply_push(conn, &push) {
+                Ok(accepted) => ok_json(&protocol::PushResponse { accepted }),
+                Err(e) => (500, error_json(&e.to_string())),
+            },
+            Err(e) => (400, error_json(&e.to_string())),
+        },
+        (Method::Get, "/tasks") => match store::get_shared_tasks(conn) {
+            Ok(tasks) => ok_json(&protocol::PullResponse { tasks }),
+            Err(e) => (500, error_json(&e.to_string())),
+        },
+        (Method::Get, "/reports") => match store::get_team_report(conn) {
+            Ok(report) => ok_json(&report),
+            Err(e) => (500, error_json(&e.to_string())),
+        },
+        (Method::Get, "/health") => (200, "\"ok\"".to_string()),
+        _ => (404, error_json("Not found")),
+    }
+}
+
+/// Whether the request carries `Authorization: Bearer <token>`.
+fn is_authorized(request: &Request, token: &str) -> bool {
+    let expected = format!("Bearer {}", token);
+    request
+        .headers()
+        .iter()
+        .filter(|header| header.field.equiv("Authorization"))
+        .any(|header| constant_time_eq(header.value.as_bytes(), expected.as_bytes()))
+}
+
+/// Compares without returning early, so the time taken doesn't tell how
+/// much of a guessed token was right.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn ok_json<T: serde::Serialize>(value: &T) -> (u16, String) {
+    (200, serde_json::to_string(value).unwrap())
+}
+
+fn error_json(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
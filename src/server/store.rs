@@ -0,0 +1,167 @@
+use rusqlite::{Connection, Result};
+
+use crate::{SERVER_DB_SCHEMA, sync::protocol};
+
+pub fn open(path: &str) -> Result<Connection> {
+    let conn = Connection::open(path)?;
+
+    conn.execute_batch(
+        "
+        PRAGMA foreign_keys = ON;
+        PRAGMA journal_mode = WAL;
+        ",
+    )?;
+
+    conn.execute_batch(SERVER_DB_SCHEMA)?;
+    Ok(conn)
+}
+
+/// Stores a client's push. Task fields are merged one by one, keeping the
+/// value with the greater hybrid logical clock, the same policy the clients
+/// apply when they pull, so every copy converges on the same data.
+pub fn apply_push(conn: &mut Connection, request: &protocol::PushRequest) -> Result<usize> {
+    let tx = conn.transaction()?;
+
+    tx.execute(
+        "INSERT INTO clients (c_uid, c_last_push) VALUES (?1, ?2)
+            ON CONFLICT(c_uid) DO UPDATE SET c_last_push = excluded.c_last_push",
+        (&request.uid, chrono::Utc::now().timestamp()),
+    )?;
+
+    for task in &request.tasks {
+        tx.execute(
+            "INSERT INTO tasks
+                (t_uuid, t_client, t_name, t_priority, t_created_at, t_updated_at, t_deleted,
+                    t_name_hlc, t_priority_hlc, t_deleted_hlc)
+                VALUES
+                (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                ON CONFLICT(t_uuid) DO UPDATE SET
+                    t_client = excluded.t_client,
+                    t_name = CASE WHEN excluded.t_name_hlc > tasks.t_name_hlc
+                        THEN excluded.t_name ELSE tasks.t_name END,
+                    t_name_hlc = MAX(excluded.t_name_hlc, tasks.t_name_hlc),
+                    t_priority = CASE WHEN excluded.t_priority_hlc > tasks.t_priority_hlc
+                        THEN excluded.t_priority ELSE tasks.t_priority END,
+                    t_priority_hlc = MAX(excluded.t_priority_hlc, tasks.t_priority_hlc),
+                    t_deleted = CASE WHEN excluded.t_deleted_hlc > tasks.t_deleted_hlc
+                        THEN excluded.t_deleted ELSE tasks.t_deleted END,
+                    t_deleted_hlc = MAX(excluded.t_deleted_hlc, tasks.t_deleted_hlc),
+                    t_updated_at = MAX(excluded.t_updated_at, tasks.t_updated_at)",
+            (
+                &task.t_uuid,
+                &request.uid,
+                &task.t_name,
+                task.t_priority,
+                task.t_created_at,
+                task.t_updated_at,
+                task.t_deleted,
+                &task.t_name_hlc,
+                &task.t_priority_hlc,
+                &task.t_deleted_hlc,
+            ),
+        )?;
+    }
+
+    for session in &request.sessions {
+        tx.execute(
+            "INSERT INTO sessions
+                (s_uuid, s_client, s_task, s_user, s_duration, s_comment,
+                    s_created_at, s_updated_at, s_deleted)
+                VALUES
+                (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                ON CONFLICT(s_uuid) DO UPDATE SET
+                    s_client = excluded.s_client,
+                    s_task = excluded.s_task,
+                    s_duration = excluded.s_duration,
+                    s_comment = excluded.s_comment,
+                    s_updated_at = excluded.s_updated_at,
+                    s_deleted = excluded.s_deleted
+                WHERE excluded.s_updated_at > sessions.s_updated_at",
+            (
+                &session.s_uuid,
+                &request.uid,
+                &session.s_task,
+                &session.s_user,
+                session.s_duration,
+                &session.s_comment,
+                session.s_created_at,
+                session.s_updated_at,
+                session.s_deleted,
+            ),
+        )?;
+    }
+
+    tx.commit()?;
+    Ok(request.tasks.len() + request.sessions.len())
+}
+
+/// Lists all tasks, including tombstones, so clients can merge deletions.
+pub fn get_shared_tasks(conn: &Connection) -> Result<Vec<protocol::TaskRecord>> {
+    let mut statement = conn.prepare(
+        "SELECT t_uuid, t_name, t_priority, t_created_at, t_updated_at, t_deleted,
+            t_name_hlc, t_priority_hlc, t_deleted_hlc
+            FROM tasks ORDER BY t_created_at, t_uuid",
+    )?;
+    let task_iter = statement.query_map([], |row| {
+        Ok(protocol::TaskRecord {
+            t_uuid: row.get(0)?,
+            t_name: row.get(1)?,
+            t_priority: row.get(2)?,
+            t_created_at: row.get(3)?,
+            t_updated_at: row.get(4)?,
+            t_deleted: row.get(5)?,
+            t_name_hlc: row.get(6)?,
+            t_priority_hlc: row.get(7)?,
+            t_deleted_hlc: row.get(8)?,
+        })
+    })?;
+
+    let mut tasks = Vec::new();
+    for task in task_iter {
+        tasks.push(task?);
+    }
+    Ok(tasks)
+}
+
+pub fn get_team_report(conn: &Connection) -> Result<protocol::TeamReport> {
+    let mut statement = conn.prepare(
+        "SELECT s_user, COUNT(*), SUM(s_duration) FROM sessions
+            WHERE s_deleted = 0
+            GROUP BY s_user
+            ORDER BY s_user",
+    )?;
+    let user_iter = statement.query_map([], |row| {
+        Ok(protocol::UserReport {
+            uid: row.get(0)?,
+            sessions: row.get(1)?,
+            total_seconds: row.get(2)?,
+        })
+    })?;
+
+    let mut users = Vec::new();
+    for user in user_iter {
+        users.push(user?);
+    }
+
+    let mut statement = conn.prepare(
+        "SELECT t_uuid, t_name, COALESCE(SUM(s_duration), 0) FROM tasks
+            LEFT JOIN sessions ON s_task = t_uuid AND s_deleted = 0
+            WHERE t_deleted = 0
+            GROUP BY t_uuid
+            ORDER BY t_name, t_uuid",
+    )?;
+    let task_iter = statement.query_map([], |row| {
+        Ok(protocol::TaskReport {
+            t_uuid: row.get(0)?,
+            t_name: row.get(1)?,
+            total_seconds: row.get(2)?,
+        })
+    })?;
+
+    let mut tasks = Vec::new();
+    for task in task_iter {
+        tasks.push(task?);
+    }
+
+    Ok(protocol::TeamReport { users, tasks })
+}
@@ -1,3 +1,24 @@
+pub mod backup;
+pub mod conflicts;
+pub mod encryption;
+pub mod hlc;
+pub mod locked;
+pub mod memory;
+pub mod merge;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod repository;
 pub mod sqlite;
 
+pub use conflicts::Conflict;
+pub use locked::LockedRepository;
+pub use memory::MemoryRepository;
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresRepository;
+pub use repository::Repository;
+pub use repository::Result;
+pub use repository::StorageError;
+pub use repository::open_repository;
+pub use repository::retry_busy;
+pub use sqlite::SqliteRepository;
 pub use sqlite::init_db;
@@ -0,0 +1,236 @@
+use std::{
+    cmp::Reverse,
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::{Datelike, NaiveDate, NaiveTime};
+use rusqlite::{Connection, OpenFlags};
+
+use crate::{DB_MIGRATIONS, config, storage};
+
+const BACKUP_PREFIX: &str = "sessions-";
+const BACKUP_EXTENSION: &str = "db";
+/// Follows the date in the names of copies taken before a restore.
+const BEFORE_RESTORE: &str = "-before-restore-";
+
+/// A copy of the database, named after the day it was taken.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub date: NaiveDate,
+    /// When the copy was taken, if it holds the data a restore replaced
+    /// rather than being that day's backup.
+    pub before_restore: Option<NaiveTime>,
+}
+
+/// Backups kept in `dir`, newest first. Other files are ignored.
+pub fn list_backups(dir: &Path) -> io::Result<Vec<Backup>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut backups = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_none_or(|extension| extension != BACKUP_EXTENSION)
+        {
+            continue;
+        }
+        let backup = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.strip_prefix(BACKUP_PREFIX))
+            .and_then(parse_name);
+        if let Some((date, before_restore)) = backup {
+            backups.push(Backup {
+                path,
+                date,
+                before_restore,
+            });
+        }
+    }
+    backups.sort_by_key(|backup| Reverse((backup.date, backup.before_restore)));
+    Ok(backups)
+}
+
+/// The date, and the time of copies taken before a restore, from a backup's
+/// name without its prefix.
+fn parse_name(name: &str) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    let (date, rest) = name.split_at_checked(10)?;
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    if rest.is_empty() {
+        return Some((date, None));
+    }
+    let time = NaiveTime::parse_from_str(rest.strip_prefix(BEFORE_RESTORE)?, "%H%M%S").ok()?;
+    Some((date, Some(time)))
+}
+
+/// Copies the database to today's backup in `dir`, replacing an earlier one
+/// from the same day.
+pub fn back_up(
+    repository: &dyn storage::Repository,
+    dir: &Path,
+    today: NaiveDate,
+) -> crate::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!(
+        "{}{}.{}",
+        BACKUP_PREFIX,
+        today.format("%Y-%m-%d"),
+        BACKUP_EXTENSION
+    ));
+
+    // Written under another name first so a failed backup never replaces a
+    // good one.
+    let partial = path.with_extension("partial");
+    let _ = fs::remove_file(&partial);
+    if let Err(e) = repository.backup(&partial) {
+        let _ = fs::remove_file(&partial);
+        return Err(e.into());
+    }
+    fs::rename(&partial, &path)?;
+    tracing::info!(path = %path.display(), "Backed up database");
+    Ok(path)
+}
+
+/// The backups to delete so that only the newest `keep_daily` days remain,
+/// plus the newest backup of each of the last `keep_weekly` weeks. Of the
+/// copies taken before a restore the newest `keep_daily` are kept, and always
+/// the latest, so the last restore can be undone.
+pub fn expired_backups(backups: &[Backup], keep_daily: usize, keep_weekly: usize) -> Vec<&Backup> {
+    let (mut before_restore, mut newest_first): (Vec<&Backup>, Vec<&Backup>) = backups
+        .iter()
+        .partition(|backup| backup.before_restore.is_some());
+    newest_first.sort_by_key(|backup| Reverse(backup.date));
+    before_restore.sort_by_key(|backup| Reverse((backup.date, backup.before_restore)));
+
+    let mut weeks = HashSet::new();
+    let mut kept_weeks = 0;
+    let mut expired = Vec::new();
+    for (index, backup) in newest_first.into_iter().enumerate() {
+        let newest_of_week = weeks.insert(backup.date.iso_week());
+        if newest_of_week && kept_weeks < keep_weekly {
+            kept_weeks += 1;
+            continue;
+        }
+        if index >= keep_daily {
+            expired.push(backup);
+        }
+    }
+    expired.extend(before_restore.into_iter().skip(keep_daily.max(1)));
+    expired
+}
+
+/// Deletes the backups in `dir` that fall out of the retention policy.
+pub fn prune_backups(dir: &Path, keep_daily: usize, keep_weekly: usize) -> io::Result<()> {
+    let backups = list_backups(dir)?;
+    for backup in expired_backups(&backups, keep_daily, keep_weekly) {
+        fs::remove_file(&backup.path)?;
+        tracing::info!(path = %backup.path.display(), "Removed old backup");
+    }
+    Ok(())
+}
+
+/// Takes today's backup unless there already is one, then prunes old ones.
+/// Returns whether a backup was taken.
+pub fn back_up_if_due(
+    repository: &dyn storage::Repository,
+    settings: &config::Settings,
+    today: NaiveDate,
+) -> crate::Result<bool> {
+    if settings.storage_backend != config::StorageBackend::Sqlite
+        || settings.backup_keep_daily + settings.backup_keep_weekly == 0
+    {
+        return Ok(false);
+    }
+
+    let dir = Path::new(&settings.backup_path);
+    if list_backups(dir)?
+        .iter()
+        .any(|backup| backup.date == today && backup.before_restore.is_none())
+    {
+        return Ok(false);
+    }
+    back_up(repository, dir, today)?;
+    prune_backups(dir, settings.backup_keep_daily, settings.backup_keep_weekly)?;
+    Ok(true)
+}
+
+/// The problems `PRAGMA integrity_check` finds; empty for a healthy database.
+pub fn integrity_problems(conn: &Connection) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+    let problems = rows.collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(problems
+        .into_iter()
+        .filter(|problem| problem != "ok")
+        .collect())
+}
+
+/// Checks that `path` is an intact time tracker database this version can
+/// open, encrypted with `passphrase` if there is one.
+pub fn validate_backup(path: &Path, passphrase: Option<&str>) -> storage::Result<()> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    if let Some(passphrase) = passphrase {
+        storage::encryption::unlock(&conn, passphrase)?;
+    }
+    let problems = integrity_problems(&conn)?;
+    if !problems.is_empty() {
+        return Err(storage::StorageError::Corrupt(problems.join("; ")));
+    }
+
+    let has_tasks: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'tasks')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_tasks {
+        return Err(storage::StorageError::Corrupt(
+            "not a time tracker database".to_string(),
+        ));
+    }
+
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > DB_MIGRATIONS.len() {
+        return Err(storage::StorageError::Unsupported(
+            "The backup was made by a newer version of the time tracker".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Replaces the database's contents with the backup at `path`. The current
+/// contents are saved to the backup folder first, listed among the backups,
+/// so a restore can be undone.
+pub fn restore(
+    repository: &mut dyn storage::Repository,
+    settings: &config::Settings,
+    path: &Path,
+    now: chrono::DateTime<chrono::Utc>,
+) -> crate::Result<()> {
+    let passphrase = storage::encryption::passphrase(settings)?;
+    validate_backup(path, passphrase.as_deref())?;
+
+    let dir = Path::new(&settings.backup_path);
+    fs::create_dir_all(dir)?;
+    let now = now.with_timezone(&chrono::Local);
+    let before_restore = dir.join(format!(
+        "{}{}{}{}.{}",
+        BACKUP_PREFIX,
+        now.format("%Y-%m-%d"),
+        BEFORE_RESTORE,
+        now.format("%H%M%S"),
+        BACKUP_EXTENSION
+    ));
+    repository.backup(&before_restore)?;
+
+    repository.restore(path)?;
+    tracing::info!(path = %path.display(), "Restored database from backup");
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use rusqlite::{Connection, OptionalExtension, Result, Row};
+
+use crate::storage;
+
+/// A field that was edited on this device and on another one before either
+/// edit had been synced. The merge already picked a winner (`c_kept`); the
+/// user can confirm it or switch to the other value.
+#[derive(Clone, Debug)]
+pub struct Conflict {
+    pub c_id: i64,
+    pub c_task: String,
+    pub c_task_name: String,
+    pub c_field: String,
+    pub c_local_value: String,
+    pub c_remote_value: String,
+    pub c_kept: String,
+    pub c_detected_at: i64,
+}
+
+fn conflict_from_row(row: &Row) -> Result<Conflict> {
+    Ok(Conflict {
+        c_id: row.get(0)?,
+        c_task: row.get(1)?,
+        c_task_name: row.get(2)?,
+        c_field: row.get(3)?,
+        c_local_value: row.get(4)?,
+        c_remote_value: row.get(5)?,
+        c_kept: row.get(6)?,
+        c_detected_at: row.get(7)?,
+    })
+}
+
+pub fn record_conflict(conn: &Connection, conflict: &Conflict) -> Result<usize> {
+    conn.execute(
+        "INSERT INTO conflicts
+            (c_task, c_field, c_local_value, c_remote_value, c_kept, c_detected_at)
+            VALUES
+            (?1, ?2, ?3, ?4, ?5, ?6)",
+        (
+            &conflict.c_task,
+            &conflict.c_field,
+            &conflict.c_local_value,
+            &conflict.c_remote_value,
+            &conflict.c_kept,
+            conflict.c_detected_at,
+        ),
+    )
+}
+
+pub fn get_unresolved_conflicts(conn: &Connection) -> Result<Vec<Conflict>> {
+    let mut statement = conn.prepare(
+        "SELECT c_id, c_task, COALESCE(t_name, ''), c_field, c_local_value, c_remote_value,
+            c_kept, c_detected_at
+            FROM conflicts LEFT JOIN tasks ON t_uuid = c_task
+            WHERE c_resolved = 0
+            ORDER BY c_detected_at, c_id",
+    )?;
+    let conflict_iter = statement.query_map([], conflict_from_row)?;
+
+    let mut conflicts = Vec::new();
+    for conflict in conflict_iter {
+        conflicts.push(conflict?);
+    }
+    Ok(conflicts)
+}
+
+/// Settles a conflict on the local or remote value. The chosen value is
+/// written with a fresh clock so it wins on every device after the next sync.
+pub fn resolve_conflict(conn: &Connection, id: i64, keep_local: bool, now: i64) -> Result<()> {
+    let conflict = conn
+        .query_row(
+            "SELECT c_id, c_task, COALESCE(t_name, ''), c_field, c_local_value, c_remote_value,
+                c_kept, c_detected_at
+                FROM conflicts LEFT JOIN tasks ON t_uuid = c_task
+                WHERE c_id = ?1",
+            [id],
+            conflict_from_row,
+        )
+        .optional()?;
+    let Some(conflict) = conflict else {
+        return Ok(());
+    };
+
+    let value = if keep_local {
+        &conflict.c_local_value
+    } else {
+        &conflict.c_remote_value
+    };
+    let tx = conn.unchecked_transaction()?;
+    let hlc = storage::hlc::next(&tx, now)?.to_string();
+    match conflict.c_field.as_str() {
+        "name" => {
+            tx.execute(
+                "UPDATE tasks SET t_name = ?1, t_name_hlc = ?2, t_updated_at = ?3
+                    WHERE t_uuid = ?4",
+                (value, &hlc, now, &conflict.c_task),
+            )?;
+        }
+        "priority" => {
+            tx.execute(
+                "UPDATE tasks SET t_priority = ?1, t_priority_hlc = ?2, t_updated_at = ?3
+                    WHERE t_uuid = ?4",
+                (
+                    value.parse::<usize>().unwrap_or(0),
+                    &hlc,
+                    now,
+                    &conflict.c_task,
+                ),
+            )?;
+        }
+        "deleted" => {
+            tx.execute(
+                "UPDATE tasks SET t_deleted = ?1, t_deleted_hlc = ?2, t_updated_at = ?3
+                    WHERE t_uuid = ?4",
+                (value == "true", &hlc, now, &conflict.c_task),
+            )?;
+        }
+        _ => (),
+    }
+    tx.execute("UPDATE conflicts SET c_resolved = 1 WHERE c_id = ?1", [id])?;
+    tx.commit()
+}
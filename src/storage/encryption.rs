@@ -0,0 +1,136 @@
+use std::{fs, io::Read, path::Path};
+
+use rusqlite::Connection;
+
+use crate::{
+    config,
+    storage::{self, Result, StorageError},
+};
+
+/// The first bytes of every unencrypted SQLite database.
+const PLAINTEXT_HEADER: &[u8] = b"SQLite format 3\0";
+#[cfg(feature = "encryption")]
+const KEYRING_SERVICE: &str = "time-tracker";
+
+/// The passphrase the database at `settings.local_database_path` is
+/// encrypted with: the one typed in this session, else the one remembered in
+/// the OS keyring. `None` when encryption is off.
+pub fn passphrase(settings: &config::Settings) -> Result<Option<String>> {
+    if !settings.encrypt_database {
+        return Ok(None);
+    }
+    // Without SQLCipher `PRAGMA key` is silently ignored.
+    if !cfg!(feature = "encryption") {
+        return Err(StorageError::Unsupported(
+            "Encrypting the database needs a build with the `encryption` feature".to_string(),
+        ));
+    }
+    match &settings.database_passphrase {
+        Some(passphrase) => Ok(Some(passphrase.clone())),
+        None => remembered_passphrase(settings).map(Some),
+    }
+}
+
+#[cfg(feature = "encryption")]
+fn remembered_passphrase(settings: &config::Settings) -> Result<String> {
+    match keyring_entry(settings)?.get_password() {
+        Ok(passphrase) => Ok(passphrase),
+        Err(keyring::Error::NoEntry) => Err(StorageError::Locked),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(not(feature = "encryption"))]
+fn remembered_passphrase(_settings: &config::Settings) -> Result<String> {
+    Err(StorageError::Locked)
+}
+
+/// Whether the user has yet to choose a passphrase, because the database
+/// doesn't exist or hasn't been encrypted yet.
+pub fn needs_new_passphrase(settings: &config::Settings) -> bool {
+    is_plaintext(Path::new(&settings.local_database_path)).unwrap_or(true)
+}
+
+/// Stores the passphrase in the OS keyring so the database opens without a
+/// prompt next time.
+#[cfg(feature = "encryption")]
+pub fn remember_passphrase(settings: &config::Settings, passphrase: &str) -> Result<()> {
+    keyring_entry(settings)?.set_password(passphrase)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "encryption"))]
+pub fn remember_passphrase(_settings: &config::Settings, _passphrase: &str) -> Result<()> {
+    Err(StorageError::Unsupported(
+        "Remembering the passphrase needs a build with the `encryption` feature".to_string(),
+    ))
+}
+
+#[cfg(feature = "encryption")]
+fn keyring_entry(settings: &config::Settings) -> Result<keyring::Entry> {
+    Ok(keyring::Entry::new(
+        KEYRING_SERVICE,
+        &settings.local_database_path,
+    )?)
+}
+
+/// Sets the key for a connection and checks it by reading the schema.
+pub(crate) fn unlock(conn: &Connection, passphrase: &str) -> Result<()> {
+    conn.pragma_update(None, "key", passphrase)?;
+    match conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(())) {
+        Err(rusqlite::Error::SqliteFailure(e, _))
+            if e.code == rusqlite::ErrorCode::NotADatabase =>
+        {
+            Err(StorageError::WrongPassphrase)
+        }
+        result => Ok(result?),
+    }
+}
+
+/// `Ok(true)` for an existing, unencrypted database; `Ok(false)` for an
+/// encrypted one.
+fn is_plaintext(path: &Path) -> std::io::Result<bool> {
+    let mut header = [0; PLAINTEXT_HEADER.len()];
+    fs::File::open(path)?.read_exact(&mut header)?;
+    Ok(header == PLAINTEXT_HEADER)
+}
+
+/// Encrypts an existing plaintext database in place, once. The encrypted
+/// copy is written next to it and only replaces it when complete.
+pub(crate) fn encrypt_if_plaintext(path: &Path, passphrase: &str) -> Result<()> {
+    if !is_plaintext(path).unwrap_or(false) {
+        return Ok(());
+    }
+    tracing::info!(path = %path.display(), "Encrypting database");
+
+    let encrypted = path.with_extension("encrypting");
+    let _ = fs::remove_file(&encrypted);
+    {
+        let conn = Connection::open(path)?;
+        // Move everything out of the write-ahead log into the main file.
+        conn.pragma_update(None, "journal_mode", "DELETE")?;
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        conn.execute(
+            "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+            (encrypted.to_string_lossy(), passphrase),
+        )?;
+        conn.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))?;
+        conn.pragma_update(Some("encrypted"), "user_version", version)?;
+        conn.execute_batch("DETACH DATABASE encrypted")?;
+    }
+
+    fs::rename(&encrypted, path)?;
+    Ok(())
+}
+
+/// Encrypts the backups in `dir` taken before encryption was turned on, so
+/// none of them leaves the data readable and all of them can be restored.
+/// One that fails is reported and the others are still encrypted.
+pub(crate) fn encrypt_backups(dir: &Path, passphrase: &str) -> Result<()> {
+    for backup in storage::backup::list_backups(dir)? {
+        if let Err(e) = encrypt_if_plaintext(&backup.path, passphrase) {
+            tracing::warn!(path = %backup.path.display(), "Couldn't encrypt backup: {}", e);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+
+use rusqlite::{Connection, Result};
+
+/// A hybrid logical clock timestamp.
+///
+/// Combines wall-clock milliseconds with a logical counter so that causally
+/// related edits are ordered correctly even when device clocks drift, and
+/// uses the node (the user's uid) as a final tie-breaker. The string form is
+/// fixed-width, so comparing two strings orders them like the timestamps.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Hlc {
+    pub wall_ms: i64,
+    pub counter: u32,
+    pub node: String,
+}
+
+impl Hlc {
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.splitn(3, '-');
+        Some(Self {
+            wall_ms: parts.next()?.parse().ok()?,
+            counter: parts.next()?.parse().ok()?,
+            node: parts.next().unwrap_or("").to_string(),
+        })
+    }
+
+    /// Timestamp for an edit made on this node.
+    pub fn tick(&self, now_ms: i64, node: &str) -> Self {
+        let wall_ms = self.wall_ms.max(now_ms);
+        let counter = if wall_ms == self.wall_ms {
+            self.counter + 1
+        } else {
+            0
+        };
+
+        Self {
+            wall_ms,
+            counter,
+            node: node.to_string(),
+        }
+    }
+
+    /// Advances this node's clock past a timestamp received from another node.
+    pub fn observe(&self, remote: &Self, now_ms: i64, node: &str) -> Self {
+        let wall_ms = self.wall_ms.max(remote.wall_ms).max(now_ms);
+        let counter = if wall_ms == self.wall_ms && wall_ms == remote.wall_ms {
+            self.counter.max(remote.counter) + 1
+        } else if wall_ms == self.wall_ms {
+            self.counter + 1
+        } else if wall_ms == remote.wall_ms {
+            remote.counter + 1
+        } else {
+            0
+        };
+
+        Self {
+            wall_ms,
+            counter,
+            node: node.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Hlc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:015}-{:05}-{}", self.wall_ms, self.counter, self.node)
+    }
+}
+
+/// Sets the node id used for timestamps issued by this database.
+pub fn init_clock(conn: &Connection, node: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO hlc_clock (h_id, h_node, h_last) VALUES (1, ?1, '')
+            ON CONFLICT(h_id) DO UPDATE SET h_node = excluded.h_node",
+        [node],
+    )?;
+    Ok(())
+}
+
+fn load_clock(conn: &Connection) -> Result<(String, Hlc)> {
+    conn.query_row(
+        "SELECT h_node, h_last FROM hlc_clock WHERE h_id = 1",
+        [],
+        |row| {
+            let node: String = row.get(0)?;
+            let last: String = row.get(1)?;
+            Ok((node, Hlc::parse(&last).unwrap_or_default()))
+        },
+    )
+}
+
+fn store_clock(conn: &Connection, last: &Hlc) -> Result<()> {
+    conn.execute(
+        "UPDATE hlc_clock SET h_last = ?1 WHERE h_id = 1",
+        [last.to_string()],
+    )?;
+    Ok(())
+}
+
+/// Issues the timestamp for a local edit.
+pub fn next(conn: &Connection, now_ms: i64) -> Result<Hlc> {
+    let (node, last) = load_clock(conn)?;
+    let hlc = last.tick(now_ms, &node);
+    store_clock(conn, &hlc)?;
+    Ok(hlc)
+}
+
+/// Records a timestamp received from the sync server.
+pub fn observe(conn: &Connection, remote: &Hlc, now_ms: i64) -> Result<()> {
+    let (node, last) = load_clock(conn)?;
+    store_clock(conn, &last.observe(remote, now_ms, &node))
+}
@@ -0,0 +1,119 @@
+use std::path::Path;
+
+use crate::{
+    agent::{
+        clients::Client, rates::Rate, sessions::Session, tasks::Task, users::User,
+        window_rules::WindowRule,
+    },
+    storage::{Conflict, Repository, Result, StorageError},
+};
+
+/// Stands in for an encrypted database until its passphrase is entered.
+/// Every operation fails with `StorageError::Locked`.
+pub struct LockedRepository;
+
+impl Repository for LockedRepository {
+    fn get_all_tasks(&self) -> Result<Vec<Task>> {
+        Err(StorageError::Locked)
+    }
+
+    fn get_task(&self, _id: i64) -> Result<Option<Task>> {
+        Err(StorageError::Locked)
+    }
+
+    fn add_task(&mut self, _task: &Task) -> Result<()> {
+        Err(StorageError::Locked)
+    }
+
+    fn update_task(&mut self, _task: &Task) -> Result<()> {
+        Err(StorageError::Locked)
+    }
+
+    fn delete_task(&mut self, _id: i64) -> Result<()> {
+        Err(StorageError::Locked)
+    }
+
+    fn get_all_sessions(&self) -> Result<Vec<Session>> {
+        Err(StorageError::Locked)
+    }
+
+    fn get_session(&self, _id: i64) -> Result<Option<Session>> {
+        Err(StorageError::Locked)
+    }
+
+    fn save_session(&mut self, _session: &Session) -> Result<()> {
+        Err(StorageError::Locked)
+    }
+
+    fn import_sessions(&mut self, _sessions: &[(String, Session)]) -> Result<()> {
+        Err(StorageError::Locked)
+    }
+
+    fn get_all_users(&self) -> Result<Vec<User>> {
+        Err(StorageError::Locked)
+    }
+
+    fn ensure_user(&mut self, _user: &User) -> Result<()> {
+        Err(StorageError::Locked)
+    }
+
+    fn get_all_clients(&self) -> Result<Vec<Client>> {
+        Err(StorageError::Locked)
+    }
+
+    fn add_client(&mut self, _client: &Client) -> Result<()> {
+        Err(StorageError::Locked)
+    }
+
+    fn update_client(&mut self, _client: &Client) -> Result<()> {
+        Err(StorageError::Locked)
+    }
+
+    fn get_all_rates(&self) -> Result<Vec<Rate>> {
+        Err(StorageError::Locked)
+    }
+
+    fn add_rate(&mut self, _rate: &Rate) -> Result<()> {
+        Err(StorageError::Locked)
+    }
+
+    fn delete_rate(&mut self, _id: i64) -> Result<()> {
+        Err(StorageError::Locked)
+    }
+
+    fn get_all_window_rules(&self) -> Result<Vec<WindowRule>> {
+        Err(StorageError::Locked)
+    }
+
+    fn add_window_rule(&mut self, _rule: &WindowRule) -> Result<()> {
+        Err(StorageError::Locked)
+    }
+
+    fn delete_window_rule(&mut self, _id: i64) -> Result<()> {
+        Err(StorageError::Locked)
+    }
+
+    fn get_unresolved_conflicts(&self) -> Result<Vec<Conflict>> {
+        Err(StorageError::Locked)
+    }
+
+    fn resolve_conflict(&mut self, _id: i64, _keep_local: bool) -> Result<()> {
+        Err(StorageError::Locked)
+    }
+
+    fn backup(&self, _path: &Path) -> Result<()> {
+        Err(StorageError::Locked)
+    }
+
+    fn restore(&mut self, _path: &Path) -> Result<()> {
+        Err(StorageError::Locked)
+    }
+
+    fn integrity_check(&self) -> Result<Vec<String>> {
+        Err(StorageError::Locked)
+    }
+
+    fn is_locked(&self) -> bool {
+        true
+    }
+}
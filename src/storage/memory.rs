@@ -0,0 +1,343 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::{
+    agent::{
+        Clock,
+        clients::Client,
+        rates::Rate,
+        sessions::{self, Session},
+        tasks::Task,
+        users::User,
+        window_rules::WindowRule,
+    },
+    storage::{Conflict, Repository, Result, StorageError, hlc::Hlc},
+};
+
+/// Keeps everything in memory; nothing survives a restart. Meant for tests
+/// and trying the app out. There is no sync, so there are never conflicts.
+pub struct MemoryRepository {
+    node: String,
+    last_hlc: Hlc,
+    tasks: Vec<Task>,
+    sessions: Vec<Session>,
+    users: Vec<User>,
+    clients: Vec<Client>,
+    rates: Vec<Rate>,
+    window_rules: Vec<WindowRule>,
+    clock: Arc<dyn Clock>,
+}
+
+impl MemoryRepository {
+    /// Creates an empty store with `uid` as its only user.
+    pub fn new(uid: &str, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            node: uid.to_string(),
+            last_hlc: Hlc::default(),
+            tasks: Vec::new(),
+            sessions: Vec::new(),
+            users: vec![User {
+                u_id: uid.to_string(),
+                u_name: uid.to_string(),
+            }],
+            clients: Vec::new(),
+            rates: Vec::new(),
+            window_rules: Vec::new(),
+            clock,
+        }
+    }
+
+    fn next_hlc(&mut self, now_ms: i64) -> String {
+        self.last_hlc = self.last_hlc.tick(now_ms, &self.node);
+        self.last_hlc.to_string()
+    }
+
+    fn task_mut(&mut self, id: i64) -> Option<&mut Task> {
+        self.tasks.iter_mut().find(|task| task.t_id == id)
+    }
+}
+
+impl Repository for MemoryRepository {
+    fn get_all_tasks(&self) -> Result<Vec<Task>> {
+        Ok(self
+            .tasks
+            .iter()
+            .filter(|task| !task.t_deleted)
+            .cloned()
+            .collect())
+    }
+
+    fn get_task(&self, id: i64) -> Result<Option<Task>> {
+        Ok(self.tasks.iter().find(|task| task.t_id == id).cloned())
+    }
+
+    fn add_task(&mut self, task: &Task) -> Result<()> {
+        let now = self.clock.now().timestamp_millis();
+        let hlc = self.next_hlc(now);
+        self.tasks.push(Task {
+            t_id: self.tasks.len() as i64 + 1,
+            t_uuid: Uuid::new_v4().to_string(),
+            t_name: task.t_name.clone(),
+            t_priority: task.t_priority,
+            t_created_at: now,
+            t_updated_at: now,
+            t_deleted: false,
+            t_name_hlc: hlc.clone(),
+            t_priority_hlc: hlc.clone(),
+            t_deleted_hlc: hlc,
+            t_client: task.t_client,
+            t_estimate: task.t_estimate,
+            t_budget: task.t_budget,
+            t_daily_goal: task.t_daily_goal,
+            t_weekly_goal: task.t_weekly_goal,
+        });
+        Ok(())
+    }
+
+    fn update_task(&mut self, task: &Task) -> Result<()> {
+        let Some(current) = self.get_task(task.t_id)? else {
+            return Ok(());
+        };
+
+        let now = self.clock.now().timestamp_millis();
+        if current.t_name != task.t_name {
+            let hlc = self.next_hlc(now);
+            if let Some(stored) = self.task_mut(task.t_id) {
+                stored.t_name = task.t_name.clone();
+                stored.t_name_hlc = hlc;
+                stored.t_updated_at = now;
+            }
+        }
+        if current.t_priority != task.t_priority {
+            let hlc = self.next_hlc(now);
+            if let Some(stored) = self.task_mut(task.t_id) {
+                stored.t_priority = task.t_priority;
+                stored.t_priority_hlc = hlc;
+                stored.t_updated_at = now;
+            }
+        }
+        if current.t_client != task.t_client
+            && let Some(stored) = self.task_mut(task.t_id)
+        {
+            stored.t_client = task.t_client;
+        }
+        if (current.t_estimate, current.t_budget) != (task.t_estimate, task.t_budget)
+            && let Some(stored) = self.task_mut(task.t_id)
+        {
+            stored.t_estimate = task.t_estimate;
+            stored.t_budget = task.t_budget;
+        }
+        if (current.t_daily_goal, current.t_weekly_goal) != (task.t_daily_goal, task.t_weekly_goal)
+            && let Some(stored) = self.task_mut(task.t_id)
+        {
+            stored.t_daily_goal = task.t_daily_goal;
+            stored.t_weekly_goal = task.t_weekly_goal;
+        }
+        Ok(())
+    }
+
+    fn delete_task(&mut self, id: i64) -> Result<()> {
+        let now = self.clock.now().timestamp_millis();
+        let hlc = self.next_hlc(now);
+        if let Some(stored) = self.task_mut(id) {
+            stored.t_deleted = true;
+            stored.t_deleted_hlc = hlc;
+            stored.t_updated_at = now;
+        }
+        Ok(())
+    }
+
+    fn get_all_sessions(&self) -> Result<Vec<Session>> {
+        Ok(self
+            .sessions
+            .iter()
+            .filter(|session| !session.s_deleted)
+            .cloned()
+            .collect())
+    }
+
+    fn get_session(&self, id: i64) -> Result<Option<Session>> {
+        Ok(self
+            .sessions
+            .iter()
+            .find(|session| session.s_id == id)
+            .cloned())
+    }
+
+    fn save_session(&mut self, session: &Session) -> Result<()> {
+        if !self.tasks.iter().any(|task| task.t_id == session.s_task) {
+            return Err(StorageError::Constraint(format!(
+                "session references unknown task {}",
+                session.s_task
+            )));
+        }
+        if !self.users.iter().any(|user| user.u_id == session.s_user) {
+            return Err(StorageError::Constraint(format!(
+                "session references unknown user '{}'",
+                session.s_user
+            )));
+        }
+
+        let now = self.clock.now().timestamp_millis();
+        self.sessions.push(Session {
+            s_id: self.sessions.len() as i64 + 1,
+            s_uuid: Uuid::new_v4().to_string(),
+            s_created_at: sessions::created_at(session, now),
+            s_updated_at: now,
+            s_deleted: false,
+            ..session.clone()
+        });
+        Ok(())
+    }
+
+    fn import_sessions(&mut self, sessions: &[(String, Session)]) -> Result<()> {
+        let (tasks, saved) = (self.tasks.clone(), self.sessions.clone());
+        let result = sessions.iter().try_for_each(|(name, session)| {
+            let existing = self
+                .tasks
+                .iter()
+                .filter(|task| !task.t_deleted && task.t_name == *name)
+                .map(|task| task.t_id)
+                .max();
+            let task = match existing {
+                Some(id) => id,
+                None => {
+                    self.add_task(&Task {
+                        t_name: name.clone(),
+                        ..Default::default()
+                    })?;
+                    self.tasks.len() as i64
+                }
+            };
+            self.save_session(&Session {
+                s_task: task,
+                ..session.clone()
+            })
+        });
+        if result.is_err() {
+            self.tasks = tasks;
+            self.sessions = saved;
+        }
+        result
+    }
+
+    fn get_all_users(&self) -> Result<Vec<User>> {
+        let mut users = self.users.clone();
+        users.sort_by(|a, b| a.u_id.cmp(&b.u_id));
+        Ok(users)
+    }
+
+    fn ensure_user(&mut self, user: &User) -> Result<()> {
+        if !self.users.iter().any(|stored| stored.u_id == user.u_id) {
+            self.users.push(user.clone());
+        }
+        Ok(())
+    }
+
+    fn get_all_clients(&self) -> Result<Vec<Client>> {
+        let mut clients = self.clients.clone();
+        clients.sort_by(|a, b| a.cl_name.cmp(&b.cl_name));
+        Ok(clients)
+    }
+
+    fn add_client(&mut self, client: &Client) -> Result<()> {
+        if self
+            .clients
+            .iter()
+            .any(|stored| stored.cl_name == client.cl_name)
+        {
+            return Err(StorageError::Constraint(format!(
+                "there already is a client named '{}'",
+                client.cl_name
+            )));
+        }
+        self.clients.push(Client {
+            cl_id: self.clients.len() as i64 + 1,
+            ..client.clone()
+        });
+        Ok(())
+    }
+
+    fn update_client(&mut self, client: &Client) -> Result<()> {
+        if let Some(stored) = self
+            .clients
+            .iter_mut()
+            .find(|stored| stored.cl_id == client.cl_id)
+        {
+            *stored = client.clone();
+        }
+        Ok(())
+    }
+
+    fn get_all_rates(&self) -> Result<Vec<Rate>> {
+        let mut rates = self.rates.clone();
+        rates.sort_by_key(|rate| (rate.r_effective_from, rate.r_id));
+        Ok(rates)
+    }
+
+    fn add_rate(&mut self, rate: &Rate) -> Result<()> {
+        let known = match (rate.r_client, rate.r_task) {
+            (Some(client), None) => self.clients.iter().any(|stored| stored.cl_id == client),
+            (None, Some(task)) => self.tasks.iter().any(|stored| stored.t_id == task),
+            _ => false,
+        };
+        if !known {
+            return Err(StorageError::Constraint(
+                "a rate needs either a known client or a known task".to_string(),
+            ));
+        }
+        self.rates.push(Rate {
+            r_id: self.rates.iter().map(|rate| rate.r_id).max().unwrap_or(0) + 1,
+            ..rate.clone()
+        });
+        Ok(())
+    }
+
+    fn delete_rate(&mut self, id: i64) -> Result<()> {
+        self.rates.retain(|rate| rate.r_id != id);
+        Ok(())
+    }
+
+    fn get_all_window_rules(&self) -> Result<Vec<WindowRule>> {
+        Ok(self.window_rules.clone())
+    }
+
+    fn add_window_rule(&mut self, rule: &WindowRule) -> Result<()> {
+        if !self.tasks.iter().any(|task| task.t_id == rule.wr_task) {
+            return Err(StorageError::Constraint(format!(
+                "window rule references unknown task {}",
+                rule.wr_task
+            )));
+        }
+        self.window_rules.push(WindowRule {
+            wr_id: self
+                .window_rules
+                .iter()
+                .map(|rule| rule.wr_id)
+                .max()
+                .unwrap_or(0)
+                + 1,
+            ..rule.clone()
+        });
+        Ok(())
+    }
+
+    fn delete_window_rule(&mut self, id: i64) -> Result<()> {
+        self.window_rules.retain(|rule| rule.wr_id != id);
+        for session in &mut self.sessions {
+            if session.s_rule == Some(id) {
+                session.s_rule = None;
+            }
+        }
+        Ok(())
+    }
+
+    fn get_unresolved_conflicts(&self) -> Result<Vec<Conflict>> {
+        Ok(Vec::new())
+    }
+
+    fn resolve_conflict(&mut self, _id: i64, _keep_local: bool) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+use rusqlite::{Connection, Result};
+
+use crate::{agent, storage, sync::protocol};
+
+/// Outcome of merging a single field.
+struct FieldMerge {
+    take_remote: bool,
+    local_newer: bool,
+    conflict: bool,
+}
+
+/// Per-field last-writer-wins: the value with the greater hybrid logical
+/// clock wins. If both sides changed the field since the last version this
+/// device saw from the server (`base`), the edits were concurrent and the
+/// losing value is recorded as a conflict for the user to review.
+fn merge_field(
+    local_value: &str,
+    local_hlc: &str,
+    base_hlc: &str,
+    remote_value: &str,
+    remote_hlc: &str,
+) -> FieldMerge {
+    if local_hlc == remote_hlc {
+        return FieldMerge {
+            take_remote: false,
+            local_newer: false,
+            conflict: false,
+        };
+    }
+
+    FieldMerge {
+        take_remote: remote_hlc > local_hlc,
+        local_newer: local_hlc > remote_hlc,
+        conflict: local_hlc != base_hlc && remote_hlc != base_hlc && local_value != remote_value,
+    }
+}
+
+/// Applies a task pulled from the sync server and returns whether the local
+/// copy changed.
+pub fn merge_task(conn: &Connection, remote: &protocol::TaskRecord, now_ms: i64) -> Result<bool> {
+    let tx = conn.unchecked_transaction()?;
+    for hlc in [
+        &remote.t_name_hlc,
+        &remote.t_priority_hlc,
+        &remote.t_deleted_hlc,
+    ] {
+        if let Some(hlc) = storage::hlc::Hlc::parse(hlc) {
+            storage::hlc::observe(&tx, &hlc, now_ms)?;
+        }
+    }
+
+    let Some(local) = agent::tasks::get_task_by_uuid(&tx, &remote.t_uuid)? else {
+        tx.execute(
+            "INSERT INTO tasks
+                (t_uuid, t_name, t_priority, t_created_at, t_updated_at, t_deleted,
+                    t_name_hlc, t_priority_hlc, t_deleted_hlc,
+                    t_name_base, t_priority_base, t_deleted_base)
+                VALUES
+                (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?7, ?8, ?9)",
+            (
+                &remote.t_uuid,
+                &remote.t_name,
+                remote.t_priority,
+                remote.t_created_at,
+                remote.t_updated_at,
+                remote.t_deleted,
+                &remote.t_name_hlc,
+                &remote.t_priority_hlc,
+                &remote.t_deleted_hlc,
+            ),
+        )?;
+        remove_from_outbox(&tx, &remote.t_uuid)?;
+        tx.commit()?;
+        return Ok(true);
+    };
+
+    let (name_base, priority_base, deleted_base): (String, String, String) = tx.query_row(
+        "SELECT t_name_base, t_priority_base, t_deleted_base FROM tasks WHERE t_uuid = ?1",
+        [&remote.t_uuid],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+
+    let name = merge_field(
+        &local.t_name,
+        &local.t_name_hlc,
+        &name_base,
+        &remote.t_name,
+        &remote.t_name_hlc,
+    );
+    let priority = merge_field(
+        &local.t_priority.to_string(),
+        &local.t_priority_hlc,
+        &priority_base,
+        &remote.t_priority.to_string(),
+        &remote.t_priority_hlc,
+    );
+    let deleted = merge_field(
+        &local.t_deleted.to_string(),
+        &local.t_deleted_hlc,
+        &deleted_base,
+        &remote.t_deleted.to_string(),
+        &remote.t_deleted_hlc,
+    );
+
+    let fields = [
+        ("name", &name, local.t_name.clone(), remote.t_name.clone()),
+        (
+            "priority",
+            &priority,
+            local.t_priority.to_string(),
+            remote.t_priority.to_string(),
+        ),
+        (
+            "deleted",
+            &deleted,
+            local.t_deleted.to_string(),
+            remote.t_deleted.to_string(),
+        ),
+    ];
+    for (field, merge, local_value, remote_value) in &fields {
+        if merge.conflict {
+            storage::conflicts::record_conflict(
+                &tx,
+                &storage::conflicts::Conflict {
+                    c_id: 0,
+                    c_task: remote.t_uuid.clone(),
+                    c_task_name: "".to_string(),
+                    c_field: field.to_string(),
+                    c_local_value: local_value.clone(),
+                    c_remote_value: remote_value.clone(),
+                    c_kept: if merge.take_remote { "remote" } else { "local" }.to_string(),
+                    c_detected_at: now_ms,
+                },
+            )?;
+        }
+    }
+
+    let changed = name.take_remote || priority.take_remote || deleted.take_remote;
+    if changed {
+        tx.execute(
+            "UPDATE tasks SET
+                t_name = CASE WHEN ?1 THEN ?2 ELSE t_name END,
+                t_name_hlc = CASE WHEN ?1 THEN ?3 ELSE t_name_hlc END,
+                t_priority = CASE WHEN ?4 THEN ?5 ELSE t_priority END,
+                t_priority_hlc = CASE WHEN ?4 THEN ?6 ELSE t_priority_hlc END,
+                t_deleted = CASE WHEN ?7 THEN ?8 ELSE t_deleted END,
+                t_deleted_hlc = CASE WHEN ?7 THEN ?9 ELSE t_deleted_hlc END,
+                t_updated_at = MAX(t_updated_at, ?10)
+                WHERE t_uuid = ?11",
+            (
+                name.take_remote,
+                &remote.t_name,
+                &remote.t_name_hlc,
+                priority.take_remote,
+                remote.t_priority,
+                &remote.t_priority_hlc,
+                deleted.take_remote,
+                remote.t_deleted,
+                &remote.t_deleted_hlc,
+                remote.t_updated_at,
+                &remote.t_uuid,
+            ),
+        )?;
+    }
+
+    tx.execute(
+        "UPDATE tasks SET t_name_base = ?1, t_priority_base = ?2, t_deleted_base = ?3
+            WHERE t_uuid = ?4
+                AND (t_name_base != ?1 OR t_priority_base != ?2 OR t_deleted_base != ?3)",
+        (
+            &remote.t_name_hlc,
+            &remote.t_priority_hlc,
+            &remote.t_deleted_hlc,
+            &remote.t_uuid,
+        ),
+    )?;
+
+    // Nothing local is newer than the server's copy, so there is nothing to push.
+    if !(name.local_newer || priority.local_newer || deleted.local_newer) {
+        remove_from_outbox(&tx, &remote.t_uuid)?;
+    }
+
+    tx.commit()?;
+    Ok(changed)
+}
+
+fn remove_from_outbox(conn: &Connection, uuid: &str) -> Result<usize> {
+    conn.execute(
+        "DELETE FROM outbox WHERE o_kind = 'task'
+            AND o_record = (SELECT t_id FROM tasks WHERE t_uuid = ?1)",
+        [uuid],
+    )
+}
@@ -0,0 +1,538 @@
+use std::{collections::HashMap, sync::Arc};
+
+use postgres::{GenericClient, NoTls, Row};
+use r2d2_postgres::PostgresConnectionManager;
+use uuid::Uuid;
+
+use crate::{
+    POSTGRES_DB_SCHEMA,
+    agent::{
+        Clock,
+        clients::Client,
+        rates::{self, Rate},
+        sessions::{self, Session},
+        tasks::Task,
+        users::User,
+        window_rules::WindowRule,
+    },
+    billing::Rounding,
+    config,
+    storage::{Conflict, Repository, Result, StorageError, hlc::Hlc},
+};
+
+const MAX_CONNECTIONS: u32 = 4;
+
+const TASK_COLUMNS: &str = "t_id, t_uuid, t_name, t_priority, t_created_at, t_updated_at, \
+    t_deleted, t_name_hlc, t_priority_hlc, t_deleted_hlc, t_client, t_estimate, t_budget, \
+    tg_daily_goal, tg_weekly_goal";
+
+/// Tasks with the goals of the user in `$1`: everyone shares the tasks, but
+/// sets goals for their own work.
+const TASKS_WITH_GOALS: &str = "tasks LEFT JOIN task_goals ON tg_task = t_id AND tg_user = $1";
+
+const SESSION_COLUMNS: &str = "s_id, s_uuid, s_task, s_user, s_duration, s_comment, \
+    s_created_at, s_updated_at, s_deleted, s_billable, s_pomodoros, s_rule";
+
+const CLIENT_COLUMNS: &str = "cl_id, cl_name, cl_currency, cl_rounding, cl_rounding_minutes";
+
+const RATE_COLUMNS: &str = "r_id, r_client, r_task, r_hourly_cents, r_effective_from";
+
+const WINDOW_RULE_COLUMNS: &str = "wr_id, wr_pattern, wr_field, wr_task, wr_action";
+
+type Pool = r2d2::Pool<PostgresConnectionManager<NoTls>>;
+
+/// A database shared by the whole team, see `assets/schema_postgres.sql`.
+///
+/// Every client reads and writes the same rows, so there is nothing to sync
+/// or merge; field edits are still stamped with hybrid logical clocks, one
+/// clock per client in `hlc_clock`. Billing clients and rates are shared by
+/// the team too, as invoices cover everyone's sessions, while task goals are
+/// kept per user in `task_goals` and window rules belong to their `wr_user`.
+pub struct PostgresRepository {
+    pool: Pool,
+    node: String,
+    clock: Arc<dyn Clock>,
+}
+
+impl PostgresRepository {
+    /// Connects to `settings.postgres_url`, either a `postgres://` URL or a
+    /// `host=... user=...` string, and creates the schema if needed.
+    pub fn open(settings: Arc<config::Settings>, clock: Arc<dyn Clock>) -> Result<Self> {
+        let manager = PostgresConnectionManager::new(settings.postgres_url.parse()?, NoTls);
+        let pool = r2d2::Pool::builder()
+            .max_size(MAX_CONNECTIONS)
+            .build(manager)?;
+
+        let mut client = pool.get()?;
+        let mut tx = client.transaction()?;
+        // Clients starting at the same time would race on `CREATE TABLE`.
+        tx.execute(
+            "SELECT pg_advisory_xact_lock(hashtext('time_tracker_schema'))",
+            &[],
+        )?;
+        tx.batch_execute(POSTGRES_DB_SCHEMA)?;
+        tx.commit()?;
+        tracing::info!("Connected to PostgreSQL");
+
+        let mut repository = Self {
+            pool,
+            node: settings.uid.clone(),
+            clock,
+        };
+        repository.ensure_user(&User {
+            u_id: settings.uid.clone(),
+            u_name: settings.uid.clone(),
+        })?;
+        Ok(repository)
+    }
+
+    /// Issues the timestamp for a local edit from this client's clock.
+    fn next_hlc(&self, client: &mut impl GenericClient, now_ms: i64) -> Result<String> {
+        let row = client.query_opt(
+            "SELECT h_last FROM hlc_clock WHERE h_node = $1 FOR UPDATE",
+            &[&self.node],
+        )?;
+        let last = row
+            .and_then(|row| Hlc::parse(row.get(0)))
+            .unwrap_or_default();
+        let hlc = last.tick(now_ms, &self.node).to_string();
+        client.execute(
+            "INSERT INTO hlc_clock (h_node, h_last) VALUES ($1, $2)
+                ON CONFLICT (h_node) DO UPDATE SET h_last = excluded.h_last",
+            &[&self.node, &hlc],
+        )?;
+        Ok(hlc)
+    }
+
+    /// Adds a task created at `now_ms` and returns its id.
+    fn insert_task(
+        &self,
+        client: &mut impl GenericClient,
+        task: &Task,
+        now_ms: i64,
+    ) -> Result<i64> {
+        let hlc = self.next_hlc(client, now_ms)?;
+        let row = client.query_one(
+            "INSERT INTO tasks
+                (t_uuid, t_name, t_priority, t_created_at, t_updated_at,
+                    t_name_hlc, t_priority_hlc, t_deleted_hlc, t_client, t_estimate, t_budget)
+                VALUES
+                ($1, $2, $3, $4, $4, $5, $5, $5, $6, $7, $8)
+                RETURNING t_id",
+            &[
+                &Uuid::new_v4().to_string(),
+                &task.t_name,
+                &(task.t_priority as i32),
+                &now_ms,
+                &hlc,
+                &task.t_client,
+                &task.t_estimate.map(|seconds| seconds as i64),
+                &task.t_budget.map(|seconds| seconds as i64),
+            ],
+        )?;
+        let id = row.get(0);
+        if task.t_daily_goal.is_some() || task.t_weekly_goal.is_some() {
+            self.save_goals(client, id, task)?;
+        }
+        Ok(id)
+    }
+
+    /// Sets this user's goals for the task `id` to those of `task`.
+    fn save_goals(&self, client: &mut impl GenericClient, id: i64, task: &Task) -> Result<()> {
+        client.execute(
+            "INSERT INTO task_goals (tg_task, tg_user, tg_daily_goal, tg_weekly_goal)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (tg_task, tg_user) DO UPDATE
+                SET tg_daily_goal = excluded.tg_daily_goal,
+                    tg_weekly_goal = excluded.tg_weekly_goal",
+            &[
+                &id,
+                &self.node,
+                &task.t_daily_goal.map(|seconds| seconds as i64),
+                &task.t_weekly_goal.map(|seconds| seconds as i64),
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+fn insert_session(client: &mut impl GenericClient, session: &Session, now_ms: i64) -> Result<()> {
+    client.execute(
+        "INSERT INTO sessions
+            (s_uuid, s_task, s_user, s_duration, s_comment, s_created_at, s_updated_at,
+                s_billable, s_pomodoros, s_rule)
+            VALUES
+            ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+        &[
+            &Uuid::new_v4().to_string(),
+            &session.s_task,
+            &session.s_user,
+            &(session.s_duration as i64),
+            &session.s_comment,
+            &sessions::created_at(session, now_ms),
+            &now_ms,
+            &session.s_billable,
+            &(session.s_pomodoros as i32),
+            &session.s_rule,
+        ],
+    )?;
+    Ok(())
+}
+
+fn task_from_row(row: &Row) -> Task {
+    Task {
+        t_id: row.get(0),
+        t_uuid: row.get(1),
+        t_name: row.get(2),
+        t_priority: row.get::<_, i32>(3) as usize,
+        t_created_at: row.get(4),
+        t_updated_at: row.get(5),
+        t_deleted: row.get(6),
+        t_name_hlc: row.get(7),
+        t_priority_hlc: row.get(8),
+        t_deleted_hlc: row.get(9),
+        t_client: row.get(10),
+        t_estimate: row.get::<_, Option<i64>>(11).map(|seconds| seconds as u64),
+        t_budget: row.get::<_, Option<i64>>(12).map(|seconds| seconds as u64),
+        t_daily_goal: row.get::<_, Option<i64>>(13).map(|seconds| seconds as u64),
+        t_weekly_goal: row.get::<_, Option<i64>>(14).map(|seconds| seconds as u64),
+    }
+}
+
+fn session_from_row(row: &Row) -> Session {
+    Session {
+        s_id: row.get(0),
+        s_uuid: row.get(1),
+        s_task: row.get(2),
+        s_user: row.get(3),
+        s_duration: row.get::<_, i64>(4) as u64,
+        s_comment: row.get(5),
+        s_created_at: row.get(6),
+        s_updated_at: row.get(7),
+        s_deleted: row.get(8),
+        s_billable: row.get(9),
+        s_pomodoros: row.get::<_, i32>(10) as u32,
+        s_rule: row.get(11),
+    }
+}
+
+fn client_from_row(row: &Row) -> Client {
+    Client {
+        cl_id: row.get(0),
+        cl_name: row.get(1),
+        cl_currency: row.get(2),
+        cl_rounding: Rounding {
+            mode: row.get::<_, String>(3).parse().unwrap_or_default(),
+            minutes: row.get::<_, i32>(4) as u32,
+        },
+    }
+}
+
+fn rate_from_row(row: &Row) -> Result<Rate> {
+    let effective_from: String = row.get(4);
+    Ok(Rate {
+        r_id: row.get(0),
+        r_client: row.get(1),
+        r_task: row.get(2),
+        r_hourly_cents: row.get(3),
+        r_effective_from: chrono::NaiveDate::parse_from_str(&effective_from, rates::DATE_FORMAT)
+            .map_err(|e| StorageError::Corrupt(format!("rate {}: {}", effective_from, e)))?,
+    })
+}
+
+fn window_rule_from_row(row: &Row) -> WindowRule {
+    WindowRule {
+        wr_id: row.get(0),
+        wr_pattern: row.get(1),
+        wr_field: row.get::<_, String>(2).parse().unwrap_or_default(),
+        wr_task: row.get(3),
+        wr_action: row.get::<_, String>(4).parse().unwrap_or_default(),
+    }
+}
+
+impl Repository for PostgresRepository {
+    fn get_all_tasks(&self) -> Result<Vec<Task>> {
+        let rows = self.pool.get()?.query(
+            &format!(
+                "SELECT {} FROM {} WHERE NOT t_deleted ORDER BY t_id",
+                TASK_COLUMNS, TASKS_WITH_GOALS
+            ),
+            &[&self.node],
+        )?;
+        Ok(rows.iter().map(task_from_row).collect())
+    }
+
+    fn get_task(&self, id: i64) -> Result<Option<Task>> {
+        let row = self.pool.get()?.query_opt(
+            &format!(
+                "SELECT {} FROM {} WHERE t_id = $2",
+                TASK_COLUMNS, TASKS_WITH_GOALS
+            ),
+            &[&self.node, &id],
+        )?;
+        Ok(row.as_ref().map(task_from_row))
+    }
+
+    fn add_task(&mut self, task: &Task) -> Result<()> {
+        let mut client = self.pool.get()?;
+        let mut tx = client.transaction()?;
+        let now = self.clock.now().timestamp_millis();
+        self.insert_task(&mut tx, task, now)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn update_task(&mut self, task: &Task) -> Result<()> {
+        let Some(current) = self.get_task(task.t_id)? else {
+            return Ok(());
+        };
+
+        let mut client = self.pool.get()?;
+        let mut tx = client.transaction()?;
+        let now = self.clock.now().timestamp_millis();
+        if current.t_name != task.t_name {
+            let hlc = self.next_hlc(&mut tx, now)?;
+            tx.execute(
+                "UPDATE tasks SET t_name = $1, t_name_hlc = $2, t_updated_at = $3
+                    WHERE t_id = $4",
+                &[&task.t_name, &hlc, &now, &task.t_id],
+            )?;
+        }
+        if current.t_priority != task.t_priority {
+            let hlc = self.next_hlc(&mut tx, now)?;
+            tx.execute(
+                "UPDATE tasks SET t_priority = $1, t_priority_hlc = $2, t_updated_at = $3
+                    WHERE t_id = $4",
+                &[&(task.t_priority as i32), &hlc, &now, &task.t_id],
+            )?;
+        }
+        if current.t_client != task.t_client {
+            tx.execute(
+                "UPDATE tasks SET t_client = $1 WHERE t_id = $2",
+                &[&task.t_client, &task.t_id],
+            )?;
+        }
+        if current.t_estimate != task.t_estimate || current.t_budget != task.t_budget {
+            tx.execute(
+                "UPDATE tasks SET t_estimate = $1, t_budget = $2 WHERE t_id = $3",
+                &[
+                    &task.t_estimate.map(|seconds| seconds as i64),
+                    &task.t_budget.map(|seconds| seconds as i64),
+                    &task.t_id,
+                ],
+            )?;
+        }
+        if current.t_daily_goal != task.t_daily_goal || current.t_weekly_goal != task.t_weekly_goal
+        {
+            self.save_goals(&mut tx, task.t_id, task)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn delete_task(&mut self, id: i64) -> Result<()> {
+        let mut client = self.pool.get()?;
+        let mut tx = client.transaction()?;
+        let now = self.clock.now().timestamp_millis();
+        let hlc = self.next_hlc(&mut tx, now)?;
+        tx.execute(
+            "UPDATE tasks SET t_deleted = TRUE, t_deleted_hlc = $1, t_updated_at = $2
+                WHERE t_id = $3",
+            &[&hlc, &now, &id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_all_sessions(&self) -> Result<Vec<Session>> {
+        let rows = self.pool.get()?.query(
+            &format!(
+                "SELECT {} FROM sessions WHERE NOT s_deleted ORDER BY s_id",
+                SESSION_COLUMNS
+            ),
+            &[],
+        )?;
+        Ok(rows.iter().map(session_from_row).collect())
+    }
+
+    fn get_session(&self, id: i64) -> Result<Option<Session>> {
+        let row = self.pool.get()?.query_opt(
+            &format!("SELECT {} FROM sessions WHERE s_id = $1", SESSION_COLUMNS),
+            &[&id],
+        )?;
+        Ok(row.as_ref().map(session_from_row))
+    }
+
+    fn save_session(&mut self, session: &Session) -> Result<()> {
+        let now = self.clock.now().timestamp_millis();
+        insert_session(&mut *self.pool.get()?, session, now)
+    }
+
+    fn import_sessions(&mut self, sessions: &[(String, Session)]) -> Result<()> {
+        let mut client = self.pool.get()?;
+        let mut tx = client.transaction()?;
+        let now = self.clock.now().timestamp_millis();
+        let mut task_ids: HashMap<String, i64> = tx
+            .query(
+                "SELECT t_name, t_id FROM tasks WHERE NOT t_deleted ORDER BY t_id",
+                &[],
+            )?
+            .iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect();
+        for (name, session) in sessions {
+            let task = match task_ids.get(name) {
+                Some(id) => *id,
+                None => {
+                    let task = Task {
+                        t_name: name.clone(),
+                        ..Default::default()
+                    };
+                    let id = self.insert_task(&mut tx, &task, now)?;
+                    task_ids.insert(name.clone(), id);
+                    id
+                }
+            };
+            let session = Session {
+                s_task: task,
+                ..session.clone()
+            };
+            insert_session(&mut tx, &session, now)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_all_users(&self) -> Result<Vec<User>> {
+        let rows = self
+            .pool
+            .get()?
+            .query("SELECT u_id, u_name FROM users ORDER BY u_id", &[])?;
+        Ok(rows
+            .iter()
+            .map(|row| User {
+                u_id: row.get(0),
+                u_name: row.get(1),
+            })
+            .collect())
+    }
+
+    fn ensure_user(&mut self, user: &User) -> Result<()> {
+        self.pool.get()?.execute(
+            "INSERT INTO users (u_id, u_name) VALUES ($1, $2) ON CONFLICT (u_id) DO NOTHING",
+            &[&user.u_id, &user.u_name],
+        )?;
+        Ok(())
+    }
+
+    fn get_all_clients(&self) -> Result<Vec<Client>> {
+        let rows = self.pool.get()?.query(
+            &format!("SELECT {} FROM clients ORDER BY cl_name", CLIENT_COLUMNS),
+            &[],
+        )?;
+        Ok(rows.iter().map(client_from_row).collect())
+    }
+
+    fn add_client(&mut self, client: &Client) -> Result<()> {
+        self.pool.get()?.execute(
+            "INSERT INTO clients (cl_name, cl_currency, cl_rounding, cl_rounding_minutes)
+                VALUES ($1, $2, $3, $4)",
+            &[
+                &client.cl_name,
+                &client.cl_currency,
+                &client.cl_rounding.mode.to_string(),
+                &(client.cl_rounding.minutes as i32),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn update_client(&mut self, client: &Client) -> Result<()> {
+        self.pool.get()?.execute(
+            "UPDATE clients SET cl_name = $1, cl_currency = $2, cl_rounding = $3,
+                cl_rounding_minutes = $4 WHERE cl_id = $5",
+            &[
+                &client.cl_name,
+                &client.cl_currency,
+                &client.cl_rounding.mode.to_string(),
+                &(client.cl_rounding.minutes as i32),
+                &client.cl_id,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_all_rates(&self) -> Result<Vec<Rate>> {
+        let rows = self.pool.get()?.query(
+            &format!(
+                "SELECT {} FROM rates ORDER BY r_effective_from, r_id",
+                RATE_COLUMNS
+            ),
+            &[],
+        )?;
+        rows.iter().map(rate_from_row).collect()
+    }
+
+    fn add_rate(&mut self, rate: &Rate) -> Result<()> {
+        self.pool.get()?.execute(
+            "INSERT INTO rates (r_client, r_task, r_hourly_cents, r_effective_from)
+                VALUES ($1, $2, $3, $4)",
+            &[
+                &rate.r_client,
+                &rate.r_task,
+                &rate.r_hourly_cents,
+                &rate.r_effective_from.format(rates::DATE_FORMAT).to_string(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn delete_rate(&mut self, id: i64) -> Result<()> {
+        self.pool
+            .get()?
+            .execute("DELETE FROM rates WHERE r_id = $1", &[&id])?;
+        Ok(())
+    }
+
+    fn get_all_window_rules(&self) -> Result<Vec<WindowRule>> {
+        let rows = self.pool.get()?.query(
+            &format!(
+                "SELECT {} FROM window_rules WHERE wr_user = $1 ORDER BY wr_id",
+                WINDOW_RULE_COLUMNS
+            ),
+            &[&self.node],
+        )?;
+        Ok(rows.iter().map(window_rule_from_row).collect())
+    }
+
+    fn add_window_rule(&mut self, rule: &WindowRule) -> Result<()> {
+        self.pool.get()?.execute(
+            "INSERT INTO window_rules (wr_pattern, wr_field, wr_task, wr_action, wr_user)
+                VALUES ($1, $2, $3, $4, $5)",
+            &[
+                &rule.wr_pattern,
+                &rule.wr_field.to_string(),
+                &rule.wr_task,
+                &rule.wr_action.to_string(),
+                &self.node,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn delete_window_rule(&mut self, id: i64) -> Result<()> {
+        self.pool.get()?.execute(
+            "DELETE FROM window_rules WHERE wr_id = $1 AND wr_user = $2",
+            &[&id, &self.node],
+        )?;
+        Ok(())
+    }
+
+    fn get_unresolved_conflicts(&self) -> Result<Vec<Conflict>> {
+        Ok(Vec::new())
+    }
+
+    fn resolve_conflict(&mut self, _id: i64, _keep_local: bool) -> Result<()> {
+        Ok(())
+    }
+}
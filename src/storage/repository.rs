@@ -0,0 +1,263 @@
+use std::{collections::HashMap, error, fmt, path::Path, sync::Arc, thread, time::Duration};
+
+use crate::{
+    agent::{
+        Clock, clients::Client, rates::Rate, sessions::Session, tasks::Task, users::User,
+        window_rules::WindowRule,
+    },
+    config::{self, settings::StorageBackend},
+    storage::{self, Conflict},
+};
+
+/// How often an operation is retried while SQLite reports the database as busy.
+const BUSY_RETRIES: u32 = 3;
+const BUSY_BACKOFF: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+pub enum StorageError {
+    Sqlite(rusqlite::Error),
+    /// Reading or replacing the database file failed.
+    Io(std::io::Error),
+    #[cfg(feature = "postgres")]
+    Postgres(postgres::Error),
+    #[cfg(feature = "postgres")]
+    Pool(r2d2::Error),
+    #[cfg(feature = "encryption")]
+    Keyring(keyring::Error),
+    /// The backend selected in the settings isn't part of this build.
+    Unsupported(String),
+    /// A write that would break the schema's constraints, e.g. a session for
+    /// a task that doesn't exist.
+    Constraint(String),
+    /// The database file is damaged, as reported by `PRAGMA integrity_check`.
+    Corrupt(String),
+    /// The database is encrypted and no passphrase has been entered yet.
+    Locked,
+    WrongPassphrase,
+}
+
+impl StorageError {
+    /// Whether another connection held a lock, so trying again may succeed.
+    pub fn is_busy(&self) -> bool {
+        match self {
+            Self::Sqlite(rusqlite::Error::SqliteFailure(e, _)) => matches!(
+                e.code,
+                rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked
+            ),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sqlite(e) => write!(f, "SQLite error: {}", e),
+            Self::Io(e) => write!(f, "I/O error: {}", e),
+            #[cfg(feature = "postgres")]
+            Self::Postgres(e) => write!(f, "PostgreSQL error: {}", e),
+            #[cfg(feature = "postgres")]
+            Self::Pool(e) => write!(f, "PostgreSQL connection pool error: {}", e),
+            #[cfg(feature = "encryption")]
+            Self::Keyring(e) => write!(f, "Keyring error: {}", e),
+            Self::Unsupported(message) => write!(f, "{}", message),
+            Self::Constraint(message) => write!(f, "Constraint failed: {}", message),
+            Self::Corrupt(message) => write!(f, "The database is damaged: {}", message),
+            Self::Locked => write!(f, "The database is locked until its passphrase is entered"),
+            Self::WrongPassphrase => write!(f, "Wrong passphrase"),
+        }
+    }
+}
+
+impl error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Sqlite(e) => Some(e),
+            Self::Io(e) => Some(e),
+            #[cfg(feature = "postgres")]
+            Self::Postgres(e) => Some(e),
+            #[cfg(feature = "postgres")]
+            Self::Pool(e) => Some(e),
+            #[cfg(feature = "encryption")]
+            Self::Keyring(e) => Some(e),
+            Self::Unsupported(_)
+            | Self::Constraint(_)
+            | Self::Corrupt(_)
+            | Self::Locked
+            | Self::WrongPassphrase => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Sqlite(e)
+    }
+}
+
+impl From<std::io::Error> for StorageError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl From<postgres::Error> for StorageError {
+    fn from(e: postgres::Error) -> Self {
+        Self::Postgres(e)
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl From<r2d2::Error> for StorageError {
+    fn from(e: r2d2::Error) -> Self {
+        Self::Pool(e)
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl From<keyring::Error> for StorageError {
+    fn from(e: keyring::Error) -> Self {
+        Self::Keyring(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, StorageError>;
+
+/// Everything the agent reads and writes, independent of where it is stored.
+///
+/// Implementations keep the semantics of `assets/schema.sql`: records get a
+/// global uuid and timestamps, from the clock they were opened with, when
+/// created, deleted tasks stay behind as
+/// tombstones, and every task field edit is stamped with a new hybrid logical
+/// clock.
+pub trait Repository: Send {
+    /// Tasks that have not been deleted.
+    fn get_all_tasks(&self) -> Result<Vec<Task>>;
+    /// Looks up a task by its local id, including deleted tasks.
+    fn get_task(&self, id: i64) -> Result<Option<Task>>;
+    fn add_task(&mut self, task: &Task) -> Result<()>;
+    /// Writes the name, priority, client, estimate, budget and goals of the
+    /// task with `task.t_id`.
+    fn update_task(&mut self, task: &Task) -> Result<()>;
+    fn delete_task(&mut self, id: i64) -> Result<()>;
+
+    /// Sessions that have not been deleted.
+    fn get_all_sessions(&self) -> Result<Vec<Session>>;
+    fn get_session(&self, id: i64) -> Result<Option<Session>>;
+    /// Sessions that overlap the time from `from` up to `to`, leaving out
+    /// those whose time is unknown.
+    fn get_sessions_between(
+        &self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<Session>> {
+        Ok(self
+            .get_all_sessions()?
+            .into_iter()
+            .filter(|session| {
+                session.time_known() && session.started_at() < to && session.ended_at() > from
+            })
+            .collect())
+    }
+    /// Total tracked seconds per task, over everyone's sessions.
+    fn get_tracked_seconds(&self) -> Result<HashMap<i64, u64>> {
+        let mut tracked = HashMap::new();
+        for session in self.get_all_sessions()? {
+            *tracked.entry(session.s_task).or_insert(0) += session.s_duration;
+        }
+        Ok(tracked)
+    }
+    /// Inserts a session, stamped as created now unless `s_created_at` is
+    /// already set, as it is for imported ones.
+    fn save_session(&mut self, session: &Session) -> Result<()>;
+    /// Saves imported sessions, each on the task named next to it, adding
+    /// the tasks that don't exist yet. If one of them fails, none are saved.
+    fn import_sessions(&mut self, sessions: &[(String, Session)]) -> Result<()>;
+
+    fn get_all_users(&self) -> Result<Vec<User>>;
+    /// Adds the user if it doesn't exist yet.
+    fn ensure_user(&mut self, user: &User) -> Result<()>;
+
+    /// Clients sorted by name.
+    fn get_all_clients(&self) -> Result<Vec<Client>>;
+    fn add_client(&mut self, client: &Client) -> Result<()>;
+    /// Renames the client with `client.cl_id` and changes its currency and
+    /// rounding.
+    fn update_client(&mut self, client: &Client) -> Result<()>;
+    /// Rates of all clients and tasks, oldest first.
+    fn get_all_rates(&self) -> Result<Vec<Rate>>;
+    fn add_rate(&mut self, rate: &Rate) -> Result<()>;
+    fn delete_rate(&mut self, id: i64) -> Result<()>;
+    /// Window rules, in the order they were added.
+    fn get_all_window_rules(&self) -> Result<Vec<WindowRule>>;
+    fn add_window_rule(&mut self, rule: &WindowRule) -> Result<()>;
+    fn delete_window_rule(&mut self, id: i64) -> Result<()>;
+
+    /// Sync conflicts the user has not reviewed yet.
+    fn get_unresolved_conflicts(&self) -> Result<Vec<Conflict>>;
+    fn resolve_conflict(&mut self, id: i64, keep_local: bool) -> Result<()>;
+
+    /// Writes a copy of the database to `path`. Only the SQLite backend keeps
+    /// a file of its own to back up.
+    fn backup(&self, _path: &Path) -> Result<()> {
+        Err(StorageError::Unsupported(
+            "This storage backend can't be backed up".to_string(),
+        ))
+    }
+    /// Replaces the database's contents with the copy at `path`.
+    fn restore(&mut self, _path: &Path) -> Result<()> {
+        Err(StorageError::Unsupported(
+            "This storage backend can't be restored from a backup".to_string(),
+        ))
+    }
+    /// Problems found in the stored data; empty if it is intact.
+    fn integrity_check(&self) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+    /// Whether the data can't be read until a passphrase is entered.
+    fn is_locked(&self) -> bool {
+        false
+    }
+}
+
+/// Runs `operation` again, with a growing delay, while the database is busy,
+/// e.g. because the sync worker is writing to it.
+pub fn retry_busy<T>(mut operation: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut attempt = 0;
+    loop {
+        match operation() {
+            Err(e) if e.is_busy() && attempt < BUSY_RETRIES => {
+                tracing::warn!(attempt, "Database is busy, retrying: {}", e);
+                thread::sleep(BUSY_BACKOFF * 2u32.pow(attempt));
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Opens the backend selected in the settings, with the settings' user
+/// already added. Records are timestamped by `clock`.
+pub fn open_repository(
+    settings: Arc<config::Settings>,
+    clock: Arc<dyn Clock>,
+) -> Result<Box<dyn Repository>> {
+    match settings.storage_backend {
+        StorageBackend::Sqlite => Ok(Box::new(storage::sqlite::SqliteRepository::open(
+            settings, clock,
+        )?)),
+        StorageBackend::Memory => Ok(Box::new(storage::memory::MemoryRepository::new(
+            &settings.uid,
+            clock,
+        ))),
+        #[cfg(feature = "postgres")]
+        StorageBackend::Postgres => Ok(Box::new(storage::postgres::PostgresRepository::open(
+            settings, clock,
+        )?)),
+        #[cfg(not(feature = "postgres"))]
+        StorageBackend::Postgres => Err(StorageError::Unsupported(
+            "This build has no PostgreSQL support; rebuild with `--features postgres`".to_string(),
+        )),
+    }
+}
@@ -1,10 +1,24 @@
-use rusqlite::{Connection, Result};
-use std::sync::Arc;
+use rusqlite::{Connection, Result, Transaction, TransactionBehavior, backup::Backup};
+use std::{collections::HashMap, path::Path, sync::Arc, time::Duration};
 
-use crate::{DB_SCHEMA, config};
+use crate::{DB_MIGRATIONS, DB_SCHEMA, agent, config, storage};
 
-pub fn init_db(settings: Arc<config::Settings>) -> Result<Connection> {
+/// How long a statement waits for another connection's lock before failing.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(2);
+const BACKUP_PAGES_PER_STEP: i32 = 100;
+
+pub fn init_db(settings: Arc<config::Settings>) -> storage::Result<Connection> {
+    let passphrase = storage::encryption::passphrase(&settings)?;
+    open_db(&settings, passphrase.as_deref())
+}
+
+fn open_db(settings: &config::Settings, passphrase: Option<&str>) -> storage::Result<Connection> {
+    tracing::debug!(path = %settings.local_database_path, "Opening database");
     let conn = Connection::open(&settings.local_database_path)?;
+    if let Some(passphrase) = passphrase {
+        storage::encryption::unlock(&conn, passphrase)?;
+    }
+    conn.busy_timeout(BUSY_TIMEOUT)?;
 
     conn.execute_batch(
         "
@@ -13,11 +27,258 @@ pub fn init_db(settings: Arc<config::Settings>) -> Result<Connection> {
         ",
     )?;
 
-    conn.execute_batch(DB_SCHEMA)?;
+    prepare(&conn, settings)?;
+    Ok(conn)
+}
+
+/// Brings the schema up to date and adds this client's user and clock.
+fn prepare(conn: &Connection, settings: &config::Settings) -> Result<()> {
+    // The agent and the sync worker open the same file at start up. Taking
+    // the write lock before reading the version lets only one of them
+    // migrate it; the other waits and finds it up to date.
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    migrate(&tx)?;
+    tx.execute_batch(DB_SCHEMA)?;
+    tx.commit()?;
     // Ensure the current user exists so sessions can reference it (foreign key)
-    conn.execute(
-        "INSERT OR IGNORE INTO users (u_id, u_name) VALUES (?1, ?2)",
-        (&settings.uid, &settings.uid),
+    agent::users::ensure_user(
+        conn,
+        &agent::users::User {
+            u_id: settings.uid.clone(),
+            u_name: settings.uid.clone(),
+        },
     )?;
-    Ok(conn)
+    storage::hlc::init_clock(conn, &settings.uid)
+}
+
+/// Upgrades databases created by older versions. A fresh database is created
+/// from `DB_SCHEMA` in its latest form, so it is only stamped with the version.
+fn migrate(conn: &Connection) -> Result<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let has_tables: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'tasks')",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if !has_tables {
+        return conn.pragma_update(None, "user_version", DB_MIGRATIONS.len());
+    }
+
+    for (index, migration) in DB_MIGRATIONS.iter().enumerate().skip(version) {
+        tracing::info!(version = index + 1, "Migrating database");
+        conn.execute_batch(migration)?;
+        conn.pragma_update(None, "user_version", index + 1)?;
+    }
+    Ok(())
+}
+
+/// The default backend: a local SQLite database, see `assets/schema.sql`.
+pub struct SqliteRepository {
+    conn: Connection,
+    settings: Arc<config::Settings>,
+    /// Backups are encrypted with the same passphrase as the database.
+    passphrase: Option<String>,
+    clock: Arc<dyn agent::Clock>,
+}
+
+impl SqliteRepository {
+    /// Opens the database, encrypting it and its backups first if encryption
+    /// was just turned on. Only the agent opens it this way, so the file is
+    /// never replaced under another connection.
+    pub fn open(
+        settings: Arc<config::Settings>,
+        clock: Arc<dyn agent::Clock>,
+    ) -> storage::Result<Self> {
+        let passphrase = storage::encryption::passphrase(&settings)?;
+        if let Some(passphrase) = &passphrase {
+            storage::encryption::encrypt_if_plaintext(
+                Path::new(&settings.local_database_path),
+                passphrase,
+            )?;
+            storage::encryption::encrypt_backups(Path::new(&settings.backup_path), passphrase)?;
+        }
+        Ok(Self {
+            conn: open_db(&settings, passphrase.as_deref())?,
+            settings,
+            passphrase,
+            clock,
+        })
+    }
+
+    /// Opens another database file with this database's passphrase.
+    fn open_copy(&self, path: &Path) -> storage::Result<Connection> {
+        let conn = Connection::open(path)?;
+        if let Some(passphrase) = &self.passphrase {
+            storage::encryption::unlock(&conn, passphrase)?;
+        }
+        Ok(conn)
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    fn now_ms(&self) -> i64 {
+        self.clock.now().timestamp_millis()
+    }
+}
+
+impl storage::Repository for SqliteRepository {
+    fn get_all_tasks(&self) -> storage::Result<Vec<agent::Task>> {
+        Ok(agent::tasks::get_all_tasks(&self.conn)?)
+    }
+
+    fn get_task(&self, id: i64) -> storage::Result<Option<agent::Task>> {
+        Ok(agent::tasks::get_task(&self.conn, id)?)
+    }
+
+    fn add_task(&mut self, task: &agent::Task) -> storage::Result<()> {
+        agent::tasks::add_new_task(&self.conn, task, self.now_ms())?;
+        Ok(())
+    }
+
+    fn update_task(&mut self, task: &agent::Task) -> storage::Result<()> {
+        agent::tasks::update_task(&self.conn, task, self.now_ms())?;
+        Ok(())
+    }
+
+    fn delete_task(&mut self, id: i64) -> storage::Result<()> {
+        agent::tasks::delete_task(&self.conn, id, self.now_ms())?;
+        Ok(())
+    }
+
+    fn get_all_sessions(&self) -> storage::Result<Vec<agent::Session>> {
+        Ok(agent::sessions::get_all_sessions(&self.conn)?)
+    }
+
+    fn get_session(&self, id: i64) -> storage::Result<Option<agent::Session>> {
+        Ok(agent::sessions::get_session(&self.conn, id)?)
+    }
+
+    fn save_session(&mut self, session: &agent::Session) -> storage::Result<()> {
+        agent::sessions::save_session(&self.conn, session, self.now_ms())?;
+        Ok(())
+    }
+
+    fn import_sessions(&mut self, sessions: &[(String, agent::Session)]) -> storage::Result<()> {
+        let now = self.now_ms();
+        let tx = Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate)?;
+        let mut task_ids: HashMap<String, i64> = agent::tasks::get_all_tasks(&tx)?
+            .into_iter()
+            .map(|task| (task.t_name, task.t_id))
+            .collect();
+        for (name, session) in sessions {
+            let task = match task_ids.get(name) {
+                Some(id) => *id,
+                None => {
+                    let task = agent::Task {
+                        t_name: name.clone(),
+                        ..Default::default()
+                    };
+                    agent::tasks::add_new_task(&tx, &task, now)?;
+                    let id = tx.last_insert_rowid();
+                    task_ids.insert(name.clone(), id);
+                    id
+                }
+            };
+            let session = agent::Session {
+                s_task: task,
+                ..session.clone()
+            };
+            agent::sessions::save_session(&tx, &session, now)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_all_users(&self) -> storage::Result<Vec<agent::users::User>> {
+        Ok(agent::users::get_all_users(&self.conn)?)
+    }
+
+    fn ensure_user(&mut self, user: &agent::users::User) -> storage::Result<()> {
+        agent::users::ensure_user(&self.conn, user)?;
+        Ok(())
+    }
+
+    fn get_all_clients(&self) -> storage::Result<Vec<agent::clients::Client>> {
+        Ok(agent::clients::get_all_clients(&self.conn)?)
+    }
+
+    fn add_client(&mut self, client: &agent::clients::Client) -> storage::Result<()> {
+        agent::clients::add_client(&self.conn, client)?;
+        Ok(())
+    }
+
+    fn update_client(&mut self, client: &agent::clients::Client) -> storage::Result<()> {
+        agent::clients::update_client(&self.conn, client)?;
+        Ok(())
+    }
+
+    fn get_all_rates(&self) -> storage::Result<Vec<agent::rates::Rate>> {
+        Ok(agent::rates::get_all_rates(&self.conn)?)
+    }
+
+    fn add_rate(&mut self, rate: &agent::rates::Rate) -> storage::Result<()> {
+        agent::rates::add_rate(&self.conn, rate)?;
+        Ok(())
+    }
+
+    fn delete_rate(&mut self, id: i64) -> storage::Result<()> {
+        agent::rates::delete_rate(&self.conn, id)?;
+        Ok(())
+    }
+
+    fn get_all_window_rules(&self) -> storage::Result<Vec<agent::window_rules::WindowRule>> {
+        Ok(agent::window_rules::get_all_window_rules(&self.conn)?)
+    }
+
+    fn add_window_rule(&mut self, rule: &agent::window_rules::WindowRule) -> storage::Result<()> {
+        agent::window_rules::add_window_rule(&self.conn, rule)?;
+        Ok(())
+    }
+
+    fn delete_window_rule(&mut self, id: i64) -> storage::Result<()> {
+        agent::window_rules::delete_window_rule(&self.conn, id)?;
+        Ok(())
+    }
+
+    fn get_unresolved_conflicts(&self) -> storage::Result<Vec<storage::Conflict>> {
+        Ok(storage::conflicts::get_unresolved_conflicts(&self.conn)?)
+    }
+
+    fn resolve_conflict(&mut self, id: i64, keep_local: bool) -> storage::Result<()> {
+        Ok(storage::conflicts::resolve_conflict(
+            &self.conn,
+            id,
+            keep_local,
+            self.now_ms(),
+        )?)
+    }
+
+    fn backup(&self, path: &Path) -> storage::Result<()> {
+        let mut copy = self.open_copy(path)?;
+        Backup::new(&self.conn, &mut copy)?.run_to_completion(
+            BACKUP_PAGES_PER_STEP,
+            Duration::ZERO,
+            None,
+        )?;
+        Ok(())
+    }
+
+    fn restore(&mut self, path: &Path) -> storage::Result<()> {
+        let copy = self.open_copy(path)?;
+        Backup::new(&copy, &mut self.conn)?.run_to_completion(
+            BACKUP_PAGES_PER_STEP,
+            Duration::ZERO,
+            None,
+        )?;
+        // The backup may predate migrations or this client.
+        prepare(&self.conn, &self.settings)?;
+        Ok(())
+    }
+
+    fn integrity_check(&self) -> storage::Result<Vec<String>> {
+        Ok(storage::backup::integrity_problems(&self.conn)?)
+    }
 }
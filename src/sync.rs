@@ -0,0 +1,7 @@
+pub mod engine;
+pub mod outbox;
+pub mod protocol;
+
+pub use engine::SyncCommand;
+pub use engine::SyncStatus;
+pub use engine::start_sync_worker;
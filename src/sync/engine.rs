@@ -0,0 +1,298 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        Arc,
+        mpsc::{self, RecvTimeoutError},
+    },
+    thread,
+    time::Duration,
+};
+
+use rusqlite::Connection;
+
+use crate::{
+    agent::{self, events},
+    config, storage,
+    sync::{outbox, protocol},
+};
+
+const BATCH_SIZE: usize = 100;
+const REQUEST_TIMEOUT_SECONDS: u64 = 10;
+
+pub enum SyncCommand {
+    SyncNow,
+    Reconfigure {
+        settings: Arc<config::settings::Settings>,
+    },
+    Quit,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SyncStatus {
+    Disabled,
+    Syncing,
+    Synced {
+        at: chrono::DateTime<chrono::Utc>,
+    },
+    Pending {
+        count: usize,
+    },
+    Failed {
+        message: String,
+        retry_at: chrono::DateTime<chrono::Utc>,
+    },
+}
+
+pub fn start_sync_worker(
+    settings: Arc<config::settings::Settings>,
+    sync_rx: mpsc::Receiver<SyncCommand>,
+    window_tx: crossbeam_channel::Sender<events::UIEvent>,
+) -> thread::JoinHandle<()> {
+    thread::Builder::new()
+        .name("sync-worker".into())
+        .spawn(move || run_sync_worker(settings, sync_rx, window_tx))
+        .expect("Failed to spawn sync-worker thread")
+}
+
+fn run_sync_worker(
+    settings: Arc<config::settings::Settings>,
+    sync_rx: mpsc::Receiver<SyncCommand>,
+    window_tx: crossbeam_channel::Sender<events::UIEvent>,
+) {
+    let http = http_agent();
+    let mut settings = settings;
+    let mut db_connection = open_sync_db(&settings, &window_tx);
+
+    loop {
+        let interval = Duration::from_secs(settings.auto_sync_interval_seconds.max(1));
+        let force = match sync_rx.recv_timeout(interval) {
+            Ok(SyncCommand::SyncNow) => true,
+            Ok(SyncCommand::Reconfigure {
+                settings: new_settings,
+            }) => {
+                settings = new_settings;
+                db_connection = open_sync_db(&settings, &window_tx);
+                true
+            }
+            Ok(SyncCommand::Quit) | Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => false,
+        };
+
+        let Some(db_connection) = &db_connection else {
+            let _ = window_tx.send(events::UIEvent::SyncStatus {
+                status: SyncStatus::Disabled,
+            });
+            continue;
+        };
+
+        if force && !settings.sync_server_url.is_empty() {
+            let _ = window_tx.send(events::UIEvent::SyncStatus {
+                status: SyncStatus::Syncing,
+            });
+        }
+
+        let status = sync_once(db_connection, &http, &settings, force);
+        let _ = window_tx.send(events::UIEvent::SyncStatus { status });
+    }
+}
+
+/// Only the local SQLite database is synced; the other backends are either
+/// not persisted or already shared between clients.
+fn open_sync_db(
+    settings: &Arc<config::settings::Settings>,
+    window_tx: &crossbeam_channel::Sender<events::UIEvent>,
+) -> Option<Connection> {
+    if settings.storage_backend != config::StorageBackend::Sqlite {
+        return None;
+    }
+    match storage::init_db(settings.clone()) {
+        Ok(conn) => Some(conn),
+        // The agent asks for the passphrase and reconfigures the worker once
+        // the database is unlocked.
+        Err(e @ (storage::StorageError::Locked | storage::StorageError::WrongPassphrase)) => {
+            tracing::debug!("Not syncing until the database is unlocked: {}", e);
+            None
+        }
+        Err(e) => {
+            tracing::error!("Couldn't open the database for syncing: {}", e);
+            let _ = window_tx.send(events::UIEvent::Error {
+                message: format!("Couldn't open the database for syncing: {}", e),
+            });
+            None
+        }
+    }
+}
+
+pub fn http_agent() -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(REQUEST_TIMEOUT_SECONDS)))
+        .build()
+        .into()
+}
+
+/// Pushes one batch of outbox entries to the sync server and merges the shared
+/// tasks back. With `force` set, entries still waiting out their backoff are
+/// sent as well.
+pub fn sync_once(
+    conn: &Connection,
+    http: &ureq::Agent,
+    settings: &config::settings::Settings,
+    force: bool,
+) -> SyncStatus {
+    if settings.sync_server_url.is_empty() {
+        return SyncStatus::Disabled;
+    }
+
+    let now = chrono::Utc::now();
+    match push_due_entries(conn, http, settings, force, now.timestamp()) {
+        Ok(status) => status,
+        Err(e) => failed(e.to_string(), now.timestamp() + outbox::backoff_seconds(1)),
+    }
+}
+
+fn push_due_entries(
+    conn: &Connection,
+    http: &ureq::Agent,
+    settings: &config::settings::Settings,
+    force: bool,
+    now: i64,
+) -> rusqlite::Result<SyncStatus> {
+    let due = if force { i64::MAX } else { now };
+    let entries = outbox::get_due_entries(conn, due, BATCH_SIZE)?;
+
+    if !entries.is_empty() {
+        let request = build_push_request(conn, settings, &entries)?;
+        tracing::debug!(
+            tasks = request.tasks.len(),
+            sessions = request.sessions.len(),
+            "Pushing changes"
+        );
+        if let Err(message) = push(http, settings, &request) {
+            let retry_at = outbox::reschedule_entries(conn, &entries, now)?;
+            return Ok(failed(message, retry_at));
+        }
+        outbox::remove_entries(conn, &entries)?;
+    }
+
+    match pull(http, settings) {
+        Ok(response) => {
+            let now_ms = chrono::Utc::now().timestamp_millis();
+            for task in &response.tasks {
+                storage::merge::merge_task(conn, task, now_ms)?;
+            }
+        }
+        Err(message) => {
+            return Ok(failed(message, now + outbox::backoff_seconds(1)));
+        }
+    }
+
+    Ok(match outbox::count_entries(conn)? {
+        0 => SyncStatus::Synced {
+            at: chrono::DateTime::from_timestamp(now, 0).unwrap_or_default(),
+        },
+        count => SyncStatus::Pending { count },
+    })
+}
+
+fn failed(message: String, retry_at: i64) -> SyncStatus {
+    tracing::warn!("Sync failed: {}", message);
+    SyncStatus::Failed {
+        message,
+        retry_at: chrono::DateTime::from_timestamp(retry_at, 0).unwrap_or_default(),
+    }
+}
+
+fn build_push_request(
+    conn: &Connection,
+    settings: &config::settings::Settings,
+    entries: &[outbox::OutboxEntry],
+) -> rusqlite::Result<protocol::PushRequest> {
+    let mut request = protocol::PushRequest {
+        uid: settings.uid.clone(),
+        ..Default::default()
+    };
+    let mut seen = HashSet::new();
+
+    for entry in entries {
+        if !seen.insert((entry.o_kind.as_str(), entry.o_record)) {
+            continue;
+        }
+
+        match entry.o_kind.as_str() {
+            "task" => {
+                if let Some(task) = agent::tasks::get_task(conn, entry.o_record)? {
+                    request.tasks.push(protocol::TaskRecord {
+                        t_uuid: task.t_uuid,
+                        t_name: task.t_name,
+                        t_priority: task.t_priority,
+                        t_created_at: task.t_created_at,
+                        t_updated_at: task.t_updated_at,
+                        t_deleted: task.t_deleted,
+                        t_name_hlc: task.t_name_hlc,
+                        t_priority_hlc: task.t_priority_hlc,
+                        t_deleted_hlc: task.t_deleted_hlc,
+                    });
+                }
+            }
+            "session" => {
+                let Some(session) = agent::sessions::get_session(conn, entry.o_record)? else {
+                    continue;
+                };
+                if let Some(task) = agent::tasks::get_task(conn, session.s_task)? {
+                    request.sessions.push(protocol::SessionRecord {
+                        s_uuid: session.s_uuid,
+                        s_task: task.t_uuid,
+                        s_user: session.s_user,
+                        s_duration: session.s_duration,
+                        s_comment: session.s_comment,
+                        s_created_at: session.s_created_at,
+                        s_updated_at: session.s_updated_at,
+                        s_deleted: session.s_deleted,
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(request)
+}
+
+fn push(
+    http: &ureq::Agent,
+    settings: &config::settings::Settings,
+    request: &protocol::PushRequest,
+) -> Result<protocol::PushResponse, String> {
+    http.post(&endpoint(settings, "push"))
+        .header("Authorization", &authorization(settings))
+        .send_json(request)
+        .map_err(|e| e.to_string())?
+        .body_mut()
+        .read_json::<protocol::PushResponse>()
+        .map_err(|e| e.to_string())
+}
+
+fn pull(
+    http: &ureq::Agent,
+    settings: &config::settings::Settings,
+) -> Result<protocol::PullResponse, String> {
+    http.get(&endpoint(settings, "tasks"))
+        .header("Authorization", &authorization(settings))
+        .call()
+        .map_err(|e| e.to_string())?
+        .body_mut()
+        .read_json::<protocol::PullResponse>()
+        .map_err(|e| e.to_string())
+}
+
+fn endpoint(settings: &config::settings::Settings, path: &str) -> String {
+    format!(
+        "{}/{}",
+        settings.sync_server_url.trim_end_matches('/'),
+        path
+    )
+}
+
+fn authorization(settings: &config::settings::Settings) -> String {
+    format!("Bearer {}", settings.sync_token)
+}
@@ -0,0 +1,67 @@
+use rusqlite::{Connection, Result};
+
+/// Seconds to wait after the first failed push; doubled for every further attempt.
+const BACKOFF_BASE_SECONDS: i64 = 5;
+const BACKOFF_MAX_SECONDS: i64 = 3600;
+
+#[derive(Debug)]
+pub struct OutboxEntry {
+    pub o_id: i64,
+    pub o_kind: String,
+    pub o_record: i64,
+    pub o_attempts: u32,
+}
+
+pub fn get_due_entries(conn: &Connection, now: i64, limit: usize) -> Result<Vec<OutboxEntry>> {
+    let mut statement = conn.prepare(
+        "SELECT o_id, o_kind, o_record, o_attempts FROM outbox
+            WHERE o_next_attempt <= ?1
+            ORDER BY o_id
+            LIMIT ?2",
+    )?;
+    let entry_iter = statement.query_map((now, limit), |row| {
+        Ok(OutboxEntry {
+            o_id: row.get(0)?,
+            o_kind: row.get(1)?,
+            o_record: row.get(2)?,
+            o_attempts: row.get(3)?,
+        })
+    })?;
+
+    let mut entries = Vec::new();
+    for entry in entry_iter {
+        entries.push(entry?);
+    }
+    Ok(entries)
+}
+
+pub fn count_entries(conn: &Connection) -> Result<usize> {
+    conn.query_row("SELECT COUNT(*) FROM outbox", [], |row| row.get(0))
+}
+
+pub fn remove_entries(conn: &Connection, entries: &[OutboxEntry]) -> Result<()> {
+    let mut statement = conn.prepare("DELETE FROM outbox WHERE o_id = ?1")?;
+    for entry in entries {
+        statement.execute([entry.o_id])?;
+    }
+    Ok(())
+}
+
+/// Pushes the entries back with exponential backoff, returning the earliest retry time.
+pub fn reschedule_entries(conn: &Connection, entries: &[OutboxEntry], now: i64) -> Result<i64> {
+    let mut statement =
+        conn.prepare("UPDATE outbox SET o_attempts = ?1, o_next_attempt = ?2 WHERE o_id = ?3")?;
+    let mut next_attempt = i64::MAX;
+    for entry in entries {
+        let attempts = entry.o_attempts + 1;
+        let delay = backoff_seconds(attempts);
+        statement.execute((attempts, now + delay, entry.o_id))?;
+        next_attempt = next_attempt.min(now + delay);
+    }
+    Ok(next_attempt)
+}
+
+pub fn backoff_seconds(attempts: u32) -> i64 {
+    let exponent = attempts.saturating_sub(1).min(16);
+    (BACKOFF_BASE_SECONDS << exponent).min(BACKOFF_MAX_SECONDS)
+}
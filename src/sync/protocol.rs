@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+/// Records are identified by their global `*_uuid`, so the same record pushed
+/// from several devices can be merged. Timestamps are Unix milliseconds.
+///
+/// Task fields carry their own hybrid logical clock (`*_hlc`) and are merged
+/// field by field, see `storage::merge`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TaskRecord {
+    pub t_uuid: String,
+    pub t_name: String,
+    pub t_priority: usize,
+    pub t_created_at: i64,
+    pub t_updated_at: i64,
+    pub t_deleted: bool,
+    pub t_name_hlc: String,
+    pub t_priority_hlc: String,
+    pub t_deleted_hlc: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SessionRecord {
+    pub s_uuid: String,
+    /// Global id of the task the session belongs to.
+    pub s_task: String,
+    pub s_user: String,
+    pub s_duration: u64,
+    pub s_comment: String,
+    pub s_created_at: i64,
+    pub s_updated_at: i64,
+    pub s_deleted: bool,
+}
+
+/// Body of `POST {sync_server_url}/push`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PushRequest {
+    pub uid: String,
+    pub tasks: Vec<TaskRecord>,
+    pub sessions: Vec<SessionRecord>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PushResponse {
+    pub accepted: usize,
+}
+
+/// Body of `GET {sync_server_url}/tasks`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PullResponse {
+    pub tasks: Vec<TaskRecord>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct UserReport {
+    pub uid: String,
+    pub sessions: u64,
+    pub total_seconds: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TaskReport {
+    pub t_uuid: String,
+    pub t_name: String,
+    pub total_seconds: u64,
+}
+
+/// Body of `GET {sync_server_url}/reports`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TeamReport {
+    pub users: Vec<UserReport>,
+    pub tasks: Vec<TaskReport>,
+}
@@ -5,12 +5,12 @@ use std::{
 
 use tray_icon::{
     Icon, TrayIcon, TrayIconBuilder,
-    menu::{Menu, MenuEvent, MenuId, MenuItemBuilder},
+    menu::{CheckMenuItemBuilder, Menu, MenuEvent, MenuId, MenuItemBuilder, SubmenuBuilder},
 };
 #[cfg(target_os = "windows")]
 use winapi::um::winuser::{DispatchMessageW, MSG, PM_REMOVE, PeekMessageW, TranslateMessage};
 
-use crate::{ACTIVE_ICON_BYTES, IDLE_ICON_BYTES, agent, ui};
+use crate::{ACTIVE_ICON_BYTES, IDLE_ICON_BYTES, agent, config, report, ui};
 
 pub struct Tray {
     tray: TrayIcon,
@@ -20,6 +20,8 @@ pub struct Tray {
     event_rx: Receiver<ui::UIEvent>,
     quit: bool,
     user_state: ui::UserState,
+    pomodoro: Option<agent::pomodoro::PomodoroStatus>,
+    goals: Vec<report::goals::GoalProgress>,
 }
 
 impl Tray {
@@ -41,7 +43,7 @@ impl Tray {
         )
         .expect("Invalid idle icon data");
 
-        let tray_menu = build_tray_menu();
+        let tray_menu = build_tray_menu(&config::Profiles::load());
 
         let tray = TrayIconBuilder::new()
             .with_tooltip("Time Tracker")
@@ -61,6 +63,8 @@ impl Tray {
             event_rx: tray_rx,
             quit,
             user_state,
+            pomodoro: None,
+            goals: Vec::new(),
         }
     }
 
@@ -69,8 +73,9 @@ impl Tray {
 
         #[cfg(not(target_os = "windows"))]
         {
-            while !quit {
-                handle_events(&command_tx, menu_event_receiver, &mut quit);
+            while !self.quit {
+                self.handle_events(menu_event_receiver);
+                self.update_icon();
                 std::thread::sleep(Duration::from_millis(100));
             }
         }
@@ -86,17 +91,34 @@ impl Tray {
                 }
 
                 self.handle_events(menu_event_receiver);
-
-                let _ = match self.user_state {
-                    ui::UserState::Active => self.tray.set_icon(Some(self.active_icon.clone())),
-                    ui::UserState::Idle => self.tray.set_icon(Some(self.idle_icon.clone())),
-                };
+                self.update_icon();
 
                 std::thread::sleep(Duration::from_millis(50));
             }
         }
     }
 
+    fn update_icon(&self) {
+        let _ = match self.user_state {
+            ui::UserState::Active => self.tray.set_icon(Some(self.active_icon.clone())),
+            ui::UserState::Idle => self.tray.set_icon(Some(self.idle_icon.clone())),
+        };
+    }
+
+    /// Shows the focus mode countdown and the goals under the app's name.
+    fn update_tooltip(&self) {
+        let mut lines = vec!["Time Tracker".to_string()];
+        if let Some(status) = self.pomodoro {
+            lines.push(format!(
+                "{} {} left",
+                status.phase.label(),
+                ui::utils::format_minutes(status.remaining)
+            ));
+        }
+        lines.extend(self.goals.iter().map(ui::utils::format_goal));
+        let _ = self.tray.set_tooltip(Some(lines.join("\n")));
+    }
+
     fn handle_events(&mut self, menu_event_receiver: &crossbeam_channel::Receiver<MenuEvent>) {
         while let Ok(event) = menu_event_receiver.try_recv() {
             match event.id.0.as_str() {
@@ -107,7 +129,19 @@ impl Tray {
                 "ui" => {
                     let _ = self.command_tx.send(agent::AgentCommand::ShowUI);
                 }
-                _ => eprintln!("Invalid menu item"),
+                "logs" => {
+                    if let Err(e) = config::logging::open_log_folder() {
+                        tracing::error!("Couldn't open the log folder: {}", e);
+                    }
+                }
+                id => match id.strip_prefix("profile:") {
+                    Some(name) => {
+                        let _ = self.command_tx.send(agent::AgentCommand::SwitchProfile {
+                            name: name.to_string(),
+                        });
+                    }
+                    None => tracing::warn!(id, "Invalid menu item"),
+                },
             }
         }
 
@@ -116,13 +150,26 @@ impl Tray {
                 ui::UIEvent::UserState { state } => {
                     self.user_state = state;
                 }
+                ui::UIEvent::Pomodoro { status } => {
+                    self.pomodoro = status;
+                    self.update_tooltip();
+                }
+                ui::UIEvent::Goals { progress } => {
+                    self.goals = progress;
+                    self.update_tooltip();
+                }
+                ui::UIEvent::Profiles { active, names } => {
+                    let profiles = config::Profiles { active, names };
+                    self.tray
+                        .set_menu(Some(Box::new(build_tray_menu(&profiles))));
+                }
                 _ => (),
             }
         }
     }
 }
 
-fn build_tray_menu() -> Menu {
+fn build_tray_menu(profiles: &config::Profiles) -> Menu {
     let quit_menu_item = MenuItemBuilder::new()
         .text("Quit")
         .id(MenuId("quit".to_string()))
@@ -135,5 +182,32 @@ fn build_tray_menu() -> Menu {
         .enabled(true)
         .build();
 
-    Menu::with_items(&[&open_ui_item, &quit_menu_item]).unwrap()
+    let open_logs_item = MenuItemBuilder::new()
+        .text("Open Log Folder")
+        .id(MenuId("logs".into()))
+        .enabled(true)
+        .build();
+
+    let profile_menu = SubmenuBuilder::new()
+        .text("Profile")
+        .enabled(true)
+        .build()
+        .unwrap();
+    for name in &profiles.names {
+        let profile_item = CheckMenuItemBuilder::new()
+            .text(name)
+            .id(MenuId(format!("profile:{}", name)))
+            .enabled(true)
+            .checked(*name == profiles.active)
+            .build();
+        let _ = profile_menu.append(&profile_item);
+    }
+
+    Menu::with_items(&[
+        &open_ui_item,
+        &profile_menu,
+        &open_logs_item,
+        &quit_menu_item,
+    ])
+    .unwrap()
 }
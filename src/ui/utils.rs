@@ -2,6 +2,8 @@ use std::time::Duration;
 
 use egui::IconData;
 
+use crate::report;
+
 pub fn load_icon_from_bytes(bytes: &[u8]) -> IconData {
     let image = image::load_from_memory(bytes)
         .expect("Failed to load icon bytes")
@@ -24,3 +26,44 @@ pub fn format_duration(d: Duration) -> String {
 
     format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
 }
+
+/// Whole minutes, e.g. `24 min`.
+pub fn format_minutes(d: Duration) -> String {
+    format!("{} min", d.as_secs() / 60)
+}
+
+/// Hours as a short decimal, e.g. `1.5` or `12`.
+pub fn format_hours(seconds: u64) -> String {
+    let hours = format!("{:.2}", seconds as f64 / 3600.0);
+    hours
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Reads hours written as `1.5`, `1,5` or `1:30` as seconds.
+pub fn parse_hours(text: &str) -> Option<u64> {
+    let text = text.trim();
+    if let Some((hours, minutes)) = text.split_once(':') {
+        let minutes: u64 = minutes.parse().ok().filter(|minutes| *minutes < 60)?;
+        return Some(hours.parse::<u64>().ok()? * 3600 + minutes * 60);
+    }
+    let hours: f64 = text.replace(',', ".").parse().ok()?;
+    (hours.is_finite() && hours >= 0.0).then(|| (hours * 3600.0).round() as u64)
+}
+
+/// A goal's progress, e.g. `Today: 3:25 of 8:00` or
+/// `Website, this week: 1:00 of 5:00`.
+pub fn format_goal(progress: &report::goals::GoalProgress) -> String {
+    let goal = &progress.goal;
+    let period = match goal.task {
+        None => goal.period.label().to_string(),
+        Some(_) => format!("{}, {}", goal.name, goal.period.label().to_lowercase()),
+    };
+    format!(
+        "{}: {} of {}",
+        period,
+        report::format_hours(progress.worked),
+        report::format_hours(goal.target)
+    )
+}
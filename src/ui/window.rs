@@ -1,20 +1,31 @@
 use std::{
+    collections::HashMap,
     sync::{Arc, mpsc},
     time::Duration,
 };
 
 use eframe::{NativeOptions, egui};
 use egui::{
-    Align, Align2, CentralPanel, Color32, Context, CursorIcon, Layout, MenuBar, Order, ScrollArea,
-    Slider, TopBottomPanel, ViewportBuilder, ViewportCommand, Window, panel::TopBottomSide,
+    Align, Align2, CentralPanel, Color32, ComboBox, Context, CursorIcon, Layout, MenuBar, Order,
+    ProgressBar, ScrollArea, Slider, TextEdit, TopBottomPanel, ViewportBuilder, ViewportCommand,
+    Window, panel::TopBottomSide,
 };
 
-use crate::{APP_ICON_BYTES, agent, config, ui};
+use crate::{APP_ICON_BYTES, agent, billing, config, import, report, storage, sync, ui};
+
+/// How many weeks the working-time overview shows.
+const COMPLIANCE_WEEKS: u32 = 8;
+/// How many days or weeks of each goal the goals overview shows.
+const GOAL_HISTORY_PERIODS: u32 = 14;
+/// Shown next to data that is local with SQLite but shared by everyone using
+/// the same PostgreSQL database.
+const TEAM_WIDE_HINT: &str = "Shared with your team when the database is PostgreSQL";
 
 pub fn run_ui(
     command_tx: mpsc::Sender<agent::AgentCommand>,
     window_rx: crossbeam_channel::Receiver<ui::viewmodels::UIEvent>,
     settings: Arc<config::Settings>,
+    calendar_feed_url: Option<String>,
 ) {
     let icon = ui::utils::load_icon_from_bytes(APP_ICON_BYTES);
     let mut options = NativeOptions {
@@ -25,7 +36,7 @@ pub fn run_ui(
     };
 
     options.viewport.icon = Some(Arc::new(icon));
-    eframe::run_native(
+    let result = eframe::run_native(
         "Time Tracker",
         options,
         Box::new(|_cc| {
@@ -41,10 +52,85 @@ pub fn run_ui(
                 tasks: Vec::new(),
                 show_new_task_dialog: false,
                 user_state: ui::viewmodels::UserState::Active,
+                profiles: config::Profiles::load(),
+                new_profile_name: "".to_string(),
+                show_new_profile_dialog: false,
+                sync_status: sync::SyncStatus::Disabled,
+                edit_task: None,
+                conflicts: Vec::new(),
+                show_conflicts_dialog: false,
+                backups: Vec::new(),
+                show_backups_dialog: false,
+                locked: false,
+                new_passphrase: false,
+                passphrase: "".to_string(),
+                passphrase_confirm: "".to_string(),
+                remember_passphrase: false,
+                unlock_error: None,
+                show_import_dialog: false,
+                import_format: import::Format::Toggl,
+                import_path: "".to_string(),
+                import_report: None,
+                show_calendar_dialog: false,
+                calendar_from: chrono::Local::now().format("%Y-%m-01").to_string(),
+                calendar_to: chrono::Local::now().format("%Y-%m-%d").to_string(),
+                calendar_path: dirs::document_dir()
+                    .unwrap_or_default()
+                    .join("time-tracker.ics")
+                    .to_string_lossy()
+                    .to_string(),
+                calendar_feed_url,
+                show_timesheet_dialog: false,
+                users: Vec::new(),
+                timesheet_user: None,
+                timesheet_week: chrono::Local::now().format("%Y-%m-%d").to_string(),
+                timesheet_folder: dirs::document_dir()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+                session_billable: true,
+                clients: Vec::new(),
+                rates: Vec::new(),
+                show_billing_dialog: false,
+                billing_client: None,
+                client_form: new_client(),
+                rate_task: None,
+                rate_from: chrono::Local::now().format("%Y-%m-%d").to_string(),
+                rate_amount: "".to_string(),
+                show_invoice_dialog: false,
+                invoice_client: None,
+                invoice_from: chrono::Local::now().format("%Y-%m-01").to_string(),
+                invoice_to: chrono::Local::now().format("%Y-%m-%d").to_string(),
+                invoice_folder: dirs::document_dir()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+                invoice_draft: None,
+                show_compliance_dialog: false,
+                compliance: None,
+                tracked: HashMap::new(),
+                new_task_estimate: "".to_string(),
+                new_task_budget: "".to_string(),
+                edit_task_estimate: "".to_string(),
+                edit_task_budget: "".to_string(),
+                new_task_daily_goal: "".to_string(),
+                new_task_weekly_goal: "".to_string(),
+                edit_task_daily_goal: "".to_string(),
+                edit_task_weekly_goal: "".to_string(),
+                goals: Vec::new(),
+                show_goals_dialog: false,
+                goal_history: None,
+                pomodoro: None,
+                window_rules: Vec::new(),
+                show_window_rules_dialog: false,
+                rule_form: agent::window_rules::WindowRule::default(),
+                task_suggestion: None,
             }))
         }),
-    )
-    .unwrap();
+    );
+    if let Err(e) = result {
+        tracing::error!("Window closed with an error: {}", e);
+    }
 }
 
 struct MyApp {
@@ -60,6 +146,93 @@ struct MyApp {
     tasks: Vec<agent::tasks::Task>,
     show_new_task_dialog: bool,
     user_state: ui::viewmodels::UserState,
+
+    profiles: config::Profiles,
+    new_profile_name: String,
+    show_new_profile_dialog: bool,
+
+    sync_status: sync::SyncStatus,
+
+    edit_task: Option<agent::tasks::Task>,
+    conflicts: Vec<storage::Conflict>,
+    show_conflicts_dialog: bool,
+
+    backups: Vec<storage::backup::Backup>,
+    show_backups_dialog: bool,
+
+    locked: bool,
+    new_passphrase: bool,
+    passphrase: String,
+    passphrase_confirm: String,
+    remember_passphrase: bool,
+    unlock_error: Option<String>,
+
+    show_import_dialog: bool,
+    import_format: import::Format,
+    import_path: String,
+    import_report: Option<import::ImportReport>,
+
+    show_calendar_dialog: bool,
+    calendar_from: String,
+    calendar_to: String,
+    calendar_path: String,
+    /// The address calendar apps subscribe to, when the feed is running.
+    calendar_feed_url: Option<String>,
+
+    show_timesheet_dialog: bool,
+    users: Vec<agent::users::User>,
+    /// `None` for this client's user.
+    timesheet_user: Option<String>,
+    timesheet_week: String,
+    timesheet_folder: String,
+
+    session_billable: bool,
+    clients: Vec<agent::clients::Client>,
+    rates: Vec<agent::rates::Rate>,
+    show_billing_dialog: bool,
+    /// `None` while a new client is being entered.
+    billing_client: Option<i64>,
+    client_form: agent::clients::Client,
+    /// `None` for a rate covering all of the client's tasks.
+    rate_task: Option<i64>,
+    rate_from: String,
+    rate_amount: String,
+
+    show_invoice_dialog: bool,
+    invoice_client: Option<i64>,
+    invoice_from: String,
+    invoice_to: String,
+    invoice_folder: String,
+    invoice_draft: Option<billing::InvoiceDraft>,
+
+    show_compliance_dialog: bool,
+    compliance: Option<report::compliance::Compliance>,
+
+    /// Seconds tracked per task before the running session.
+    tracked: HashMap<i64, u64>,
+    new_task_estimate: String,
+    new_task_budget: String,
+    edit_task_estimate: String,
+    edit_task_budget: String,
+    new_task_daily_goal: String,
+    new_task_weekly_goal: String,
+    edit_task_daily_goal: String,
+    edit_task_weekly_goal: String,
+
+    /// Progress towards the goals in their current periods.
+    goals: Vec<report::goals::GoalProgress>,
+    show_goals_dialog: bool,
+    goal_history: Option<Vec<report::goals::GoalHistory>>,
+
+    /// Focus mode's countdown while it's on.
+    pomodoro: Option<agent::pomodoro::PomodoroStatus>,
+
+    window_rules: Vec<agent::window_rules::WindowRule>,
+    show_window_rules_dialog: bool,
+    rule_form: agent::window_rules::WindowRule,
+    /// A window rule's offer to switch tasks, until it's taken up or
+    /// dismissed.
+    task_suggestion: Option<(agent::Task, agent::window_rules::WindowRule)>,
 }
 
 impl eframe::App for MyApp {
@@ -68,17 +241,133 @@ impl eframe::App for MyApp {
             match event {
                 ui::viewmodels::UIEvent::TaskList { task_list } => self.tasks = task_list,
                 ui::viewmodels::UIEvent::ElapsedTime { elapsed } => self.elapsed_time = elapsed,
+                ui::UIEvent::TrackedTime { tracked } => self.tracked = tracked,
+                ui::UIEvent::Pomodoro { status } => self.pomodoro = status,
+                ui::UIEvent::SessionStopped { reason } => {
+                    self.active_task_id = -1;
+                    self.session_comment = "".into();
+                    self.session_billable = true;
+                    self.elapsed_time = Duration::ZERO;
+                    self.task_suggestion = None;
+                    self.dialog_info = ui::DialogInfo {
+                        title: "Information",
+                        message: reason,
+                        shown: false,
+                    };
+                }
+                ui::UIEvent::SessionSwitched { task } => {
+                    self.active_task_id = task;
+                    self.session_comment = "".into();
+                    self.session_billable = true;
+                    self.elapsed_time = Duration::ZERO;
+                    self.task_suggestion = None;
+                }
+                ui::UIEvent::TaskSuggestion { task, rule } => {
+                    self.task_suggestion = Some((task, rule));
+                }
+                ui::UIEvent::BudgetWarning {
+                    task,
+                    percent,
+                    budget,
+                } => {
+                    self.dialog_info = ui::DialogInfo {
+                        title: "Warning",
+                        message: format!(
+                            "'{}' has used {}% of its {} h budget.",
+                            task,
+                            percent,
+                            ui::utils::format_hours(budget)
+                        ),
+                        shown: false,
+                    };
+                }
+                ui::UIEvent::WorkLimitWarning {
+                    weekly,
+                    worked,
+                    limit,
+                } => {
+                    self.dialog_info = ui::DialogInfo {
+                        title: "Warning",
+                        message: format!(
+                            "You've worked {} h {}; the limit is {} h.",
+                            ui::utils::format_hours(worked),
+                            if weekly { "this week" } else { "today" },
+                            ui::utils::format_hours(limit)
+                        ),
+                        shown: false,
+                    };
+                }
                 ui::viewmodels::UIEvent::Quit => ctx.send_viewport_cmd(ViewportCommand::Close),
                 ui::UIEvent::Repaint { time_out } => {
                     ctx.request_repaint_after(Duration::from_secs(time_out));
                 }
                 ui::UIEvent::UserState { state } => self.user_state = state,
+                ui::UIEvent::Profiles { active, names } => {
+                    if active != self.profiles.active {
+                        self.active_task_id = -1;
+                        self.session_comment = "".into();
+                        self.elapsed_time = Duration::ZERO;
+                        self.task_suggestion = None;
+                        self.tasks.clear();
+                    }
+                    self.profiles = config::Profiles { active, names };
+                }
+                ui::UIEvent::SyncStatus { status } => {
+                    // A pull may have merged remote edits or recorded conflicts.
+                    if let sync::SyncStatus::Synced { .. } = status {
+                        let _ = self.command_tx.send(agent::AgentCommand::RequestTaskList);
+                        let _ = self.command_tx.send(agent::AgentCommand::RequestConflicts);
+                    }
+                    self.sync_status = status;
+                }
+                ui::UIEvent::Conflicts { conflicts } => self.conflicts = conflicts,
+                ui::UIEvent::Backups { backups } => self.backups = backups,
+                ui::UIEvent::Users { users } => self.users = users,
+                ui::UIEvent::Billing { clients, rates } => {
+                    self.clients = clients;
+                    self.rates = rates;
+                }
+                ui::UIEvent::InvoiceDraft { draft } => self.invoice_draft = Some(draft),
+                ui::UIEvent::WindowRules { rules } => self.window_rules = rules,
+                ui::UIEvent::Compliance { compliance } => self.compliance = Some(compliance),
+                ui::UIEvent::Goals { progress } => self.goals = progress,
+                ui::UIEvent::GoalHistory { history } => self.goal_history = Some(history),
+                ui::UIEvent::Exported { path } => {
+                    self.dialog_info = ui::DialogInfo {
+                        title: "Information",
+                        message: format!("Saved {}", path.display()),
+                        shown: false,
+                    };
+                }
+                ui::UIEvent::ImportReport { report } => self.import_report = Some(report),
+                ui::UIEvent::Locked {
+                    new_passphrase,
+                    error,
+                } => {
+                    self.locked = true;
+                    self.new_passphrase = new_passphrase;
+                    if error.is_some() {
+                        self.unlock_error = error;
+                    }
+                }
+                ui::UIEvent::Unlocked => {
+                    self.locked = false;
+                    self.unlock_error = None;
+                }
+                ui::UIEvent::Error { message } => {
+                    self.dialog_info = ui::DialogInfo {
+                        title: "Error",
+                        message,
+                        shown: false,
+                    };
+                }
             }
 
             ctx.request_repaint();
         }
 
-        if self.tasks.is_empty() {
+        // Nothing can be read until the database is unlocked.
+        if self.tasks.is_empty() && !self.locked {
             if let Err(e) = self.command_tx.send(agent::AgentCommand::RequestTaskList) {
                 self.dialog_info = ui::DialogInfo {
                     title: "Error",
@@ -111,6 +400,69 @@ impl eframe::App for MyApp {
             self.new_task_dialog(ctx);
         } else {
             self.new_task = agent::tasks::Task::default();
+            self.new_task_estimate.clear();
+            self.new_task_budget.clear();
+            self.new_task_daily_goal.clear();
+            self.new_task_weekly_goal.clear();
+        }
+
+        if self.edit_task.is_some() {
+            self.edit_task_dialog(ctx);
+        }
+
+        if self.show_conflicts_dialog {
+            self.conflicts_dialog(ctx);
+        }
+
+        if self.show_backups_dialog {
+            self.backups_dialog(ctx);
+        }
+
+        if self.show_import_dialog {
+            self.import_dialog(ctx);
+        } else {
+            self.import_report = None;
+        }
+
+        if self.show_calendar_dialog {
+            self.calendar_dialog(ctx);
+        }
+
+        if self.show_timesheet_dialog {
+            self.timesheet_dialog(ctx);
+        }
+
+        if self.show_billing_dialog {
+            self.billing_dialog(ctx);
+        }
+
+        if self.show_invoice_dialog {
+            self.invoice_dialog(ctx);
+        }
+
+        if self.show_compliance_dialog {
+            self.compliance_dialog(ctx);
+        }
+
+        if self.show_goals_dialog {
+            self.goals_dialog(ctx);
+        }
+
+        if self.show_window_rules_dialog {
+            self.window_rules_dialog(ctx);
+        }
+
+        if self.locked {
+            self.unlock_dialog(ctx);
+        } else {
+            self.passphrase.clear();
+            self.passphrase_confirm.clear();
+        }
+
+        if self.show_new_profile_dialog {
+            self.new_profile_dialog(ctx);
+        } else {
+            self.new_profile_name.clear();
         }
 
         if !self.dialog_info.shown {
@@ -164,6 +516,41 @@ impl MyApp {
                     );
                 });
 
+                ui.horizontal(|ui| {
+                    ui.label("Client");
+                    let selected = match self.new_task.t_client {
+                        None => "None".to_string(),
+                        Some(id) => client_name(&self.clients, id),
+                    };
+                    ComboBox::from_id_salt("new_task_client")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.new_task.t_client, None, "None");
+                            for client in &self.clients {
+                                ui.selectable_value(
+                                    &mut self.new_task.t_client,
+                                    Some(client.cl_id),
+                                    &client.cl_name,
+                                );
+                            }
+                        });
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Estimate (h)").on_hover_text(TEAM_WIDE_HINT);
+                    ui.add(TextEdit::singleline(&mut self.new_task_estimate).desired_width(50.0));
+                    ui.label("Budget (h)").on_hover_text(TEAM_WIDE_HINT);
+                    ui.add(TextEdit::singleline(&mut self.new_task_budget).desired_width(50.0));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Daily goal (h)");
+                    ui.add(TextEdit::singleline(&mut self.new_task_daily_goal).desired_width(50.0));
+                    ui.label("Weekly goal (h)");
+                    ui.add(
+                        TextEdit::singleline(&mut self.new_task_weekly_goal).desired_width(50.0),
+                    );
+                });
+
                 ui.separator();
                 ui.horizontal(|ui| {
                     ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
@@ -180,8 +567,34 @@ impl MyApp {
                             .on_hover_cursor(CursorIcon::PointingHand)
                             .clicked()
                         {
+                            let hours = task_hours(&self.new_task_estimate, &self.new_task_budget)
+                                .and_then(|hours| {
+                                    let goals = task_goals(
+                                        &self.new_task_daily_goal,
+                                        &self.new_task_weekly_goal,
+                                    )?;
+                                    Ok((hours, goals))
+                                });
+                            let ((t_estimate, t_budget), (t_daily_goal, t_weekly_goal)) =
+                                match hours {
+                                    Ok(hours) => hours,
+                                    Err(message) => {
+                                        self.dialog_info = ui::DialogInfo {
+                                            title: "Error",
+                                            message,
+                                            shown: false,
+                                        };
+                                        return;
+                                    }
+                                };
                             if let Err(e) = self.command_tx.send(agent::AgentCommand::AddTask {
-                                task: self.new_task.clone(),
+                                task: agent::Task {
+                                    t_estimate,
+                                    t_budget,
+                                    t_daily_goal,
+                                    t_weekly_goal,
+                                    ..self.new_task.clone()
+                                },
                             }) {
                                 self.dialog_info = ui::DialogInfo {
                                     title: "Error",
@@ -201,120 +614,1581 @@ impl MyApp {
             });
     }
 
-    fn menu_bar(&mut self, ctx: &Context) {
-        TopBottomPanel::new(TopBottomSide::Top, "Menu Bar").show(ctx, |ui| {
-            MenuBar::new().ui(ui, |ui| {
-                ui.menu_button("File", |ui| {
-                    if ui
-                        .button("Exit")
-                        .on_hover_cursor(CursorIcon::PointingHand)
-                        .clicked()
-                    {
-                        ctx.send_viewport_cmd(ViewportCommand::Close);
-                    }
-                })
-                .response
-                .on_hover_cursor(CursorIcon::PointingHand);
+    fn edit_task_dialog(&mut self, ctx: &Context) {
+        let Some(task) = &mut self.edit_task else {
+            return;
+        };
+        let mut close = false;
 
-                ui.menu_button("Task", |ui| {
-                    if ui
-                        .button("New Task...")
-                        .on_hover_cursor(CursorIcon::PointingHand)
-                        .clicked()
-                    {
-                        self.show_new_task_dialog = !self.show_new_task_dialog;
-                    }
-                })
-                .response
-                .on_hover_cursor(CursorIcon::PointingHand)
+        Window::new("Edit Task")
+            .collapsible(false)
+            .fixed_size([400.0, 100.0])
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .order(Order::Foreground)
+            .show(ctx, |ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut task.t_name);
+
+                ui.horizontal(|ui| {
+                    ui.label("Priority");
+                    let level = task.t_priority;
+                    ui.add(
+                        Slider::new(&mut task.t_priority, 0..=2)
+                            .step_by(0.33)
+                            .text(agent::tasks::PRIORITY_LEVELS[level])
+                            .show_value(false),
+                    );
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Client");
+                    let selected = match task.t_client {
+                        None => "None".to_string(),
+                        Some(id) => client_name(&self.clients, id),
+                    };
+                    ComboBox::from_id_salt("task_client")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut task.t_client, None, "None");
+                            for client in &self.clients {
+                                ui.selectable_value(
+                                    &mut task.t_client,
+                                    Some(client.cl_id),
+                                    &client.cl_name,
+                                );
+                            }
+                        });
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Estimate (h)").on_hover_text(TEAM_WIDE_HINT);
+                    ui.add(TextEdit::singleline(&mut self.edit_task_estimate).desired_width(50.0));
+                    ui.label("Budget (h)").on_hover_text(TEAM_WIDE_HINT);
+                    ui.add(TextEdit::singleline(&mut self.edit_task_budget).desired_width(50.0));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Daily goal (h)");
+                    ui.add(
+                        TextEdit::singleline(&mut self.edit_task_daily_goal).desired_width(50.0),
+                    );
+                    ui.label("Weekly goal (h)");
+                    ui.add(
+                        TextEdit::singleline(&mut self.edit_task_weekly_goal).desired_width(50.0),
+                    );
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
+                        if ui
+                            .button("Cancel")
+                            .on_hover_cursor(CursorIcon::PointingHand)
+                            .clicked()
+                        {
+                            close = true;
+                        }
+
+                        if ui
+                            .button("Save")
+                            .on_hover_cursor(CursorIcon::PointingHand)
+                            .clicked()
+                        {
+                            let hours =
+                                task_hours(&self.edit_task_estimate, &self.edit_task_budget)
+                                    .and_then(|hours| {
+                                        let goals = task_goals(
+                                            &self.edit_task_daily_goal,
+                                            &self.edit_task_weekly_goal,
+                                        )?;
+                                        Ok((hours, goals))
+                                    });
+                            match hours {
+                                Ok(((estimate, budget), (daily_goal, weekly_goal))) => {
+                                    task.t_estimate = estimate;
+                                    task.t_budget = budget;
+                                    task.t_daily_goal = daily_goal;
+                                    task.t_weekly_goal = weekly_goal;
+                                }
+                                Err(message) => {
+                                    self.dialog_info = ui::DialogInfo {
+                                        title: "Error",
+                                        message,
+                                        shown: false,
+                                    };
+                                    return;
+                                }
+                            }
+                            if let Err(e) = self
+                                .command_tx
+                                .send(agent::AgentCommand::UpdateTask { task: task.clone() })
+                            {
+                                self.dialog_info = ui::DialogInfo {
+                                    title: "Error",
+                                    message: format!("{}", e),
+                                    shown: false,
+                                }
+                            }
+                            close = true;
+                        }
+                    });
+                });
             });
-        });
+
+        if close {
+            self.edit_task = None;
+        }
     }
 
-    fn main_window(&mut self, ctx: &Context) {
-        CentralPanel::default().show(ctx, |ui| {
-            ui.group(|ui| {
-                ui.heading("Tasks");
-                ScrollArea::vertical().show(ui, |ui| {
-                    for task in &mut self.tasks {
+    fn conflicts_dialog(&mut self, ctx: &Context) {
+        Window::new("Conflicts")
+            .collapsible(false)
+            .fixed_size([400.0, 300.0])
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .order(Order::Foreground)
+            .show(ctx, |ui| {
+                if self.conflicts.is_empty() {
+                    ui.label("No conflicts to review.");
+                }
+
+                ScrollArea::vertical().max_height(250.0).show(ui, |ui| {
+                    for conflict in &self.conflicts {
                         ui.group(|ui| {
                             ui.take_available_width();
-                            ui.horizontal(|ui| {
-                                ui.vertical(|ui| {
-                                    ui.label(&task.t_name).on_hover_text("Name");
-                                    ui.text_edit_multiline(&mut self.session_comment)
-                                        .on_hover_text("Comment");
-                                });
+                            ui.label(format!("{} ({})", conflict.c_task_name, conflict.c_field));
+                            ui.label(format!("This device: {}", conflict.c_local_value));
+                            ui.label(format!("Other device: {}", conflict.c_remote_value));
+                            ui.label(format!("Currently kept: {}", conflict.c_kept));
 
-                                ui.vertical(|ui| {
-                                    ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
-                                        ui.colored_label(
-                                            match task.t_priority {
-                                                0 => Color32::DARK_GREEN,
-                                                1 => Color32::YELLOW,
-                                                2 => Color32::DARK_RED,
-                                                _ => Color32::DARK_BLUE,
+                            ui.horizontal(|ui| {
+                                for (label, keep_local) in
+                                    [("Keep this device", true), ("Keep other device", false)]
+                                {
+                                    if ui
+                                        .button(label)
+                                        .on_hover_cursor(CursorIcon::PointingHand)
+                                        .clicked()
+                                        && let Err(e) = self.command_tx.send(
+                                            agent::AgentCommand::ResolveConflict {
+                                                id: conflict.c_id,
+                                                keep_local,
                                             },
-                                            "⏺",
                                         )
-                                        .on_hover_cursor(CursorIcon::Default)
-                                        .on_hover_text("Priority")
-                                    });
-
-                                    ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
-                                        if self.active_task_id == task.t_id {
-                                            if ui
-                                                .button("⏸")
-                                                .on_hover_cursor(CursorIcon::PointingHand)
-                                                .on_hover_text("Stop")
-                                                .clicked()
-                                            {
-                                                if let Err(e) = self.command_tx.send(
-                                                    agent::AgentCommand::EndSession {
-                                                        comment: self.session_comment.clone(),
-                                                    },
-                                                ) {
-                                                    self.dialog_info = ui::DialogInfo {
-                                                        title: "Error",
-                                                        message: format!("{}", e),
-                                                        shown: false,
-                                                    }
-                                                }
-                                                self.active_task_id = -1;
-                                                self.session_comment = "".into();
-                                            }
-                                        } else {
-                                            if ui
-                                                .button("▶")
-                                                .on_hover_cursor(CursorIcon::PointingHand)
-                                                .on_hover_text("Start")
-                                                .clicked()
-                                            {
-                                                if let Err(e) = self.command_tx.send(
-                                                    agent::AgentCommand::StartSession {
-                                                        id: task.t_id,
-                                                    },
-                                                ) {
-                                                    self.dialog_info = ui::DialogInfo {
-                                                        title: "Error",
-                                                        message: format!("{}", e),
-                                                        shown: false,
-                                                    }
-                                                }
-                                                self.active_task_id = task.t_id;
-                                            }
+                                    {
+                                        self.dialog_info = ui::DialogInfo {
+                                            title: "Error",
+                                            message: format!("{}", e),
+                                            shown: false,
                                         }
-                                    });
-                                });
+                                    }
+                                }
                             });
                         });
                     }
                 });
+
+                ui.separator();
+                ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
+                    if ui
+                        .button("Close")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        self.show_conflicts_dialog = false;
+                    }
+                });
             });
-        });
     }
 
-    fn status_bar(&mut self, ctx: &Context) {
+    fn backups_dialog(&mut self, ctx: &Context) {
+        Window::new("Backups")
+            .collapsible(false)
+            .fixed_size([400.0, 300.0])
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .order(Order::Foreground)
+            .show(ctx, |ui| {
+                if self.backups.is_empty() {
+                    ui.label("No backups yet.");
+                } else {
+                    ui.label(
+                        "Restoring saves the current data to the backup folder first, \
+                        so it's listed here to undo the restore.",
+                    );
+                }
+
+                ScrollArea::vertical().max_height(250.0).show(ui, |ui| {
+                    for backup in &self.backups {
+                        ui.horizontal(|ui| {
+                            let date = backup.date.format("%A %-d %B %Y");
+                            ui.label(match backup.before_restore {
+                                Some(time) => format!(
+                                    "{}, before restoring at {}",
+                                    date,
+                                    time.format("%H:%M")
+                                ),
+                                None => date.to_string(),
+                            });
+                            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                if ui
+                                    .button("Restore")
+                                    .on_hover_cursor(CursorIcon::PointingHand)
+                                    .clicked()
+                                {
+                                    if let Err(e) =
+                                        self.command_tx.send(agent::AgentCommand::RestoreBackup {
+                                            path: backup.path.clone(),
+                                        })
+                                    {
+                                        self.dialog_info = ui::DialogInfo {
+                                            title: "Error",
+                                            message: format!("{}", e),
+                                            shown: false,
+                                        }
+                                    }
+                                    self.active_task_id = -1;
+                                    self.elapsed_time = Duration::ZERO;
+                                    self.show_backups_dialog = false;
+                                }
+                            });
+                        });
+                    }
+                });
+
+                ui.separator();
+                ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
+                    if ui
+                        .button("Close")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        self.show_backups_dialog = false;
+                    }
+
+                    if ui
+                        .button("Back Up Now")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        let _ = self.command_tx.send(agent::AgentCommand::BackupNow);
+                    }
+                });
+            });
+    }
+
+    fn import_dialog(&mut self, ctx: &Context) {
+        Window::new("Import")
+            .collapsible(false)
+            .fixed_size([400.0, 300.0])
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .order(Order::Foreground)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Format:");
+                    ComboBox::from_id_salt("import_format")
+                        .selected_text(import_format_label(self.import_format))
+                        .show_ui(ui, |ui| {
+                            for format in import::Format::ALL {
+                                ui.selectable_value(
+                                    &mut self.import_format,
+                                    format,
+                                    import_format_label(format),
+                                );
+                            }
+                        });
+                });
+                ui.label("File:");
+                ui.text_edit_singleline(&mut self.import_path);
+
+                if let Some(report) = &self.import_report {
+                    ui.separator();
+                    ui.label(if report.dry_run {
+                        format!("{} entries will be imported.", report.imported)
+                    } else {
+                        format!("{} entries were imported.", report.imported)
+                    });
+                    if !report.new_tasks.is_empty() {
+                        ui.label(format!("New tasks: {}", report.new_tasks.join(", ")));
+                    }
+                    if report.duplicates > 0 {
+                        ui.label(format!(
+                            "{} entries are already in the database and are skipped.",
+                            report.duplicates
+                        ));
+                    }
+                    if !report.unmapped.is_empty() {
+                        ui.label(format!("{} rows can't be imported:", report.unmapped.len()));
+                        ScrollArea::vertical().max_height(100.0).show(ui, |ui| {
+                            for unmapped in &report.unmapped {
+                                ui.label(format!("Row {}: {}", unmapped.row, unmapped.reason));
+                            }
+                        });
+                    }
+                }
+
+                ui.separator();
+                ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
+                    if ui
+                        .button("Close")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        self.show_import_dialog = false;
+                    }
+
+                    let has_path = !self.import_path.trim().is_empty();
+                    for (label, dry_run) in [("Import", false), ("Preview", true)] {
+                        if ui
+                            .add_enabled(has_path, egui::Button::new(label))
+                            .on_hover_cursor(CursorIcon::PointingHand)
+                            .clicked()
+                        {
+                            self.import_report = None;
+                            let _ = self.command_tx.send(agent::AgentCommand::Import {
+                                path: self.import_path.trim().into(),
+                                format: self.import_format,
+                                dry_run,
+                            });
+                        }
+                    }
+                });
+            });
+    }
+
+    fn calendar_dialog(&mut self, ctx: &Context) {
+        Window::new("Export Calendar")
+            .collapsible(false)
+            .fixed_size([400.0, 200.0])
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .order(Order::Foreground)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("From:");
+                    ui.text_edit_singleline(&mut self.calendar_from);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("To:");
+                    ui.text_edit_singleline(&mut self.calendar_to);
+                });
+                ui.label("File:");
+                ui.text_edit_singleline(&mut self.calendar_path);
+
+                let parse = |date: &str| chrono::NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d");
+                let range = match (parse(&self.calendar_from), parse(&self.calendar_to)) {
+                    (Ok(from), Ok(to)) if from <= to => Some((from, to)),
+                    _ => None,
+                };
+                if range.is_none() {
+                    ui.colored_label(Color32::DARK_RED, "Enter the days as YYYY-MM-DD.");
+                }
+
+                if let Some(url) = &self.calendar_feed_url {
+                    ui.separator();
+                    ui.label("Or subscribe a calendar app to the feed, which stays up to date:");
+                    ui.horizontal(|ui| {
+                        ui.add(TextEdit::singleline(&mut url.as_str()).desired_width(320.0));
+                        if ui
+                            .button("Copy")
+                            .on_hover_cursor(CursorIcon::PointingHand)
+                            .clicked()
+                        {
+                            ui.ctx().copy_text(url.clone());
+                        }
+                    });
+                }
+
+                ui.separator();
+                ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
+                    if ui
+                        .button("Close")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        self.show_calendar_dialog = false;
+                    }
+
+                    let can_export = range.is_some() && !self.calendar_path.trim().is_empty();
+                    if ui
+                        .add_enabled(can_export, egui::Button::new("Export"))
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                        && let Some((from, to)) = range
+                    {
+                        let _ = self.command_tx.send(agent::AgentCommand::ExportCalendar {
+                            path: self.calendar_path.trim().into(),
+                            from,
+                            to,
+                        });
+                        self.show_calendar_dialog = false;
+                    }
+                });
+            });
+    }
+
+    fn timesheet_dialog(&mut self, ctx: &Context) {
+        Window::new("Weekly Timesheet")
+            .collapsible(false)
+            .fixed_size([400.0, 150.0])
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .order(Order::Foreground)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Week of:");
+                    ui.text_edit_singleline(&mut self.timesheet_week);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Person:");
+                    let selected = match &self.timesheet_user {
+                        None => "Me".to_string(),
+                        Some(id) => user_name(&self.users, id),
+                    };
+                    ComboBox::from_id_salt("timesheet_user")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.timesheet_user, None, "Me");
+                            for user in &self.users {
+                                ui.selectable_value(
+                                    &mut self.timesheet_user,
+                                    Some(user.u_id.clone()),
+                                    &user.u_name,
+                                );
+                            }
+                        });
+                });
+                ui.label("Folder:");
+                ui.text_edit_singleline(&mut self.timesheet_folder);
+
+                let date =
+                    chrono::NaiveDate::parse_from_str(self.timesheet_week.trim(), "%Y-%m-%d").ok();
+                if date.is_none() {
+                    ui.colored_label(Color32::DARK_RED, "Enter a day of the week as YYYY-MM-DD.");
+                }
+                ui.label(format!(
+                    "The layout can be changed in {}.",
+                    report::template_path().display()
+                ));
+
+                ui.separator();
+                ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
+                    if ui
+                        .button("Close")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        self.show_timesheet_dialog = false;
+                    }
+
+                    if ui
+                        .add_enabled(date.is_some(), egui::Button::new("Save"))
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                        && let Some(date) = date
+                    {
+                        let path = std::path::Path::new(self.timesheet_folder.trim())
+                            .join(report::default_file_name(date));
+                        let _ = self.command_tx.send(agent::AgentCommand::ExportTimesheet {
+                            path,
+                            user: self.timesheet_user.clone(),
+                            date,
+                        });
+                        self.show_timesheet_dialog = false;
+                    }
+                });
+            });
+    }
+
+    fn billing_dialog(&mut self, ctx: &Context) {
+        Window::new("Clients and Rates")
+            .collapsible(false)
+            .fixed_size([450.0, 400.0])
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .order(Order::Foreground)
+            .show(ctx, |ui| {
+                ui.weak(TEAM_WIDE_HINT);
+                ui.horizontal(|ui| {
+                    ui.label("Client:");
+                    let selected = match self.billing_client {
+                        None => "New client".to_string(),
+                        Some(id) => client_name(&self.clients, id),
+                    };
+                    ComboBox::from_id_salt("billing_client")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            if ui
+                                .selectable_value(&mut self.billing_client, None, "New client")
+                                .clicked()
+                            {
+                                self.client_form = new_client();
+                            }
+                            for client in &self.clients {
+                                if ui
+                                    .selectable_value(
+                                        &mut self.billing_client,
+                                        Some(client.cl_id),
+                                        &client.cl_name,
+                                    )
+                                    .clicked()
+                                {
+                                    self.client_form = client.clone();
+                                    self.rate_task = None;
+                                }
+                            }
+                        });
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Name:");
+                    ui.text_edit_singleline(&mut self.client_form.cl_name);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Currency:");
+                    ui.add(
+                        TextEdit::singleline(&mut self.client_form.cl_currency).desired_width(60.0),
+                    );
+                    ui.label("Round each session");
+                    ComboBox::from_id_salt("client_rounding")
+                        .selected_text(rounding_label(self.client_form.cl_rounding.mode))
+                        .show_ui(ui, |ui| {
+                            for mode in billing::RoundingMode::ALL {
+                                ui.selectable_value(
+                                    &mut self.client_form.cl_rounding.mode,
+                                    mode,
+                                    rounding_label(mode),
+                                );
+                            }
+                        });
+                    ui.add(
+                        egui::DragValue::new(&mut self.client_form.cl_rounding.minutes)
+                            .range(0..=60)
+                            .suffix(" min"),
+                    )
+                    .on_hover_text("0 bills sessions as they are");
+                });
+
+                let form_valid = !self.client_form.cl_name.trim().is_empty()
+                    && !self.client_form.cl_currency.trim().is_empty();
+                let label = match self.billing_client {
+                    None => "Add Client",
+                    Some(_) => "Save Client",
+                };
+                if ui
+                    .add_enabled(form_valid, egui::Button::new(label))
+                    .on_hover_cursor(CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    let client = agent::clients::Client {
+                        cl_name: self.client_form.cl_name.trim().to_string(),
+                        cl_currency: self.client_form.cl_currency.trim().to_uppercase(),
+                        ..self.client_form.clone()
+                    };
+                    let _ = self.command_tx.send(match self.billing_client {
+                        None => agent::AgentCommand::AddClient { client },
+                        Some(_) => agent::AgentCommand::UpdateClient { client },
+                    });
+                    if self.billing_client.is_none() {
+                        self.client_form = new_client();
+                    }
+                }
+
+                if let Some(client_id) = self.billing_client {
+                    ui.separator();
+                    ui.label("Hourly rates:");
+                    let client_tasks: Vec<&agent::Task> = self
+                        .tasks
+                        .iter()
+                        .filter(|task| task.t_client == Some(client_id))
+                        .collect();
+                    let currency = client_currency(&self.clients, client_id);
+                    ScrollArea::vertical().max_height(150.0).show(ui, |ui| {
+                        for rate in self.rates.iter().filter(|rate| {
+                            rate.r_client == Some(client_id)
+                                || client_tasks
+                                    .iter()
+                                    .any(|task| rate.r_task == Some(task.t_id))
+                        }) {
+                            ui.horizontal(|ui| {
+                                let applies_to = match rate.r_task {
+                                    None => "all tasks".to_string(),
+                                    Some(id) => self
+                                        .tasks
+                                        .iter()
+                                        .find(|task| task.t_id == id)
+                                        .map_or_else(|| id.to_string(), |task| task.t_name.clone()),
+                                };
+                                ui.label(format!(
+                                    "From {}: {} per hour for {}",
+                                    rate.r_effective_from,
+                                    billing::format_money(rate.r_hourly_cents, &currency),
+                                    applies_to
+                                ));
+                                if ui
+                                    .button("🗑")
+                                    .on_hover_cursor(CursorIcon::PointingHand)
+                                    .on_hover_text("Delete")
+                                    .clicked()
+                                {
+                                    let _ = self
+                                        .command_tx
+                                        .send(agent::AgentCommand::DeleteRate { id: rate.r_id });
+                                }
+                            });
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        let selected = match self.rate_task {
+                            None => "All tasks".to_string(),
+                            Some(id) => client_tasks
+                                .iter()
+                                .find(|task| task.t_id == id)
+                                .map_or_else(|| id.to_string(), |task| task.t_name.clone()),
+                        };
+                        ComboBox::from_id_salt("rate_task")
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.rate_task, None, "All tasks");
+                                for task in &client_tasks {
+                                    ui.selectable_value(
+                                        &mut self.rate_task,
+                                        Some(task.t_id),
+                                        &task.t_name,
+                                    );
+                                }
+                            });
+                        ui.label("from");
+                        ui.add(TextEdit::singleline(&mut self.rate_from).desired_width(80.0));
+                        ui.add(TextEdit::singleline(&mut self.rate_amount).desired_width(60.0));
+                        ui.label(format!("{} per hour", currency));
+                    });
+                    if client_tasks.is_empty() {
+                        ui.label("Assign tasks to this client with ✏ in the task list.");
+                    }
+
+                    let from =
+                        chrono::NaiveDate::parse_from_str(self.rate_from.trim(), "%Y-%m-%d").ok();
+                    let cents = billing::parse_amount(&self.rate_amount);
+                    if ui
+                        .add_enabled(
+                            from.is_some() && cents.is_some(),
+                            egui::Button::new("Add Rate"),
+                        )
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                        && let (Some(from), Some(cents)) = (from, cents)
+                    {
+                        let _ = self.command_tx.send(agent::AgentCommand::AddRate {
+                            rate: agent::rates::Rate {
+                                r_client: self.rate_task.is_none().then_some(client_id),
+                                r_task: self.rate_task,
+                                r_hourly_cents: cents,
+                                r_effective_from: from,
+                                ..Default::default()
+                            },
+                        });
+                        self.rate_amount.clear();
+                    }
+                }
+
+                ui.separator();
+                ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
+                    if ui
+                        .button("Close")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        self.show_billing_dialog = false;
+                    }
+                });
+            });
+    }
+
+    fn window_rules_dialog(&mut self, ctx: &Context) {
+        Window::new("Window Rules")
+            .collapsible(false)
+            .fixed_size([450.0, 350.0])
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .order(Order::Foreground)
+            .show(ctx, |ui| {
+                ui.label(
+                    "While a session runs, a window that keeps the focus switches to the task of \
+                     the first rule it matches, or suggests doing so.",
+                );
+                ScrollArea::vertical().max_height(150.0).show(ui, |ui| {
+                    if self.window_rules.is_empty() {
+                        ui.label("No rules yet.");
+                    }
+                    for rule in &self.window_rules {
+                        ui.horizontal(|ui| {
+                            ui.label(format!(
+                                "{} matches '{}': {} '{}'",
+                                rule_field_label(rule.wr_field),
+                                rule.wr_pattern,
+                                rule_action_label(rule.wr_action).to_lowercase(),
+                                task_name(&self.tasks, rule.wr_task)
+                            ));
+                            if ui
+                                .button("🗑")
+                                .on_hover_cursor(CursorIcon::PointingHand)
+                                .on_hover_text("Delete")
+                                .clicked()
+                            {
+                                let _ = self
+                                    .command_tx
+                                    .send(agent::AgentCommand::DeleteWindowRule { id: rule.wr_id });
+                            }
+                        });
+                    }
+                });
+
+                ui.separator();
+                let task_chosen = self
+                    .tasks
+                    .iter()
+                    .any(|task| task.t_id == self.rule_form.wr_task);
+                ui.horizontal(|ui| {
+                    ComboBox::from_id_salt("rule_field")
+                        .selected_text(rule_field_label(self.rule_form.wr_field))
+                        .show_ui(ui, |ui| {
+                            for field in agent::window_rules::RuleField::ALL {
+                                ui.selectable_value(
+                                    &mut self.rule_form.wr_field,
+                                    field,
+                                    rule_field_label(field),
+                                );
+                            }
+                        });
+                    ui.label("matches");
+                    ui.add(
+                        TextEdit::singleline(&mut self.rule_form.wr_pattern)
+                            .hint_text("Regular expression"),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ComboBox::from_id_salt("rule_action")
+                        .selected_text(rule_action_label(self.rule_form.wr_action))
+                        .show_ui(ui, |ui| {
+                            for action in agent::window_rules::RuleAction::ALL {
+                                ui.selectable_value(
+                                    &mut self.rule_form.wr_action,
+                                    action,
+                                    rule_action_label(action),
+                                );
+                            }
+                        });
+                    let selected = match task_chosen {
+                        true => task_name(&self.tasks, self.rule_form.wr_task),
+                        false => "Choose a task".to_string(),
+                    };
+                    ComboBox::from_id_salt("rule_task")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            for task in &self.tasks {
+                                ui.selectable_value(
+                                    &mut self.rule_form.wr_task,
+                                    task.t_id,
+                                    &task.t_name,
+                                );
+                            }
+                        });
+                });
+
+                let pattern = self.rule_form.wr_pattern.trim().to_string();
+                let pattern_error = agent::window_rules::check_pattern(&pattern).err();
+                if !pattern.is_empty()
+                    && let Some(error) = &pattern_error
+                {
+                    ui.colored_label(Color32::DARK_RED, error);
+                }
+                if ui
+                    .add_enabled(
+                        pattern_error.is_none() && task_chosen,
+                        egui::Button::new("Add Rule"),
+                    )
+                    .on_hover_cursor(CursorIcon::PointingHand)
+                    .clicked()
+                {
+                    let _ = self.command_tx.send(agent::AgentCommand::AddWindowRule {
+                        rule: agent::window_rules::WindowRule {
+                            wr_pattern: pattern,
+                            ..self.rule_form.clone()
+                        },
+                    });
+                    self.rule_form.wr_pattern.clear();
+                }
+
+                ui.separator();
+                ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
+                    if ui
+                        .button("Close")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        self.show_window_rules_dialog = false;
+                    }
+                });
+            });
+    }
+
+    fn goals_dialog(&mut self, ctx: &Context) {
+        Window::new("Goals")
+            .collapsible(false)
+            .fixed_size([450.0, 350.0])
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .order(Order::Foreground)
+            .show(ctx, |ui| {
+                match &self.goal_history {
+                    None => {
+                        ui.label("Loading...");
+                    }
+                    Some(history) if history.is_empty() => {
+                        ui.label(
+                            "No goals yet. Set daily_goal_hours or weekly_goal_hours in the \
+                             settings, or goals on a task.",
+                        );
+                    }
+                    Some(history) => {
+                        ScrollArea::vertical().max_height(280.0).show(ui, |ui| {
+                            for goal in history {
+                                let current = goal.current();
+                                ui.strong(ui::utils::format_goal(&current));
+                                ui.add(ProgressBar::new(current.fraction()).show_percentage());
+                                ui.label(format!(
+                                    "Streak: {} (best {}), met {} of {} {}",
+                                    goal.streak(),
+                                    goal.best_streak(),
+                                    goal.attained(),
+                                    goal.counted().len(),
+                                    match goal.goal.period {
+                                        report::goals::Period::Day => "days",
+                                        report::goals::Period::Week => "weeks",
+                                    }
+                                ));
+                                ui.horizontal(|ui| {
+                                    for period in &goal.periods {
+                                        let color = if goal.met(period) {
+                                            Color32::DARK_GREEN
+                                        } else {
+                                            Color32::GRAY
+                                        };
+                                        ui.colored_label(color, "■").on_hover_text(format!(
+                                            "{}: {}",
+                                            period.start.format("%a %-d %b"),
+                                            report::format_hours(period.worked)
+                                        ));
+                                    }
+                                });
+                                ui.separator();
+                            }
+                        });
+                    }
+                }
+
+                ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
+                    if ui
+                        .button("Close")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        self.show_goals_dialog = false;
+                        self.goal_history = None;
+                    }
+                });
+            });
+    }
+
+    fn compliance_dialog(&mut self, ctx: &Context) {
+        Window::new("Working Time")
+            .collapsible(false)
+            .fixed_size([450.0, 300.0])
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .order(Order::Foreground)
+            .show(ctx, |ui| {
+                match &self.compliance {
+                    None => {
+                        ui.label("Loading...");
+                    }
+                    Some(compliance) => {
+                        let limits = &compliance.limits;
+                        let limit = |seconds: Option<u64>| {
+                            seconds.map_or_else(
+                                || "none".to_string(),
+                                |seconds| format!("{} h", ui::utils::format_hours(seconds)),
+                            )
+                        };
+                        ui.label(format!(
+                            "Limits: {} a day, {} a week, a break every {}.",
+                            limit(limits.daily),
+                            limit(limits.weekly),
+                            limit(limits.stretch)
+                        ));
+                        egui::Grid::new("compliance_weeks")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.strong("Week of");
+                                ui.strong("Hours");
+                                ui.strong("Long days");
+                                ui.strong("Missed breaks");
+                                ui.end_row();
+                                for week in compliance.weeks.iter().rev() {
+                                    ui.label(week.start.format("%-d %b %Y").to_string());
+                                    let hours = ui::utils::format_hours(week.worked());
+                                    if week.over_limit(limits) {
+                                        ui.colored_label(Color32::DARK_RED, hours);
+                                    } else {
+                                        ui.label(hours);
+                                    }
+                                    ui.label(week.days_over_limit(limits).to_string());
+                                    ui.label(week.missed_breaks(limits).to_string());
+                                    if week.compliant(limits) {
+                                        ui.label("✔");
+                                    } else {
+                                        ui.colored_label(Color32::DARK_RED, "⚠");
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    }
+                }
+
+                ui.separator();
+                ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
+                    if ui
+                        .button("Close")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        self.show_compliance_dialog = false;
+                        self.compliance = None;
+                    }
+                });
+            });
+    }
+
+    fn invoice_dialog(&mut self, ctx: &Context) {
+        Window::new("Invoice Draft")
+            .collapsible(false)
+            .fixed_size([450.0, 350.0])
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .order(Order::Foreground)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Client:");
+                    let selected = match self.invoice_client {
+                        None => "Choose a client".to_string(),
+                        Some(id) => client_name(&self.clients, id),
+                    };
+                    ComboBox::from_id_salt("invoice_client")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            for client in &self.clients {
+                                ui.selectable_value(
+                                    &mut self.invoice_client,
+                                    Some(client.cl_id),
+                                    &client.cl_name,
+                                );
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    ui.label("From:");
+                    ui.text_edit_singleline(&mut self.invoice_from);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("To:");
+                    ui.text_edit_singleline(&mut self.invoice_to);
+                });
+                ui.label("Folder:");
+                ui.text_edit_singleline(&mut self.invoice_folder);
+
+                let parse = |date: &str| chrono::NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d");
+                let range = match (parse(&self.invoice_from), parse(&self.invoice_to)) {
+                    (Ok(from), Ok(to)) if from <= to => Some((from, to)),
+                    _ => None,
+                };
+                if range.is_none() {
+                    ui.colored_label(Color32::DARK_RED, "Enter the days as YYYY-MM-DD.");
+                }
+
+                if let Some(draft) = &self.invoice_draft {
+                    ui.separator();
+                    let currency = &draft.client.cl_currency;
+                    if draft.lines.is_empty() {
+                        ui.label("No billable time in this period.");
+                    }
+                    ScrollArea::vertical().max_height(150.0).show(ui, |ui| {
+                        egui::Grid::new("invoice_lines")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for line in &draft.lines {
+                                    ui.label(&line.task);
+                                    ui.label(format!(
+                                        "{} h",
+                                        billing::format_decimal_hours(line.seconds)
+                                    ));
+                                    ui.label(line.hourly_cents.map_or_else(
+                                        || "no rate".to_string(),
+                                        |cents| billing::format_money(cents, currency),
+                                    ));
+                                    ui.label(billing::format_money(line.amount_cents, currency));
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                    ui.label(format!(
+                        "Total: {} h, {}",
+                        billing::format_decimal_hours(draft.total_seconds()),
+                        billing::format_money(draft.total_cents(), currency)
+                    ));
+                    let unpriced = draft.unpriced_tasks();
+                    if !unpriced.is_empty() {
+                        ui.colored_label(
+                            Color32::DARK_RED,
+                            format!("No rate applies to: {}", unpriced.join(", ")),
+                        );
+                    }
+                }
+
+                ui.separator();
+                ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
+                    if ui
+                        .button("Close")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        self.show_invoice_dialog = false;
+                    }
+
+                    let request = self.invoice_client.zip(range);
+                    if ui
+                        .add_enabled(request.is_some(), egui::Button::new("Save CSV"))
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                        && let Some((client_id, (from, to))) = request
+                        && let Some(client) =
+                            self.clients.iter().find(|client| client.cl_id == client_id)
+                    {
+                        let path = std::path::Path::new(self.invoice_folder.trim())
+                            .join(billing::default_file_name(client, from, to));
+                        let _ = self
+                            .command_tx
+                            .send(agent::AgentCommand::ExportInvoiceDraft {
+                                path,
+                                client: client_id,
+                                from,
+                                to,
+                            });
+                    }
+
+                    if ui
+                        .add_enabled(request.is_some(), egui::Button::new("Preview"))
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                        && let Some((client, (from, to))) = request
+                    {
+                        self.invoice_draft = None;
+                        let _ = self
+                            .command_tx
+                            .send(agent::AgentCommand::RequestInvoiceDraft { client, from, to });
+                    }
+                });
+            });
+    }
+
+    fn unlock_dialog(&mut self, ctx: &Context) {
+        Window::new("Unlock Database")
+            .collapsible(false)
+            .fixed_size([400.0, 150.0])
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .order(Order::Foreground)
+            .show(ctx, |ui| {
+                if self.new_passphrase {
+                    ui.label("Choose a passphrase to encrypt the database with.");
+                    ui.label("The data can't be recovered if it is forgotten.");
+                } else {
+                    ui.label("The database is encrypted. Enter its passphrase:");
+                }
+                ui.add(TextEdit::singleline(&mut self.passphrase).password(true));
+                if self.new_passphrase {
+                    ui.label("Repeat the passphrase:");
+                    ui.add(TextEdit::singleline(&mut self.passphrase_confirm).password(true));
+                }
+                ui.checkbox(&mut self.remember_passphrase, "Remember on this computer");
+                if let Some(error) = &self.unlock_error {
+                    ui.colored_label(Color32::DARK_RED, error);
+                }
+
+                let can_unlock = !self.passphrase.is_empty()
+                    && (!self.new_passphrase || self.passphrase == self.passphrase_confirm);
+
+                ui.separator();
+                ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
+                    if ui
+                        .button("Exit")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        let _ = self.command_tx.send(agent::AgentCommand::Quit);
+                    }
+
+                    if ui
+                        .add_enabled(can_unlock, egui::Button::new("Unlock"))
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        if let Err(e) = self.command_tx.send(agent::AgentCommand::Unlock {
+                            passphrase: std::mem::take(&mut self.passphrase),
+                            remember: self.remember_passphrase,
+                        }) {
+                            self.dialog_info = ui::DialogInfo {
+                                title: "Error",
+                                message: format!("{}", e),
+                                shown: false,
+                            }
+                        }
+                        self.passphrase_confirm.clear();
+                    }
+                });
+            });
+    }
+
+    fn new_profile_dialog(&mut self, ctx: &Context) {
+        Window::new("New Profile")
+            .collapsible(false)
+            .fixed_size([400.0, 100.0])
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .order(Order::Foreground)
+            .show(ctx, |ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut self.new_profile_name);
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
+                        if ui
+                            .button("Cancel")
+                            .on_hover_cursor(CursorIcon::PointingHand)
+                            .clicked()
+                        {
+                            self.show_new_profile_dialog = false;
+                        }
+
+                        if ui
+                            .button("Add")
+                            .on_hover_cursor(CursorIcon::PointingHand)
+                            .clicked()
+                        {
+                            if let Err(e) = self.command_tx.send(agent::AgentCommand::AddProfile {
+                                name: self.new_profile_name.clone(),
+                            }) {
+                                self.dialog_info = ui::DialogInfo {
+                                    title: "Error",
+                                    message: format!("{}", e),
+                                    shown: false,
+                                }
+                            }
+                            self.show_new_profile_dialog = false;
+                        }
+                    });
+                });
+            });
+    }
+
+    fn menu_bar(&mut self, ctx: &Context) {
+        TopBottomPanel::new(TopBottomSide::Top, "Menu Bar").show(ctx, |ui| {
+            MenuBar::new().ui(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    if ui
+                        .button("Open Log Folder")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                        && let Err(e) = config::logging::open_log_folder()
+                    {
+                        tracing::error!("Couldn't open the log folder: {}", e);
+                        self.dialog_info = ui::DialogInfo {
+                            title: "Error",
+                            message: format!("Couldn't open the log folder: {}", e),
+                            shown: false,
+                        }
+                    }
+
+                    if ui
+                        .button("Backups...")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        let _ = self.command_tx.send(agent::AgentCommand::RequestBackups);
+                        self.show_backups_dialog = !self.show_backups_dialog;
+                    }
+
+                    if ui
+                        .button("Import...")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        self.show_import_dialog = !self.show_import_dialog;
+                    }
+
+                    if ui
+                        .button("Export Calendar...")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        self.show_calendar_dialog = !self.show_calendar_dialog;
+                    }
+
+                    ui.separator();
+                    if ui
+                        .button("Exit")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        ctx.send_viewport_cmd(ViewportCommand::Close);
+                    }
+                })
+                .response
+                .on_hover_cursor(CursorIcon::PointingHand);
+
+                ui.menu_button("Task", |ui| {
+                    if ui
+                        .button("New Task...")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        let _ = self.command_tx.send(agent::AgentCommand::RequestBilling);
+                        self.show_new_task_dialog = !self.show_new_task_dialog;
+                    }
+
+                    if ui
+                        .button(format!("Conflicts ({})...", self.conflicts.len()))
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        let _ = self.command_tx.send(agent::AgentCommand::RequestConflicts);
+                        self.show_conflicts_dialog = !self.show_conflicts_dialog;
+                    }
+
+                    if ui
+                        .button("Window Rules...")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        let _ = self
+                            .command_tx
+                            .send(agent::AgentCommand::RequestWindowRules);
+                        self.show_window_rules_dialog = !self.show_window_rules_dialog;
+                    }
+                })
+                .response
+                .on_hover_cursor(CursorIcon::PointingHand);
+
+                ui.menu_button("Profile", |ui| {
+                    for name in &self.profiles.names {
+                        if ui
+                            .radio(*name == self.profiles.active, name)
+                            .on_hover_cursor(CursorIcon::PointingHand)
+                            .clicked()
+                            && *name != self.profiles.active
+                            && let Err(e) = self
+                                .command_tx
+                                .send(agent::AgentCommand::SwitchProfile { name: name.clone() })
+                        {
+                            self.dialog_info = ui::DialogInfo {
+                                title: "Error",
+                                message: format!("{}", e),
+                                shown: false,
+                            }
+                        }
+                    }
+
+                    ui.separator();
+                    if ui
+                        .button("New Profile...")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        self.show_new_profile_dialog = !self.show_new_profile_dialog;
+                    }
+                })
+                .response
+                .on_hover_cursor(CursorIcon::PointingHand);
+
+                ui.menu_button("Billing", |ui| {
+                    if ui
+                        .button("Clients and Rates...")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        let _ = self.command_tx.send(agent::AgentCommand::RequestBilling);
+                        self.show_billing_dialog = !self.show_billing_dialog;
+                    }
+                    if ui
+                        .button("Invoice Draft...")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        let _ = self.command_tx.send(agent::AgentCommand::RequestBilling);
+                        self.invoice_draft = None;
+                        self.show_invoice_dialog = !self.show_invoice_dialog;
+                    }
+                })
+                .response
+                .on_hover_cursor(CursorIcon::PointingHand);
+
+                ui.menu_button("Reports", |ui| {
+                    if ui
+                        .button("Weekly Timesheet...")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        let _ = self.command_tx.send(agent::AgentCommand::RequestUsers);
+                        self.show_timesheet_dialog = !self.show_timesheet_dialog;
+                    }
+                    if ui
+                        .button("Goals...")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        let _ = self
+                            .command_tx
+                            .send(agent::AgentCommand::RequestGoalHistory {
+                                periods: GOAL_HISTORY_PERIODS,
+                            });
+                        self.show_goals_dialog = !self.show_goals_dialog;
+                    }
+                    if ui
+                        .button("Working Time...")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        let _ = self
+                            .command_tx
+                            .send(agent::AgentCommand::RequestCompliance {
+                                weeks: COMPLIANCE_WEEKS,
+                            });
+                        self.show_compliance_dialog = !self.show_compliance_dialog;
+                    }
+                })
+                .response
+                .on_hover_cursor(CursorIcon::PointingHand)
+            });
+        });
+    }
+
+    fn main_window(&mut self, ctx: &Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            if self.active_task_id != -1
+                && let Some((task, rule)) = &self.task_suggestion
+            {
+                let mut answered = false;
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "Switch to '{}'? The focused window matches '{}'.",
+                        task.t_name, rule.wr_pattern
+                    ));
+                    if ui
+                        .button("Switch")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .on_hover_text("Save the running session and start this task")
+                        .clicked()
+                    {
+                        let _ = self.command_tx.send(agent::AgentCommand::AcceptSuggestion {
+                            comment: self.session_comment.clone(),
+                            billable: self.session_billable,
+                        });
+                        answered = true;
+                    }
+                    if ui
+                        .button("Dismiss")
+                        .on_hover_cursor(CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        answered = true;
+                    }
+                });
+                if answered {
+                    self.task_suggestion = None;
+                }
+            }
+
+            ui.group(|ui| {
+                ui.heading("Tasks");
+                ScrollArea::vertical().show(ui, |ui| {
+                    for task in &mut self.tasks {
+                        ui.group(|ui| {
+                            ui.take_available_width();
+                            ui.horizontal(|ui| {
+                                ui.vertical(|ui| {
+                                    ui.label(&task.t_name).on_hover_text("Name");
+                                    ui.text_edit_multiline(&mut self.session_comment)
+                                        .on_hover_text("Comment");
+                                    if self.active_task_id == task.t_id {
+                                        ui.checkbox(&mut self.session_billable, "Billable");
+                                    }
+
+                                    let mut tracked =
+                                        self.tracked.get(&task.t_id).copied().unwrap_or(0);
+                                    if self.active_task_id == task.t_id {
+                                        tracked += self.elapsed_time.as_secs();
+                                    }
+                                    if let Some(bar) = task_progress(task, tracked) {
+                                        ui.add(bar);
+                                    }
+                                });
+
+                                ui.vertical(|ui| {
+                                    ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
+                                        ui.colored_label(
+                                            match task.t_priority {
+                                                0 => Color32::DARK_GREEN,
+                                                1 => Color32::YELLOW,
+                                                2 => Color32::DARK_RED,
+                                                _ => Color32::DARK_BLUE,
+                                            },
+                                            "⏺",
+                                        )
+                                        .on_hover_cursor(CursorIcon::Default)
+                                        .on_hover_text("Priority")
+                                    });
+
+                                    ui.with_layout(Layout::right_to_left(Align::Max), |ui| {
+                                        if self.active_task_id == task.t_id {
+                                            let (command, hover) = match self.pomodoro {
+                                                Some(_) => (
+                                                    agent::AgentCommand::StopPomodoro,
+                                                    "Stop focus mode",
+                                                ),
+                                                None => (
+                                                    agent::AgentCommand::StartPomodoro,
+                                                    "Start focus mode",
+                                                ),
+                                            };
+                                            if ui
+                                                .selectable_label(self.pomodoro.is_some(), "🍅")
+                                                .on_hover_cursor(CursorIcon::PointingHand)
+                                                .on_hover_text(hover)
+                                                .clicked()
+                                                && let Err(e) = self.command_tx.send(command)
+                                            {
+                                                self.dialog_info = ui::DialogInfo {
+                                                    title: "Error",
+                                                    message: format!("{}", e),
+                                                    shown: false,
+                                                }
+                                            }
+
+                                            if ui
+                                                .button("⏸")
+                                                .on_hover_cursor(CursorIcon::PointingHand)
+                                                .on_hover_text("Stop")
+                                                .clicked()
+                                            {
+                                                if let Err(e) = self.command_tx.send(
+                                                    agent::AgentCommand::EndSession {
+                                                        comment: self.session_comment.clone(),
+                                                        billable: self.session_billable,
+                                                    },
+                                                ) {
+                                                    self.dialog_info = ui::DialogInfo {
+                                                        title: "Error",
+                                                        message: format!("{}", e),
+                                                        shown: false,
+                                                    }
+                                                }
+                                                self.active_task_id = -1;
+                                                self.session_comment = "".into();
+                                                self.session_billable = true;
+                                                self.task_suggestion = None;
+                                            }
+                                        } else {
+                                            if ui
+                                                .button("▶")
+                                                .on_hover_cursor(CursorIcon::PointingHand)
+                                                .on_hover_text("Start")
+                                                .clicked()
+                                            {
+                                                if let Err(e) = self.command_tx.send(
+                                                    agent::AgentCommand::StartSession {
+                                                        id: task.t_id,
+                                                    },
+                                                ) {
+                                                    self.dialog_info = ui::DialogInfo {
+                                                        title: "Error",
+                                                        message: format!("{}", e),
+                                                        shown: false,
+                                                    }
+                                                }
+                                                self.active_task_id = task.t_id;
+                                                self.task_suggestion = None;
+                                            }
+
+                                            if ui
+                                                .button("✏")
+                                                .on_hover_cursor(CursorIcon::PointingHand)
+                                                .on_hover_text("Edit")
+                                                .clicked()
+                                            {
+                                                let _ = self
+                                                    .command_tx
+                                                    .send(agent::AgentCommand::RequestBilling);
+                                                self.edit_task_estimate = task
+                                                    .t_estimate
+                                                    .map(ui::utils::format_hours)
+                                                    .unwrap_or_default();
+                                                self.edit_task_budget = task
+                                                    .t_budget
+                                                    .map(ui::utils::format_hours)
+                                                    .unwrap_or_default();
+                                                self.edit_task_daily_goal = task
+                                                    .t_daily_goal
+                                                    .map(ui::utils::format_hours)
+                                                    .unwrap_or_default();
+                                                self.edit_task_weekly_goal = task
+                                                    .t_weekly_goal
+                                                    .map(ui::utils::format_hours)
+                                                    .unwrap_or_default();
+                                                self.edit_task = Some(task.clone());
+                                            }
+
+                                            if ui
+                                                .button("🗑")
+                                                .on_hover_cursor(CursorIcon::PointingHand)
+                                                .on_hover_text("Delete")
+                                                .clicked()
+                                                && let Err(e) = self.command_tx.send(
+                                                    agent::AgentCommand::DeleteTask {
+                                                        id: task.t_id,
+                                                    },
+                                                )
+                                            {
+                                                self.dialog_info = ui::DialogInfo {
+                                                    title: "Error",
+                                                    message: format!("{}", e),
+                                                    shown: false,
+                                                }
+                                            }
+                                        }
+                                    });
+                                });
+                            });
+                        });
+                    }
+                });
+            });
+        });
+    }
+
+    fn status_bar(&mut self, ctx: &Context) {
         TopBottomPanel::new(TopBottomSide::Bottom, "Status Bar").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.with_layout(Layout::left_to_right(egui::Align::Min), |ui| {
@@ -335,11 +2209,191 @@ impl MyApp {
                         .on_hover_cursor(CursorIcon::PointingHand)
                         .on_hover_text("Sync")
                         .clicked()
-                    {}
+                        && let Err(e) = self.command_tx.send(agent::AgentCommand::SyncNow)
+                    {
+                        self.dialog_info = ui::DialogInfo {
+                            title: "Error",
+                            message: format!("{}", e),
+                            shown: false,
+                        }
+                    }
+
+                    let (color, text, hover) = match &self.sync_status {
+                        sync::SyncStatus::Disabled => (
+                            Color32::DARK_GRAY,
+                            "⏺".to_string(),
+                            "Sync disabled: no server configured".to_string(),
+                        ),
+                        sync::SyncStatus::Syncing => {
+                            (Color32::YELLOW, "⏺".to_string(), "Syncing...".to_string())
+                        }
+                        sync::SyncStatus::Synced { at } => (
+                            Color32::DARK_GREEN,
+                            "⏺".to_string(),
+                            format!(
+                                "Synced at {}",
+                                at.with_timezone(&chrono::Local).format("%H:%M:%S")
+                            ),
+                        ),
+                        sync::SyncStatus::Pending { count } => (
+                            Color32::YELLOW,
+                            format!("{}", count),
+                            format!("{} change(s) waiting to sync", count),
+                        ),
+                        sync::SyncStatus::Failed { message, retry_at } => (
+                            Color32::DARK_RED,
+                            "⏺".to_string(),
+                            format!(
+                                "Sync failed: {}\nRetrying at {}",
+                                message,
+                                retry_at.with_timezone(&chrono::Local).format("%H:%M:%S")
+                            ),
+                        ),
+                    };
+                    ui.colored_label(color, text)
+                        .on_hover_cursor(CursorIcon::Default)
+                        .on_hover_text(hover);
 
                     ui.label(ui::utils::format_duration(self.elapsed_time));
+
+                    // Goals for all work and for the running task.
+                    for progress in self.goals.iter().filter(|progress| {
+                        progress
+                            .goal
+                            .task
+                            .is_none_or(|id| id == self.active_task_id)
+                    }) {
+                        let bar = ProgressBar::new(progress.fraction())
+                            .desired_width(90.0)
+                            .text(format!(
+                                "{} / {}",
+                                report::format_hours(progress.worked),
+                                report::format_hours(progress.goal.target)
+                            ));
+                        ui.add(bar).on_hover_text(ui::utils::format_goal(progress));
+                    }
+
+                    if let Some(status) = self.pomodoro {
+                        ui.label(format!(
+                            "🍅 {} {}",
+                            status.phase.label(),
+                            ui::utils::format_minutes(status.remaining)
+                        ))
+                        .on_hover_text(format!("Pomodoros completed: {}", status.completed));
+                    }
                 });
             });
         });
     }
 }
+
+/// Tracked time against the task's estimate, or its budget if it has no
+/// estimate. Red once the budget is used up.
+fn task_progress(task: &agent::Task, tracked: u64) -> Option<ProgressBar> {
+    let target = task
+        .t_estimate
+        .or(task.t_budget)
+        .filter(|seconds| *seconds > 0)?;
+    let mut bar = ProgressBar::new(tracked as f32 / target as f32).text(format!(
+        "{} / {} h",
+        ui::utils::format_hours(tracked),
+        ui::utils::format_hours(target)
+    ));
+    if task.t_budget.is_some_and(|budget| tracked >= budget) {
+        bar = bar.fill(Color32::DARK_RED);
+    }
+    Some(bar)
+}
+
+/// The estimate and budget typed into a task dialog; empty fields mean none.
+fn task_hours(estimate: &str, budget: &str) -> Result<(Option<u64>, Option<u64>), String> {
+    Ok((
+        hours_field(estimate, "estimate")?,
+        hours_field(budget, "budget")?,
+    ))
+}
+
+/// The daily and weekly goals typed into a task dialog.
+fn task_goals(daily: &str, weekly: &str) -> Result<(Option<u64>, Option<u64>), String> {
+    Ok((
+        hours_field(daily, "daily goal")?,
+        hours_field(weekly, "weekly goal")?,
+    ))
+}
+
+fn hours_field(text: &str, field: &str) -> Result<Option<u64>, String> {
+    if text.trim().is_empty() {
+        return Ok(None);
+    }
+    ui::utils::parse_hours(text)
+        .map(Some)
+        .ok_or_else(|| format!("The {} must be a number of hours, e.g. 1.5 or 1:30", field))
+}
+
+fn import_format_label(format: import::Format) -> &'static str {
+    match format {
+        import::Format::Toggl => "Toggl (CSV)",
+        import::Format::Clockify => "Clockify (CSV)",
+        import::Format::Timewarrior => "Timewarrior (JSON)",
+    }
+}
+
+fn user_name(users: &[agent::users::User], id: &str) -> String {
+    users
+        .iter()
+        .find(|user| user.u_id == id)
+        .map_or_else(|| id.to_string(), |user| user.u_name.clone())
+}
+
+fn client_name(clients: &[agent::clients::Client], id: i64) -> String {
+    clients
+        .iter()
+        .find(|client| client.cl_id == id)
+        .map_or_else(|| id.to_string(), |client| client.cl_name.clone())
+}
+
+fn client_currency(clients: &[agent::clients::Client], id: i64) -> String {
+    clients
+        .iter()
+        .find(|client| client.cl_id == id)
+        .map(|client| client.cl_currency.clone())
+        .unwrap_or_default()
+}
+
+/// The form's contents for a client that hasn't been added yet.
+fn new_client() -> agent::clients::Client {
+    agent::clients::Client {
+        cl_currency: "EUR".to_string(),
+        ..Default::default()
+    }
+}
+
+/// The task's name, or its id if it isn't in the list.
+fn task_name(tasks: &[agent::Task], id: i64) -> String {
+    tasks
+        .iter()
+        .find(|task| task.t_id == id)
+        .map_or_else(|| id.to_string(), |task| task.t_name.clone())
+}
+
+fn rule_field_label(field: agent::window_rules::RuleField) -> &'static str {
+    match field {
+        agent::window_rules::RuleField::Title => "Title",
+        agent::window_rules::RuleField::Class => "Application",
+    }
+}
+
+fn rule_action_label(action: agent::window_rules::RuleAction) -> &'static str {
+    match action {
+        agent::window_rules::RuleAction::Suggest => "Suggest",
+        agent::window_rules::RuleAction::Switch => "Switch to",
+    }
+}
+
+fn rounding_label(mode: billing::RoundingMode) -> &'static str {
+    match mode {
+        billing::RoundingMode::Up => "up to",
+        billing::RoundingMode::Nearest => "to the nearest",
+        billing::RoundingMode::Down => "down to",
+    }
+}
@@ -0,0 +1,1408 @@
+mod common;
+
+use std::{
+    sync::{Arc, mpsc},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use time_tracker::{
+    agent::{
+        self, AgentCommand, Clock, ManualClock, RecordingNotifier, StopWatch,
+        active_window::{ActiveWindow, FakeWindowSource},
+        events::{UIControl, UIEvent, UserState},
+        notifications::Category,
+        pomodoro::{Phase, Pomodoro, PomodoroPlan, PomodoroStatus},
+        window_rules::{RuleAction, RuleField, RuleSet, WindowRule},
+    },
+    billing,
+    config::{Settings, StorageBackend},
+    storage, sync,
+};
+
+use common::TestDb;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An agent running on its own thread on a manual clock, wired to channels
+/// the test can inspect.
+struct TestAgent {
+    clock: Arc<ManualClock>,
+    notifier: Arc<RecordingNotifier>,
+    command_tx: mpsc::Sender<AgentCommand>,
+    window_rx: crossbeam_channel::Receiver<UIEvent>,
+    tray_rx: mpsc::Receiver<UIEvent>,
+    ui_control_rx: mpsc::Receiver<UIControl>,
+    sync_rx: mpsc::Receiver<sync::SyncCommand>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl TestAgent {
+    fn start(db: &TestDb) -> Self {
+        let (command_tx, command_rx) = mpsc::channel();
+        let (window_tx, window_rx) = crossbeam_channel::unbounded();
+        let (tray_tx, tray_rx) = mpsc::channel();
+        let (ui_control_tx, ui_control_rx) = mpsc::channel();
+        let (sync_tx, sync_rx) = mpsc::channel();
+
+        let clock = Arc::new(ManualClock::new(chrono::Utc::now()));
+        let notifier = Arc::new(RecordingNotifier::default());
+        let settings = db.settings.clone();
+        let agent_clock = clock.clone();
+        let agent_notifier = notifier.clone();
+        let handle = thread::spawn(move || {
+            agent::start_agent(
+                command_rx,
+                window_tx,
+                tray_tx,
+                ui_control_tx,
+                sync_tx,
+                settings,
+                agent_clock,
+                agent_notifier,
+            )
+        });
+
+        let agent = Self {
+            clock,
+            notifier,
+            command_tx,
+            window_rx,
+            tray_rx,
+            ui_control_rx,
+            sync_rx,
+            handle: Some(handle),
+        };
+        // Don't move the clock before the agent has read its start time.
+        agent.elapsed();
+        agent
+    }
+
+    fn send(&self, command: AgentCommand) {
+        self.command_tx.send(command).unwrap();
+    }
+
+    /// Waits for the first window event `matches` accepts, skipping others.
+    fn wait_for<T>(&self, matches: impl Fn(UIEvent) -> Option<T>) -> T {
+        loop {
+            let event = self
+                .window_rx
+                .recv_timeout(TIMEOUT)
+                .expect("agent did not send the expected event");
+            if let Some(value) = matches(event) {
+                return value;
+            }
+        }
+    }
+
+    /// Reports user activity at the current time of the manual clock.
+    fn activity(&self) {
+        self.send(AgentCommand::UserActivity {
+            time_stamp: self.clock.now(),
+        });
+    }
+
+    /// Returns the running session's time once every command sent so far has
+    /// been handled, so the clock can be moved without racing the agent.
+    fn elapsed(&self) -> Duration {
+        self.send(AgentCommand::RequestElapsedTime);
+        self.wait_for(|event| match event {
+            UIEvent::ElapsedTime { elapsed } => Some(elapsed),
+            _ => None,
+        })
+    }
+
+    /// Returns once the agent has run its periodic checks at the current
+    /// time of the manual clock.
+    fn checked(&self) {
+        self.elapsed();
+        self.elapsed();
+    }
+
+    /// The categories of the notifications shown so far.
+    fn notified(&self) -> Vec<Category> {
+        self.notifier
+            .notifications()
+            .iter()
+            .map(|notification| notification.category)
+            .collect()
+    }
+
+    fn quit(mut self) {
+        // The agent may already have stopped after an earlier `Quit`.
+        let _ = self.command_tx.send(AgentCommand::Quit);
+        self.handle.take().unwrap().join().unwrap();
+    }
+}
+
+impl Drop for TestAgent {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            let _ = self.command_tx.send(AgentCommand::Quit);
+            let _ = handle.join();
+        }
+    }
+}
+
+fn task_list(event: UIEvent) -> Option<Vec<agent::Task>> {
+    match event {
+        UIEvent::TaskList { task_list } => Some(task_list),
+        _ => None,
+    }
+}
+
+fn user_state(event: UIEvent) -> Option<UserState> {
+    match event {
+        UIEvent::UserState { state } => Some(state),
+        _ => None,
+    }
+}
+
+#[test]
+fn add_task_sends_updated_task_list() {
+    let db = TestDb::new();
+    let agent = TestAgent::start(&db);
+
+    agent.send(AgentCommand::AddTask {
+        task: agent::Task {
+            t_name: "Write tests".to_string(),
+            t_priority: 1,
+            ..Default::default()
+        },
+    });
+    let tasks = agent.wait_for(task_list);
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].t_name, "Write tests");
+
+    agent.send(AgentCommand::DeleteTask { id: tasks[0].t_id });
+    assert!(agent.wait_for(task_list).is_empty());
+    agent.quit();
+}
+
+#[test]
+fn agent_runs_on_memory_backend() {
+    let db = TestDb::with_settings(Settings {
+        storage_backend: StorageBackend::Memory,
+        ..Settings::default()
+    });
+    let agent = TestAgent::start(&db);
+
+    agent.send(AgentCommand::AddTask {
+        task: agent::Task {
+            t_name: "In memory".to_string(),
+            ..Default::default()
+        },
+    });
+    let task = agent.wait_for(task_list).remove(0);
+    agent.send(AgentCommand::UpdateTask {
+        task: agent::Task {
+            t_name: "Still in memory".to_string(),
+            ..task
+        },
+    });
+    assert_eq!(agent.wait_for(task_list)[0].t_name, "Still in memory");
+
+    agent.send(AgentCommand::StartSession { id: 1 });
+    agent.send(AgentCommand::EndSession {
+        comment: "".to_string(),
+        billable: true,
+    });
+    agent.send(AgentCommand::RequestConflicts);
+    assert!(
+        agent
+            .wait_for(|event| match event {
+                UIEvent::Conflicts { conflicts } => Some(conflicts),
+                _ => None,
+            })
+            .is_empty()
+    );
+    agent.quit();
+    assert!(!std::path::Path::new(db.path()).exists());
+}
+
+#[test]
+fn ending_session_saves_it() {
+    let db = TestDb::new();
+    let agent = TestAgent::start(&db);
+
+    agent.send(AgentCommand::AddTask {
+        task: agent::Task {
+            t_name: "Focus".to_string(),
+            ..Default::default()
+        },
+    });
+    let task = agent.wait_for(task_list).remove(0);
+
+    agent.send(AgentCommand::StartSession { id: task.t_id });
+    agent.send(AgentCommand::EndSession {
+        comment: "Finished".to_string(),
+        billable: false,
+    });
+    agent.quit();
+
+    let conn = storage::init_db(db.settings.clone()).unwrap();
+    let session = agent::sessions::get_session(&conn, 1).unwrap().unwrap();
+    assert_eq!(session.s_task, task.t_id);
+    assert_eq!(session.s_user, db.settings.uid);
+    assert_eq!(session.s_comment, "Finished");
+    assert!(!session.s_billable);
+}
+
+#[test]
+fn user_goes_idle_after_timeout_and_back_on_activity() {
+    let db = TestDb::with_settings(Settings {
+        active_timeout_seconds: 60,
+        ..Settings::default()
+    });
+    let agent = TestAgent::start(&db);
+
+    agent.clock.advance(chrono::Duration::seconds(60));
+    assert!(agent.wait_for(user_state) == UserState::Idle);
+    assert!(matches!(
+        agent.tray_rx.recv_timeout(TIMEOUT),
+        Ok(UIEvent::UserState {
+            state: UserState::Idle
+        })
+    ));
+
+    agent.activity();
+    assert!(agent.wait_for(user_state) == UserState::Active);
+    agent.quit();
+}
+
+#[test]
+fn session_counts_hours_of_activity() {
+    let db = TestDb::new();
+    let agent = TestAgent::start(&db);
+    agent.send(AgentCommand::AddTask {
+        task: agent::Task::default(),
+    });
+    let task = agent.wait_for(task_list).remove(0);
+
+    agent.send(AgentCommand::StartSession { id: task.t_id });
+    agent.elapsed();
+    // Activity every 10 seconds keeps the user active for two hours.
+    for _ in 0..720 {
+        agent.clock.advance(chrono::Duration::seconds(10));
+        agent.activity();
+        agent.elapsed();
+    }
+    assert_eq!(agent.elapsed(), Duration::from_secs(2 * 60 * 60));
+
+    agent.send(AgentCommand::EndSession {
+        comment: "".to_string(),
+        billable: true,
+    });
+    agent.quit();
+
+    let conn = storage::init_db(db.settings.clone()).unwrap();
+    let session = agent::sessions::get_session(&conn, 1).unwrap().unwrap();
+    assert_eq!(session.s_duration, 2 * 60 * 60);
+}
+
+#[test]
+fn idle_time_is_not_counted() {
+    let db = TestDb::with_settings(Settings {
+        active_timeout_seconds: 60,
+        ..Settings::default()
+    });
+    let agent = TestAgent::start(&db);
+    agent.send(AgentCommand::AddTask {
+        task: agent::Task::default(),
+    });
+    let task = agent.wait_for(task_list).remove(0);
+
+    agent.send(AgentCommand::StartSession { id: task.t_id });
+    agent.activity();
+    agent.elapsed();
+
+    // The user stops right away and stays away for three hours. The minute
+    // waited out before going idle isn't counted either.
+    agent.clock.advance(chrono::Duration::seconds(60));
+    assert!(agent.wait_for(user_state) == UserState::Idle);
+    agent.clock.advance(chrono::Duration::hours(3));
+    assert_eq!(agent.elapsed(), Duration::ZERO);
+
+    // Coming back resumes the session.
+    agent.activity();
+    agent.elapsed();
+    agent.clock.advance(chrono::Duration::seconds(30));
+    assert_eq!(agent.elapsed(), Duration::from_secs(30));
+    agent.quit();
+}
+
+#[test]
+fn stop_watch_follows_the_clock() {
+    let clock = Arc::new(ManualClock::new(chrono::Utc::now()));
+    let mut stop_watch = StopWatch::new(clock.clone());
+
+    stop_watch.start();
+    clock.advance(chrono::Duration::minutes(90));
+    assert_eq!(stop_watch.elapsed(), Duration::from_secs(90 * 60));
+
+    stop_watch.stop();
+    clock.advance(chrono::Duration::hours(8));
+    assert_eq!(stop_watch.elapsed(), Duration::from_secs(90 * 60));
+
+    stop_watch.start();
+    clock.advance(chrono::Duration::minutes(30));
+    assert_eq!(stop_watch.elapsed(), Duration::from_secs(2 * 60 * 60));
+
+    stop_watch.reset();
+    assert_eq!(stop_watch.elapsed(), Duration::ZERO);
+}
+
+#[test]
+fn stop_watch_ignores_wall_clock_changes() {
+    let start = chrono::Utc::now();
+    let clock = Arc::new(ManualClock::new(start));
+    let mut stop_watch = StopWatch::new(clock.clone());
+
+    stop_watch.start();
+    clock.advance(chrono::Duration::minutes(10));
+    clock.set(start - chrono::Duration::days(1));
+    assert_eq!(stop_watch.elapsed(), Duration::from_secs(10 * 60));
+    clock.set(start + chrono::Duration::days(1));
+    assert_eq!(stop_watch.elapsed(), Duration::from_secs(10 * 60));
+}
+
+#[test]
+fn sessions_are_timestamped_by_the_agent_clock() {
+    let db = TestDb::new();
+    let agent = TestAgent::start(&db);
+    agent.send(AgentCommand::AddTask {
+        task: common::new_task("Website", 0),
+    });
+    let task = agent.wait_for(task_list).remove(0);
+    assert_eq!(task.t_created_at, agent.clock.now().timestamp_millis());
+
+    agent.send(AgentCommand::StartSession { id: task.t_id });
+    agent.activity();
+    agent.elapsed();
+    agent.clock.advance(chrono::Duration::seconds(10));
+    agent.send(AgentCommand::EndSession {
+        comment: String::new(),
+        billable: true,
+    });
+    let ended_at = agent.clock.now().timestamp_millis();
+    agent.quit();
+
+    let session = saved_session(&db).unwrap();
+    assert_eq!(
+        (session.s_created_at, session.s_updated_at),
+        (ended_at, ended_at)
+    );
+}
+
+fn error(event: UIEvent) -> Option<String> {
+    match event {
+        UIEvent::Error { message } => Some(message),
+        _ => None,
+    }
+}
+
+#[test]
+fn failed_command_is_reported_and_agent_keeps_running() {
+    let db = TestDb::new();
+    let agent = TestAgent::start(&db);
+
+    // Sessions must belong to an existing task.
+    agent.send(AgentCommand::StartSession { id: 42 });
+    agent.send(AgentCommand::EndSession {
+        comment: "".to_string(),
+        billable: true,
+    });
+    assert!(agent.wait_for(error).contains("FOREIGN KEY"));
+
+    agent.send(AgentCommand::AddTask {
+        task: agent::Task {
+            t_name: "Still working".to_string(),
+            ..Default::default()
+        },
+    });
+    assert_eq!(agent.wait_for(task_list).len(), 1);
+    agent.quit();
+}
+
+#[test]
+fn unusable_database_falls_back_to_memory() {
+    let db = TestDb::with_settings(Settings::default());
+    let settings = Arc::new(Settings {
+        // A directory can't be opened as a database.
+        local_database_path: std::env::temp_dir().to_string_lossy().to_string(),
+        ..(*db.settings).clone()
+    });
+    let (command_tx, command_rx) = mpsc::channel();
+    let (window_tx, window_rx) = crossbeam_channel::unbounded();
+    let (tray_tx, _tray_rx) = mpsc::channel();
+    let (ui_control_tx, _ui_control_rx) = mpsc::channel();
+    let (sync_tx, _sync_rx) = mpsc::channel();
+    let handle = thread::spawn(move || {
+        agent::start_agent(
+            command_rx,
+            window_tx,
+            tray_tx,
+            ui_control_tx,
+            sync_tx,
+            settings,
+            Arc::new(agent::SystemClock),
+            Arc::new(RecordingNotifier::default()),
+        )
+    });
+
+    assert!(matches!(
+        window_rx.recv_timeout(TIMEOUT),
+        Ok(UIEvent::Error { .. })
+    ));
+    command_tx
+        .send(AgentCommand::AddTask {
+            task: agent::Task::default(),
+        })
+        .unwrap();
+    assert!(matches!(
+        window_rx.recv_timeout(TIMEOUT),
+        Ok(UIEvent::TaskList { task_list }) if task_list.len() == 1
+    ));
+
+    command_tx.send(AgentCommand::Quit).unwrap();
+    handle.join().unwrap();
+}
+
+#[test]
+fn backup_is_taken_and_restored() {
+    let db = TestDb::new();
+    let agent = TestAgent::start(&db);
+
+    agent.send(AgentCommand::AddTask {
+        task: common::new_task("Kept", 0),
+    });
+    agent.wait_for(task_list);
+    agent.send(AgentCommand::BackupNow);
+    let backups = agent.wait_for(|event| match event {
+        UIEvent::Backups { backups } => Some(backups),
+        _ => None,
+    });
+    assert_eq!(backups.len(), 1);
+
+    agent.send(AgentCommand::AddTask {
+        task: common::new_task("Lost", 0),
+    });
+    assert_eq!(agent.wait_for(task_list).len(), 2);
+    agent.send(AgentCommand::RestoreBackup {
+        path: backups[0].path.clone(),
+    });
+    let tasks = agent.wait_for(task_list);
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].t_name, "Kept");
+    agent.quit();
+}
+
+#[test]
+fn calendar_is_exported_and_served() {
+    let db = TestDb::with_settings(Settings {
+        active_timeout_seconds: 60 * 60,
+        ..Settings::default()
+    });
+    let agent = TestAgent::start(&db);
+
+    agent.send(AgentCommand::AddTask {
+        task: common::new_task("Website", 0),
+    });
+    let task = agent.wait_for(task_list)[0].t_id;
+    agent.send(AgentCommand::StartSession { id: task });
+    agent.activity();
+    agent.clock.advance(chrono::Duration::seconds(60));
+    agent.send(AgentCommand::EndSession {
+        comment: "".to_string(),
+        billable: true,
+    });
+
+    let today = agent.clock.now().with_timezone(&chrono::Local).date_naive();
+    let path = db.backup_dir().with_extension("ics");
+    agent.send(AgentCommand::ExportCalendar {
+        path: path.clone(),
+        from: today,
+        to: today,
+    });
+    let exported = agent.wait_for(|event| match event {
+        UIEvent::Exported { path } => Some(path),
+        _ => None,
+    });
+    assert_eq!(exported, path);
+    let ics = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert!(ics.contains("SUMMARY:Website"));
+
+    let (reply_tx, reply_rx) = mpsc::channel();
+    agent.send(AgentCommand::CalendarFeed { reply: reply_tx });
+    let feed = reply_rx.recv_timeout(TIMEOUT).unwrap();
+    assert_eq!(feed.matches("BEGIN:VEVENT").count(), 1);
+    agent.quit();
+}
+
+fn billing(event: UIEvent) -> Option<(Vec<agent::clients::Client>, Vec<agent::rates::Rate>)> {
+    match event {
+        UIEvent::Billing { clients, rates } => Some((clients, rates)),
+        _ => None,
+    }
+}
+
+#[test]
+fn billable_time_is_invoiced() {
+    let db = TestDb::with_settings(Settings {
+        active_timeout_seconds: 60 * 60,
+        ..Settings::default()
+    });
+    let agent = TestAgent::start(&db);
+
+    agent.send(AgentCommand::AddClient {
+        client: agent::clients::Client {
+            cl_name: "Acme".to_string(),
+            cl_currency: "EUR".to_string(),
+            cl_rounding: billing::Rounding {
+                mode: billing::RoundingMode::Up,
+                minutes: 15,
+            },
+            ..Default::default()
+        },
+    });
+    let client = agent.wait_for(billing).0.remove(0);
+    agent.send(AgentCommand::AddTask {
+        task: agent::Task {
+            t_client: Some(client.cl_id),
+            ..common::new_task("Website", 0)
+        },
+    });
+    let task = agent.wait_for(task_list).remove(0);
+    assert_eq!(task.t_client, Some(client.cl_id));
+
+    let today = agent.clock.now().with_timezone(&chrono::Local).date_naive();
+    agent.send(AgentCommand::AddRate {
+        rate: agent::rates::Rate {
+            r_client: Some(client.cl_id),
+            r_hourly_cents: 10000,
+            r_effective_from: today,
+            ..Default::default()
+        },
+    });
+    assert_eq!(agent.wait_for(billing).1.len(), 1);
+
+    for billable in [true, false] {
+        agent.send(AgentCommand::StartSession { id: task.t_id });
+        agent.activity();
+        agent.elapsed();
+        agent.clock.advance(chrono::Duration::seconds(60));
+        assert_eq!(agent.elapsed(), Duration::from_secs(60));
+        agent.send(AgentCommand::EndSession {
+            comment: "".to_string(),
+            billable,
+        });
+    }
+
+    agent.send(AgentCommand::RequestInvoiceDraft {
+        client: client.cl_id,
+        from: today,
+        to: today,
+    });
+    let draft = agent.wait_for(|event| match event {
+        UIEvent::InvoiceDraft { draft } => Some(draft),
+        _ => None,
+    });
+    assert_eq!(draft.lines.len(), 1);
+    assert_eq!(draft.lines[0].sessions, 1);
+    assert_eq!(draft.total_seconds(), 15 * 60);
+    assert_eq!(draft.total_cents(), 2500);
+
+    let path = db.backup_dir().with_extension("csv");
+    agent.send(AgentCommand::ExportInvoiceDraft {
+        path: path.clone(),
+        client: client.cl_id,
+        from: today,
+        to: today,
+    });
+    let exported = agent.wait_for(|event| match event {
+        UIEvent::Exported { path } => Some(path),
+        _ => None,
+    });
+    assert_eq!(exported, path);
+    let csv = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert!(csv.contains("Website,0.25,100.00,25.00,EUR"));
+    agent.quit();
+}
+
+fn budget_warning(event: UIEvent) -> Option<(String, u8)> {
+    match event {
+        UIEvent::BudgetWarning { task, percent, .. } => Some((task, percent)),
+        _ => None,
+    }
+}
+
+#[test]
+fn running_session_warns_as_budget_runs_out() {
+    let db = TestDb::with_settings(Settings {
+        active_timeout_seconds: 60 * 60,
+        ..Settings::default()
+    });
+    let agent = TestAgent::start(&db);
+
+    agent.send(AgentCommand::AddTask {
+        task: agent::Task {
+            t_estimate: Some(60),
+            t_budget: Some(100),
+            ..common::new_task("Website", 0)
+        },
+    });
+    let task = agent.wait_for(task_list).remove(0);
+    assert_eq!((task.t_estimate, task.t_budget), (Some(60), Some(100)));
+
+    agent.send(AgentCommand::StartSession { id: task.t_id });
+    agent.activity();
+    agent.elapsed();
+    agent.clock.advance(chrono::Duration::seconds(50));
+    assert_eq!(agent.elapsed(), Duration::from_secs(50));
+    agent.send(AgentCommand::EndSession {
+        comment: "".to_string(),
+        billable: true,
+    });
+    let tracked = agent.wait_for(|event| match event {
+        UIEvent::TrackedTime { tracked } => Some(tracked),
+        _ => None,
+    });
+    assert_eq!(tracked.get(&task.t_id), Some(&50));
+
+    // 50 of 100 seconds were tracked before this session.
+    agent.send(AgentCommand::StartSession { id: task.t_id });
+    agent.activity();
+    agent.elapsed();
+    agent.clock.advance(chrono::Duration::seconds(30));
+    assert_eq!(agent.wait_for(budget_warning), ("Website".to_string(), 80));
+    agent.activity();
+    agent.elapsed();
+    agent.clock.advance(chrono::Duration::seconds(20));
+    assert_eq!(agent.wait_for(budget_warning), ("Website".to_string(), 100));
+
+    // Each warning is given once; a warning would arrive before the second
+    // reply.
+    agent.activity();
+    agent.elapsed();
+    agent.clock.advance(chrono::Duration::seconds(30));
+    assert_eq!(agent.elapsed(), Duration::from_secs(80));
+    agent.send(AgentCommand::RequestElapsedTime);
+    let warned_again = agent.wait_for(|event| match event {
+        UIEvent::BudgetWarning { .. } => Some(true),
+        UIEvent::ElapsedTime { .. } => Some(false),
+        _ => None,
+    });
+    assert!(!warned_again);
+    let budget_notifications = agent
+        .notified()
+        .into_iter()
+        .filter(|category| *category == Category::Budget)
+        .count();
+    assert_eq!(budget_notifications, 2);
+    agent.quit();
+}
+
+/// Starts a session, then leaves it running for two hours without activity.
+fn leave_session_running(agent: &TestAgent) {
+    agent.send(AgentCommand::AddTask {
+        task: common::new_task("Website", 0),
+    });
+    let task = agent.wait_for(task_list).remove(0);
+    agent.send(AgentCommand::StartSession { id: task.t_id });
+    agent.activity();
+    agent.checked();
+    assert!(agent.notified().is_empty());
+
+    agent.clock.advance(chrono::Duration::seconds(20));
+    agent.checked();
+    agent.clock.advance(chrono::Duration::hours(1));
+    agent.checked();
+    agent.clock.advance(chrono::Duration::hours(1));
+    agent.checked();
+}
+
+#[test]
+fn idle_and_forgotten_sessions_are_notified() {
+    let db = TestDb::with_settings(Settings {
+        forgotten_timer_hours: 1,
+        ..Settings::default()
+    });
+    let agent = TestAgent::start(&db);
+
+    leave_session_running(&agent);
+    assert_eq!(
+        agent.notified(),
+        vec![Category::Idle, Category::ForgottenTimer]
+    );
+    let notifications = agent.notifier.notifications();
+    assert!(notifications[0].body.contains("'Website'"));
+    assert!(notifications[1].body.contains("1 hours"));
+    agent.quit();
+}
+
+#[test]
+fn notifications_can_be_turned_off() {
+    let db = TestDb::with_settings(Settings {
+        forgotten_timer_hours: 1,
+        notify_idle: false,
+        notify_forgotten_timer: false,
+        ..Settings::default()
+    });
+    let agent = TestAgent::start(&db);
+
+    leave_session_running(&agent);
+    assert!(agent.notified().is_empty());
+    agent.quit();
+}
+
+#[test]
+fn pomodoro_alternates_focus_and_breaks() {
+    let clock = Arc::new(ManualClock::new(chrono::Utc::now()));
+    let plan = PomodoroPlan {
+        focus: Duration::from_secs(25 * 60),
+        short_break: Duration::from_secs(5 * 60),
+        long_break: Duration::from_secs(15 * 60),
+        long_break_every: 2,
+    };
+    let mut pomodoro = Pomodoro::start(plan, clock.clone());
+
+    clock.advance(chrono::Duration::milliseconds(60_500));
+    assert_eq!(
+        pomodoro.status(),
+        PomodoroStatus {
+            phase: Phase::Focus,
+            remaining: Duration::from_secs(24 * 60),
+            completed: 0,
+        }
+    );
+    assert_eq!(pomodoro.advance(), None);
+
+    // Idle time doesn't count towards focus.
+    pomodoro.pause();
+    clock.advance(chrono::Duration::hours(1));
+    assert_eq!(pomodoro.advance(), None);
+    pomodoro.resume();
+
+    let mut phases = Vec::new();
+    for minutes in [24, 5, 25, 15] {
+        clock.advance(chrono::Duration::minutes(minutes));
+        phases.push(pomodoro.advance().unwrap());
+    }
+    assert_eq!(
+        phases,
+        vec![
+            Phase::ShortBreak,
+            Phase::Focus,
+            Phase::LongBreak,
+            Phase::Focus
+        ]
+    );
+    assert_eq!(pomodoro.status().completed, 2);
+
+    // Breaks go on while the user is away.
+    clock.advance(chrono::Duration::minutes(25));
+    pomodoro.advance();
+    pomodoro.pause();
+    clock.advance(chrono::Duration::minutes(5));
+    assert_eq!(pomodoro.advance(), Some(Phase::Focus));
+}
+
+fn pomodoro(event: UIEvent) -> Option<Option<PomodoroStatus>> {
+    match event {
+        UIEvent::Pomodoro { status } => Some(status),
+        _ => None,
+    }
+}
+
+#[test]
+fn focus_mode_counts_pomodoros_and_pauses_for_breaks() {
+    let db = TestDb::with_settings(Settings {
+        active_timeout_seconds: 60 * 60,
+        pomodoro_focus_minutes: 10,
+        pomodoro_break_minutes: 5,
+        ..Settings::default()
+    });
+    let agent = TestAgent::start(&db);
+    start_session(&agent);
+    agent.send(AgentCommand::StartPomodoro);
+    let status = agent.wait_for(pomodoro).unwrap();
+    assert_eq!(status.phase, Phase::Focus);
+    assert_eq!(status.remaining, Duration::from_secs(10 * 60));
+
+    for _ in 0..10 {
+        agent.clock.advance(chrono::Duration::seconds(60));
+        agent.activity();
+        agent.checked();
+    }
+    assert_eq!(agent.notified(), vec![Category::Pomodoro]);
+    assert_eq!(agent.elapsed(), Duration::from_secs(10 * 60));
+
+    // The break isn't counted as work.
+    agent.clock.advance(chrono::Duration::seconds(60));
+    agent.activity();
+    agent.checked();
+    assert_eq!(agent.elapsed(), Duration::from_secs(10 * 60));
+
+    agent.send(AgentCommand::StopPomodoro);
+    assert_eq!(agent.wait_for(pomodoro), None);
+    agent.clock.advance(chrono::Duration::seconds(10));
+    assert_eq!(agent.elapsed(), Duration::from_secs(10 * 60 + 10));
+
+    agent.send(AgentCommand::EndSession {
+        comment: String::new(),
+        billable: true,
+    });
+    agent.quit();
+    assert_eq!(saved_session(&db).unwrap().s_pomodoros, 1);
+}
+
+#[test]
+fn focus_mode_countdown_is_sent_once_a_minute() {
+    let db = TestDb::with_settings(Settings {
+        active_timeout_seconds: 60 * 60,
+        pomodoro_focus_minutes: 10,
+        ..Settings::default()
+    });
+    let agent = TestAgent::start(&db);
+    start_session(&agent);
+    agent.send(AgentCommand::StartPomodoro);
+    agent.checked();
+
+    for _ in 0..30 {
+        agent.clock.advance(chrono::Duration::seconds(2));
+        agent.activity();
+        agent.checked();
+    }
+    let remaining: Vec<u64> = agent
+        .tray_rx
+        .try_iter()
+        .filter_map(pomodoro)
+        .flatten()
+        .map(|status| status.remaining.as_secs() / 60)
+        .collect();
+    assert_eq!(remaining, vec![10, 9]);
+    agent.quit();
+}
+
+/// Reports activity every minute for `minutes` minutes.
+fn keep_busy(agent: &TestAgent, minutes: u32) {
+    for _ in 0..minutes {
+        agent.clock.advance(chrono::Duration::seconds(60));
+        agent.activity();
+        agent.checked();
+    }
+}
+
+#[test]
+fn breaks_are_suggested_after_long_stretches_of_activity() {
+    let db = TestDb::with_settings(Settings {
+        break_reminder_minutes: 30,
+        min_break_minutes: 5,
+        ..Settings::default()
+    });
+    let agent = TestAgent::start(&db);
+
+    keep_busy(&agent, 29);
+    assert!(agent.notified().is_empty());
+    keep_busy(&agent, 1);
+    assert_eq!(agent.notified(), vec![Category::Break]);
+    assert!(agent.notifier.notifications()[0].body.contains("0:30"));
+
+    // A short pause doesn't count as a break.
+    agent.clock.advance(chrono::Duration::minutes(3));
+    agent.activity();
+    keep_busy(&agent, 27);
+    assert_eq!(agent.notified(), vec![Category::Break, Category::Break]);
+
+    agent.clock.advance(chrono::Duration::minutes(10));
+    agent.checked();
+    agent.activity();
+    keep_busy(&agent, 29);
+    assert_eq!(agent.notified().len(), 2);
+    agent.quit();
+}
+
+fn work_limit_warning(event: UIEvent) -> Option<(bool, u64)> {
+    match event {
+        UIEvent::WorkLimitWarning { weekly, worked, .. } => Some((weekly, worked)),
+        _ => None,
+    }
+}
+
+#[test]
+fn passing_the_working_time_limits_is_warned_about_once() {
+    let db = TestDb::with_settings(Settings {
+        active_timeout_seconds: 2 * 60 * 60,
+        daily_hours_limit: 1,
+        weekly_hours_limit: 2,
+        ..Settings::default()
+    });
+    let agent = TestAgent::start(&db);
+    let task = start_session(&agent);
+    agent.clock.advance(chrono::Duration::minutes(61));
+    assert_eq!(agent.wait_for(work_limit_warning), (false, 61 * 60));
+    agent.send(AgentCommand::EndSession {
+        comment: String::new(),
+        billable: true,
+    });
+
+    // The time already saved today counts towards the limits.
+    agent.send(AgentCommand::StartSession { id: task.t_id });
+    agent.activity();
+    agent.elapsed();
+    agent.clock.advance(chrono::Duration::minutes(60));
+    assert_eq!(agent.wait_for(work_limit_warning), (true, 121 * 60));
+    agent.checked();
+    let warnings = agent
+        .notified()
+        .into_iter()
+        .filter(|category| *category == Category::WorkLimit)
+        .count();
+    assert_eq!(warnings, 2);
+
+    agent.send(AgentCommand::RequestCompliance { weeks: 4 });
+    let compliance = agent.wait_for(|event| match event {
+        UIEvent::Compliance { compliance } => Some(compliance),
+        _ => None,
+    });
+    assert_eq!(compliance.weeks.len(), 4);
+    assert_eq!(compliance.weeks[3].worked(), 61 * 60);
+    agent.quit();
+}
+
+/// Waits for the goals' progress to reach `expected`, as (task, seconds).
+fn wait_for_goals(agent: &TestAgent, expected: &[(Option<i64>, u64)]) {
+    agent.wait_for(|event| match event {
+        UIEvent::Goals { progress } => {
+            let progress: Vec<_> = progress
+                .iter()
+                .map(|progress| (progress.goal.task, progress.worked))
+                .collect();
+            (progress == expected).then_some(())
+        }
+        _ => None,
+    });
+}
+
+#[test]
+fn goal_progress_counts_the_running_session() {
+    let db = TestDb::with_settings(Settings {
+        active_timeout_seconds: 60 * 60,
+        daily_goal_hours: 1.0,
+        ..Settings::default()
+    });
+    let agent = TestAgent::start(&db);
+    agent.send(AgentCommand::AddTask {
+        task: agent::Task {
+            t_daily_goal: Some(1800),
+            ..common::new_task("Website", 0)
+        },
+    });
+    agent.send(AgentCommand::AddTask {
+        task: common::new_task("Accounting", 0),
+    });
+    let tasks = agent.wait_for(|event| task_list(event).filter(|tasks| tasks.len() == 2));
+    let task = |name: &str| tasks.iter().find(|task| task.t_name == name).unwrap().t_id;
+    let website = task("Website");
+    wait_for_goals(&agent, &[(None, 0), (Some(website), 0)]);
+
+    agent.send(AgentCommand::StartSession { id: website });
+    agent.activity();
+    agent.elapsed();
+    agent.clock.advance(chrono::Duration::seconds(10 * 60 + 30));
+    wait_for_goals(&agent, &[(None, 600), (Some(website), 600)]);
+    agent.send(AgentCommand::EndSession {
+        comment: String::new(),
+        billable: true,
+    });
+
+    // Other tasks only count towards the goal for all work.
+    agent.send(AgentCommand::StartSession {
+        id: task("Accounting"),
+    });
+    agent.activity();
+    agent.elapsed();
+    agent.clock.advance(chrono::Duration::minutes(5));
+    wait_for_goals(&agent, &[(None, 900), (Some(website), 600)]);
+    agent.quit();
+}
+
+fn session_stopped(event: UIEvent) -> Option<String> {
+    match event {
+        UIEvent::SessionStopped { reason } => Some(reason),
+        _ => None,
+    }
+}
+
+/// Adds a task and starts a session on it with the user active.
+fn start_session(agent: &TestAgent) -> agent::Task {
+    agent.send(AgentCommand::AddTask {
+        task: common::new_task("Website", 0),
+    });
+    let task = agent.wait_for(task_list).remove(0);
+    agent.send(AgentCommand::StartSession { id: task.t_id });
+    agent.activity();
+    agent.elapsed();
+    task
+}
+
+/// The only session saved in the test database.
+fn saved_session(db: &TestDb) -> Option<agent::Session> {
+    let conn = storage::init_db(db.settings.clone()).unwrap();
+    let mut sessions = agent::sessions::get_all_sessions(&conn).unwrap();
+    assert!(sessions.len() <= 1);
+    sessions.pop()
+}
+
+#[test]
+fn idle_session_is_stopped_automatically() {
+    let db = TestDb::with_settings(Settings {
+        active_timeout_seconds: 2 * 60,
+        auto_stop_idle_hours: 1,
+        ..Settings::default()
+    });
+    let agent = TestAgent::start(&db);
+    let task = start_session(&agent);
+
+    agent.clock.advance(chrono::Duration::seconds(60));
+    agent.activity();
+    agent.checked();
+    let last_activity = agent.clock.now();
+    agent.clock.advance(chrono::Duration::hours(1));
+    let reason = agent.wait_for(session_stopped);
+    assert_eq!(
+        reason,
+        "Stopped automatically after 1 hours without activity"
+    );
+    assert_eq!(agent.elapsed(), Duration::ZERO);
+    assert!(agent.notified().contains(&Category::ForgottenTimer));
+    agent.quit();
+
+    let session = saved_session(&db).unwrap();
+    assert_eq!(session.s_task, task.t_id);
+    assert_eq!(session.s_comment, reason);
+    assert_eq!(session.s_duration, 60);
+    assert_eq!(session.s_created_at, last_activity.timestamp_millis());
+}
+
+#[test]
+fn session_is_stopped_at_the_end_of_the_workday() {
+    let end_of_day = chrono::Local::now() + chrono::Duration::minutes(2);
+    let db = TestDb::with_settings(Settings {
+        auto_stop_at: end_of_day.format("%H:%M").to_string(),
+        ..Settings::default()
+    });
+    let agent = TestAgent::start(&db);
+    start_session(&agent);
+
+    agent.clock.advance(chrono::Duration::minutes(5));
+    let reason = agent.wait_for(session_stopped);
+    assert!(reason.contains("end of the workday"), "{}", reason);
+    agent.quit();
+    assert_eq!(saved_session(&db).unwrap().s_comment, reason);
+}
+
+#[test]
+fn running_session_is_saved_on_shutdown() {
+    for save in [true, false] {
+        let db = TestDb::with_settings(Settings {
+            auto_stop_on_shutdown: save,
+            ..Settings::default()
+        });
+        let agent = TestAgent::start(&db);
+        start_session(&agent);
+
+        agent.send(AgentCommand::Shutdown);
+        agent.quit();
+        let session = saved_session(&db);
+        assert_eq!(session.is_some(), save);
+        if let Some(session) = session {
+            assert_eq!(
+                session.s_comment,
+                "Stopped automatically because the system shut down"
+            );
+        }
+    }
+}
+
+fn window(title: &str, class: &str) -> Option<ActiveWindow> {
+    Some(ActiveWindow {
+        title: title.to_string(),
+        class: class.to_string(),
+    })
+}
+
+#[test]
+fn window_rules_match_the_first_rule_on_title_or_class() {
+    let rules = RuleSet::new(vec![
+        WindowRule {
+            wr_id: 1,
+            wr_pattern: "(?i)invoice".to_string(),
+            wr_task: 1,
+            ..Default::default()
+        },
+        WindowRule {
+            wr_id: 2,
+            wr_pattern: "[unclosed".to_string(),
+            wr_task: 2,
+            ..Default::default()
+        },
+        WindowRule {
+            wr_id: 3,
+            wr_pattern: "^code$".to_string(),
+            wr_field: RuleField::Class,
+            wr_task: 3,
+            wr_action: RuleAction::Switch,
+        },
+    ]);
+    let matched = |title, class| {
+        rules
+            .matching(&window(title, class).unwrap())
+            .map(|rule| rule.wr_id)
+    };
+
+    assert_eq!(
+        matched("Invoice 42.txt - Visual Studio Code", "code"),
+        Some(1)
+    );
+    assert_eq!(matched("main.rs - Visual Studio Code", "code"), Some(3));
+    assert_eq!(
+        matched("main.rs - Visual Studio Code", "code-insiders"),
+        None
+    );
+    assert_eq!(matched("[unclosed", "firefox"), None);
+    assert!(agent::window_rules::check_pattern("[unclosed").is_err());
+    assert!(agent::window_rules::check_pattern("").is_err());
+    assert!(agent::window_rules::check_pattern("^code$").is_ok());
+}
+
+#[test]
+fn window_watcher_reports_focus_changes() {
+    let (command_tx, command_rx) = mpsc::channel();
+    let clock = Arc::new(ManualClock::new(chrono::Utc::now()));
+    let source = FakeWindowSource::default();
+    source.set(window("Inbox", "thunderbird"));
+    agent::start_window_watcher(
+        command_tx,
+        clock.clone(),
+        Box::new(source.clone()),
+        Duration::from_millis(5),
+    );
+    let next = || match command_rx.recv_timeout(TIMEOUT).unwrap() {
+        AgentCommand::ActiveWindow { window, time_stamp } => (window, time_stamp),
+        _ => panic!("expected the active window"),
+    };
+
+    assert_eq!(next(), (window("Inbox", "thunderbird"), clock.now()));
+    thread::sleep(Duration::from_millis(50));
+    assert!(command_rx.try_recv().is_err());
+
+    clock.advance(chrono::Duration::seconds(30));
+    source.set(None);
+    assert_eq!(next(), (None, clock.now()));
+}
+
+fn window_rules(event: UIEvent) -> Option<Vec<WindowRule>> {
+    match event {
+        UIEvent::WindowRules { rules } => Some(rules),
+        _ => None,
+    }
+}
+
+fn session_switched(event: UIEvent) -> Option<i64> {
+    match event {
+        UIEvent::SessionSwitched { task } => Some(task),
+        _ => None,
+    }
+}
+
+fn task_suggestion(event: UIEvent) -> Option<(agent::Task, WindowRule)> {
+    match event {
+        UIEvent::TaskSuggestion { task, rule } => Some((task, rule)),
+        _ => None,
+    }
+}
+
+#[test]
+fn window_rules_switch_or_suggest_tasks() {
+    let db = TestDb::with_settings(Settings {
+        window_rule_delay_seconds: 10,
+        ..Settings::default()
+    });
+    let agent = TestAgent::start(&db);
+    let website = start_session(&agent);
+    for name in ["Invoices", "Email"] {
+        agent.send(AgentCommand::AddTask {
+            task: common::new_task(name, 0),
+        });
+    }
+    let tasks = agent.wait_for(|event| task_list(event).filter(|tasks| tasks.len() == 3));
+    let (invoices, email) = (tasks[1].t_id, tasks[2].t_id);
+    for rule in [
+        WindowRule {
+            wr_pattern: "(?i)invoice".to_string(),
+            wr_task: invoices,
+            wr_action: RuleAction::Switch,
+            ..Default::default()
+        },
+        WindowRule {
+            wr_pattern: "thunderbird".to_string(),
+            wr_field: RuleField::Class,
+            wr_task: email,
+            ..Default::default()
+        },
+    ] {
+        agent.send(AgentCommand::AddWindowRule { rule });
+    }
+    let rules = agent.wait_for(|event| window_rules(event).filter(|rules| rules.len() == 2));
+    let focus = |title, class, seconds| {
+        agent.send(AgentCommand::ActiveWindow {
+            window: window(title, class),
+            time_stamp: agent.clock.now(),
+        });
+        agent.clock.advance(chrono::Duration::seconds(seconds));
+        agent.activity();
+    };
+    let switches = || {
+        agent.checked();
+        agent
+            .notified()
+            .into_iter()
+            .filter(|category| *category == Category::TaskSwitch)
+            .count()
+    };
+
+    // Passing through a window doesn't switch.
+    focus("Invoice 7.pdf", "evince", 5);
+    focus("Website - Firefox", "firefox", 10);
+    assert_eq!(switches(), 0);
+
+    focus("Invoice 7.pdf", "evince", 10);
+    assert_eq!(agent.wait_for(session_switched), invoices);
+    assert_eq!(switches(), 1);
+
+    focus("Inbox - Thunderbird", "thunderbird", 10);
+    let (task, rule) = agent.wait_for(task_suggestion);
+    assert_eq!((task.t_id, rule.wr_id), (email, rules[1].wr_id));
+    assert_eq!(switches(), 2);
+    agent.send(AgentCommand::AcceptSuggestion {
+        comment: "Sent the invoice".to_string(),
+        billable: false,
+    });
+    assert_eq!(agent.wait_for(session_switched), email);
+    agent.send(AgentCommand::EndSession {
+        comment: "".to_string(),
+        billable: true,
+    });
+    agent.quit();
+
+    let conn = storage::init_db(db.settings.clone()).unwrap();
+    let sessions = agent::sessions::get_all_sessions(&conn).unwrap();
+    let summary: Vec<(i64, Option<i64>)> = sessions
+        .iter()
+        .map(|session| (session.s_task, session.s_rule))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (website.t_id, None),
+            (invoices, Some(rules[0].wr_id)),
+            (email, Some(rules[1].wr_id)),
+        ]
+    );
+    assert_eq!(
+        sessions[0].s_comment,
+        "Switched to 'Invoices' by the window rule '(?i)invoice'"
+    );
+    assert_eq!(sessions[1].s_comment, "Sent the invoice");
+    assert!(!sessions[1].s_billable);
+}
+
+#[cfg(feature = "encryption")]
+fn locked(event: UIEvent) -> Option<(bool, Option<String>)> {
+    match event {
+        UIEvent::Locked {
+            new_passphrase,
+            error,
+        } => Some((new_passphrase, error)),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn locked_database_is_unlocked_with_its_passphrase() {
+    // No passphrase is remembered for a fresh database, so the user is asked.
+    let db = TestDb::with_settings(Settings {
+        encrypt_database: true,
+        ..Settings::default()
+    });
+    let agent = TestAgent::start(&db);
+    agent.send(AgentCommand::RequestTaskList);
+    assert_eq!(agent.wait_for(locked), (true, None));
+
+    agent.send(AgentCommand::Unlock {
+        passphrase: "secret".to_string(),
+        remember: false,
+    });
+    agent.wait_for(|event| matches!(event, UIEvent::Unlocked).then_some(()));
+    assert!(agent.wait_for(task_list).is_empty());
+    agent.send(AgentCommand::AddTask {
+        task: common::new_task("Private", 0),
+    });
+    assert_eq!(agent.wait_for(task_list).len(), 1);
+    agent.quit();
+
+    let agent = TestAgent::start(&db);
+    agent.send(AgentCommand::Unlock {
+        passphrase: "guess".to_string(),
+        remember: false,
+    });
+    let (new_passphrase, error) = agent.wait_for(locked);
+    assert!(!new_passphrase);
+    assert!(error.is_some());
+
+    agent.send(AgentCommand::Unlock {
+        passphrase: "secret".to_string(),
+        remember: false,
+    });
+    assert_eq!(agent.wait_for(task_list)[0].t_name, "Private");
+    agent.quit();
+}
+
+#[test]
+fn quit_notifies_every_listener() {
+    let db = TestDb::new();
+    let agent = TestAgent::start(&db);
+
+    agent.send(AgentCommand::Quit);
+    agent.wait_for(|event| matches!(event, UIEvent::Quit).then_some(()));
+    assert!(matches!(
+        agent.tray_rx.recv_timeout(TIMEOUT),
+        Ok(UIEvent::Quit)
+    ));
+    assert!(matches!(
+        agent.ui_control_rx.recv_timeout(TIMEOUT),
+        Ok(UIControl::Quit)
+    ));
+    assert!(matches!(
+        agent.sync_rx.recv_timeout(TIMEOUT),
+        Ok(sync::SyncCommand::Quit)
+    ));
+    agent.quit();
+}
+
+#[test]
+fn sync_now_is_forwarded_to_sync_worker() {
+    let db = TestDb::new();
+    let agent = TestAgent::start(&db);
+
+    agent.send(AgentCommand::SyncNow);
+    assert!(matches!(
+        agent.sync_rx.recv_timeout(TIMEOUT),
+        Ok(sync::SyncCommand::SyncNow)
+    ));
+    agent.quit();
+}
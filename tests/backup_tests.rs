@@ -0,0 +1,193 @@
+mod common;
+
+use std::{fs, path::PathBuf};
+
+use chrono::{NaiveDate, NaiveTime};
+use time_tracker::{
+    config::Settings,
+    storage::{
+        Repository, SqliteRepository, StorageError,
+        backup::{self, Backup},
+    },
+};
+
+use common::{TestDb, new_task};
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 1, day).unwrap()
+}
+
+fn task_names(repository: &dyn Repository) -> Vec<String> {
+    repository
+        .get_all_tasks()
+        .unwrap()
+        .into_iter()
+        .map(|task| task.t_name)
+        .collect()
+}
+
+#[test]
+fn backup_can_be_restored() {
+    let db = TestDb::new();
+    let mut repository = SqliteRepository::open(db.settings.clone(), common::clock()).unwrap();
+    repository.add_task(&new_task("Kept", 0)).unwrap();
+
+    let path = backup::back_up(&repository, db.backup_dir(), date(5)).unwrap();
+    assert_eq!(
+        backup::list_backups(db.backup_dir()).unwrap(),
+        vec![Backup {
+            path: path.clone(),
+            date: date(5),
+            before_restore: None,
+        }]
+    );
+
+    repository.add_task(&new_task("Lost", 0)).unwrap();
+    let now = chrono::Utc::now();
+    backup::restore(&mut repository, &db.settings, &path, now).unwrap();
+    assert_eq!(task_names(&repository), vec!["Kept"]);
+    assert!(repository.integrity_check().unwrap().is_empty());
+
+    // The data replaced by the restore is listed with the backups, so the
+    // restore can be undone.
+    let backups = backup::list_backups(db.backup_dir()).unwrap();
+    assert_eq!(backups.len(), 2);
+    let before_restore = backups
+        .iter()
+        .find(|backup| backup.before_restore.is_some())
+        .unwrap();
+    let later = now + chrono::Duration::minutes(1);
+    backup::restore(&mut repository, &db.settings, &before_restore.path, later).unwrap();
+    assert_eq!(task_names(&repository), vec!["Kept", "Lost"]);
+}
+
+#[test]
+fn damaged_backups_are_not_restored() {
+    let db = TestDb::new();
+    let mut repository = SqliteRepository::open(db.settings.clone(), common::clock()).unwrap();
+    for i in 0..200 {
+        repository
+            .add_task(&new_task(&format!("Task {}", i), 0))
+            .unwrap();
+    }
+    let path = backup::back_up(&repository, db.backup_dir(), date(5)).unwrap();
+
+    let mut bytes = fs::read(&path).unwrap();
+    bytes[4096..8192].fill(0xAB);
+    fs::write(&path, bytes).unwrap();
+
+    assert!(backup::validate_backup(&path, None).is_err());
+    assert!(backup::restore(&mut repository, &db.settings, &path, chrono::Utc::now()).is_err());
+    assert_eq!(repository.get_all_tasks().unwrap().len(), 200);
+}
+
+#[test]
+fn other_databases_are_not_restored() {
+    let db = TestDb::new();
+    fs::create_dir_all(db.backup_dir()).unwrap();
+    let path = db.backup_dir().join("sessions-2026-01-05.db");
+    rusqlite::Connection::open(&path)
+        .unwrap()
+        .execute_batch("CREATE TABLE notes (text TEXT)")
+        .unwrap();
+
+    assert!(matches!(
+        backup::validate_backup(&path, None),
+        Err(StorageError::Corrupt(_))
+    ));
+}
+
+#[test]
+fn retention_keeps_recent_days_and_weeks() {
+    // 2026-01-01 is a Thursday, so ISO weeks start on the 5th, 12th, 19th
+    // and 26th.
+    let backups: Vec<Backup> = (1..=30)
+        .map(|day| Backup {
+            path: PathBuf::from(format!("sessions-2026-01-{:02}.db", day)),
+            date: date(day),
+            before_restore: None,
+        })
+        .collect();
+
+    let expired: Vec<NaiveDate> = backup::expired_backups(&backups, 7, 4)
+        .into_iter()
+        .map(|backup| backup.date)
+        .collect();
+    let kept: Vec<NaiveDate> = backups
+        .iter()
+        .map(|backup| backup.date)
+        .filter(|date| !expired.contains(date))
+        .collect();
+    // The last seven days, plus the newest backup of the three weeks before.
+    assert_eq!(
+        kept,
+        vec![
+            date(11),
+            date(18),
+            date(24),
+            date(25),
+            date(26),
+            date(27),
+            date(28),
+            date(29),
+            date(30)
+        ]
+    );
+
+    assert!(backup::expired_backups(&backups, 30, 0).is_empty());
+    assert_eq!(backup::expired_backups(&backups, 0, 0).len(), 30);
+}
+
+#[test]
+fn retention_keeps_the_latest_copies_taken_before_a_restore() {
+    let backups: Vec<Backup> = (1..=3)
+        .map(|hour| Backup {
+            path: PathBuf::from(format!(
+                "sessions-2026-01-05-before-restore-{:02}0000.db",
+                hour
+            )),
+            date: date(5),
+            before_restore: NaiveTime::from_hms_opt(hour, 0, 0),
+        })
+        .collect();
+
+    let expired = backup::expired_backups(&backups, 2, 4);
+    assert_eq!(expired, vec![&backups[0]]);
+    // Even with daily backups turned off the last restore can be undone.
+    assert_eq!(backup::expired_backups(&backups, 0, 0).len(), 2);
+}
+
+#[test]
+fn scheduled_backup_is_taken_once_a_day() {
+    let db = TestDb::with_settings(Settings {
+        backup_keep_daily: 2,
+        backup_keep_weekly: 0,
+        ..Settings::default()
+    });
+    let repository = SqliteRepository::open(db.settings.clone(), common::clock()).unwrap();
+
+    assert!(backup::back_up_if_due(&repository, &db.settings, date(5)).unwrap());
+    assert!(!backup::back_up_if_due(&repository, &db.settings, date(5)).unwrap());
+    assert!(backup::back_up_if_due(&repository, &db.settings, date(6)).unwrap());
+    assert!(backup::back_up_if_due(&repository, &db.settings, date(7)).unwrap());
+
+    let dates: Vec<NaiveDate> = backup::list_backups(db.backup_dir())
+        .unwrap()
+        .into_iter()
+        .map(|backup| backup.date)
+        .collect();
+    assert_eq!(dates, vec![date(7), date(6)]);
+}
+
+#[test]
+fn scheduled_backups_can_be_turned_off() {
+    let db = TestDb::with_settings(Settings {
+        backup_keep_daily: 0,
+        backup_keep_weekly: 0,
+        ..Settings::default()
+    });
+    let repository = SqliteRepository::open(db.settings.clone(), common::clock()).unwrap();
+
+    assert!(!backup::back_up_if_due(&repository, &db.settings, date(5)).unwrap());
+    assert!(backup::list_backups(db.backup_dir()).unwrap().is_empty());
+}
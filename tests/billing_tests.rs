@@ -0,0 +1,207 @@
+mod common;
+
+use chrono::NaiveDate;
+use time_tracker::{
+    agent::{self, clients::Client, rates::Rate},
+    billing::{self, Rounding, RoundingMode},
+    storage::{MemoryRepository, Repository},
+};
+
+use common::{day, new_task};
+
+fn find_task(repository: &MemoryRepository, name: &str) -> agent::Task {
+    repository
+        .get_all_tasks()
+        .unwrap()
+        .into_iter()
+        .find(|task| task.t_name == name)
+        .unwrap()
+}
+
+/// A session of mine on `task`.
+fn session(task: i64, billable: bool) -> agent::Session {
+    agent::Session {
+        s_task: task,
+        s_user: "me".to_string(),
+        s_billable: billable,
+        ..Default::default()
+    }
+}
+
+/// Acme, billed in quarter hours rounded up, with a client rate that rises
+/// on the 15th and a task rate for "Support". "Internal" isn't billed to
+/// anyone and "Other" belongs to another client.
+fn repository() -> MemoryRepository {
+    let mut repository = MemoryRepository::new("me", common::clock());
+    for name in ["Acme", "Globex"] {
+        repository
+            .add_client(&Client {
+                cl_name: name.to_string(),
+                cl_currency: "EUR".to_string(),
+                cl_rounding: Rounding {
+                    mode: RoundingMode::Up,
+                    minutes: 15,
+                },
+                ..Default::default()
+            })
+            .unwrap();
+    }
+    let clients = repository.get_all_clients().unwrap();
+    let (acme, globex) = (clients[0].cl_id, clients[1].cl_id);
+
+    for (name, client) in [
+        ("Website", Some(acme)),
+        ("Support", Some(acme)),
+        ("Hosting", Some(acme)),
+        ("Internal", None),
+        ("Other", Some(globex)),
+    ] {
+        repository
+            .add_task(&agent::Task {
+                t_client: client,
+                ..new_task(name, 0)
+            })
+            .unwrap();
+    }
+    let website = find_task(&repository, "Website").t_id;
+    let support = find_task(&repository, "Support").t_id;
+    let internal = find_task(&repository, "Internal").t_id;
+    let other = find_task(&repository, "Other").t_id;
+
+    for (client, task, cents, from) in [
+        (Some(acme), None, 8000, day(1)),
+        (Some(acme), None, 10000, day(15)),
+        (None, Some(support), 6000, day(1)),
+    ] {
+        repository
+            .add_rate(&Rate {
+                r_client: client,
+                r_task: task,
+                r_hourly_cents: cents,
+                r_effective_from: from,
+                ..Default::default()
+            })
+            .unwrap();
+    }
+
+    common::save_session(&mut repository, 10, 50, session(website, true));
+    common::save_session(&mut repository, 16, 20, session(website, true));
+    common::save_session(&mut repository, 16, 10, session(website, false));
+    common::save_session(&mut repository, 16, 30, session(support, true));
+    common::save_session(&mut repository, 16, 30, session(internal, true));
+    common::save_session(&mut repository, 16, 30, session(other, true));
+    common::save_session(&mut repository, 31, 60, session(website, true));
+    repository
+}
+
+#[test]
+fn sessions_are_rounded() {
+    let quarter = |mode| Rounding { mode, minutes: 15 };
+    assert_eq!(quarter(RoundingMode::Up).apply(60), 15 * 60);
+    assert_eq!(quarter(RoundingMode::Up).apply(15 * 60), 15 * 60);
+    assert_eq!(quarter(RoundingMode::Nearest).apply(7 * 60), 0);
+    assert_eq!(quarter(RoundingMode::Nearest).apply(8 * 60), 15 * 60);
+    assert_eq!(quarter(RoundingMode::Down).apply(29 * 60), 15 * 60);
+    assert_eq!(Rounding::default().apply(61), 61);
+    assert_eq!("nearest".parse(), Ok(RoundingMode::Nearest));
+    assert!("sideways".parse::<RoundingMode>().is_err());
+}
+
+#[test]
+fn task_rates_win_over_client_rates() {
+    let repository = repository();
+    let rates = repository.get_all_rates().unwrap();
+    let rate = |name, date| {
+        billing::rate_on(&rates, &find_task(&repository, name), date)
+            .map(|rate| rate.r_hourly_cents)
+    };
+
+    assert_eq!(rate("Website", day(14)), Some(8000));
+    assert_eq!(rate("Website", day(15)), Some(10000));
+    assert_eq!(rate("Support", day(20)), Some(6000));
+    assert_eq!(rate("Internal", day(20)), None);
+    assert_eq!(rate("Other", day(20)), None);
+    assert_eq!(
+        rate("Website", NaiveDate::from_ymd_opt(2023, 12, 31).unwrap()),
+        None
+    );
+}
+
+#[test]
+fn invoice_draft_totals_billable_time() {
+    let repository = repository();
+    let acme = repository.get_all_clients().unwrap()[0].cl_id;
+    let draft = billing::invoice_draft(&repository, acme, day(1), day(30)).unwrap();
+
+    let lines: Vec<_> = draft
+        .lines
+        .iter()
+        .map(|line| {
+            (
+                line.task.as_str(),
+                line.sessions,
+                line.seconds / 60,
+                line.hourly_cents,
+                line.amount_cents,
+            )
+        })
+        .collect();
+    assert_eq!(
+        lines,
+        vec![
+            ("Support", 1, 30, Some(6000), 3000),
+            ("Website", 1, 60, Some(8000), 8000),
+            ("Website", 1, 30, Some(10000), 5000),
+        ]
+    );
+    assert_eq!(draft.total_seconds(), 120 * 60);
+    assert_eq!(draft.total_cents(), 16000);
+    assert!(draft.unpriced_tasks().is_empty());
+
+    let csv = billing::to_csv(&draft).unwrap();
+    assert_eq!(
+        csv,
+        "Task,Hours,Hourly rate,Amount,Currency\n\
+         Support,0.50,60.00,30.00,EUR\n\
+         Website,1.00,80.00,80.00,EUR\n\
+         Website,0.50,100.00,50.00,EUR\n\
+         Total,2.00,,160.00,EUR\n"
+    );
+}
+
+#[test]
+fn time_without_a_rate_is_listed_but_not_charged() {
+    let mut repository = repository();
+    let acme = repository.get_all_clients().unwrap()[0].cl_id;
+    let hosting = find_task(&repository, "Hosting").t_id;
+    let first_rate = repository.get_all_rates().unwrap()[0].r_id;
+    repository.delete_rate(first_rate).unwrap();
+    common::save_session(&mut repository, 12, 45, session(hosting, true));
+
+    let draft = billing::invoice_draft(&repository, acme, day(10), day(12)).unwrap();
+    assert_eq!(draft.unpriced_tasks(), vec!["Hosting", "Website"]);
+    assert_eq!(draft.total_cents(), 0);
+    assert!(billing::invoice_draft(&repository, 99, day(1), day(31)).is_err());
+}
+
+#[test]
+fn amounts_are_formatted_and_parsed() {
+    assert_eq!(billing::amount_cents(20 * 60, 8550), 2850);
+    assert_eq!(billing::format_money(123_405, "EUR"), "1234.05 EUR");
+    assert_eq!(billing::format_decimal_hours(5400), "1.50");
+    assert_eq!(billing::parse_amount("85"), Some(8500));
+    assert_eq!(billing::parse_amount(" 85,5 "), Some(8550));
+    assert_eq!(billing::parse_amount("85.05"), Some(8505));
+    for invalid in ["", ".5", "85.505", "-1", "eighty"] {
+        assert_eq!(billing::parse_amount(invalid), None, "{}", invalid);
+    }
+
+    let client = Client {
+        cl_name: "Acme & Co".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(
+        billing::default_file_name(&client, day(1), day(31)),
+        "invoice-acme---co-2024-01-01-2024-01-31.csv"
+    );
+}
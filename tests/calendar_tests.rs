@@ -0,0 +1,173 @@
+mod common;
+
+use std::{
+    fs,
+    io::{Read, Write},
+    net::TcpStream,
+    sync::mpsc,
+    thread,
+};
+
+use time_tracker::{
+    agent::{self, AgentCommand},
+    calendar::{self, feed::CalendarFeed},
+    storage::{MemoryRepository, Repository},
+    sync,
+};
+
+use common::{day, new_task};
+
+/// A repository with one task and a session ending at noon UTC on each of
+/// the given days.
+fn repository_with_sessions(days: &[u32], comment: &str) -> MemoryRepository {
+    let mut repository = MemoryRepository::new("me", common::clock());
+    repository.add_task(&new_task("Website, v2", 0)).unwrap();
+    let task = repository.get_all_tasks().unwrap()[0].t_id;
+    for day in days {
+        let session = agent::Session {
+            s_task: task,
+            s_user: "me".to_string(),
+            s_comment: comment.to_string(),
+            ..Default::default()
+        };
+        common::save_session(&mut repository, *day, 90, session);
+    }
+    repository
+}
+
+/// Joins folded lines back together.
+fn unfold(ics: &str) -> String {
+    ics.replace("\r\n ", "")
+}
+
+#[test]
+fn sessions_become_events() {
+    let repository = repository_with_sessions(&[10, 15, 20], "Header;\nfooter");
+    let ics = calendar::export(&repository, "me", day(14), day(16)).unwrap();
+
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+    assert!(ics.contains("\r\nDTSTART:20240115T103000Z\r\n"));
+    assert!(ics.contains("\r\nDTEND:20240115T120000Z\r\n"));
+    assert!(ics.contains("\r\nSUMMARY:Website\\, v2\r\n"));
+    assert!(ics.contains("\r\nDESCRIPTION:Header\\;\\nfooter\r\n"));
+}
+
+#[test]
+fn only_the_users_sessions_are_exported() {
+    let mut repository = repository_with_sessions(&[15], "Mine");
+    let colleague = agent::users::User {
+        u_id: "colleague".to_string(),
+        u_name: "Colleague".to_string(),
+    };
+    repository.ensure_user(&colleague).unwrap();
+    let session = repository.get_all_sessions().unwrap().remove(0);
+    repository
+        .save_session(&agent::Session {
+            s_user: colleague.u_id,
+            s_comment: "Theirs".to_string(),
+            ..session
+        })
+        .unwrap();
+
+    let ics = calendar::export(&repository, "me", day(15), day(15)).unwrap();
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+    assert!(ics.contains("DESCRIPTION:Mine"));
+}
+
+#[test]
+fn long_lines_are_folded() {
+    let comment = "Überarbeitung der Startseite ".repeat(10);
+    let repository = repository_with_sessions(&[15], &comment);
+    let ics = calendar::export(&repository, "me", day(15), day(15)).unwrap();
+
+    assert!(ics.split("\r\n").all(|line| line.len() <= 75));
+    assert!(unfold(&ics).contains(&format!("DESCRIPTION:{}\r\n", comment)));
+}
+
+#[test]
+fn feed_serves_the_calendar() {
+    assert!(CalendarFeed::bind(0, String::new()).is_err());
+    let feed = CalendarFeed::bind(0, "secret".to_string()).unwrap();
+    let addr = feed.local_addr().unwrap();
+    let url = format!("http://{}", addr);
+    assert_eq!(
+        feed.url().unwrap(),
+        format!("http://localhost:{}/calendar.ics?token=secret", addr.port())
+    );
+    let feed_url = format!("{}{}?token=secret", url, calendar::feed::FEED_PATH);
+    let (command_tx, command_rx) = mpsc::channel();
+    feed.serve(command_tx);
+
+    // Stands in for the agent: answers once, then ignores the request.
+    thread::spawn(move || {
+        let repository = repository_with_sessions(&[15], "");
+        let mut answered = false;
+        for command in command_rx {
+            if let AgentCommand::CalendarFeed { reply } = command
+                && !answered
+            {
+                let ics = calendar::export(&repository, "me", day(1), day(31)).unwrap();
+                reply.send(ics).unwrap();
+                answered = true;
+            }
+        }
+    });
+
+    let http = sync::engine::http_agent();
+    for wrong in ["", "?token=guess", "?token="] {
+        let refused = http
+            .get(&format!("{}{}{}", url, calendar::feed::FEED_PATH, wrong))
+            .call();
+        assert!(matches!(refused, Err(ureq::Error::StatusCode(403))));
+    }
+
+    // A web page reaching the feed through its own domain names that
+    // domain as the host.
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "GET {}?token=secret HTTP/1.1\r\nHost: attacker.example:{}\r\nConnection: close\r\n\r\n",
+        calendar::feed::FEED_PATH,
+        addr.port()
+    )
+    .unwrap();
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).unwrap();
+    assert!(reply.starts_with("HTTP/1.1 403"));
+
+    let mut response = http.get(&feed_url).call().unwrap();
+    assert!(
+        response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/calendar")
+    );
+    let ics = response.body_mut().read_to_string().unwrap();
+    assert!(ics.contains("SUMMARY:Website\\, v2"));
+
+    let unavailable = http.get(&feed_url).call();
+    assert!(matches!(unavailable, Err(ureq::Error::StatusCode(503))));
+    let missing = http.get(&format!("{}/other", url)).call();
+    assert!(matches!(missing, Err(ureq::Error::StatusCode(404))));
+}
+
+#[test]
+fn feed_token_is_kept_private() {
+    let dir = std::env::temp_dir().join(format!("time_tracker_feed_{}", uuid::Uuid::new_v4()));
+    let token = calendar::feed::load_token(&dir).unwrap();
+    assert_eq!(token.len(), 32);
+    assert_eq!(calendar::feed::load_token(&dir).unwrap(), token);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(dir.join("calendar_feed.token"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,391 @@
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use chrono::{NaiveDate, TimeZone, Utc};
+use time_tracker::{agent, billing, config::Settings, storage::Repository};
+
+/// Settings pointing at a throwaway database and backup folder that are
+/// removed on drop.
+pub struct TestDb {
+    pub settings: Arc<Settings>,
+    path: PathBuf,
+}
+
+impl TestDb {
+    pub fn new() -> Self {
+        Self::with_settings(Settings::default())
+    }
+
+    pub fn with_settings(settings: Settings) -> Self {
+        let name = format!("time_tracker_test_{}", uuid::Uuid::new_v4());
+        let path = std::env::temp_dir().join(format!("{}.db", name));
+        let settings = Settings {
+            local_database_path: path.to_string_lossy().to_string(),
+            backup_path: std::env::temp_dir()
+                .join(format!("{}-backups", name))
+                .to_string_lossy()
+                .to_string(),
+            open_ui_at_start_up: false,
+            ..settings
+        };
+        Self {
+            settings: Arc::new(settings),
+            path,
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.settings.local_database_path
+    }
+
+    pub fn backup_dir(&self) -> &Path {
+        Path::new(&self.settings.backup_path)
+    }
+}
+
+/// The clock repositories are opened with outside the agent tests.
+pub fn clock() -> Arc<dyn agent::Clock> {
+    Arc::new(agent::SystemClock)
+}
+
+/// The current time in Unix milliseconds, as the SQLite functions take it.
+pub fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+impl Drop for TestDb {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let mut path = self.path.clone().into_os_string();
+            path.push(suffix);
+            let _ = fs::remove_file(path);
+        }
+        let _ = fs::remove_dir_all(self.backup_dir());
+    }
+}
+
+pub fn new_task(name: &str, priority: usize) -> agent::Task {
+    agent::Task {
+        t_name: name.to_string(),
+        t_priority: priority,
+        ..Default::default()
+    }
+}
+
+/// A day of January 2024, the month the report, billing and calendar tests
+/// work in.
+pub fn day(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+}
+
+/// Saves `session` as lasting `minutes` and ending at noon UTC on `day` of
+/// January 2024.
+pub fn save_session(
+    repository: &mut dyn Repository,
+    day: u32,
+    minutes: u64,
+    session: agent::Session,
+) {
+    repository
+        .save_session(&agent::Session {
+            s_duration: minutes * 60,
+            s_created_at: Utc
+                .with_ymd_and_hms(2024, 1, day, 12, 0, 0)
+                .unwrap()
+                .timestamp_millis(),
+            ..session
+        })
+        .unwrap();
+}
+
+/// Behaviour every `Repository` implementation has to share.
+pub fn check_repository(repository: &mut dyn Repository, uid: &str) {
+    repository.add_task(&new_task("First", 0)).unwrap();
+    repository.add_task(&new_task("Second", 2)).unwrap();
+    let tasks = repository.get_all_tasks().unwrap();
+    assert_eq!(tasks.len(), 2);
+    assert_ne!(tasks[0].t_uuid, tasks[1].t_uuid);
+    let first = tasks[0].clone();
+
+    repository
+        .update_task(&agent::Task {
+            t_priority: 1,
+            ..first.clone()
+        })
+        .unwrap();
+    let updated = repository.get_task(first.t_id).unwrap().unwrap();
+    assert_eq!(updated.t_priority, 1);
+    assert!(updated.t_priority_hlc > first.t_priority_hlc);
+    assert_eq!(updated.t_name_hlc, first.t_name_hlc);
+
+    repository.delete_task(tasks[1].t_id).unwrap();
+    assert_eq!(repository.get_all_tasks().unwrap().len(), 1);
+    assert!(
+        repository
+            .get_task(tasks[1].t_id)
+            .unwrap()
+            .unwrap()
+            .t_deleted
+    );
+
+    repository
+        .save_session(&agent::Session {
+            s_task: first.t_id,
+            s_user: uid.to_string(),
+            s_duration: 300,
+            s_comment: "Worked".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+    let sessions = repository.get_all_sessions().unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].s_duration, 300);
+    assert!(!sessions[0].s_uuid.is_empty());
+    let session = repository.get_session(sessions[0].s_id).unwrap().unwrap();
+    assert_eq!(session.s_comment, "Worked");
+
+    let unknown_task = repository.save_session(&agent::Session {
+        s_task: 999,
+        s_user: uid.to_string(),
+        ..Default::default()
+    });
+    assert!(unknown_task.is_err());
+
+    let colleague = agent::users::User {
+        u_id: "colleague".to_string(),
+        u_name: "Colleague".to_string(),
+    };
+    repository.ensure_user(&colleague).unwrap();
+    repository.ensure_user(&colleague).unwrap();
+    let users = repository.get_all_users().unwrap();
+    assert_eq!(users.len(), 2);
+    assert!(users.iter().any(|user| user.u_id == uid));
+
+    assert!(repository.get_unresolved_conflicts().unwrap().is_empty());
+
+    check_billing(repository, uid, &first);
+    check_estimates(repository, &first);
+    check_window_rules(repository, uid, &first);
+    check_import(repository, uid);
+}
+
+fn check_billing(repository: &mut dyn Repository, uid: &str, task: &agent::Task) {
+    repository
+        .add_client(&agent::clients::Client {
+            cl_name: "Acme".to_string(),
+            cl_currency: "EUR".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+    let client = repository.get_all_clients().unwrap().remove(0);
+    assert_eq!(client.cl_name, "Acme");
+    let rounded = agent::clients::Client {
+        cl_rounding: billing::Rounding {
+            mode: billing::RoundingMode::Nearest,
+            minutes: 15,
+        },
+        ..client.clone()
+    };
+    repository.update_client(&rounded).unwrap();
+    assert_eq!(repository.get_all_clients().unwrap(), vec![rounded]);
+
+    repository
+        .update_task(&agent::Task {
+            t_client: Some(client.cl_id),
+            ..task.clone()
+        })
+        .unwrap();
+    let assigned = repository.get_task(task.t_id).unwrap().unwrap();
+    assert_eq!(assigned.t_client, Some(client.cl_id));
+    assert_eq!(assigned.t_name_hlc, task.t_name_hlc);
+
+    let day = |day| chrono::NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+    for rate in [
+        agent::rates::Rate {
+            r_task: Some(task.t_id),
+            r_hourly_cents: 12000,
+            r_effective_from: day(15),
+            ..Default::default()
+        },
+        agent::rates::Rate {
+            r_client: Some(client.cl_id),
+            r_hourly_cents: 9000,
+            r_effective_from: day(1),
+            ..Default::default()
+        },
+    ] {
+        repository.add_rate(&rate).unwrap();
+    }
+    let rates = repository.get_all_rates().unwrap();
+    assert_eq!(rates.len(), 2);
+    assert_eq!(rates[0].r_effective_from, day(1));
+    assert_eq!(rates[0].r_client, Some(client.cl_id));
+    assert_eq!(rates[1].r_task, Some(task.t_id));
+    let neither = repository.add_rate(&agent::rates::Rate {
+        r_hourly_cents: 1,
+        ..Default::default()
+    });
+    assert!(neither.is_err());
+    repository.delete_rate(rates[0].r_id).unwrap();
+    assert_eq!(repository.get_all_rates().unwrap().len(), 1);
+
+    repository
+        .save_session(&agent::Session {
+            s_task: task.t_id,
+            s_user: uid.to_string(),
+            s_duration: 60,
+            s_billable: false,
+            s_pomodoros: 2,
+            ..Default::default()
+        })
+        .unwrap();
+    let sessions = repository.get_all_sessions().unwrap();
+    assert!(sessions[0].s_billable);
+    assert!(!sessions.last().unwrap().s_billable);
+    assert_eq!(sessions.last().unwrap().s_pomodoros, 2);
+}
+
+fn check_estimates(repository: &mut dyn Repository, task: &agent::Task) {
+    let current = repository.get_task(task.t_id).unwrap().unwrap();
+    assert_eq!((current.t_estimate, current.t_budget), (None, None));
+    repository
+        .update_task(&agent::Task {
+            t_estimate: Some(3600),
+            t_budget: Some(5400),
+            ..current.clone()
+        })
+        .unwrap();
+    let estimated = repository.get_task(task.t_id).unwrap().unwrap();
+    assert_eq!(estimated.t_estimate, Some(3600));
+    assert_eq!(estimated.t_budget, Some(5400));
+    assert_eq!(estimated.t_name_hlc, current.t_name_hlc);
+    assert_eq!(estimated.t_updated_at, current.t_updated_at);
+
+    repository
+        .update_task(&agent::Task {
+            t_daily_goal: Some(1800),
+            t_weekly_goal: Some(7200),
+            ..estimated.clone()
+        })
+        .unwrap();
+    let with_goals = repository.get_task(task.t_id).unwrap().unwrap();
+    assert_eq!(
+        (with_goals.t_daily_goal, with_goals.t_weekly_goal),
+        (Some(1800), Some(7200))
+    );
+    assert_eq!(with_goals.t_estimate, Some(3600));
+    assert_eq!(with_goals.t_updated_at, current.t_updated_at);
+
+    let tracked: u64 = repository
+        .get_all_sessions()
+        .unwrap()
+        .iter()
+        .filter(|session| session.s_task == task.t_id)
+        .map(|session| session.s_duration)
+        .sum();
+    assert!(tracked > 0);
+    assert_eq!(
+        repository.get_tracked_seconds().unwrap().get(&task.t_id),
+        Some(&tracked)
+    );
+}
+
+fn check_import(repository: &mut dyn Repository, uid: &str) {
+    let session = |user: &str| agent::Session {
+        s_user: user.to_string(),
+        s_duration: 60,
+        s_created_at: 1_700_000_000_000,
+        s_billable: false,
+        ..Default::default()
+    };
+    let count = |repository: &dyn Repository| {
+        (
+            repository.get_all_tasks().unwrap().len(),
+            repository.get_all_sessions().unwrap().len(),
+        )
+    };
+    let (tasks, sessions) = count(repository);
+
+    repository
+        .import_sessions(&[
+            ("First".to_string(), session(uid)),
+            ("Imported".to_string(), session(uid)),
+            ("Imported".to_string(), session(uid)),
+        ])
+        .unwrap();
+    assert_eq!(count(repository), (tasks + 1, sessions + 3));
+    let imported = repository.get_all_sessions().unwrap().pop().unwrap();
+    assert_eq!(imported.s_created_at, 1_700_000_000_000);
+    assert!(!imported.s_billable);
+    let task = repository.get_task(imported.s_task).unwrap().unwrap();
+    assert_eq!(task.t_name, "Imported");
+
+    // The second session's user doesn't exist, so the first one and the task
+    // added for it are rolled back too.
+    let failed = repository.import_sessions(&[
+        ("Rolled back".to_string(), session(uid)),
+        ("Rolled back".to_string(), session("nobody")),
+    ]);
+    assert!(failed.is_err());
+    assert_eq!(count(repository), (tasks + 1, sessions + 3));
+}
+
+fn check_window_rules(repository: &mut dyn Repository, uid: &str, task: &agent::Task) {
+    use agent::window_rules::{RuleAction, RuleField, WindowRule};
+
+    for rule in [
+        WindowRule {
+            wr_pattern: "(?i)invoice".to_string(),
+            wr_task: task.t_id,
+            ..Default::default()
+        },
+        WindowRule {
+            wr_pattern: "^code$".to_string(),
+            wr_field: RuleField::Class,
+            wr_task: task.t_id,
+            wr_action: RuleAction::Switch,
+            ..Default::default()
+        },
+    ] {
+        repository.add_window_rule(&rule).unwrap();
+    }
+    let rules = repository.get_all_window_rules().unwrap();
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0].wr_pattern, "(?i)invoice");
+    assert_eq!(
+        (rules[0].wr_field, rules[0].wr_action),
+        (RuleField::Title, RuleAction::Suggest)
+    );
+    assert_eq!(
+        (rules[1].wr_field, rules[1].wr_action),
+        (RuleField::Class, RuleAction::Switch)
+    );
+    let unknown_task = repository.add_window_rule(&WindowRule {
+        wr_pattern: "x".to_string(),
+        wr_task: 999,
+        ..Default::default()
+    });
+    assert!(unknown_task.is_err());
+
+    repository
+        .save_session(&agent::Session {
+            s_task: task.t_id,
+            s_user: uid.to_string(),
+            s_duration: 60,
+            s_rule: Some(rules[1].wr_id),
+            ..Default::default()
+        })
+        .unwrap();
+    let switched = repository.get_all_sessions().unwrap().pop().unwrap();
+    assert_eq!(switched.s_rule, Some(rules[1].wr_id));
+
+    repository.delete_window_rule(rules[1].wr_id).unwrap();
+    assert_eq!(repository.get_all_window_rules().unwrap().len(), 1);
+    let kept = repository.get_session(switched.s_id).unwrap().unwrap();
+    assert_eq!(kept.s_rule, None);
+}
@@ -0,0 +1,144 @@
+//! Needs a build with the `encryption` feature, which links SQLCipher.
+#![cfg(feature = "encryption")]
+
+mod common;
+
+use std::{fs, io::Read, path::Path, sync::Arc};
+
+use rusqlite::Connection;
+use time_tracker::{
+    config::Settings,
+    storage::{Repository, SqliteRepository, StorageError, backup},
+};
+
+use common::{TestDb, new_task};
+
+fn encrypted_db(passphrase: &str) -> TestDb {
+    TestDb::with_settings(Settings {
+        encrypt_database: true,
+        database_passphrase: Some(passphrase.to_string()),
+        ..Settings::default()
+    })
+}
+
+fn with_passphrase(settings: &Settings, passphrase: Option<&str>) -> Arc<Settings> {
+    Arc::new(Settings {
+        database_passphrase: passphrase.map(str::to_string),
+        ..settings.clone()
+    })
+}
+
+fn is_plaintext(path: &Path) -> bool {
+    let mut header = [0; 16];
+    fs::File::open(path)
+        .unwrap()
+        .read_exact(&mut header)
+        .unwrap();
+    &header == b"SQLite format 3\0"
+}
+
+fn user_version(path: &str, passphrase: Option<&str>) -> usize {
+    let conn = Connection::open(path).unwrap();
+    if let Some(passphrase) = passphrase {
+        conn.pragma_update(None, "key", passphrase).unwrap();
+    }
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+        .unwrap()
+}
+
+#[test]
+fn encrypted_database_needs_its_passphrase() {
+    let db = encrypted_db("secret");
+    let mut repository = SqliteRepository::open(db.settings.clone(), common::clock()).unwrap();
+    repository.add_task(&new_task("Private", 0)).unwrap();
+    drop(repository);
+
+    assert!(!is_plaintext(Path::new(db.path())));
+    let conn = Connection::open(db.path()).unwrap();
+    assert!(
+        conn.query_row("SELECT count(*) FROM tasks", [], |_| Ok(()))
+            .is_err()
+    );
+
+    let wrong = SqliteRepository::open(
+        with_passphrase(&db.settings, Some("guess")),
+        common::clock(),
+    );
+    assert!(matches!(wrong, Err(StorageError::WrongPassphrase)));
+
+    let repository = SqliteRepository::open(db.settings.clone(), common::clock()).unwrap();
+    assert_eq!(repository.get_all_tasks().unwrap()[0].t_name, "Private");
+}
+
+#[test]
+fn plaintext_database_is_encrypted_once() {
+    let db = TestDb::new();
+    let mut repository = SqliteRepository::open(db.settings.clone(), common::clock()).unwrap();
+    repository.add_task(&new_task("Existing", 0)).unwrap();
+    drop(repository);
+    let version = user_version(db.path(), None);
+
+    let encrypted = Arc::new(Settings {
+        encrypt_database: true,
+        database_passphrase: Some("secret".to_string()),
+        ..(*db.settings).clone()
+    });
+    let repository = SqliteRepository::open(encrypted.clone(), common::clock()).unwrap();
+    assert_eq!(repository.get_all_tasks().unwrap()[0].t_name, "Existing");
+    drop(repository);
+
+    assert!(!is_plaintext(Path::new(db.path())));
+    assert_eq!(user_version(db.path(), Some("secret")), version);
+    assert!(!Path::new(db.path()).with_extension("encrypting").exists());
+
+    // Already encrypted, so opening again doesn't touch the file.
+    let repository = SqliteRepository::open(encrypted, common::clock()).unwrap();
+    assert_eq!(repository.get_all_tasks().unwrap().len(), 1);
+}
+
+#[test]
+fn earlier_backups_are_encrypted_with_the_database() {
+    let db = TestDb::new();
+    let mut repository = SqliteRepository::open(db.settings.clone(), common::clock()).unwrap();
+    repository.add_task(&new_task("Kept", 0)).unwrap();
+    let today = chrono::Local::now().date_naive();
+    let path = backup::back_up(&repository, db.backup_dir(), today).unwrap();
+    drop(repository);
+    assert!(is_plaintext(&path));
+
+    let encrypted = Arc::new(Settings {
+        encrypt_database: true,
+        database_passphrase: Some("secret".to_string()),
+        ..(*db.settings).clone()
+    });
+    let mut repository = SqliteRepository::open(encrypted.clone(), common::clock()).unwrap();
+    assert!(!is_plaintext(&path));
+    assert!(backup::validate_backup(&path, Some("secret")).is_ok());
+
+    repository.add_task(&new_task("Lost", 0)).unwrap();
+    backup::restore(&mut repository, &encrypted, &path, chrono::Utc::now()).unwrap();
+    assert_eq!(repository.get_all_tasks().unwrap()[0].t_name, "Kept");
+    assert_eq!(repository.get_all_tasks().unwrap().len(), 1);
+}
+
+#[test]
+fn backups_are_encrypted_with_the_database() {
+    let db = encrypted_db("secret");
+    let mut repository = SqliteRepository::open(db.settings.clone(), common::clock()).unwrap();
+    repository.add_task(&new_task("Kept", 0)).unwrap();
+
+    let today = chrono::Local::now().date_naive();
+    let path = backup::back_up(&repository, db.backup_dir(), today).unwrap();
+    assert!(!is_plaintext(&path));
+    assert!(backup::validate_backup(&path, Some("secret")).is_ok());
+    assert!(matches!(
+        backup::validate_backup(&path, Some("guess")),
+        Err(StorageError::WrongPassphrase)
+    ));
+
+    repository.add_task(&new_task("Lost", 0)).unwrap();
+    backup::restore(&mut repository, &db.settings, &path, chrono::Utc::now()).unwrap();
+    let tasks = repository.get_all_tasks().unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].t_name, "Kept");
+}
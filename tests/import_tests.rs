@@ -0,0 +1,203 @@
+mod common;
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use time_tracker::{
+    import::{self, Entry, Format, ImportError, Unmapped},
+    storage::{Repository, SqliteRepository},
+};
+
+use common::{TestDb, new_task};
+
+const TOGGL_CSV: &str = "\u{feff}User,Email,Client,Project,Task,Description,Billable,Start date,Start time,End date,End time,Duration,Tags,Amount ()
+Ada,ada@example.com,Acme,Website,Design,Landing page,Yes,2024-01-15,09:00:00,2024-01-15,10:30:00,01:30:00,,
+Ada,ada@example.com,Acme,Website,,Standup,No,2024-01-15,11:00:00,2024-01-15,11:15:00,00:15:00,,
+Ada,ada@example.com,,,,Lunch,No,2024-01-15,12:00:00,2024-01-15,13:00:00,01:00:00,,
+Ada,ada@example.com,Acme,Website,,Broken,No,2024-01-15,soon,2024-01-15,13:00:00,,,
+";
+
+const CLOCKIFY_CSV: &str = "Project,Client,Description,Task,User,Group,Email,Tags,Billable,Start Date,Start Time,End Date,End Time,Duration (h),Duration (decimal)
+Backend,Acme,Night shift,,Ada,,ada@example.com,,Yes,01/15/2024,11:00 PM,01/16/2024,01:00 AM,02:00:00,2.00
+";
+
+const TIMEWARRIOR_JSON: &str = r#"[
+{"id":3,"start":"20240115T080000Z","end":"20240115T090000Z","tags":["Website","review"]},
+{"id":2,"start":"20240115T100000Z","end":"20240115T103000Z","tags":["Website"],"annotation":"Fixed the footer"},
+{"id":1,"start":"20240115T110000Z","end":"20240115T120000Z"},
+{"id":0,"start":"20240115T130000Z","tags":["Website"]}
+]"#;
+
+fn local(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+    let time = NaiveDate::from_ymd_opt(2024, 1, day)
+        .unwrap()
+        .and_hms_opt(hour, minute, 0)
+        .unwrap();
+    chrono::Local
+        .from_local_datetime(&time)
+        .earliest()
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+fn utc(hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 15, hour, minute, 0).unwrap()
+}
+
+#[test]
+fn toggl_report_is_read() {
+    let export = import::parse(Format::Toggl, TOGGL_CSV.as_bytes()).unwrap();
+    assert_eq!(
+        export.entries,
+        vec![
+            Entry {
+                task: "Website / Design".to_string(),
+                description: "Landing page".to_string(),
+                start: local(15, 9, 0),
+                end: local(15, 10, 30),
+                billable: true,
+            },
+            Entry {
+                task: "Website".to_string(),
+                description: "Standup".to_string(),
+                start: local(15, 11, 0),
+                end: local(15, 11, 15),
+                billable: false,
+            },
+        ]
+    );
+    assert_eq!(
+        export.unmapped,
+        vec![
+            Unmapped {
+                row: 4,
+                reason: "no project".to_string()
+            },
+            Unmapped {
+                row: 5,
+                reason: "unreadable start or end time".to_string()
+            },
+        ]
+    );
+}
+
+#[test]
+fn clockify_report_is_read() {
+    let export = import::parse(Format::Clockify, CLOCKIFY_CSV.as_bytes()).unwrap();
+    assert!(export.unmapped.is_empty());
+    assert_eq!(
+        export.entries,
+        vec![Entry {
+            task: "Backend".to_string(),
+            description: "Night shift".to_string(),
+            start: local(15, 23, 0),
+            end: local(16, 1, 0),
+            billable: true,
+        }]
+    );
+}
+
+#[test]
+fn timewarrior_export_is_read() {
+    let export = import::parse(Format::Timewarrior, TIMEWARRIOR_JSON.as_bytes()).unwrap();
+    assert_eq!(
+        export.entries,
+        vec![
+            Entry {
+                task: "Website".to_string(),
+                description: "review".to_string(),
+                start: utc(8, 0),
+                end: utc(9, 0),
+                billable: true,
+            },
+            Entry {
+                task: "Website".to_string(),
+                description: "Fixed the footer".to_string(),
+                start: utc(10, 0),
+                end: utc(10, 30),
+                billable: true,
+            },
+        ]
+    );
+    let reasons: Vec<&str> = export
+        .unmapped
+        .iter()
+        .map(|unmapped| unmapped.reason.as_str())
+        .collect();
+    assert_eq!(reasons, vec!["no tags", "still running"]);
+}
+
+#[test]
+fn export_without_times_is_rejected() {
+    let result = import::parse(
+        Format::Toggl,
+        "Project,Description\nWebsite,Design\n".as_bytes(),
+    );
+    assert!(matches!(result, Err(ImportError::MissingColumn(column)) if column == "Start date"));
+    assert!(import::parse(Format::Timewarrior, "not json".as_bytes()).is_err());
+}
+
+#[test]
+fn dry_run_only_reports() {
+    let db = TestDb::new();
+    let mut repository = SqliteRepository::open(db.settings.clone(), common::clock()).unwrap();
+    let export = import::parse(Format::Toggl, TOGGL_CSV.as_bytes()).unwrap();
+
+    let report = import::import(&mut repository, &db.settings.uid, export, true).unwrap();
+    assert!(report.dry_run);
+    assert_eq!(report.imported, 2);
+    assert_eq!(report.new_tasks, vec!["Website / Design", "Website"]);
+    assert_eq!(report.unmapped.len(), 2);
+    assert!(repository.get_all_tasks().unwrap().is_empty());
+    assert!(repository.get_all_sessions().unwrap().is_empty());
+}
+
+#[test]
+fn entries_become_sessions_once() {
+    let db = TestDb::new();
+    let mut repository = SqliteRepository::open(db.settings.clone(), common::clock()).unwrap();
+    repository.add_task(&new_task("Website", 0)).unwrap();
+
+    let export = import::parse(Format::Timewarrior, TIMEWARRIOR_JSON.as_bytes()).unwrap();
+    let report = import::import(&mut repository, &db.settings.uid, export, false).unwrap();
+    assert_eq!(report.imported, 2);
+    assert!(report.new_tasks.is_empty());
+
+    let tasks = repository.get_all_tasks().unwrap();
+    assert_eq!(tasks.len(), 1);
+    let sessions = repository.get_all_sessions().unwrap();
+    assert_eq!(sessions.len(), 2);
+    assert!(
+        sessions
+            .iter()
+            .all(|session| session.s_task == tasks[0].t_id)
+    );
+    assert_eq!(sessions[1].s_duration, 30 * 60);
+    assert_eq!(sessions[1].s_comment, "Fixed the footer");
+    // Sessions are stamped when they end.
+    assert_eq!(sessions[1].s_created_at, utc(10, 30).timestamp_millis());
+
+    let toggl = import::parse(Format::Toggl, TOGGL_CSV.as_bytes()).unwrap();
+    import::import(&mut repository, &db.settings.uid, toggl, false).unwrap();
+    let billable: Vec<bool> = repository.get_all_sessions().unwrap()[2..]
+        .iter()
+        .map(|session| session.s_billable)
+        .collect();
+    assert_eq!(billable, vec![true, false]);
+
+    let again = import::parse(Format::Timewarrior, TIMEWARRIOR_JSON.as_bytes()).unwrap();
+    let report = import::import(&mut repository, &db.settings.uid, again, false).unwrap();
+    assert_eq!(report.imported, 0);
+    assert_eq!(report.duplicates, 2);
+    assert_eq!(repository.get_all_sessions().unwrap().len(), 4);
+}
+
+#[test]
+fn failed_import_saves_nothing() {
+    let db = TestDb::new();
+    let mut repository = SqliteRepository::open(db.settings.clone(), common::clock()).unwrap();
+    let export = import::parse(Format::Toggl, TOGGL_CSV.as_bytes()).unwrap();
+
+    // Sessions of an unknown user break a foreign key.
+    assert!(import::import(&mut repository, "nobody", export, false).is_err());
+    assert!(repository.get_all_tasks().unwrap().is_empty());
+    assert!(repository.get_all_sessions().unwrap().is_empty());
+}
@@ -0,0 +1,143 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+    path::PathBuf,
+    sync::mpsc,
+    time::Duration,
+};
+
+use time_tracker::{
+    agent::AgentCommand,
+    instance::{self, Instance, Request},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+struct TestDir(PathBuf);
+
+impl TestDir {
+    fn new() -> Self {
+        Self(std::env::temp_dir().join(format!("time_tracker_instance_{}", uuid::Uuid::new_v4())))
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn command_line_is_parsed() {
+    assert_eq!(Request::from_args(&[]), Ok(None));
+    assert_eq!(
+        Request::from_args(&args(&["show"])),
+        Ok(Some(Request::Show))
+    );
+    assert_eq!(
+        Request::from_args(&args(&["sync"])),
+        Ok(Some(Request::SyncNow))
+    );
+    assert_eq!(
+        Request::from_args(&args(&["quit"])),
+        Ok(Some(Request::Quit))
+    );
+    assert_eq!(
+        Request::from_args(&args(&["unlock"])),
+        Ok(Some(Request::Unlock {
+            passphrase: String::new()
+        }))
+    );
+    assert!(Request::from_args(&args(&["start"])).is_err());
+    assert!(Request::from_args(&args(&["show", "now"])).is_err());
+}
+
+#[test]
+fn only_one_instance_holds_the_lock() {
+    let dir = TestDir::new();
+    let first = Instance::acquire(&dir.0).unwrap();
+    assert!(first.is_some());
+    assert!(Instance::acquire(&dir.0).unwrap().is_none());
+
+    // Quitting, or crashing, releases the lock for the next launch.
+    drop(first);
+    assert!(Instance::acquire(&dir.0).unwrap().is_some());
+}
+
+#[test]
+fn second_launch_is_forwarded() {
+    let dir = TestDir::new();
+    let (command_tx, command_rx) = mpsc::channel();
+    Instance::acquire(&dir.0)
+        .unwrap()
+        .unwrap()
+        .listen(command_tx);
+
+    instance::forward(&dir.0, Request::Show).unwrap();
+    assert!(matches!(
+        command_rx.recv_timeout(TIMEOUT),
+        Ok(AgentCommand::ShowUI)
+    ));
+
+    instance::forward(
+        &dir.0,
+        Request::Unlock {
+            passphrase: " two words ".to_string(),
+        },
+    )
+    .unwrap();
+    assert!(matches!(
+        command_rx.recv_timeout(TIMEOUT),
+        Ok(AgentCommand::Unlock { passphrase, remember: false }) if passphrase == " two words "
+    ));
+
+    instance::forward(&dir.0, Request::Quit).unwrap();
+    assert!(matches!(
+        command_rx.recv_timeout(TIMEOUT),
+        Ok(AgentCommand::Quit)
+    ));
+}
+
+#[test]
+fn requests_without_the_token_are_dropped() {
+    let dir = TestDir::new();
+    let (command_tx, command_rx) = mpsc::channel();
+    Instance::acquire(&dir.0)
+        .unwrap()
+        .unwrap()
+        .listen(command_tx);
+
+    let port_file = fs::read_to_string(dir.0.join("instance.port")).unwrap();
+    let (port, token) = port_file.split_once(' ').unwrap();
+    for line in ["quit\n".to_string(), format!("{}x quit\n", token)] {
+        let stream = TcpStream::connect(("127.0.0.1", port.parse::<u16>().unwrap())).unwrap();
+        stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+        (&stream).write_all(line.as_bytes()).unwrap();
+        let mut reply = String::new();
+        let _ = BufReader::new(&stream).read_line(&mut reply);
+        assert_eq!(reply, "");
+    }
+    assert!(command_rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(dir.0.join("instance.port"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
+
+#[test]
+fn forwarding_without_an_instance_fails() {
+    let dir = TestDir::new();
+    fs::create_dir_all(&dir.0).unwrap();
+    assert!(instance::forward(&dir.0, Request::Show).is_err());
+}
@@ -0,0 +1,38 @@
+use std::fs;
+
+use time_tracker::config::{LogLevel, Settings, logging};
+
+#[test]
+fn log_file_gets_messages_at_the_configured_level() {
+    let dir = std::env::temp_dir().join(format!("time_tracker_logs_{}", uuid::Uuid::new_v4()));
+    let guard = logging::init_logging(&dir, LogLevel::Info).unwrap();
+
+    tracing::info!(task = 7, "Starting session");
+    tracing::debug!("Too detailed for info");
+    // Dropping the guard flushes the background writer.
+    drop(guard);
+
+    let files: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(files.len(), 1);
+    let name = files[0].file_name().unwrap().to_string_lossy().to_string();
+    assert!(name.starts_with("time-tracker.") && name.ends_with(".log"));
+
+    let contents = fs::read_to_string(&files[0]).unwrap();
+    assert!(contents.contains("INFO"));
+    assert!(contents.contains("Starting session task=7"));
+    assert!(!contents.contains("Too detailed"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn log_level_is_read_from_settings() {
+    let settings: Settings = serde_json::from_str(r#"{ "log_level": "debug" }"#).unwrap();
+    assert_eq!(settings.log_level, LogLevel::Debug);
+
+    let settings: Settings = serde_json::from_str("{}").unwrap();
+    assert_eq!(settings.log_level, LogLevel::Info);
+}
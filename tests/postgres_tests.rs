@@ -0,0 +1,172 @@
+//! Runs against the PostgreSQL server in `TIME_TRACKER_TEST_POSTGRES_URL`,
+//! e.g. `host=localhost user=postgres`. Each test works in its own schema.
+//! The tests are skipped when the variable isn't set.
+#![cfg(feature = "postgres")]
+
+mod common;
+
+use std::sync::Arc;
+
+use time_tracker::{
+    agent,
+    config::{Settings, StorageBackend},
+    storage::{self, PostgresRepository, Repository},
+};
+
+use common::new_task;
+
+const URL_VARIABLE: &str = "TIME_TRACKER_TEST_POSTGRES_URL";
+
+/// Settings for a fresh schema on the test server, or `None` to skip.
+fn test_settings() -> Option<Arc<Settings>> {
+    let Ok(url) = std::env::var(URL_VARIABLE) else {
+        eprintln!("{} is not set, skipping", URL_VARIABLE);
+        return None;
+    };
+
+    let schema = format!("test_{}", uuid::Uuid::new_v4().simple());
+    let mut client = postgres::Client::connect(&url, postgres::NoTls).unwrap();
+    client
+        .batch_execute(&format!("CREATE SCHEMA {}", schema))
+        .unwrap();
+
+    let search_path = format!("-c search_path={}", schema);
+    let postgres_url = if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+        let separator = if url.contains('?') { '&' } else { '?' };
+        format!(
+            "{}{}options={}",
+            url,
+            separator,
+            search_path.replace(' ', "%20").replace('=', "%3D")
+        )
+    } else {
+        format!("{} options='{}'", url, search_path)
+    };
+
+    Some(Arc::new(Settings {
+        storage_backend: StorageBackend::Postgres,
+        postgres_url,
+        ..Settings::default()
+    }))
+}
+
+#[test]
+fn postgres_repository() {
+    let Some(settings) = test_settings() else {
+        return;
+    };
+    let mut repository = PostgresRepository::open(settings.clone(), common::clock()).unwrap();
+    common::check_repository(&mut repository, &settings.uid);
+}
+
+#[test]
+fn clients_share_one_database() {
+    let Some(settings) = test_settings() else {
+        return;
+    };
+    let colleague = Arc::new(Settings {
+        uid: uuid::Uuid::new_v4().to_string(),
+        ..(*settings).clone()
+    });
+    let mut mine = storage::open_repository(settings.clone(), common::clock()).unwrap();
+    let mut theirs = storage::open_repository(colleague.clone(), common::clock()).unwrap();
+
+    mine.add_task(&new_task("Shared", 0)).unwrap();
+    let task = theirs.get_all_tasks().unwrap().remove(0);
+    theirs
+        .update_task(&agent::Task {
+            t_name: "Renamed by colleague".to_string(),
+            ..task.clone()
+        })
+        .unwrap();
+    theirs
+        .save_session(&agent::Session {
+            s_task: task.t_id,
+            s_user: colleague.uid.clone(),
+            s_duration: 60,
+            ..Default::default()
+        })
+        .unwrap();
+
+    let renamed = mine.get_task(task.t_id).unwrap().unwrap();
+    assert_eq!(renamed.t_name, "Renamed by colleague");
+    assert!(renamed.t_name_hlc.ends_with(&colleague.uid));
+    assert_eq!(mine.get_all_sessions().unwrap().len(), 1);
+    assert_eq!(mine.get_all_users().unwrap().len(), 2);
+}
+
+#[test]
+fn goals_are_per_user() {
+    let Some(settings) = test_settings() else {
+        return;
+    };
+    let colleague = Arc::new(Settings {
+        uid: uuid::Uuid::new_v4().to_string(),
+        ..(*settings).clone()
+    });
+    let mut mine = storage::open_repository(settings, common::clock()).unwrap();
+    let mut theirs = storage::open_repository(colleague, common::clock()).unwrap();
+
+    mine.add_task(&agent::Task {
+        t_daily_goal: Some(3600),
+        ..new_task("Shared", 0)
+    })
+    .unwrap();
+    let task = theirs.get_all_tasks().unwrap().remove(0);
+    assert_eq!((task.t_daily_goal, task.t_weekly_goal), (None, None));
+
+    theirs
+        .update_task(&agent::Task {
+            t_weekly_goal: Some(7200),
+            ..task.clone()
+        })
+        .unwrap();
+    let own = theirs.get_task(task.t_id).unwrap().unwrap();
+    assert_eq!((own.t_daily_goal, own.t_weekly_goal), (None, Some(7200)));
+    let unchanged = mine.get_task(task.t_id).unwrap().unwrap();
+    assert_eq!(
+        (unchanged.t_daily_goal, unchanged.t_weekly_goal),
+        (Some(3600), None)
+    );
+}
+
+#[test]
+fn window_rules_are_per_user() {
+    let Some(settings) = test_settings() else {
+        return;
+    };
+    let colleague = Arc::new(Settings {
+        uid: uuid::Uuid::new_v4().to_string(),
+        ..(*settings).clone()
+    });
+    let mut mine = storage::open_repository(settings, common::clock()).unwrap();
+    let mut theirs = storage::open_repository(colleague, common::clock()).unwrap();
+
+    mine.add_task(&new_task("Shared", 0)).unwrap();
+    let task = mine.get_all_tasks().unwrap().remove(0);
+    mine.add_window_rule(&agent::window_rules::WindowRule {
+        wr_pattern: "(?i)invoice".to_string(),
+        wr_task: task.t_id,
+        ..Default::default()
+    })
+    .unwrap();
+    let rule = mine.get_all_window_rules().unwrap().remove(0);
+
+    assert!(theirs.get_all_window_rules().unwrap().is_empty());
+    theirs.delete_window_rule(rule.wr_id).unwrap();
+    assert_eq!(mine.get_all_window_rules().unwrap(), vec![rule]);
+}
+
+#[test]
+fn reopening_keeps_the_schema() {
+    let Some(settings) = test_settings() else {
+        return;
+    };
+    PostgresRepository::open(settings.clone(), common::clock())
+        .unwrap()
+        .add_task(&new_task("Persisted", 1))
+        .unwrap();
+
+    let repository = PostgresRepository::open(settings, common::clock()).unwrap();
+    assert_eq!(repository.get_all_tasks().unwrap()[0].t_name, "Persisted");
+}
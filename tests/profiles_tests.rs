@@ -0,0 +1,18 @@
+use time_tracker::config::Profiles;
+
+#[test]
+fn profile_names_are_unique_ignoring_case() {
+    let mut profiles = Profiles::default();
+    assert!(profiles.add(" Work "));
+    assert_eq!(profiles.names, vec!["default", "Work"]);
+
+    assert!(!profiles.add("work"));
+    assert!(!profiles.add("DEFAULT"));
+    assert!(!profiles.add("../work"));
+    assert_eq!(profiles.names.len(), 2);
+
+    assert!(profiles.set_active("WORK"));
+    assert_eq!(profiles.active, "Work");
+    assert!(!profiles.set_active("Home"));
+    assert_eq!(profiles.active, "Work");
+}
@@ -0,0 +1,298 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use time_tracker::{
+    agent::{self, users::User},
+    config::Settings,
+    report::{
+        self, ReportRequest,
+        compliance::{self, WorkLimits},
+        goals::{self, Period},
+    },
+    storage::{MemoryRepository, Repository},
+};
+
+use common::{day, new_task};
+
+/// A session by `user` on `task`, with `comment`.
+fn session(task: i64, user: &str, comment: &str) -> agent::Session {
+    agent::Session {
+        s_task: task,
+        s_user: user.to_string(),
+        s_comment: comment.to_string(),
+        ..Default::default()
+    }
+}
+
+/// Two tasks worked on in the week of Monday 15 January 2024, plus sessions
+/// outside the week and by someone else.
+fn repository() -> MemoryRepository {
+    let mut repository = MemoryRepository::new("me", common::clock());
+    for (id, name) in [("ada", "Ada <Lovelace>"), ("other", "Grace")] {
+        repository
+            .ensure_user(&User {
+                u_id: id.to_string(),
+                u_name: name.to_string(),
+            })
+            .unwrap();
+    }
+    repository.add_task(&new_task("Website", 0)).unwrap();
+    repository.add_task(&new_task("Accounting", 0)).unwrap();
+    let tasks = repository.get_all_tasks().unwrap();
+    let website = tasks.iter().find(|t| t.t_name == "Website").unwrap().t_id;
+    let accounting = tasks
+        .iter()
+        .find(|t| t.t_name == "Accounting")
+        .unwrap()
+        .t_id;
+
+    common::save_session(
+        &mut repository,
+        15,
+        90,
+        session(website, "ada", "Header & footer"),
+    );
+    common::save_session(&mut repository, 15, 30, session(website, "ada", ""));
+    common::save_session(&mut repository, 17, 60, session(website, "ada", ""));
+    common::save_session(&mut repository, 21, 45, session(accounting, "ada", ""));
+    common::save_session(&mut repository, 14, 60, session(website, "ada", ""));
+    common::save_session(&mut repository, 22, 60, session(website, "ada", ""));
+    common::save_session(&mut repository, 16, 60, session(accounting, "other", ""));
+    repository
+}
+
+#[test]
+fn weeks_start_on_monday() {
+    assert_eq!(report::week_start(day(15)), day(15));
+    assert_eq!(report::week_start(day(21)), day(15));
+    assert_eq!(report::week_start(day(14)), day(8));
+    assert_eq!(
+        report::default_file_name(day(18)),
+        "timesheet-2024-01-15.html"
+    );
+}
+
+#[test]
+fn sessions_are_summed_per_day_and_task() {
+    let repository = repository();
+    let timesheet = report::timesheet(&repository, "ada", day(17)).unwrap();
+
+    assert_eq!(timesheet.person, "Ada <Lovelace>");
+    assert_eq!(
+        timesheet.days,
+        day(15).iter_days().take(7).collect::<Vec<_>>()
+    );
+    let rows: Vec<_> = timesheet
+        .rows
+        .iter()
+        .map(|row| (row.task.as_str(), row.seconds.clone()))
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            ("Accounting", vec![0, 0, 0, 0, 0, 0, 45 * 60]),
+            ("Website", vec![120 * 60, 0, 60 * 60, 0, 0, 0, 0]),
+        ]
+    );
+    assert_eq!(
+        timesheet.day_totals(),
+        vec![120 * 60, 0, 60 * 60, 0, 0, 0, 45 * 60]
+    );
+    assert_eq!(timesheet.total(), 225 * 60);
+    assert_eq!(timesheet.comments.len(), 1);
+    assert_eq!(timesheet.comments[0].date, day(15));
+    assert_eq!(timesheet.comments[0].task, "Website");
+}
+
+#[test]
+fn working_time_is_measured_against_the_limits() {
+    let mut repository = MemoryRepository::new("me", common::clock());
+    for id in ["ada", "other"] {
+        repository
+            .ensure_user(&User {
+                u_id: id.to_string(),
+                u_name: id.to_string(),
+            })
+            .unwrap();
+    }
+    repository.add_task(&new_task("Website", 0)).unwrap();
+    let task = repository.get_all_tasks().unwrap()[0].t_id;
+    // Sessions ending at `hour`:`minute` UTC on `day` of January 2024.
+    let mut save = |user: &str, day: u32, hour: u32, minute: u32, minutes: u64| {
+        repository
+            .save_session(&agent::Session {
+                s_task: task,
+                s_user: user.to_string(),
+                s_duration: minutes * 60,
+                s_created_at: Utc
+                    .with_ymd_and_hms(2024, 1, day, hour, minute, 0)
+                    .unwrap()
+                    .timestamp_millis(),
+                ..Default::default()
+            })
+            .unwrap();
+    };
+    save("ada", 8, 12, 0, 60);
+    // 9:00 to 12:00 and 12:03 to 15:00, too short a pause to count.
+    save("ada", 15, 12, 0, 180);
+    save("ada", 15, 15, 0, 177);
+    // 9:00 to 12:00 and 13:00 to 16:00.
+    save("ada", 16, 12, 0, 180);
+    save("ada", 16, 16, 0, 180);
+    save("other", 17, 16, 0, 600);
+
+    let limits = WorkLimits {
+        daily: Some(5 * 3600),
+        weekly: Some(8 * 3600),
+        stretch: Some(4 * 3600),
+        min_break: 5 * 60,
+    };
+    let compliance = compliance::compliance(&repository, "ada", limits, day(17), 2).unwrap();
+    assert_eq!(compliance.limits, limits);
+    let [earlier, week] = &compliance.weeks[..] else {
+        panic!("expected two weeks, got {}", compliance.weeks.len());
+    };
+
+    assert_eq!(earlier.start, day(8));
+    assert_eq!(earlier.worked(), 3600);
+    assert!(earlier.compliant(&limits));
+
+    assert_eq!(week.start, day(15));
+    assert_eq!(week.days.len(), 7);
+    assert_eq!(week.days[0].worked, 357 * 60);
+    assert_eq!(week.days[0].longest_stretch, 6 * 3600);
+    assert_eq!(week.days[1].longest_stretch, 3 * 3600);
+    assert_eq!(week.days[2].worked, 0);
+    assert_eq!(week.worked(), 717 * 60);
+    assert!(week.over_limit(&limits));
+    assert_eq!(week.days_over_limit(&limits), 2);
+    assert_eq!(week.missed_breaks(&limits), 1);
+    assert!(!week.compliant(&limits));
+
+    assert_eq!(
+        compliance::worked(&repository, "ada", day(16)).unwrap(),
+        (360 * 60, 717 * 60)
+    );
+}
+
+#[test]
+fn goals_are_met_in_streaks() {
+    let mut repository = repository();
+    let tasks = repository.get_all_tasks().unwrap();
+    let website = tasks.iter().find(|t| t.t_name == "Website").unwrap();
+    repository
+        .update_task(&agent::Task {
+            t_weekly_goal: Some(3 * 3600),
+            ..website.clone()
+        })
+        .unwrap();
+    for day in [16, 19, 20] {
+        common::save_session(&mut repository, day, 60, session(website.t_id, "ada", ""));
+    }
+    let settings = Settings {
+        daily_goal_hours: 1.0,
+        ..Settings::default()
+    };
+    let goals = goals::goals(&settings, &repository.get_all_tasks().unwrap());
+    assert_eq!(goals.len(), 2);
+    assert_eq!((goals[0].task, goals[0].period), (None, Period::Day));
+    assert_eq!(goals[0].target, 3600);
+    assert_eq!(
+        (goals[1].task, goals[1].period),
+        (Some(website.t_id), Period::Week)
+    );
+
+    // Ada worked an hour or more on the 14th to the 17th, the 19th and 20th,
+    // and today, the 22nd, but only 45 minutes on Sunday the 21st.
+    let history = goals::history(&repository, "ada", &goals, day(22), 14).unwrap();
+    let daily = &history[0];
+    assert_eq!(daily.periods.len(), 14);
+    assert_eq!(daily.periods[0].start, day(9));
+    assert_eq!(daily.periods[13].start, day(22));
+    assert_eq!(daily.current().worked, 3600);
+    assert!(daily.current().met());
+    // Missed weekends don't count, missed weekdays do.
+    assert_eq!(daily.counted().len(), 12);
+    assert_eq!(daily.attained(), 7);
+    assert_eq!(daily.streak(), 3);
+    assert_eq!(daily.best_streak(), 4);
+
+    let weekly = &history[1];
+    let worked: Vec<u64> = weekly.periods.iter().map(|p| p.worked).collect();
+    assert_eq!(worked.len(), 14);
+    assert_eq!(&worked[11..], &[60 * 60, 6 * 60 * 60, 60 * 60]);
+    assert_eq!(weekly.attained(), 1);
+    // The current week isn't over, so it doesn't break the streak.
+    assert_eq!(weekly.streak(), 1);
+
+    let progress = goals::progress(&repository, "ada", &goals, day(22)).unwrap();
+    assert_eq!(progress[1].worked, 3600);
+    assert!((progress[1].fraction() - 1.0 / 3.0).abs() < 1e-6);
+}
+
+#[test]
+fn template_placeholders_are_filled() {
+    let repository = repository();
+    let timesheet = report::timesheet(&repository, "ada", day(15)).unwrap();
+    let generated_at = chrono::Local
+        .with_ymd_and_hms(2024, 1, 22, 9, 5, 0)
+        .unwrap();
+    let template = "{{person}}|{{period}}|{{total}}|{{generated_at}}\n{{table}}\n{{comments}}";
+    let html = report::render_html(&timesheet, template, generated_at);
+
+    assert!(html.starts_with(
+        "Ada &lt;Lovelace&gt;|15 January 2024 – 21 January 2024|3:45|2024-01-22 09:05\n"
+    ));
+    assert!(html.contains("<th class=\"task\">Website</th><td class=\"hours\">2:00</td>"));
+    assert!(html.contains("<th class=\"hours\">3:45</th></tr></tfoot>"));
+    assert!(html.contains(": Header &amp; footer</li>"));
+    assert!(!html.contains("{{"));
+
+    let html = report::render_html(&timesheet, report::DEFAULT_TEMPLATE, generated_at);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(!html.contains("{{"));
+}
+
+#[test]
+fn default_template_is_saved_for_editing() {
+    let dir = std::env::temp_dir().join(format!("time_tracker_report_{}", uuid::Uuid::new_v4()));
+    let path = dir.join("templates").join("timesheet.html");
+
+    assert_eq!(
+        report::load_template(&path).unwrap(),
+        report::DEFAULT_TEMPLATE
+    );
+    std::fs::write(&path, "<p>{{person}}</p>").unwrap();
+    assert_eq!(report::load_template(&path).unwrap(), "<p>{{person}}</p>");
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn report_arguments_are_parsed() {
+    let args = |args: &[&str]| -> Vec<String> { args.iter().map(|a| a.to_string()).collect() };
+
+    assert_eq!(
+        ReportRequest::from_args(&[]).unwrap(),
+        ReportRequest::default()
+    );
+    assert_eq!(
+        ReportRequest::from_args(&args(&[
+            "--week",
+            "2024-01-17",
+            "--user",
+            "other",
+            "--output",
+            "week.html"
+        ]))
+        .unwrap(),
+        ReportRequest {
+            week: Some(day(17)),
+            user: Some("other".to_string()),
+            output: Some("week.html".into()),
+        }
+    );
+    assert!(ReportRequest::from_args(&args(&["--week", "last"])).is_err());
+    assert!(ReportRequest::from_args(&args(&["--user"])).is_err());
+    assert!(ReportRequest::from_args(&args(&["--pdf"])).is_err());
+}
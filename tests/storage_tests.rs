@@ -0,0 +1,464 @@
+mod common;
+
+use rusqlite::Connection;
+use time_tracker::{
+    DB_MIGRATIONS, agent,
+    config::{Settings, StorageBackend},
+    storage::{self, MemoryRepository, Repository, SqliteRepository, StorageError, hlc::Hlc},
+    sync::{self, protocol::TaskRecord},
+};
+
+use common::{TestDb, new_task};
+
+fn user_version(conn: &Connection) -> usize {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+        .unwrap()
+}
+
+fn to_record(task: &agent::Task) -> TaskRecord {
+    TaskRecord {
+        t_uuid: task.t_uuid.clone(),
+        t_name: task.t_name.clone(),
+        t_priority: task.t_priority,
+        t_created_at: task.t_created_at,
+        t_updated_at: task.t_updated_at,
+        t_deleted: task.t_deleted,
+        t_name_hlc: task.t_name_hlc.clone(),
+        t_priority_hlc: task.t_priority_hlc.clone(),
+        t_deleted_hlc: task.t_deleted_hlc.clone(),
+    }
+}
+
+#[test]
+fn fresh_database_is_stamped_with_latest_version() {
+    let db = TestDb::new();
+    let conn = storage::init_db(db.settings.clone()).unwrap();
+
+    assert_eq!(user_version(&conn), DB_MIGRATIONS.len());
+    assert!(agent::get_all_tasks(&conn).unwrap().is_empty());
+}
+
+/// A database as created before tasks and sessions had global ids.
+fn create_legacy_database(db: &TestDb) {
+    let conn = Connection::open(db.path()).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE tasks (
+            t_id INTEGER PRIMARY KEY AUTOINCREMENT,
+            t_name TEXT NOT NULL,
+            t_priority INTEGER NOT NULL
+        );
+        CREATE TABLE users (
+            u_id TEXT PRIMARY KEY,
+            u_name TEXT NOT NULL
+        );
+        CREATE TABLE sessions (
+            s_id INTEGER PRIMARY KEY AUTOINCREMENT,
+            s_task INTEGER NOT NULL,
+            s_user TEXT NOT NULL,
+            s_duration INTEGER NOT NULL,
+            s_comment TEXT NOT NULL
+        );
+        INSERT INTO tasks (t_name, t_priority) VALUES ('Old task', 2);
+        INSERT INTO sessions (s_task, s_user, s_duration, s_comment)
+            VALUES (1, 'old-user', 3600, 'Old work');",
+    )
+    .unwrap();
+}
+
+#[test]
+fn legacy_database_is_migrated() {
+    let db = TestDb::new();
+    create_legacy_database(&db);
+
+    let conn = storage::init_db(db.settings.clone()).unwrap();
+    assert_eq!(user_version(&conn), DB_MIGRATIONS.len());
+
+    let tasks = agent::get_all_tasks(&conn).unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].t_name, "Old task");
+    assert_eq!(tasks[0].t_priority, 2);
+    assert!(!tasks[0].t_uuid.is_empty());
+
+    let without_id = conn.execute(
+        "INSERT INTO tasks (t_name, t_priority) VALUES ('New', 1)",
+        [],
+    );
+    assert!(without_id.is_err());
+}
+
+#[test]
+fn legacy_sessions_are_left_out_of_time_ranges() {
+    let db = TestDb::new();
+    create_legacy_database(&db);
+
+    let repository = SqliteRepository::open(db.settings.clone(), common::clock()).unwrap();
+    let sessions = repository.get_all_sessions().unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].s_created_at, 0);
+    assert!(!sessions[0].time_known());
+
+    let between = repository
+        .get_sessions_between(chrono::DateTime::<chrono::Utc>::MIN_UTC, chrono::Utc::now())
+        .unwrap();
+    assert!(between.is_empty());
+}
+
+#[test]
+fn concurrent_opens_migrate_once() {
+    let db = TestDb::new();
+    create_legacy_database(&db);
+
+    let opens: Vec<_> = (0..2)
+        .map(|_| {
+            let settings = db.settings.clone();
+            std::thread::spawn(move || storage::init_db(settings).map(|_| ()))
+        })
+        .collect();
+    for open in opens {
+        open.join().unwrap().unwrap();
+    }
+
+    let conn = storage::init_db(db.settings.clone()).unwrap();
+    assert_eq!(user_version(&conn), DB_MIGRATIONS.len());
+    assert_eq!(agent::get_all_tasks(&conn).unwrap().len(), 1);
+}
+
+#[test]
+fn reopening_database_keeps_tasks() {
+    let db = TestDb::new();
+    {
+        let conn = storage::init_db(db.settings.clone()).unwrap();
+        agent::add_new_task(&conn, &new_task("Write report", 1), common::now_ms()).unwrap();
+    }
+
+    let conn = storage::init_db(db.settings.clone()).unwrap();
+    let tasks = agent::get_all_tasks(&conn).unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].t_name, "Write report");
+}
+
+#[test]
+fn add_update_and_delete_task() {
+    let db = TestDb::new();
+    let conn = storage::init_db(db.settings.clone()).unwrap();
+
+    agent::add_new_task(&conn, &new_task("Draft", 0), common::now_ms()).unwrap();
+    let added = agent::get_all_tasks(&conn).unwrap().remove(0);
+    assert_eq!(added.t_name_hlc, added.t_priority_hlc);
+    assert!(added.t_name_hlc.ends_with(&db.settings.uid));
+
+    let renamed = agent::Task {
+        t_name: "Final".to_string(),
+        ..added.clone()
+    };
+    assert_eq!(
+        agent::tasks::update_task(&conn, &renamed, common::now_ms()).unwrap(),
+        1
+    );
+    let updated = agent::tasks::get_task(&conn, added.t_id).unwrap().unwrap();
+    assert_eq!(updated.t_name, "Final");
+    assert!(updated.t_name_hlc > added.t_name_hlc);
+    assert_eq!(updated.t_priority_hlc, added.t_priority_hlc);
+
+    agent::tasks::delete_task(&conn, added.t_id, common::now_ms()).unwrap();
+    assert!(agent::get_all_tasks(&conn).unwrap().is_empty());
+    let tombstone = agent::tasks::get_task(&conn, added.t_id).unwrap().unwrap();
+    assert!(tombstone.t_deleted);
+}
+
+#[test]
+fn failed_task_update_changes_nothing() {
+    let db = TestDb::new();
+    let conn = storage::init_db(db.settings.clone()).unwrap();
+    agent::add_new_task(&conn, &new_task("Draft", 0), common::now_ms()).unwrap();
+    let added = agent::get_all_tasks(&conn).unwrap().remove(0);
+    let queued = sync::outbox::count_entries(&conn).unwrap();
+
+    // The rename comes first, then the unknown client fails.
+    let edit = agent::Task {
+        t_name: "Final".to_string(),
+        t_client: Some(999),
+        ..added.clone()
+    };
+    assert!(agent::tasks::update_task(&conn, &edit, common::now_ms()).is_err());
+
+    let task = agent::tasks::get_task(&conn, added.t_id).unwrap().unwrap();
+    assert_eq!(task.t_name, "Draft");
+    assert_eq!(task.t_name_hlc, added.t_name_hlc);
+    assert_eq!(sync::outbox::count_entries(&conn).unwrap(), queued);
+}
+
+#[test]
+fn saved_session_gets_global_id() {
+    let db = TestDb::new();
+    let conn = storage::init_db(db.settings.clone()).unwrap();
+    agent::add_new_task(&conn, &new_task("Review", 1), common::now_ms()).unwrap();
+    let task = agent::get_all_tasks(&conn).unwrap().remove(0);
+
+    let session = agent::Session {
+        s_task: task.t_id,
+        s_user: db.settings.uid.clone(),
+        s_duration: 90,
+        s_comment: "Done".to_string(),
+        ..Default::default()
+    };
+    agent::save_session(&conn, &session, common::now_ms()).unwrap();
+
+    let saved = agent::sessions::get_session(&conn, 1).unwrap().unwrap();
+    assert_eq!(saved.s_task, task.t_id);
+    assert_eq!(saved.s_duration, 90);
+    assert_eq!(saved.s_comment, "Done");
+    assert!(!saved.s_uuid.is_empty());
+}
+
+#[test]
+fn hlc_orders_like_its_string_form() {
+    let first = Hlc::default().tick(1_000, "a");
+    let same_ms = first.tick(1_000, "a");
+    let clock_behind = same_ms.tick(500, "a");
+    let later = clock_behind.tick(2_000, "a");
+
+    assert_eq!(same_ms.counter, first.counter + 1);
+    assert_eq!(clock_behind.wall_ms, 1_000);
+    for (earlier, later) in [
+        (&first, &same_ms),
+        (&same_ms, &clock_behind),
+        (&clock_behind, &later),
+    ] {
+        assert!(earlier < later);
+        assert!(earlier.to_string() < later.to_string());
+    }
+    assert_eq!(Hlc::parse(&later.to_string()), Some(later));
+}
+
+#[test]
+fn hlc_observe_moves_past_remote_clock() {
+    let local = Hlc::default().tick(1_000, "a");
+    let remote = Hlc::default().tick(5_000, "b");
+
+    let observed = local.observe(&remote, 2_000, "a");
+    assert!(observed > remote);
+    assert_eq!(observed.node, "a");
+}
+
+#[test]
+fn merge_inserts_unknown_remote_task() {
+    let db = TestDb::new();
+    let conn = storage::init_db(db.settings.clone()).unwrap();
+    let hlc = Hlc::default().tick(1_000, "remote").to_string();
+    let remote = TaskRecord {
+        t_uuid: uuid::Uuid::new_v4().to_string(),
+        t_name: "Shared".to_string(),
+        t_priority: 2,
+        t_created_at: 1_000,
+        t_updated_at: 1_000,
+        t_deleted: false,
+        t_name_hlc: hlc.clone(),
+        t_priority_hlc: hlc.clone(),
+        t_deleted_hlc: hlc,
+    };
+
+    assert!(storage::merge::merge_task(&conn, &remote, 1_000).unwrap());
+    let task = agent::tasks::get_task_by_uuid(&conn, &remote.t_uuid)
+        .unwrap()
+        .unwrap();
+    assert_eq!(task.t_name, "Shared");
+    assert_eq!(time_tracker::sync::outbox::count_entries(&conn).unwrap(), 0);
+}
+
+#[test]
+fn merge_keeps_edits_to_different_fields() {
+    let db = TestDb::new();
+    let conn = storage::init_db(db.settings.clone()).unwrap();
+    agent::add_new_task(&conn, &new_task("Plan", 0), common::now_ms()).unwrap();
+    let base = agent::get_all_tasks(&conn).unwrap().remove(0);
+    storage::merge::merge_task(&conn, &to_record(&base), 0).unwrap();
+
+    // This device renames the task while another one changes its priority.
+    agent::tasks::update_task(
+        &conn,
+        &agent::Task {
+            t_name: "Plan sprint".to_string(),
+            ..base.clone()
+        },
+        common::now_ms(),
+    )
+    .unwrap();
+    let mut remote = to_record(&base);
+    remote.t_priority = 2;
+    remote.t_priority_hlc = Hlc::parse(&base.t_priority_hlc)
+        .unwrap()
+        .tick(0, "remote")
+        .to_string();
+
+    assert!(storage::merge::merge_task(&conn, &remote, 0).unwrap());
+    let merged = agent::tasks::get_task(&conn, base.t_id).unwrap().unwrap();
+    assert_eq!(merged.t_name, "Plan sprint");
+    assert_eq!(merged.t_priority, 2);
+    assert!(
+        storage::conflicts::get_unresolved_conflicts(&conn)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn concurrent_rename_is_recorded_and_resolved() {
+    let db = TestDb::new();
+    let conn = storage::init_db(db.settings.clone()).unwrap();
+    agent::add_new_task(&conn, &new_task("Plan", 0), common::now_ms()).unwrap();
+    let base = agent::get_all_tasks(&conn).unwrap().remove(0);
+    storage::merge::merge_task(&conn, &to_record(&base), 0).unwrap();
+
+    agent::tasks::update_task(
+        &conn,
+        &agent::Task {
+            t_name: "Local name".to_string(),
+            ..base.clone()
+        },
+        common::now_ms(),
+    )
+    .unwrap();
+    let mut remote = to_record(&base);
+    remote.t_name = "Remote name".to_string();
+    let an_hour_later = chrono::Utc::now().timestamp_millis() + 3_600_000;
+    remote.t_name_hlc = Hlc::default().tick(an_hour_later, "remote").to_string();
+
+    storage::merge::merge_task(&conn, &remote, 0).unwrap();
+    let merged = agent::tasks::get_task(&conn, base.t_id).unwrap().unwrap();
+    assert_eq!(merged.t_name, "Remote name");
+
+    let conflicts = storage::conflicts::get_unresolved_conflicts(&conn).unwrap();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].c_field, "name");
+    assert_eq!(conflicts[0].c_local_value, "Local name");
+    assert_eq!(conflicts[0].c_kept, "remote");
+
+    storage::conflicts::resolve_conflict(&conn, conflicts[0].c_id, true, common::now_ms()).unwrap();
+    let resolved = agent::tasks::get_task(&conn, base.t_id).unwrap().unwrap();
+    assert_eq!(resolved.t_name, "Local name");
+    assert!(resolved.t_name_hlc > remote.t_name_hlc);
+    assert!(
+        storage::conflicts::get_unresolved_conflicts(&conn)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn sqlite_repository() {
+    let db = TestDb::new();
+    let mut repository = SqliteRepository::open(db.settings.clone(), common::clock()).unwrap();
+    common::check_repository(&mut repository, &db.settings.uid);
+}
+
+#[test]
+fn memory_repository() {
+    let mut repository = MemoryRepository::new("me", common::clock());
+    common::check_repository(&mut repository, "me");
+}
+
+#[test]
+fn memory_repository_rejects_unknown_user() {
+    let mut repository = MemoryRepository::new("me", common::clock());
+    repository.add_task(&new_task("Task", 0)).unwrap();
+
+    let result = repository.save_session(&agent::Session {
+        s_user: "someone else".to_string(),
+        ..Default::default()
+    });
+    assert!(matches!(result, Err(StorageError::Constraint(_))));
+}
+
+#[test]
+fn repository_backend_is_chosen_by_settings() {
+    let db = TestDb::with_settings(Settings {
+        storage_backend: StorageBackend::Memory,
+        ..Settings::default()
+    });
+    let mut repository = storage::open_repository(db.settings.clone(), common::clock()).unwrap();
+    repository.add_task(&new_task("Scratch", 0)).unwrap();
+
+    assert!(!std::path::Path::new(db.path()).exists());
+    assert_eq!(repository.get_all_tasks().unwrap().len(), 1);
+}
+
+#[cfg(not(feature = "postgres"))]
+#[test]
+fn postgres_backend_needs_the_feature() {
+    let db = TestDb::with_settings(Settings {
+        storage_backend: StorageBackend::Postgres,
+        ..Settings::default()
+    });
+    let result = storage::open_repository(db.settings.clone(), common::clock());
+    assert!(matches!(result, Err(StorageError::Unsupported(_))));
+}
+
+#[cfg(not(feature = "encryption"))]
+#[test]
+fn encryption_needs_the_feature() {
+    let db = TestDb::with_settings(Settings {
+        encrypt_database: true,
+        database_passphrase: Some("secret".to_string()),
+        ..Settings::default()
+    });
+    let result = storage::open_repository(db.settings.clone(), common::clock());
+    assert!(matches!(result, Err(StorageError::Unsupported(_))));
+}
+
+fn busy_error() -> StorageError {
+    StorageError::Sqlite(rusqlite::Error::SqliteFailure(
+        rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
+        None,
+    ))
+}
+
+#[test]
+fn busy_errors_are_retried() {
+    let mut attempts = 0;
+    let result = storage::retry_busy(|| {
+        attempts += 1;
+        if attempts < 3 {
+            Err(busy_error())
+        } else {
+            Ok(attempts)
+        }
+    });
+    assert_eq!(result.unwrap(), 3);
+
+    let mut attempts = 0;
+    let result: storage::Result<()> = storage::retry_busy(|| {
+        attempts += 1;
+        Err(busy_error())
+    });
+    assert!(result.unwrap_err().is_busy());
+    assert_eq!(attempts, 4);
+}
+
+#[test]
+fn other_errors_are_not_retried() {
+    let mut attempts = 0;
+    let result: storage::Result<()> = storage::retry_busy(|| {
+        attempts += 1;
+        Err(StorageError::Constraint("broken".to_string()))
+    });
+    assert!(!result.unwrap_err().is_busy());
+    assert_eq!(attempts, 1);
+}
+
+#[test]
+fn writes_wait_for_a_locked_database() {
+    let db = TestDb::new();
+    let mut repository = SqliteRepository::open(db.settings.clone(), common::clock()).unwrap();
+
+    let locker = Connection::open(db.path()).unwrap();
+    locker.execute_batch("BEGIN EXCLUSIVE").unwrap();
+    let release = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        locker.execute_batch("COMMIT").unwrap();
+    });
+
+    storage::retry_busy(|| repository.add_task(&new_task("After lock", 0))).unwrap();
+    release.join().unwrap();
+    assert_eq!(repository.get_all_tasks().unwrap().len(), 1);
+}
@@ -0,0 +1,240 @@
+mod common;
+
+use std::{thread, time::Duration};
+
+use rusqlite::Connection;
+use time_tracker::{
+    agent,
+    config::Settings,
+    server::SyncServer,
+    storage,
+    sync::{self, SyncStatus, outbox, protocol},
+};
+
+use common::TestDb;
+
+const TOKEN: &str = "team-secret";
+
+/// Starts a sync server with an in-memory database and returns its URL.
+fn start_server() -> String {
+    let server = SyncServer::bind("127.0.0.1:0", ":memory:", TOKEN).unwrap();
+    let url = format!("http://{}", server.local_addr().unwrap());
+    thread::spawn(move || server.run());
+    url
+}
+
+fn client(url: &str) -> (TestDb, Connection) {
+    let db = TestDb::with_settings(Settings {
+        sync_server_url: url.to_string(),
+        sync_token: TOKEN.to_string(),
+        ..Settings::default()
+    });
+    let conn = storage::init_db(db.settings.clone()).unwrap();
+    (db, conn)
+}
+
+fn sync(db: &TestDb, conn: &Connection) -> SyncStatus {
+    sync::engine::sync_once(conn, &sync::engine::http_agent(), &db.settings, false)
+}
+
+fn add_task(conn: &Connection, name: &str) -> agent::Task {
+    agent::add_new_task(
+        conn,
+        &agent::Task {
+            t_name: name.to_string(),
+            ..Default::default()
+        },
+        common::now_ms(),
+    )
+    .unwrap();
+    agent::get_all_tasks(conn)
+        .unwrap()
+        .into_iter()
+        .find(|task| task.t_name == name)
+        .unwrap()
+}
+
+fn rename(conn: &Connection, uuid: &str, name: &str) {
+    let task = agent::tasks::get_task_by_uuid(conn, uuid).unwrap().unwrap();
+    agent::tasks::update_task(
+        conn,
+        &agent::Task {
+            t_name: name.to_string(),
+            ..task
+        },
+        common::now_ms(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn sync_is_disabled_without_server_url() {
+    let db = TestDb::new();
+    let conn = storage::init_db(db.settings.clone()).unwrap();
+    add_task(&conn, "Offline");
+
+    assert!(matches!(sync(&db, &conn), SyncStatus::Disabled));
+    assert_eq!(outbox::count_entries(&conn).unwrap(), 1);
+}
+
+#[test]
+fn only_synced_fields_fill_the_outbox() {
+    let db = TestDb::new();
+    let conn = storage::init_db(db.settings.clone()).unwrap();
+    let task = add_task(&conn, "Local");
+
+    agent::tasks::update_task(
+        &conn,
+        &agent::Task {
+            t_estimate: Some(3600),
+            t_daily_goal: Some(1800),
+            ..task.clone()
+        },
+        common::now_ms(),
+    )
+    .unwrap();
+    assert_eq!(outbox::count_entries(&conn).unwrap(), 1);
+
+    rename(&conn, &task.t_uuid, "Synced");
+    assert_eq!(outbox::count_entries(&conn).unwrap(), 2);
+}
+
+#[test]
+fn tasks_are_shared_between_clients() {
+    let url = start_server();
+    let (db_a, conn_a) = client(&url);
+    let (db_b, conn_b) = client(&url);
+
+    let task = add_task(&conn_a, "Shared task");
+    assert!(matches!(sync(&db_a, &conn_a), SyncStatus::Synced { .. }));
+    assert_eq!(outbox::count_entries(&conn_a).unwrap(), 0);
+
+    assert!(matches!(sync(&db_b, &conn_b), SyncStatus::Synced { .. }));
+    let pulled = agent::tasks::get_task_by_uuid(&conn_b, &task.t_uuid)
+        .unwrap()
+        .unwrap();
+    assert_eq!(pulled.t_name, "Shared task");
+
+    agent::tasks::delete_task(&conn_b, pulled.t_id, common::now_ms()).unwrap();
+    sync(&db_b, &conn_b);
+    sync(&db_a, &conn_a);
+    assert!(agent::get_all_tasks(&conn_a).unwrap().is_empty());
+}
+
+#[test]
+fn concurrent_rename_is_recorded_as_conflict() {
+    let url = start_server();
+    let (db_a, conn_a) = client(&url);
+    let (db_b, conn_b) = client(&url);
+
+    let task = add_task(&conn_a, "Plan");
+    sync(&db_a, &conn_a);
+    sync(&db_b, &conn_b);
+
+    rename(&conn_b, &task.t_uuid, "Plan by B");
+    thread::sleep(Duration::from_millis(5));
+    rename(&conn_a, &task.t_uuid, "Plan by A");
+    sync(&db_a, &conn_a);
+    sync(&db_b, &conn_b);
+
+    // A's rename is newer, so it wins on both devices.
+    for conn in [&conn_a, &conn_b] {
+        let merged = agent::tasks::get_task_by_uuid(conn, &task.t_uuid)
+            .unwrap()
+            .unwrap();
+        assert_eq!(merged.t_name, "Plan by A");
+    }
+
+    let conflicts = storage::conflicts::get_unresolved_conflicts(&conn_b).unwrap();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].c_field, "name");
+    assert_eq!(conflicts[0].c_local_value, "Plan by B");
+    assert_eq!(conflicts[0].c_remote_value, "Plan by A");
+    assert!(
+        storage::conflicts::get_unresolved_conflicts(&conn_a)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn sessions_show_up_in_team_report() {
+    let url = start_server();
+    let (db, conn) = client(&url);
+    let task = add_task(&conn, "Reported");
+    agent::save_session(
+        &conn,
+        &agent::Session {
+            s_task: task.t_id,
+            s_user: db.settings.uid.clone(),
+            s_duration: 120,
+            ..Default::default()
+        },
+        common::now_ms(),
+    )
+    .unwrap();
+    assert!(matches!(sync(&db, &conn), SyncStatus::Synced { .. }));
+
+    let report: protocol::TeamReport = sync::engine::http_agent()
+        .get(&format!("{}/reports", url))
+        .header("Authorization", format!("Bearer {}", TOKEN))
+        .call()
+        .unwrap()
+        .body_mut()
+        .read_json()
+        .unwrap();
+    assert_eq!(report.users.len(), 1);
+    assert_eq!(report.users[0].uid, db.settings.uid);
+    assert_eq!(report.users[0].total_seconds, 120);
+    assert_eq!(report.tasks[0].t_uuid, task.t_uuid);
+}
+
+#[test]
+fn server_needs_a_token() {
+    assert!(SyncServer::bind("127.0.0.1:0", ":memory:", "").is_err());
+}
+
+#[test]
+fn wrong_token_is_rejected() {
+    let url = start_server();
+    let db = TestDb::with_settings(Settings {
+        sync_server_url: url,
+        sync_token: "guess".to_string(),
+        ..Settings::default()
+    });
+    let conn = storage::init_db(db.settings.clone()).unwrap();
+    add_task(&conn, "Private");
+
+    let SyncStatus::Failed { message, .. } = sync(&db, &conn) else {
+        panic!("sync with a wrong token succeeded");
+    };
+    assert!(message.contains("401"), "{}", message);
+    assert_eq!(outbox::count_entries(&conn).unwrap(), 1);
+}
+
+#[test]
+fn oversized_push_is_rejected() {
+    let url = start_server();
+    let body = "x".repeat(time_tracker::server::routes::MAX_BODY_BYTES as usize + 1);
+    let response = sync::engine::http_agent()
+        .post(&format!("{}/push", url))
+        .header("Authorization", format!("Bearer {}", TOKEN))
+        .send(body);
+
+    assert!(matches!(response, Err(ureq::Error::StatusCode(413))));
+}
+
+#[test]
+fn unreachable_server_reschedules_outbox() {
+    let (db, conn) = client("http://127.0.0.1:9");
+    add_task(&conn, "Pending");
+
+    assert!(matches!(sync(&db, &conn), SyncStatus::Failed { .. }));
+    let entries = outbox::get_due_entries(&conn, i64::MAX, 10).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].o_attempts, 1);
+
+    // The entry is waiting out its backoff, so a regular sync skips the push.
+    let now = chrono::Utc::now().timestamp();
+    assert!(outbox::get_due_entries(&conn, now, 10).unwrap().is_empty());
+}